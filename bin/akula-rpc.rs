@@ -1,9 +1,41 @@
-use akula::{binutil::AkulaDataDir, kv::mdbx::*, models::*, stagedsync::stages::*};
+use akula::{
+    accessors::chain,
+    binutil::AkulaDataDir,
+    crypto::keccak256,
+    execution::{
+        address::create_address,
+        replay_block, trace_block, trace_transaction,
+        tracer::{CallTrace, StructLog, StructLogger, StructLoggerConfig},
+    },
+    kv::{mdbx::*, tables},
+    models::*,
+    sentry_connector::sentry_address::SentryAddress,
+    stagedsync::{progress::SyncProgress, stages::*},
+    trie::generate_proof,
+    Buffer, DecodeHex, EncodeHex,
+};
+use anyhow::format_err;
 use async_trait::async_trait;
+use bytes::Bytes;
 use clap::Parser;
+use ethereum_interfaces::sentry::{
+    peers_reply::PeerEvent, sentry_client::SentryClient, PeerCountRequest, PeersRequest,
+};
 use ethnum::U256;
 use jsonrpsee::{core::RpcResult, http_server::HttpServerBuilder, proc_macros::rpc};
-use std::{future::pending, net::SocketAddr, sync::Arc};
+use lru::LruCache;
+use parking_lot::{Mutex, RwLock};
+use serde::{Serialize, Serializer};
+use std::{
+    collections::HashSet,
+    future::pending,
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio_stream::StreamExt;
+use tonic::transport::Channel;
+use tracing::*;
 use tracing_subscriber::{prelude::*, EnvFilter};
 
 #[derive(Parser)]
@@ -14,14 +46,243 @@ pub struct Opt {
 
     #[clap(long)]
     pub listen_address: SocketAddr,
+
+    /// Sentry to query for peer information.
+    #[clap(long)]
+    pub sentry_api_addr: Option<SentryAddress>,
+}
+
+fn serialize_hex_list<S>(list: &[Bytes], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(list.iter().map(EncodeHex::to_hex_string))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    #[serde(serialize_with = "serialize_hex_list")]
+    pub proof: Vec<Bytes>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: Address,
+    #[serde(serialize_with = "serialize_hex_list")]
+    pub account_proof: Vec<Bytes>,
+    pub balance: U256,
+    pub code_hash: H256,
+    pub nonce: U64,
+    pub storage_hash: H256,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeHistory {
+    pub oldest_block: U64,
+    pub base_fee_per_gas: Vec<U256>,
+    pub gas_used_ratio: Vec<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward: Option<Vec<Vec<U256>>>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionLog {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    #[serde(with = "akula::hexbytes")]
+    pub data: Bytes,
+    pub block_hash: H256,
+    pub block_number: U64,
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub log_index: U64,
+    pub removed: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionReceipt {
+    pub transaction_hash: H256,
+    pub transaction_index: U64,
+    pub block_hash: H256,
+    pub block_number: U64,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub cumulative_gas_used: U64,
+    pub gas_used: U64,
+    pub contract_address: Option<Address>,
+    pub logs: Vec<TransactionLog>,
+    pub logs_bloom: Bloom,
+    #[serde(rename = "type")]
+    pub tx_type: U64,
+    /// Post-transaction state root, which receipts carry instead of the status before Byzantium.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<U64>,
+}
+
+/// `eth_syncing` result: `false`, or the progress made towards the downloaded headers.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SyncStatus {
+    NotSyncing(bool),
+    #[serde(rename_all = "camelCase")]
+    Syncing {
+        starting_block: U64,
+        current_block: U64,
+        highest_block: U64,
+    },
+}
+
+/// Number of latest blocks to keep receipts for.
+const RECEIPTS_CACHE_SIZE: usize = 256;
+
+/// How long a node that caught up keeps reporting it is not syncing while processing new blocks.
+const SYNCED_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+fn sync_status(
+    progress: &SyncProgress,
+    synced_at: &mut Option<Instant>,
+    now: Instant,
+) -> SyncStatus {
+    let current_block = progress.current_block();
+    let highest_block = progress.highest_block();
+
+    if current_block >= highest_block {
+        *synced_at = Some(now);
+        return SyncStatus::NotSyncing(false);
+    }
+
+    if let Some(synced_at) = *synced_at {
+        if now.saturating_duration_since(synced_at) <= SYNCED_GRACE_PERIOD {
+            return SyncStatus::NotSyncing(false);
+        }
+    }
+
+    SyncStatus::Syncing {
+        starting_block: progress.starting_block().0.into(),
+        current_block: current_block.0.into(),
+        highest_block: highest_block.0.into(),
+    }
+}
+
+/// Converts receipts of replayed transactions into their RPC representation.
+///
+/// Before Byzantium receipts have no status. Their intermediate state roots are not kept, so the root is left
+/// out as well.
+fn rpc_receipts(
+    block_hash: H256,
+    block_number: BlockNumber,
+    revision: Revision,
+    tx_hashes: &[H256],
+    transactions: &[MessageWithSender],
+    receipts: Vec<Receipt>,
+) -> Vec<TransactionReceipt> {
+    let mut last_cumulative_gas_used = 0;
+    let mut log_index = 0;
+    transactions
+        .iter()
+        .zip(receipts)
+        .enumerate()
+        .map(|(i, (tx, receipt))| {
+            let transaction_hash = tx_hashes[i];
+            let gas_used = receipt.cumulative_gas_used - last_cumulative_gas_used;
+            last_cumulative_gas_used = receipt.cumulative_gas_used;
+
+            let (to, contract_address) = match tx.action() {
+                TransactionAction::Call(to) => (Some(to), None),
+                TransactionAction::Create => (None, Some(create_address(tx.sender, tx.nonce()))),
+            };
+
+            TransactionReceipt {
+                transaction_hash,
+                transaction_index: (i as u64).into(),
+                block_hash,
+                block_number: block_number.0.into(),
+                from: tx.sender,
+                to,
+                cumulative_gas_used: receipt.cumulative_gas_used.into(),
+                gas_used: gas_used.into(),
+                contract_address,
+                logs: receipt
+                    .logs
+                    .into_iter()
+                    .map(|log| {
+                        log_index += 1;
+                        TransactionLog {
+                            address: log.address,
+                            topics: log.topics,
+                            data: log.data,
+                            block_hash,
+                            block_number: block_number.0.into(),
+                            transaction_hash,
+                            transaction_index: (i as u64).into(),
+                            log_index: (log_index - 1).into(),
+                            removed: false,
+                        }
+                    })
+                    .collect(),
+                logs_bloom: receipt.bloom,
+                tx_type: (receipt.tx_type as u64).into(),
+                root: None,
+                status: (revision >= Revision::Byzantium).then(|| (receipt.success as u64).into()),
+            }
+        })
+        .collect()
 }
 
 #[rpc(server, namespace = "eth")]
 pub trait EthApi {
     #[method(name = "blockNumber")]
     async fn block_number(&self) -> RpcResult<BlockNumber>;
+    #[method(name = "chainId")]
+    async fn chain_id(&self) -> RpcResult<U64>;
+    #[method(name = "syncing")]
+    async fn syncing(&self) -> RpcResult<SyncStatus>;
+    #[method(name = "gasPrice")]
+    async fn gas_price(&self) -> RpcResult<U256>;
+    #[method(name = "maxPriorityFeePerGas")]
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256>;
     #[method(name = "getBalance")]
     async fn get_balance(&self, address: Address, block_number: BlockNumber) -> RpcResult<U256>;
+    #[method(name = "getCode")]
+    async fn get_code(&self, address: Address, block_number: BlockNumber) -> RpcResult<String>;
+    #[method(name = "getTransactionCount")]
+    async fn get_transaction_count(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+    ) -> RpcResult<U64>;
+    #[method(name = "feeHistory")]
+    async fn fee_history(
+        &self,
+        block_count: U64,
+        newest_block: BlockNumber,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> RpcResult<FeeHistory>;
+    #[method(name = "getBlockReceipts")]
+    async fn get_block_receipts(
+        &self,
+        block_number: BlockNumber,
+    ) -> RpcResult<Vec<TransactionReceipt>>;
+    #[method(name = "getTransactionReceipt")]
+    async fn get_transaction_receipt(&self, tx_hash: H256)
+        -> RpcResult<Option<TransactionReceipt>>;
+    #[method(name = "getProof")]
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<H256>,
+        block_number: BlockNumber,
+    ) -> RpcResult<AccountProof>;
 }
 
 pub struct EthApiServerImpl<E>
@@ -29,6 +290,81 @@ where
     E: EnvironmentKind,
 {
     db: Arc<MdbxEnvironment<E>>,
+    receipts_cache: Mutex<LruCache<H256, Vec<TransactionReceipt>>>,
+    sync_progress: SyncProgress,
+    synced_at: Mutex<Option<Instant>>,
+}
+
+impl<E> EthApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    pub fn new(db: Arc<MdbxEnvironment<E>>) -> anyhow::Result<Self> {
+        // Headers set the target, everything else is done once Finish catches up.
+        let sync_progress = SyncProgress::default();
+        sync_progress.init(&db.begin()?, &[HEADERS, FINISH])?;
+
+        Ok(Self {
+            db,
+            receipts_cache: Mutex::new(LruCache::new(RECEIPTS_CACHE_SIZE)),
+            sync_progress,
+            synced_at: Mutex::new(None),
+        })
+    }
+
+    /// Receipts are not stored, so they are recreated by executing the block.
+    fn read_block_receipts(
+        &self,
+        block_number: BlockNumber,
+    ) -> anyhow::Result<Vec<TransactionReceipt>> {
+        let txn = self.db.begin()?;
+
+        let block_hash = txn
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("Canonical hash for block {} not found", block_number))?;
+
+        if let Some(receipts) = self.receipts_cache.lock().get(&block_hash) {
+            return Ok(receipts.clone());
+        }
+
+        let header = txn
+            .get(tables::Header, (block_number, block_hash))?
+            .ok_or_else(|| format_err!("Header for block {} not found", block_number))?;
+        let tx_hashes = chain::block_body::read_without_senders(&txn, block_hash, block_number)?
+            .ok_or_else(|| format_err!("Body for block {} not found", block_number))?
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect::<Vec<_>>();
+        let body = chain::block_body::read_with_senders(&txn, block_hash, block_number)?
+            .ok_or_else(|| format_err!("Body for block {} not found", block_number))?;
+        let chain_spec = txn
+            .get(tables::Config, Default::default())?
+            .ok_or_else(|| format_err!("No chain specification set"))?;
+
+        // State at the end of the previous block
+        let mut state = Buffer::new(
+            &txn,
+            BlockNumber(0),
+            Some(BlockNumber(block_number.0.saturating_sub(1))),
+        );
+        let header = PartialHeader::from(header);
+        let revision = chain_spec.collect_header_spec(&header).revision;
+        let receipts = replay_block(&mut state, &chain_spec, &header, &body)?;
+
+        let receipts = rpc_receipts(
+            block_hash,
+            block_number,
+            revision,
+            &tx_hashes,
+            &body.transactions,
+            receipts,
+        );
+
+        self.receipts_cache.lock().put(block_hash, receipts.clone());
+
+        Ok(receipts)
+    }
 }
 
 #[async_trait]
@@ -42,6 +378,42 @@ where
             .unwrap_or(BlockNumber(0)))
     }
 
+    async fn chain_id(&self) -> RpcResult<U64> {
+        Ok(self
+            .db
+            .begin()?
+            .get(tables::Config, Default::default())?
+            .ok_or_else(|| format_err!("No chain specification set"))?
+            .params
+            .chain_id
+            .0
+            .into())
+    }
+
+    async fn syncing(&self) -> RpcResult<SyncStatus> {
+        self.sync_progress.update(&self.db.begin()?)?;
+        Ok(sync_status(
+            &self.sync_progress,
+            &mut self.synced_at.lock(),
+            Instant::now(),
+        ))
+    }
+
+    async fn gas_price(&self) -> RpcResult<U256> {
+        let txn = self.db.begin()?;
+        let latest_block = FINISH.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+        Ok(akula::accessors::gas_price::suggest(&txn, latest_block)?)
+    }
+
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
+        let txn = self.db.begin()?;
+        let latest_block = FINISH.get_progress(&txn)?.unwrap_or(BlockNumber(0));
+        Ok(akula::accessors::gas_price::suggest_tip(
+            &txn,
+            latest_block,
+        )?)
+    }
+
     async fn get_balance(&self, address: Address, block_number: BlockNumber) -> RpcResult<U256> {
         Ok(
             akula::accessors::state::account::read(&self.db.begin()?, address, Some(block_number))?
@@ -49,6 +421,414 @@ where
                 .unwrap_or(U256::ZERO),
         )
     }
+
+    async fn get_code(&self, address: Address, block_number: BlockNumber) -> RpcResult<String> {
+        Ok(
+            akula::accessors::state::code::read(&self.db.begin()?, address, Some(block_number))?
+                .to_hex_string(),
+        )
+    }
+
+    async fn get_transaction_count(
+        &self,
+        address: Address,
+        block_number: BlockNumber,
+    ) -> RpcResult<U64> {
+        Ok(
+            akula::accessors::state::account::read(&self.db.begin()?, address, Some(block_number))?
+                .map(|acc| acc.nonce)
+                .unwrap_or(0)
+                .into(),
+        )
+    }
+
+    async fn fee_history(
+        &self,
+        block_count: U64,
+        newest_block: BlockNumber,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> RpcResult<FeeHistory> {
+        let history = akula::accessors::fee_history::read(
+            &self.db.begin()?,
+            block_count.as_u64(),
+            newest_block,
+            &reward_percentiles.unwrap_or_default(),
+        )?;
+
+        Ok(FeeHistory {
+            oldest_block: history.oldest_block.0.into(),
+            base_fee_per_gas: history.base_fee_per_gas,
+            gas_used_ratio: history.gas_used_ratio,
+            reward: history.reward,
+        })
+    }
+
+    async fn get_block_receipts(
+        &self,
+        block_number: BlockNumber,
+    ) -> RpcResult<Vec<TransactionReceipt>> {
+        Ok(self.read_block_receipts(block_number)?)
+    }
+
+    async fn get_transaction_receipt(
+        &self,
+        tx_hash: H256,
+    ) -> RpcResult<Option<TransactionReceipt>> {
+        let block_number = match chain::tl::read(&self.db.begin()?, tx_hash)? {
+            Some(block_number) => block_number,
+            None => return Ok(None),
+        };
+
+        Ok(self
+            .read_block_receipts(block_number)?
+            .into_iter()
+            .find(|receipt| receipt.transaction_hash == tx_hash))
+    }
+
+    async fn get_proof(
+        &self,
+        address: Address,
+        storage_keys: Vec<H256>,
+        block_number: BlockNumber,
+    ) -> RpcResult<AccountProof> {
+        let txn = self.db.begin()?;
+
+        // Intermediate hashes are only available for the latest state
+        let latest = INTERMEDIATE_HASHES
+            .get_progress(&txn)?
+            .unwrap_or(BlockNumber(0));
+        if block_number != latest {
+            return Err(format_err!(
+                "Proofs are only available for the latest block {}, requested {}",
+                latest,
+                block_number
+            )
+            .into());
+        }
+
+        let proof = generate_proof(&txn, address, &storage_keys)?;
+        let account = proof.account.unwrap_or_default();
+
+        Ok(AccountProof {
+            address,
+            account_proof: proof.account_proof,
+            balance: account.balance,
+            code_hash: account.code_hash,
+            nonce: account.nonce.into(),
+            storage_hash: proof.storage_root,
+            storage_proof: proof
+                .storage_proofs
+                .into_iter()
+                .map(|p| StorageProof {
+                    key: p.key,
+                    value: p.value,
+                    proof: p.proof,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Peer information in Geth's `admin_peers` format.
+///
+/// Only the ID is filled in: the sentry interface reports peers by their connect and disconnect events, which
+/// carry nothing but the peer ID, and has no request for the details of a peer such as its enode, client name
+/// or capabilities.
+#[derive(Serialize)]
+pub struct PeerInfo {
+    pub id: H512,
+}
+
+#[rpc(server, namespace = "net")]
+pub trait NetApi {
+    #[method(name = "peerCount")]
+    async fn peer_count(&self) -> RpcResult<U64>;
+    #[method(name = "peers")]
+    async fn peers(&self) -> RpcResult<Vec<PeerInfo>>;
+    #[method(name = "listening")]
+    async fn listening(&self) -> RpcResult<bool>;
+    #[method(name = "version")]
+    async fn version(&self) -> RpcResult<String>;
+}
+
+pub struct NetApiServerImpl {
+    sentry: Option<SentryClient<Channel>>,
+    peers: Arc<RwLock<HashSet<H512>>>,
+    network_id: NetworkId,
+}
+
+impl NetApiServerImpl {
+    pub async fn new(
+        sentry_api_addr: Option<SentryAddress>,
+        network_id: NetworkId,
+    ) -> anyhow::Result<Self> {
+        let peers = Arc::new(RwLock::new(HashSet::new()));
+
+        let sentry = if let Some(addr) = sentry_api_addr {
+            let mut client = SentryClient::connect(addr.addr).await?;
+
+            // Sentry starts the stream with already connected peers, which seeds the set.
+            let mut peer_events = client.peers(PeersRequest {}).await?.into_inner();
+            tokio::spawn({
+                let peers = peers.clone();
+                async move {
+                    while let Some(event) = peer_events.next().await {
+                        match event {
+                            Ok(event) => {
+                                if let Some(peer_id) = event.peer_id {
+                                    let peer_id = H512::from(peer_id);
+                                    if event.event == PeerEvent::Connect as i32 {
+                                        peers.write().insert(peer_id);
+                                    } else if event.event == PeerEvent::Disconnect as i32 {
+                                        peers.write().remove(&peer_id);
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                warn!("Failed to receive peer event: {}", e);
+                            }
+                        }
+                    }
+                    warn!("Peer event stream from sentry closed");
+                }
+            });
+
+            Some(client)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            sentry,
+            peers,
+            network_id,
+        })
+    }
+}
+
+#[async_trait]
+impl NetApiServer for NetApiServerImpl {
+    async fn peer_count(&self) -> RpcResult<U64> {
+        Ok(match &self.sentry {
+            Some(sentry) => sentry
+                .clone()
+                .peer_count(PeerCountRequest {})
+                .await
+                .map_err(anyhow::Error::from)?
+                .into_inner()
+                .count
+                .into(),
+            None => U64::zero(),
+        })
+    }
+
+    async fn peers(&self) -> RpcResult<Vec<PeerInfo>> {
+        Ok(self
+            .peers
+            .read()
+            .iter()
+            .map(|&id| PeerInfo { id })
+            .collect())
+    }
+
+    async fn listening(&self) -> RpcResult<bool> {
+        Ok(self.sentry.is_some())
+    }
+
+    async fn version(&self) -> RpcResult<String> {
+        Ok(self.network_id.0.to_string())
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionTrace {
+    pub gas: u64,
+    pub failed: bool,
+    pub return_value: String,
+    pub struct_logs: Vec<StructLog>,
+}
+
+#[rpc(server, namespace = "debug")]
+pub trait DebugApi {
+    #[method(name = "traceTransaction")]
+    async fn trace_transaction(
+        &self,
+        tx_hash: H256,
+        config: Option<StructLoggerConfig>,
+    ) -> RpcResult<TransactionTrace>;
+}
+
+pub struct DebugApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    db: Arc<MdbxEnvironment<E>>,
+}
+
+#[async_trait]
+impl<E> DebugApiServer for DebugApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    async fn trace_transaction(
+        &self,
+        tx_hash: H256,
+        config: Option<StructLoggerConfig>,
+    ) -> RpcResult<TransactionTrace> {
+        let txn = self.db.begin()?;
+
+        let block_number = chain::tl::read(&txn, tx_hash)?
+            .ok_or_else(|| format_err!("Transaction {:?} not found", tx_hash))?;
+        let block_hash = txn
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("Canonical hash for block {} not found", block_number))?;
+        let header = txn
+            .get(tables::Header, (block_number, block_hash))?
+            .ok_or_else(|| format_err!("Header for block {} not found", block_number))?;
+        let index = chain::block_body::read_without_senders(&txn, block_hash, block_number)?
+            .ok_or_else(|| format_err!("Body for block {} not found", block_number))?
+            .transactions
+            .iter()
+            .position(|tx| tx.hash() == tx_hash)
+            .ok_or_else(|| {
+                format_err!(
+                    "Transaction {:?} not found in block {}",
+                    tx_hash,
+                    block_number
+                )
+            })?;
+        let body = chain::block_body::read_with_senders(&txn, block_hash, block_number)?
+            .ok_or_else(|| format_err!("Body for block {} not found", block_number))?;
+        let chain_spec = txn
+            .get(tables::Config, Default::default())?
+            .ok_or_else(|| format_err!("No chain specification set"))?;
+
+        // State at the end of the previous block
+        let mut state = Buffer::new(
+            &txn,
+            BlockNumber(0),
+            Some(BlockNumber(block_number.0.saturating_sub(1))),
+        );
+        let mut tracer = StructLogger::new(config.unwrap_or_default());
+        let (receipt, gas) = trace_transaction(
+            &mut state,
+            &chain_spec,
+            &header.into(),
+            &body,
+            index,
+            &mut tracer,
+        )?;
+
+        Ok(TransactionTrace {
+            gas,
+            failed: !receipt.success,
+            return_value: hex::encode(tracer.return_value()),
+            struct_logs: tracer.into_logs(),
+        })
+    }
+}
+
+#[rpc(server, namespace = "web3")]
+pub trait Web3Api {
+    #[method(name = "clientVersion")]
+    async fn client_version(&self) -> RpcResult<String>;
+    #[method(name = "sha3")]
+    async fn sha3(&self, data: String) -> RpcResult<H256>;
+}
+
+pub struct Web3ApiServerImpl {
+    /// Name of the served network, appended to the client version.
+    pub chain_name: &'static str,
+}
+
+#[async_trait]
+impl Web3ApiServer for Web3ApiServerImpl {
+    async fn client_version(&self) -> RpcResult<String> {
+        Ok(format!("{}/{}", akula::version_string(), self.chain_name))
+    }
+
+    async fn sha3(&self, data: String) -> RpcResult<H256> {
+        Ok(keccak256(Bytes::from_hex_string(&data)?))
+    }
+}
+
+/// Trace in OpenEthereum's `trace_block` format.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalizedTrace {
+    #[serde(flatten)]
+    pub trace: CallTrace,
+    pub block_hash: H256,
+    pub block_number: u64,
+    pub transaction_hash: Option<H256>,
+    pub transaction_position: Option<usize>,
+}
+
+#[rpc(server, namespace = "trace")]
+pub trait TraceApi {
+    #[method(name = "block")]
+    async fn block(&self, block_number: BlockNumber) -> RpcResult<Vec<LocalizedTrace>>;
+}
+
+pub struct TraceApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    db: Arc<MdbxEnvironment<E>>,
+}
+
+#[async_trait]
+impl<E> TraceApiServer for TraceApiServerImpl<E>
+where
+    E: EnvironmentKind,
+{
+    async fn block(&self, block_number: BlockNumber) -> RpcResult<Vec<LocalizedTrace>> {
+        let txn = self.db.begin()?;
+
+        let block_hash = txn
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("Canonical hash for block {} not found", block_number))?;
+        let header = txn
+            .get(tables::Header, (block_number, block_hash))?
+            .ok_or_else(|| format_err!("Header for block {} not found", block_number))?;
+        let tx_hashes = chain::block_body::read_without_senders(&txn, block_hash, block_number)?
+            .ok_or_else(|| format_err!("Body for block {} not found", block_number))?
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect::<Vec<_>>();
+        let body = chain::block_body::read_with_senders(&txn, block_hash, block_number)?
+            .ok_or_else(|| format_err!("Body for block {} not found", block_number))?;
+        let chain_spec = txn
+            .get(tables::Config, Default::default())?
+            .ok_or_else(|| format_err!("No chain specification set"))?;
+
+        // State at the end of the previous block
+        let mut state = Buffer::new(
+            &txn,
+            BlockNumber(0),
+            Some(BlockNumber(block_number.0.saturating_sub(1))),
+        );
+        let block_trace = trace_block(&mut state, &chain_spec, &header.into(), &body)?;
+
+        let localize = |trace, position: Option<usize>| LocalizedTrace {
+            trace,
+            block_hash,
+            block_number: block_number.0,
+            transaction_hash: position.map(|i| tx_hashes[i]),
+            transaction_position: position,
+        };
+
+        Ok(block_trace
+            .transactions
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, traces)| traces.into_iter().map(move |trace| (trace, Some(i))))
+            .chain(block_trace.rewards.into_iter().map(|trace| (trace, None)))
+            .map(|(trace, position)| localize(trace, position))
+            .collect())
+    }
 }
 
 #[tokio::main]
@@ -75,9 +855,283 @@ async fn main() -> anyhow::Result<()> {
             akula::kv::tables::CHAINDATA_TABLES.clone(),
         )?,
     );
+    akula::kv::check_schema_version(&db.begin()?)?;
+
+    let chain_spec = db
+        .begin()?
+        .get(tables::Config, Default::default())?
+        .ok_or_else(|| format_err!("No chain specification set"))?;
 
+    // Batch requests are dispatched by the server itself, responses keep the order of requests.
     let server = HttpServerBuilder::default().build(opt.listen_address)?;
-    let _server_handle = server.start(EthApiServerImpl { db }.into_rpc())?;
+    let mut api = EthApiServerImpl::new(db.clone())?.into_rpc();
+    api.merge(DebugApiServerImpl { db: db.clone() }.into_rpc())?;
+    api.merge(TraceApiServerImpl { db }.into_rpc())?;
+    api.merge(
+        Web3ApiServerImpl {
+            chain_name: ChainSpec::chain_name(chain_spec.params.chain_id.0),
+        }
+        .into_rpc(),
+    )?;
+    api.merge(
+        NetApiServerImpl::new(opt.sentry_api_addr, chain_spec.params.network_id)
+            .await?
+            .into_rpc(),
+    )?;
+    let _server_handle = server.start(api)?;
 
     pending().await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    fn message(nonce: u64, action: TransactionAction) -> Message {
+        Message::Legacy {
+            chain_id: None,
+            nonce,
+            gas_price: 1.as_u256(),
+            gas_limit: 1_000_000,
+            action,
+            value: U256::ZERO,
+            input: Bytes::new(),
+        }
+    }
+
+    #[test]
+    fn eth_syncing() {
+        let db = akula::kv::new_mem_database().unwrap();
+        let set_progress = |stage: StageId, block: u64| {
+            let txn = db.begin_mutable().unwrap();
+            stage.save_progress(&txn, BlockNumber(block)).unwrap();
+            txn.commit().unwrap();
+        };
+        set_progress(HEADERS, 100);
+        set_progress(FINISH, 40);
+
+        let progress = SyncProgress::default();
+        progress
+            .init(&db.begin().unwrap(), &[HEADERS, FINISH])
+            .unwrap();
+        let mut synced_at = None;
+        let now = Instant::now();
+
+        let status = sync_status(&progress, &mut synced_at, now);
+        assert_eq!(
+            status,
+            SyncStatus::Syncing {
+                starting_block: 40.into(),
+                current_block: 40.into(),
+                highest_block: 100.into(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!({
+                "startingBlock": "0x28",
+                "currentBlock": "0x28",
+                "highestBlock": "0x64",
+            })
+        );
+
+        set_progress(FINISH, 100);
+        progress.update(&db.begin().unwrap()).unwrap();
+        let status = sync_status(&progress, &mut synced_at, now);
+        assert_eq!(status, SyncStatus::NotSyncing(false));
+        assert_eq!(
+            serde_json::to_value(&status).unwrap(),
+            serde_json::json!(false)
+        );
+
+        // A new block arriving shortly after catching up does not flip the status.
+        set_progress(HEADERS, 101);
+        progress.update(&db.begin().unwrap()).unwrap();
+        assert_eq!(
+            sync_status(&progress, &mut synced_at, now + Duration::from_secs(5)),
+            SyncStatus::NotSyncing(false)
+        );
+        assert_eq!(
+            sync_status(
+                &progress,
+                &mut synced_at,
+                now + SYNCED_GRACE_PERIOD + Duration::from_secs(1)
+            ),
+            SyncStatus::Syncing {
+                starting_block: 40.into(),
+                current_block: 100.into(),
+                highest_block: 101.into(),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn net_version_is_network_id() {
+        let net = NetApiServerImpl::new(None, akula::res::chainspec::RINKEBY.params.network_id)
+            .await
+            .unwrap();
+
+        assert_eq!(net.version().await.unwrap(), "4");
+    }
+
+    #[test]
+    fn transfer_and_creation_receipts() {
+        let sender = Address::from(hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b"));
+        let recipient = Address::from(hex!("3535353535353535353535353535353535353535"));
+        let block_hash = H256::repeat_byte(0xbb);
+        let tx_hashes = [H256::repeat_byte(1), H256::repeat_byte(2)];
+
+        let transactions = vec![
+            MessageWithSender {
+                message: message(0, TransactionAction::Call(recipient)),
+                sender,
+            },
+            MessageWithSender {
+                message: message(1, TransactionAction::Create),
+                sender,
+            },
+        ];
+        let log = Log {
+            address: create_address(sender, 1),
+            topics: vec![H256::repeat_byte(3)],
+            data: Bytes::new(),
+        };
+        let receipts = vec![
+            Receipt {
+                tx_type: TxType::Legacy,
+                success: true,
+                cumulative_gas_used: 21_000,
+                bloom: Bloom::zero(),
+                logs: vec![],
+            },
+            Receipt {
+                tx_type: TxType::Legacy,
+                success: true,
+                cumulative_gas_used: 121_000,
+                bloom: Bloom::zero(),
+                logs: vec![log.clone()],
+            },
+        ];
+
+        let receipts = rpc_receipts(
+            block_hash,
+            BlockNumber(5),
+            Revision::London,
+            &tx_hashes,
+            &transactions,
+            receipts,
+        );
+
+        let transfer = &receipts[0];
+        assert_eq!(transfer.transaction_hash, tx_hashes[0]);
+        assert_eq!(transfer.transaction_index, U64::from(0));
+        assert_eq!(transfer.block_number, U64::from(5));
+        assert_eq!(transfer.from, sender);
+        assert_eq!(transfer.to, Some(recipient));
+        assert_eq!(transfer.contract_address, None);
+        assert_eq!(transfer.gas_used, U64::from(21_000));
+        assert_eq!(transfer.root, None);
+        assert_eq!(transfer.status, Some(U64::from(1)));
+
+        let creation = &receipts[1];
+        assert_eq!(creation.transaction_index, U64::from(1));
+        assert_eq!(creation.to, None);
+        assert_eq!(creation.contract_address, Some(log.address));
+        assert_eq!(creation.cumulative_gas_used, U64::from(121_000));
+        assert_eq!(creation.gas_used, U64::from(100_000));
+        assert_eq!(creation.logs.len(), 1);
+        assert_eq!(creation.logs[0].log_index, U64::from(0));
+        assert_eq!(creation.logs[0].transaction_hash, tx_hashes[1]);
+        assert_eq!(creation.logs[0].block_hash, block_hash);
+    }
+
+    #[test]
+    fn pre_byzantium_receipts_have_no_status() {
+        let transactions = vec![MessageWithSender {
+            message: message(0, TransactionAction::Call(Address::repeat_byte(0x35))),
+            sender: Address::repeat_byte(0xa9),
+        }];
+        let receipts = vec![Receipt {
+            tx_type: TxType::Legacy,
+            success: true,
+            cumulative_gas_used: 21_000,
+            bloom: Bloom::zero(),
+            logs: vec![],
+        }];
+
+        let receipts = rpc_receipts(
+            H256::repeat_byte(0xbb),
+            BlockNumber(5),
+            Revision::Homestead,
+            &[H256::repeat_byte(1)],
+            &transactions,
+            receipts,
+        );
+
+        assert_eq!(receipts[0].status, None);
+        let json = serde_json::to_value(&receipts[0]).unwrap();
+        assert!(json.get("status").is_none());
+        assert!(json.get("root").is_none());
+    }
+
+    #[tokio::test]
+    async fn client_version() {
+        let version = Web3ApiServerImpl {
+            chain_name: ChainSpec::chain_name(1),
+        }
+        .client_version()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            version,
+            format!("{}/Ethereum Mainnet", akula::version_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_responses_keep_request_order() {
+        let server = HttpServerBuilder::default()
+            .build("127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        let addr = server.local_addr().unwrap();
+        let mut api = Web3ApiServerImpl {
+            chain_name: ChainSpec::chain_name(1),
+        }
+        .into_rpc();
+        api.merge(
+            NetApiServerImpl::new(None, akula::res::chainspec::RINKEBY.params.network_id)
+                .await
+                .unwrap()
+                .into_rpc(),
+        )
+        .unwrap();
+        let _server_handle = server.start(api).unwrap();
+
+        let batch = serde_json::json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "net_version", "params": [] },
+            { "jsonrpc": "2.0", "id": 2, "method": "web3_clientVersion", "params": [] },
+            { "jsonrpc": "2.0", "id": 3, "method": "eth_unknown", "params": [] },
+            { "jsonrpc": "2.0", "id": 4, "method": "net_listening", "params": [] },
+        ]);
+        let request = hyper::Request::post(format!("http://{}", addr))
+            .header("content-type", "application/json")
+            .body(hyper::Body::from(batch.to_string()))
+            .unwrap();
+        let response = hyper::Client::new().request(request).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let responses = serde_json::from_slice::<Vec<serde_json::Value>>(&body).unwrap();
+
+        assert_eq!(
+            responses
+                .iter()
+                .map(|response| response["id"].as_u64().unwrap())
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3, 4]
+        );
+        assert_eq!(responses[0]["result"], "4");
+        assert!(responses[2].get("error").is_some());
+        assert_eq!(responses[3]["result"], false);
+    }
+}
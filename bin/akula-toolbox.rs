@@ -1,19 +1,31 @@
 use akula::{
     binutil::AkulaDataDir,
+    crypto::root_hash,
+    execution::replay_block,
     hex_to_bytes,
     kv::{
-        tables::{self, CHAINDATA_TABLES},
+        diff::diff_tables,
+        mdbx::*,
+        tables::{self, ErasedTable, CHAINDATA_TABLES},
         traits::*,
     },
     models::*,
-    stagedsync,
+    stagedsync::{self, stage::*, stages::*},
     stages::*,
+    Buffer, StageId,
 };
 use anyhow::{bail, ensure, format_err, Context};
 use bytes::Bytes;
 use clap::Parser;
 use itertools::Itertools;
-use std::{borrow::Cow, collections::BTreeMap, path::PathBuf, sync::Arc};
+use regex::Regex;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use tokio::pin;
 use tracing::*;
 use tracing_subscriber::{prelude::*, EnvFilter};
@@ -53,6 +65,15 @@ pub enum OptCommand {
         starting_key: Option<Bytes>,
         #[clap(long)]
         max_entries: Option<usize>,
+        /// Only print entries whose hex encoded key matches this regex
+        #[clap(long)]
+        key_filter: Option<Regex>,
+        /// Only print entries whose hex encoded value matches this regex
+        #[clap(long)]
+        value_filter: Option<Regex>,
+        /// Print the number of matching entries instead of the entries themselves
+        #[clap(long)]
+        count_only: bool,
     },
 
     /// Check table equality in two databases
@@ -65,9 +86,40 @@ pub enum OptCommand {
         table: String,
     },
 
+    /// Collect all differences of a table in two databases
+    DbDiff {
+        #[clap(long, parse(from_os_str))]
+        db1: PathBuf,
+        #[clap(long, parse(from_os_str))]
+        db2: PathBuf,
+        #[clap(long)]
+        table: String,
+        /// Write the full diff with hex encoded keys and values to this file
+        #[clap(long, parse(from_os_str))]
+        json: Option<PathBuf>,
+    },
+
     /// Execute Block Hashes stage
     Blockhashes,
 
+    /// Clear an index and rebuild it from genesis
+    RebuildIndex {
+        /// Index to rebuild: TxLookup or CallTraces
+        index: String,
+        /// Compare a sample of the original index with the rebuilt one before committing
+        #[clap(long)]
+        verify: bool,
+    },
+
+    /// Re-execute blocks and check the resulting receipts against the receipts root of each header
+    VerifyReceipts {
+        #[clap(long, default_value = "1")]
+        from: BlockNumber,
+        /// Defaults to the progress of the Execution stage
+        #[clap(long)]
+        to: Option<BlockNumber>,
+    },
+
     /// Execute HeaderDownload stage
     #[clap(name = "download-headers", about = "Run block headers downloader")]
     HeaderDownload {
@@ -135,11 +187,250 @@ async fn blockhashes(data_dir: AkulaDataDir) -> anyhow::Result<()> {
     staged_sync.push(BlockHashes {
         temp_dir: etl_temp_dir.clone(),
     });
-    staged_sync.run(&env).await?;
+    staged_sync.run_once(&env).await?;
+    Ok(())
+}
+
+/// How many entries of each original index table are compared with the rebuilt one.
+const VERIFY_SAMPLE_SIZE: usize = 10_000;
+
+/// Clear the index table, returning its first entries if they are to be verified later.
+fn clear_index<T>(
+    tx: &MdbxTransaction<'_, RW, mdbx::NoWriteMap>,
+    table: T,
+    verify: bool,
+) -> anyhow::Result<Vec<(Vec<u8>, Vec<u8>)>>
+where
+    T: Table + Copy,
+{
+    let sample = if verify {
+        tx.cursor(ErasedTable(table))?
+            .walk(None)
+            .take(VERIFY_SAMPLE_SIZE)
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        vec![]
+    };
+
+    info!("Clearing {}", &*table.db_name());
+    tx.clear_table(ErasedTable(table))?;
+
+    Ok(sample)
+}
+
+fn verify_index<T>(
+    tx: &MdbxTransaction<'_, RW, mdbx::NoWriteMap>,
+    table: T,
+    sample: Vec<(Vec<u8>, Vec<u8>)>,
+) -> anyhow::Result<()>
+where
+    T: Table + Copy,
+{
+    let sample_size = sample.len();
+    for (key, value) in sample {
+        let rebuilt = tx.get(ErasedTable(table), key.clone())?;
+        ensure!(
+            rebuilt.as_ref() == Some(&value),
+            "MISMATCH DETECTED in {}: {}: {} != {:?}",
+            &*table.db_name(),
+            hex::encode(&key),
+            hex::encode(&value),
+            rebuilt.map(hex::encode)
+        );
+    }
+
+    info!("{} entries of {} verified", sample_size, &*table.db_name());
+
+    Ok(())
+}
+
+/// Run a single stage from scratch until it catches up with the stage it follows.
+async fn rerun_stage<'db, S>(
+    tx: &mut MdbxTransaction<'db, RW, mdbx::NoWriteMap>,
+    mut stage: S,
+    previous_stage: StageId,
+) -> anyhow::Result<()>
+where
+    S: Stage<'db, mdbx::NoWriteMap>,
+{
+    let stage_id = stage.id();
+    tx.del(tables::SyncStage, stage_id, None)?;
+
+    let target = previous_stage.get_progress(tx)?.unwrap_or_default();
+    let started_at = Instant::now();
+
+    info!("Rebuilding {} up to block {}", stage_id, target);
+    loop {
+        let stage_progress = stage_id.get_progress(tx)?;
+        match stage
+            .execute(
+                tx,
+                StageInput {
+                    restarted: stage_progress.is_some(),
+                    is_first_run: stage_progress.is_none(),
+                    first_started_at: (started_at, None),
+                    previous_stage: Some((previous_stage, target)),
+                    stage_progress,
+                },
+            )
+            .await?
+        {
+            ExecOutput::Progress {
+                stage_progress,
+                done,
+                ..
+            } => {
+                stage_id.save_progress(tx, stage_progress)?;
+
+                if done || stage_progress >= target {
+                    info!(
+                        "Rebuilt {} @ {} in {}",
+                        stage_id,
+                        stage_progress,
+                        stagedsync::format_duration(Instant::now() - started_at, true)
+                    );
+                    return Ok(());
+                }
+            }
+            ExecOutput::Unwind { unwind_to } => {
+                bail!(
+                    "{} requested unwind to {} while rebuilding",
+                    stage_id,
+                    unwind_to
+                )
+            }
+        }
+    }
+}
+
+async fn rebuild_index(data_dir: AkulaDataDir, index: String, verify: bool) -> anyhow::Result<()> {
+    let etl_temp_path = data_dir.etl_temp_dir();
+    let _ = std::fs::remove_dir_all(&etl_temp_path);
+    std::fs::create_dir_all(&etl_temp_path)?;
+    let etl_temp_dir =
+        Arc::new(tempfile::tempdir_in(&etl_temp_path).context("failed to create ETL temp dir")?);
+
+    let env = akula::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_rw(
+        mdbx::Environment::new(),
+        &data_dir.chain_data_dir(),
+        CHAINDATA_TABLES.clone(),
+    )?;
+
+    // The whole rebuild happens in one transaction, so the original index is kept if anything fails.
+    let mut tx = env.begin_mutable()?;
+    match index.as_str() {
+        "TxLookup" => {
+            let sample = clear_index(&tx, tables::BlockTransactionLookup, verify)?;
+            rerun_stage(
+                &mut tx,
+                TxLookup {
+                    temp_dir: etl_temp_dir,
+                },
+                BODIES,
+            )
+            .await?;
+            verify_index(&tx, tables::BlockTransactionLookup, sample)?;
+        }
+        "CallTraces" => {
+            let from_sample = clear_index(&tx, tables::CallFromIndex, verify)?;
+            let to_sample = clear_index(&tx, tables::CallToIndex, verify)?;
+            rerun_stage(
+                &mut tx,
+                CallTraceIndex {
+                    temp_dir: etl_temp_dir,
+                    flush_interval: 50_000,
+                },
+                EXECUTION,
+            )
+            .await?;
+            verify_index(&tx, tables::CallFromIndex, from_sample)?;
+            verify_index(&tx, tables::CallToIndex, to_sample)?;
+        }
+        "AccountHistory" | "StorageHistory" | "LogIndex" => {
+            bail!(
+                "{} is written during execution and has no stage to rebuild it",
+                index
+            )
+        }
+        other => bail!("Unknown index {}, expected TxLookup or CallTraces", other),
+    }
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Receipts are not stored, so this replays each block on top of the historical state of its parent.
+fn verify_receipts(
+    data_dir: AkulaDataDir,
+    from: BlockNumber,
+    to: Option<BlockNumber>,
+) -> anyhow::Result<()> {
+    let env = open_db(data_dir)?;
+    let tx = env.begin()?;
+
+    let chain_spec = tx
+        .get(tables::Config, Default::default())?
+        .ok_or_else(|| format_err!("No chain specification set"))?;
+    let to = match to {
+        Some(to) => to,
+        None => EXECUTION.get_progress(&tx)?.unwrap_or_default(),
+    };
+    ensure!(from > BlockNumber(0), "genesis has no receipts to verify");
+
+    let started_at = Instant::now();
+    let mut last_report = (started_at, from);
+    let mut mismatches = 0_u64;
+    for block_number in from.0..=to.0 {
+        let block_number = BlockNumber(block_number);
+        let block_hash = tx
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("no canonical block {}", block_number))?;
+        let header = tx
+            .get(tables::Header, (block_number, block_hash))?
+            .ok_or_else(|| format_err!("header of block {} not found", block_number))?;
+        let body =
+            akula::accessors::chain::block_body::read_with_senders(&tx, block_hash, block_number)?
+                .ok_or_else(|| format_err!("body of block {} not found", block_number))?;
+
+        let mut state = Buffer::new(&tx, BlockNumber(0), Some(BlockNumber(block_number.0 - 1)));
+        let receipts = replay_block(&mut state, &chain_spec, &header.clone().into(), &body)?;
+
+        let receipts_root = root_hash(&receipts);
+        if receipts_root != header.receipts_root {
+            warn!(
+                "Receipts root mismatch in block {}: expected {:?}, computed {:?}",
+                block_number, header.receipts_root, receipts_root
+            );
+            mismatches += 1;
+        }
+
+        let now = Instant::now();
+        if now - last_report.0 >= Duration::from_secs(10) {
+            info!(
+                "Verified receipts up to block {} at {:.1} blocks/s",
+                block_number,
+                (block_number.0 - last_report.1 .0) as f64 / (now - last_report.0).as_secs_f64()
+            );
+            last_report = (now, block_number);
+        }
+    }
+
+    let blocks = to.0.saturating_sub(from.0) + 1;
+    info!(
+        "Verified receipts of {} blocks in {}",
+        blocks,
+        stagedsync::format_duration(Instant::now() - started_at, false)
+    );
+    ensure!(
+        mismatches == 0,
+        "{} of {} blocks have mismatching receipts",
+        mismatches,
+        blocks
+    );
+
     Ok(())
 }
 
-#[allow(unreachable_code)]
 async fn header_download(data_dir: AkulaDataDir, opts: HeaderDownloadOpts) -> anyhow::Result<()> {
     let chains_config = akula::sentry_connector::chain_config::ChainsConfig::new()?;
     let chain_config = chains_config.get(&opts.chain_name)?;
@@ -190,10 +481,10 @@ fn open_db(
     )
 }
 
-fn table_sizes(data_dir: AkulaDataDir, csv: bool) -> anyhow::Result<()> {
-    let env = open_db(data_dir)?;
+async fn table_sizes(data_dir: AkulaDataDir, csv: bool) -> anyhow::Result<()> {
+    let env = Arc::new(open_db(data_dir)?);
 
-    let mut sizes = env.begin()?.table_sizes()?.into_iter().collect::<Vec<_>>();
+    let mut sizes = env.table_sizes().await?.into_iter().collect::<Vec<_>>();
     sizes.sort_by_key(|(_, size)| *size);
 
     let mut out = Vec::new();
@@ -244,6 +535,9 @@ fn db_walk(
     table: String,
     starting_key: Option<Bytes>,
     max_entries: Option<usize>,
+    key_filter: Option<Regex>,
+    value_filter: Option<Regex>,
+    count_only: bool,
 ) -> anyhow::Result<()> {
     let env = open_db(data_dir)?;
 
@@ -252,6 +546,7 @@ fn db_walk(
         .open_db(Some(&table))
         .with_context(|| format!("failed to open table: {}", table))?;
     let mut cur = txn.cursor(&db)?;
+    let mut matching = 0_usize;
     for (i, item) in if let Some(starting_key) = starting_key {
         cur.iter_from::<Cow<[u8]>, Cow<[u8]>>(&starting_key)
     } else {
@@ -261,14 +556,30 @@ fn db_walk(
     .take(max_entries.unwrap_or(usize::MAX))
     {
         let (k, v) = item?;
-        println!(
-            "{} / {:?} / {:?} / {:?} / {:?}",
-            i,
-            hex::encode(k),
-            hex::encode(&v),
-            Account::decode_for_storage(&v),
-            BlockHeader::decode(&v)
-        );
+        let key = hex::encode(k);
+        let value = hex::encode(&v);
+
+        if !key_filter.as_ref().map_or(true, |f| f.is_match(&key))
+            || !value_filter.as_ref().map_or(true, |f| f.is_match(&value))
+        {
+            continue;
+        }
+
+        matching += 1;
+        if !count_only {
+            println!(
+                "{} / {:?} / {:?} / {:?} / {:?}",
+                i,
+                key,
+                value,
+                Account::decode_for_storage(&v),
+                BlockHeader::decode(&v)
+            );
+        }
+    }
+
+    if count_only {
+        println!("{}", matching);
     }
 
     Ok(())
@@ -343,6 +654,59 @@ fn check_table_eq(db1_path: PathBuf, db2_path: PathBuf, table: String) -> anyhow
     Ok(())
 }
 
+fn db_diff(
+    db1_path: PathBuf,
+    db2_path: PathBuf,
+    table: String,
+    json: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let env1 = akula::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
+        mdbx::Environment::new(),
+        &db1_path,
+        Default::default(),
+    )?;
+    let env2 = akula::kv::mdbx::MdbxEnvironment::<mdbx::NoWriteMap>::open_ro(
+        mdbx::Environment::new(),
+        &db2_path,
+        Default::default(),
+    )?;
+
+    let dup_sort = CHAINDATA_TABLES
+        .get(table.as_str())
+        .ok_or_else(|| format_err!("Unknown table {}", table))?
+        .dup_sort;
+    let diff = diff_tables(&env1.begin()?, &env2.begin()?, &table, dup_sort)?;
+
+    println!("Only in db1: {} entries", diff.only_in_left.len());
+    println!("Only in db2: {} entries", diff.only_in_right.len());
+    println!("Differing values: {} entries", diff.differing_values.len());
+
+    if let Some(path) = json {
+        let json = serde_json::json!({
+            "only_in_left": diff
+                .only_in_left
+                .iter()
+                .map(|(k, v)| [hex::encode(k), hex::encode(v)])
+                .collect::<Vec<_>>(),
+            "only_in_right": diff
+                .only_in_right
+                .iter()
+                .map(|(k, v)| [hex::encode(k), hex::encode(v)])
+                .collect::<Vec<_>>(),
+            "differing_values": diff
+                .differing_values
+                .iter()
+                .map(|(k, v1, v2)| [hex::encode(k), hex::encode(v1), hex::encode(v2)])
+                .collect::<Vec<_>>(),
+        });
+        std::fs::write(&path, serde_json::to_string_pretty(&json)?)
+            .with_context(|| format!("failed to write diff to {}", path.display()))?;
+        info!("Full diff written to {}", path.display());
+    }
+
+    Ok(())
+}
+
 fn read_block(data_dir: AkulaDataDir, block_num: BlockNumber) -> anyhow::Result<()> {
     let env = open_db(data_dir)?;
 
@@ -360,7 +724,12 @@ fn read_block(data_dir: AkulaDataDir, block_num: BlockNumber) -> anyhow::Result<
 
     let partial_header = PartialHeader::from(header.clone());
 
-    let block = Block::new(partial_header.clone(), body.transactions, body.ommers);
+    let block = Block::new(
+        partial_header.clone(),
+        body.transactions,
+        body.ommers,
+        body.withdrawals,
+    );
 
     ensure!(
         block.header.transactions_root == header.transactions_root,
@@ -484,15 +853,36 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     match opt.command {
-        OptCommand::DbStats { csv } => table_sizes(opt.data_dir, csv)?,
+        OptCommand::DbStats { csv } => table_sizes(opt.data_dir, csv).await?,
         OptCommand::Blockhashes => blockhashes(opt.data_dir).await?,
+        OptCommand::RebuildIndex { index, verify } => {
+            rebuild_index(opt.data_dir, index, verify).await?
+        }
         OptCommand::DbQuery { table, key } => db_query(opt.data_dir, table, key)?,
         OptCommand::DbWalk {
             table,
             starting_key,
             max_entries,
-        } => db_walk(opt.data_dir, table, starting_key, max_entries)?,
+            key_filter,
+            value_filter,
+            count_only,
+        } => db_walk(
+            opt.data_dir,
+            table,
+            starting_key,
+            max_entries,
+            key_filter,
+            value_filter,
+            count_only,
+        )?,
         OptCommand::CheckEqual { db1, db2, table } => check_table_eq(db1, db2, table)?,
+        OptCommand::DbDiff {
+            db1,
+            db2,
+            table,
+            json,
+        } => db_diff(db1, db2, table, json)?,
+        OptCommand::VerifyReceipts { from, to } => verify_receipts(opt.data_dir, from, to)?,
         OptCommand::HeaderDownload { opts } => header_download(opt.data_dir, opts).await?,
         OptCommand::ReadBlock { block_number } => read_block(opt.data_dir, block_number)?,
         OptCommand::ReadAccount { address } => read_account(opt.data_dir, address)?,
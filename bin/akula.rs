@@ -1,6 +1,7 @@
 use akula::{
     binutil::AkulaDataDir,
     downloader::sentry_status_provider::SentryStatusProvider,
+    engine_api,
     kv::{
         mdbx::*,
         tables::{self, ErasedTable},
@@ -11,18 +12,23 @@ use akula::{
         sentry_client_connector::SentryClientConnectorImpl,
         sentry_client_reactor::SentryClientReactor,
     },
-    stagedsync::{self, stage::*, stages::*},
+    stagedsync::{self, metrics, stage::*, stages::*},
     stages::*,
     version_string, StageId,
 };
-use anyhow::{bail, format_err, Context};
+use anyhow::{bail, ensure, format_err, Context};
 use async_trait::async_trait;
 use clap::Parser;
 use rayon::prelude::*;
 use std::{
+    collections::VecDeque,
+    net::{Ipv4Addr, SocketAddr},
     panic,
     path::PathBuf,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::{Duration, Instant},
 };
 use tokio::pin;
@@ -95,6 +101,18 @@ pub struct Opt {
     /// Delay applied at the terminating stage.
     #[clap(long, default_value = "2000")]
     pub delay_after_sync: u64,
+
+    /// Stages to bypass, keeping their progress. For debugging only, never use in production.
+    #[clap(long = "skip-stage")]
+    pub skip_stages: Vec<StageId>,
+
+    /// Serve Prometheus metrics at `/metrics` on this port of localhost.
+    #[clap(long)]
+    pub metrics_port: Option<u16>,
+
+    /// Engine API options. Headers come from the consensus layer instead of the downloader when a JWT secret is given.
+    #[clap(flatten)]
+    pub engine_opts: akula::engine_api::opts::Opts,
 }
 
 #[derive(Debug)]
@@ -193,6 +211,7 @@ where
         Ok(ExecOutput::Progress {
             stage_progress: highest_block,
             done: true,
+            must_commit: false,
         })
     }
 
@@ -245,6 +264,49 @@ where
 {
     db: Arc<MdbxEnvironment<SE>>,
     commit_after: Duration,
+    /// Transactions read from Erigon per batch.
+    buffering_factor: usize,
+    /// Block the conversion is expected to end at, used to estimate the time left.
+    expected_total_blocks: Option<BlockNumber>,
+}
+
+/// Span of recent progress the conversion speed is measured over.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Blocks per second over the progress samples in the window.
+fn blocks_per_second(window: &VecDeque<(Instant, BlockNumber)>) -> Option<f64> {
+    let (first_at, first_block) = window.front()?;
+    let (last_at, last_block) = window.back()?;
+    let elapsed = (*last_at - *first_at).as_secs_f64();
+
+    if elapsed > 0_f64 && last_block > first_block {
+        Some((last_block.0 - first_block.0) as f64 / elapsed)
+    } else {
+        None
+    }
+}
+
+const DEFAULT_BUFFERING_FACTOR: usize = 500_000;
+const BUFFERING_FACTOR_ENV: &str = "AKULA_BUFFER_FACTOR";
+
+/// Read conversion buffering factor from `AKULA_BUFFER_FACTOR`, falling back to the default if unset.
+fn buffering_factor_from_env() -> anyhow::Result<usize> {
+    let buffering_factor = match std::env::var(BUFFERING_FACTOR_ENV) {
+        Ok(v) => v
+            .parse::<usize>()
+            .with_context(|| format!("failed to parse {}: {}", BUFFERING_FACTOR_ENV, v))?,
+        Err(std::env::VarError::NotPresent) => return Ok(DEFAULT_BUFFERING_FACTOR),
+        Err(e) => return Err(e).context(BUFFERING_FACTOR_ENV),
+    };
+
+    ensure!(
+        (1_000..=10_000_000).contains(&buffering_factor),
+        "{} must be between 1000 and 10000000, got {}",
+        BUFFERING_FACTOR_ENV,
+        buffering_factor
+    );
+
+    Ok(buffering_factor)
 }
 
 #[async_trait]
@@ -268,8 +330,9 @@ where
         let original_highest_block = input.stage_progress.unwrap_or(BlockNumber(0));
         let mut highest_block = original_highest_block;
 
-        const MAX_TXS_PER_BATCH: usize = 500_000;
-        const BUFFERING_FACTOR: usize = 100_000;
+        if !input.restarted {
+            info!("Using buffering factor {}", self.buffering_factor);
+        }
         let erigon_tx = self.db.begin()?;
 
         if erigon_tx.get(tables::CanonicalHeader, highest_block)?
@@ -299,19 +362,20 @@ where
             )?
             .unwrap();
 
-        let mut starting_index = prev_body.base_tx_id + prev_body.tx_amount as u64;
+        let mut starting_index = prev_body.tx_id_range().end;
         let canonical_header_walker = canonical_header_cur.walk(Some(highest_block + 1));
         pin!(canonical_header_walker);
         let erigon_body_walker =
             erigon_body_cur.walk(Some(TableEncode::encode(highest_block + 1).to_vec()));
         pin!(erigon_body_walker);
-        let mut batch = Vec::with_capacity(BUFFERING_FACTOR);
+        let mut batch = Vec::new();
         let mut converted = Vec::new();
 
         let mut extracted_blocks_num = 0;
         let mut extracted_txs_num = 0;
         let started_at = Instant::now();
         let mut last_check = started_at;
+        let mut window = VecDeque::from([(started_at, highest_block)]);
 
         let done = loop {
             let mut no_more_bodies = true;
@@ -361,7 +425,7 @@ where
                     }
                 }
 
-                if accum_txs > MAX_TXS_PER_BATCH {
+                if accum_txs > self.buffering_factor {
                     no_more_bodies = false;
                     break;
                 }
@@ -384,9 +448,10 @@ where
                         block_number,
                         block_hash,
                         body.uncles,
+                        body.withdrawals,
                         txs.into_iter()
                             .map(|v| {
-                                Ok(rlp::decode::<akula::models::MessageWithSignature>(&v)?
+                                Ok(MessageWithSignature::decode_standalone(&v)?
                                     .encode()
                                     .to_vec())
                             })
@@ -396,12 +461,13 @@ where
                 .collect_into_vec(&mut converted);
 
             for res in converted.drain(..) {
-                let (block_num, block_hash, uncles, txs) = res?;
+                let (block_num, block_hash, uncles, withdrawals, txs) = res?;
                 highest_block = block_num;
                 let body = BodyForStorage {
                     base_tx_id: starting_index,
                     tx_amount: txs.len().try_into()?,
                     uncles,
+                    withdrawals,
                 };
 
                 body_cur.append((block_num, block_hash), body)?;
@@ -421,15 +487,38 @@ where
             }
 
             let now = Instant::now();
+            window.push_back((now, highest_block));
+            while window.len() > 2 && now - window[1].0 >= THROUGHPUT_WINDOW {
+                window.pop_front();
+            }
+
             let elapsed = now - last_check;
             if elapsed > Duration::from_secs(30) {
+                let eta = self
+                    .expected_total_blocks
+                    .zip(blocks_per_second(&window))
+                    .map(|(total_blocks, blocks_per_second)| {
+                        let remaining_blocks = total_blocks.0.saturating_sub(highest_block.0);
+                        format!(
+                            ", ETA {}",
+                            stagedsync::format_duration(
+                                Duration::from_secs_f64(
+                                    remaining_blocks as f64 / blocks_per_second
+                                ),
+                                false
+                            )
+                        )
+                    })
+                    .unwrap_or_default();
+
                 info!(
-                    "Highest block {}, batch size: {} blocks with {} transactions, {} tx/sec",
+                    "Highest block {}, batch size: {} blocks with {} transactions, {} tx/sec{}",
                     highest_block.0,
                     extracted_blocks_num,
                     extracted_txs_num,
                     extracted_txs_num as f64
-                        / (elapsed.as_secs() as f64 + (elapsed.subsec_millis() as f64 / 1000_f64))
+                        / (elapsed.as_secs() as f64 + (elapsed.subsec_millis() as f64 / 1000_f64)),
+                    eta
                 );
 
                 if now - started_at > self.commit_after {
@@ -445,6 +534,7 @@ where
         Ok(ExecOutput::Progress {
             stage_progress: highest_block,
             done,
+            must_commit: false,
         })
     }
     async fn unwind<'tx>(
@@ -464,14 +554,11 @@ where
 
             block_body_cur.delete_current()?;
 
-            let mut deleted = 0;
-            while deleted < body.tx_amount {
-                let to_delete = body.base_tx_id + deleted;
-                if block_tx_cur.seek_exact(to_delete)?.is_some() {
+            let tx_ids = body.tx_id_range();
+            for to_delete in *tx_ids.start..*tx_ids.end {
+                if block_tx_cur.seek_exact(TxIndex(to_delete))?.is_some() {
                     block_tx_cur.delete_current()?;
                 }
-
-                deleted += 1;
             }
         }
 
@@ -508,6 +595,7 @@ where
         Ok(ExecOutput::Progress {
             stage_progress: prev_stage,
             done: true,
+            must_commit: false,
         })
     }
     async fn unwind<'tx>(
@@ -524,6 +612,40 @@ where
     }
 }
 
+async fn shutdown_signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            res = tokio::signal::ctrl_c() => res?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
+
+/// Set `shutdown` on SIGINT or SIGTERM. A second signal exits the process without waiting for the sync.
+async fn listen_for_shutdown(shutdown: Arc<AtomicBool>) {
+    loop {
+        if let Err(e) = shutdown_signal().await {
+            warn!("Failed to listen for shutdown signals: {}", e);
+            return;
+        }
+
+        if shutdown.swap(true, Ordering::SeqCst) {
+            warn!("Shutdown requested again, exiting immediately");
+            std::process::exit(1);
+        }
+
+        info!("Shutdown requested, exiting once the current batch is committed");
+    }
+}
+
 #[allow(unreachable_code)]
 fn main() -> anyhow::Result<()> {
     let opt: Opt = Opt::parse();
@@ -563,6 +685,10 @@ fn main() -> anyhow::Result<()> {
 
                 let chains_config = akula::sentry_connector::chain_config::ChainsConfig::new()?;
                 let chain_config = chains_config.get(&opt.chain_name)?;
+                info!(
+                    "Network: {}",
+                    ChainSpec::chain_name(chain_config.chain_spec().params.chain_id.0)
+                );
 
                 // database setup
                 let erigon_db = if let Some(erigon_data_dir) = opt.erigon_data_dir {
@@ -586,19 +712,26 @@ fn main() -> anyhow::Result<()> {
                     tempfile::tempdir_in(&etl_temp_path)
                         .context("failed to create ETL temp dir")?,
                 );
-                let db = akula::kv::new_database(&akula_chain_data_dir)?;
-                {
-                    let span = span!(Level::INFO, "", " Genesis initialization ");
-                    let _g = span.enter();
-                    let txn = db.begin_mutable()?;
-                    if akula::genesis::initialize_genesis(
-                        &txn,
-                        &*etl_temp_dir,
-                        chain_config.chain_spec().clone(),
-                    )? {
-                        txn.commit()?;
-                    }
-                }
+                let db = Arc::new(akula::kv::new_database(&akula_chain_data_dir)?);
+
+                let engine_headers = if opt.engine_opts.jwt_secret_file.is_some() {
+                    let secret = opt.engine_opts.jwt_secret()?;
+                    let listen_address = opt.engine_opts.listen_address;
+                    let engine_headers = engine_api::EngineHeaders::new(chain_config.chain_spec());
+                    let api = engine_api::EngineApiServerImpl::new(
+                        engine_api::EngineNewPayload::new(db.clone(), &engine_headers),
+                    );
+                    tokio::spawn(async move {
+                        if let Err(e) =
+                            engine_api::run_engine_api_server(listen_address, secret, api).await
+                        {
+                            error!("Engine API server failed: {}", e);
+                        }
+                    });
+                    Some(engine_headers)
+                } else {
+                    None
+                };
 
                 let sentry_status_provider = SentryStatusProvider::new(chain_config.clone());
                 // staged sync setup
@@ -607,12 +740,45 @@ fn main() -> anyhow::Result<()> {
                 staged_sync.set_max_block(opt.max_block);
                 staged_sync.set_exit_after_sync(opt.exit_after_sync);
                 staged_sync.set_delay_after_sync(Some(Duration::from_millis(opt.delay_after_sync)));
+                if let Some(metrics_port) = opt.metrics_port {
+                    let registry = metrics::Registry::install()?;
+                    tokio::spawn(async move {
+                        let listen_address = SocketAddr::from((Ipv4Addr::LOCALHOST, metrics_port));
+                        if let Err(e) = metrics::run_metrics_server(listen_address, registry).await
+                        {
+                            error!("Metrics endpoint failed: {}", e);
+                        }
+                    });
+                }
+                staged_sync.push(InitializeGenesis {
+                    chain_spec: chain_config.chain_spec().clone(),
+                    temp_dir: etl_temp_dir.clone(),
+                });
                 if let Some(erigon_db) = erigon_db.clone() {
                     staged_sync.push(ConvertHeaders {
                         db: erigon_db,
                         max_block: opt.max_block,
                         exit_after_progress: opt.increment,
                     });
+                } else if let Some(engine_headers) = engine_headers {
+                    // sentry setup, to fetch the blocks the consensus layer does not send
+                    let mut sentry_reactor = SentryClientReactor::new(
+                        Box::new(SentryClientConnectorImpl::new(opt.sentry_api_addr.clone())),
+                        sentry_status_provider.current_status_stream(),
+                    );
+                    sentry_reactor.start()?;
+
+                    let backfill = engine_api::EngineBackfill::new(
+                        sentry_reactor.into_shared(),
+                        &engine_headers,
+                    );
+                    tokio::spawn(async move {
+                        if let Err(e) = backfill.run().await {
+                            error!("Engine API backfill failed: {}", e);
+                        }
+                    });
+
+                    staged_sync.push(engine_headers);
                 } else {
                     // sentry setup
                     let mut sentry_reactor = SentryClientReactor::new(
@@ -634,9 +800,19 @@ fn main() -> anyhow::Result<()> {
                     temp_dir: etl_temp_dir.clone(),
                 });
                 if let Some(erigon_db) = erigon_db {
+                    let expected_total_blocks = match opt.max_block {
+                        Some(max_block) => Some(max_block),
+                        None => erigon_db
+                            .begin()?
+                            .cursor(tables::CanonicalHeader)?
+                            .last()?
+                            .map(|(block_number, _)| block_number),
+                    };
                     staged_sync.push(ConvertBodies {
                         db: erigon_db,
                         commit_after: Duration::from_secs(120),
+                        buffering_factor: buffering_factor_from_env()?,
+                        expected_total_blocks,
                     });
                 } else {
                     // also add body download stage here
@@ -644,6 +820,7 @@ fn main() -> anyhow::Result<()> {
                 staged_sync.push(TotalTxIndex);
                 staged_sync.push(SenderRecovery {
                     batch_size: opt.sender_recovery_batch_size.try_into().unwrap(),
+                    shutdown: staged_sync.shutdown_handle(),
                 });
                 staged_sync.push(Execution {
                     batch_size: opt.execution_batch_size.saturating_mul(1_000_000_000_u64),
@@ -654,9 +831,14 @@ fn main() -> anyhow::Result<()> {
                     batch_until: None,
                     commit_every: None,
                     prune_from: BlockNumber(0),
+                    shutdown: staged_sync.shutdown_handle(),
                 });
                 if !opt.skip_commitment {
-                    staged_sync.push(HashState::new(etl_temp_dir.clone(), None));
+                    staged_sync.push(HashState::new(
+                        etl_temp_dir.clone(),
+                        None,
+                        staged_sync.shutdown_handle(),
+                    ));
                     staged_sync.push(Interhashes::new(etl_temp_dir.clone(), None));
                 }
                 staged_sync.push(CallTraceIndex {
@@ -664,9 +846,14 @@ fn main() -> anyhow::Result<()> {
                     flush_interval: 50_000,
                 });
                 staged_sync.push(FinishStage);
+                for &stage_id in &opt.skip_stages {
+                    staged_sync.skip_stage(stage_id)?;
+                }
+
+                tokio::spawn(listen_for_shutdown(staged_sync.shutdown_handle()));
 
                 info!("Running staged sync");
-                staged_sync.run(&db).await?;
+                staged_sync.run(&**db).await?;
 
                 Ok(())
             })
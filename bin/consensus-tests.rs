@@ -6,6 +6,7 @@ use akula::{
         *,
     },
     crypto::keccak256,
+    execution::{analysis_cache::AnalysisCache, processor::ExecutionProcessor, tracer::NoopTracer},
     models::*,
     res::chainspec::*,
     *,
@@ -36,6 +37,7 @@ pub static DIFFICULTY_DIR: Lazy<PathBuf> = Lazy::new(|| Path::new("DifficultyTes
 pub static BLOCKCHAIN_DIR: Lazy<PathBuf> = Lazy::new(|| Path::new("BlockchainTests").to_path_buf());
 pub static TRANSACTION_DIR: Lazy<PathBuf> =
     Lazy::new(|| Path::new("TransactionTests").to_path_buf());
+pub static STATE_DIR: Lazy<PathBuf> = Lazy::new(|| Path::new("GeneralStateTests").to_path_buf());
 
 pub static IGNORED_TX_EXCEPTIONS: Lazy<HashSet<String>> = Lazy::new(|| {
     hashset! {
@@ -57,6 +59,8 @@ pub static EXCLUDED_TESTS: Lazy<Vec<PathBuf>> = Lazy::new(|| {
             .join("GeneralStateTests")
             .join("VMTests")
             .join("vmPerformance"),
+        STATE_DIR.join("stTimeConsuming"),
+        STATE_DIR.join("VMTests").join("vmPerformance"),
         // We do not have extra data check
         BLOCKCHAIN_DIR
             .join("TransitionTests")
@@ -297,6 +301,8 @@ static NETWORK_CONFIG: Lazy<HashMap<Network, ChainSpec>> = Lazy::new(|| {
                 istanbul: Some(0.into()),
                 berlin: Some(0.into()),
                 london: Some(0.into()),
+                shanghai: None,
+                cancun: None,
             },
             None,
             9700000,
@@ -367,6 +373,8 @@ static NETWORK_CONFIG: Lazy<HashMap<Network, ChainSpec>> = Lazy::new(|| {
                 istanbul: Some(0.into()),
                 berlin: Some(0.into()),
                 london: Some(5.into()),
+                shanghai: None,
+                cancun: None,
             },
             None,
             9700000,
@@ -398,6 +406,8 @@ static NETWORK_CONFIG: Lazy<HashMap<Network, ChainSpec>> = Lazy::new(|| {
                 istanbul: Some(0.into()),
                 berlin: Some(0.into()),
                 london: Some(0.into()),
+                shanghai: None,
+                cancun: None,
             },
             None,
             10700000,
@@ -772,6 +782,191 @@ fn transaction_test(testdata: TransactionTest) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateEnv {
+    pub current_coinbase: Address,
+    pub current_difficulty: U256,
+    #[serde(deserialize_with = "deserialize_hexstr_as_u64")]
+    pub current_gas_limit: u64,
+    #[serde(deserialize_with = "deserialize_str_as_blocknumber")]
+    pub current_number: BlockNumber,
+    #[serde(deserialize_with = "deserialize_hexstr_as_u64")]
+    pub current_timestamp: u64,
+    #[serde(default)]
+    pub current_base_fee: Option<U256>,
+    pub previous_hash: H256,
+}
+
+#[derive(Deserialize, Educe)]
+#[educe(Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StatePostEntry {
+    pub hash: H256,
+    pub logs: H256,
+    #[serde(with = "hexbytes")]
+    #[educe(Debug(method = "write_hex_string"))]
+    pub txbytes: Bytes,
+    #[serde(default)]
+    pub expect_exception: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StateTest {
+    pub env: StateEnv,
+    pub pre: HashMap<Address, AccountState>,
+    pub post: HashMap<String, Vec<StatePostEntry>>,
+}
+
+/// Engine for state tests, where the miner is not rewarded.
+#[derive(Debug)]
+struct NoRewardEngine;
+
+impl Consensus for NoRewardEngine {
+    fn pre_validate_block(&self, _: &Block, _: &mut dyn State) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn validate_block_header(
+        &self,
+        _: &BlockHeader,
+        _: &mut dyn State,
+        _: bool,
+    ) -> Result<(), DuoError> {
+        Ok(())
+    }
+
+    fn validate_seal(&self, _: &BlockHeader) -> Result<(), DuoError> {
+        Ok(())
+    }
+
+    fn finalize(
+        &self,
+        _: &PartialHeader,
+        _: &[BlockHeader],
+        _: Revision,
+    ) -> anyhow::Result<Vec<FinalizationChange>> {
+        Ok(vec![])
+    }
+
+    fn get_beneficiary(&self, header: &BlockHeader) -> anyhow::Result<Address> {
+        Ok(header.beneficiary)
+    }
+}
+
+/// Applies the transaction on top of the state, returning its logs.
+fn apply_state_transaction(
+    state: &mut InMemoryState,
+    config: &ChainSpec,
+    env: &StateEnv,
+    txbytes: &[u8],
+) -> anyhow::Result<Vec<Log>> {
+    let txn = rlp::decode::<MessageWithSignature>(txbytes)?;
+    pre_validate_transaction(&txn, config.params.chain_id, env.current_base_fee)?;
+    let txn = MessageWithSender {
+        sender: txn.recover_sender()?,
+        message: txn.message,
+    };
+
+    let header = PartialHeader {
+        parent_hash: env.previous_hash,
+        beneficiary: env.current_coinbase,
+        state_root: H256::zero(),
+        receipts_root: H256::zero(),
+        logs_bloom: Bloom::zero(),
+        difficulty: env.current_difficulty,
+        number: env.current_number,
+        gas_limit: env.current_gas_limit,
+        gas_used: 0,
+        timestamp: env.current_timestamp,
+        extra_data: Bytes::new(),
+        mix_hash: H256::zero(),
+        nonce: H64::zero(),
+        base_fee_per_gas: env.current_base_fee,
+        withdrawals_root: None,
+        blob_gas_used: None,
+        excess_blob_gas: None,
+        parent_beacon_block_root: None,
+    };
+    let body = BlockBodyWithSenders {
+        transactions: vec![txn],
+        ommers: vec![],
+        withdrawals: None,
+    };
+    let block_spec = config.collect_header_spec(&header);
+
+    let mut analysis_cache = AnalysisCache::default();
+    let mut engine = NoRewardEngine;
+    let mut tracer = NoopTracer;
+    let mut processor = ExecutionProcessor::new(
+        state,
+        &mut tracer,
+        &mut analysis_cache,
+        &mut engine,
+        &header,
+        &body,
+        &block_spec,
+    );
+    let receipts = processor.execute_block_no_post_validation()?;
+    processor.into_state().write_to_db(header.number)?;
+
+    Ok(receipts
+        .into_iter()
+        .flat_map(|receipt| receipt.logs)
+        .collect())
+}
+
+/// https://ethereum-tests.readthedocs.io/en/latest/test_types/state_tests.html
+#[instrument(skip(testdata))]
+fn state_test(testdata: StateTest) -> anyhow::Result<()> {
+    for (network, entries) in &testdata.post {
+        // Skip forks we do not support yet.
+        let Ok(network) = network.parse::<Network>() else { continue };
+        let config = &NETWORK_CONFIG[&network];
+
+        for (i, entry) in entries.iter().enumerate() {
+            let mut state = InMemoryState::default();
+            init_pre_state(&testdata.pre, &mut state);
+
+            match (
+                apply_state_transaction(&mut state, config, &testdata.env, &entry.txbytes),
+                &entry.expect_exception,
+            ) {
+                (Ok(logs), None) => {
+                    let state_root = state.state_root_hash();
+                    ensure!(
+                        state_root == entry.hash,
+                        "State root mismatch for {:?} #{}: {} != {}",
+                        network,
+                        i,
+                        state_root,
+                        entry.hash
+                    );
+
+                    let logs_hash = keccak256(rlp::encode_list::<Log, _>(&logs));
+                    ensure!(
+                        logs_hash == entry.logs,
+                        "Logs hash mismatch for {:?} #{}: {} != {}",
+                        network,
+                        i,
+                        logs_hash,
+                        entry.logs
+                    );
+                }
+                (Err(e), None) => {
+                    return Err(e.context(format!("Unexpected error for {:?} #{}", network, i)));
+                }
+                (Ok(_), Some(exception)) => {
+                    bail!("Expected exception {} for {:?} #{}", exception, network, i);
+                }
+                (Err(_), Some(_)) => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
 type NetworkDifficultyTests = HashMap<String, DifficultyTest>;
 
 #[instrument(skip(testdata))]
@@ -993,6 +1188,28 @@ async fn run() {
         }
     }
 
+    for entry in walkdir::WalkDir::new(root_dir.join(&*STATE_DIR))
+        .into_iter()
+        .filter_entry(|e| {
+            if exclude_test(e.path(), &root_dir) {
+                skipped += 1;
+                return false;
+            }
+
+            true
+        })
+    {
+        let e = entry.unwrap();
+
+        if e.file_type().is_file() {
+            let p = e.into_path();
+            let test_names = Arc::clone(&test_names);
+            tasks.push(tokio::spawn(async move {
+                run_test_file(p.as_path(), &test_names, state_test)
+            }));
+        }
+    }
+
     for task in tasks {
         res += task.await.unwrap();
     }
@@ -160,6 +160,7 @@ pub mod block_body {
                 BlockBody {
                     transactions,
                     ommers: body.uncles,
+                    withdrawals: body.withdrawals,
                 },
                 body.base_tx_id,
             )));
@@ -196,6 +197,7 @@ pub mod block_body {
                     })
                     .collect(),
                 ommers: body.ommers,
+                withdrawals: body.withdrawals,
             }));
         }
 
@@ -252,7 +254,7 @@ pub mod tl {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::kv::new_mem_database;
+    use crate::{crypto::TrieEncode, kv::new_mem_database};
     use bytes::Bytes;
 
     #[test]
@@ -294,6 +296,7 @@ mod tests {
             base_tx_id: 1.into(),
             tx_amount: 2,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let db = new_mem_database().unwrap();
@@ -321,4 +324,73 @@ mod tests {
         assert_eq!(txs, *recovered_txs);
         assert_eq!(senders, *recovered_senders);
     }
+
+    #[test]
+    fn typed_transactions_roundtrip() {
+        let signature =
+            MessageSignature::new(true, H256::repeat_byte(2), H256::repeat_byte(3)).unwrap();
+        let access_list = vec![AccessListItem {
+            address: Address::repeat_byte(0xaa),
+            slots: vec![H256::repeat_byte(1)],
+        }];
+        let txs = [
+            MessageWithSignature {
+                message: Message::Legacy {
+                    chain_id: Some(ChainId(1)),
+                    nonce: 1,
+                    gas_price: 20_000.as_u256(),
+                    gas_limit: 21_000,
+                    action: TransactionAction::Call(Address::repeat_byte(0xbb)),
+                    value: 1.as_u256(),
+                    input: Bytes::new(),
+                },
+                signature: signature.clone(),
+            },
+            MessageWithSignature {
+                message: Message::EIP2930 {
+                    chain_id: ChainId(1),
+                    nonce: 2,
+                    gas_price: 20_000.as_u256(),
+                    gas_limit: 30_000,
+                    action: TransactionAction::Create,
+                    value: 0.as_u256(),
+                    input: Bytes::from_static(&[0x60, 0x00]),
+                    access_list: access_list.clone(),
+                },
+                signature: signature.clone(),
+            },
+            MessageWithSignature {
+                message: Message::EIP1559 {
+                    chain_id: ChainId(1),
+                    nonce: 3,
+                    max_priority_fee_per_gas: 1_000.as_u256(),
+                    max_fee_per_gas: 30_000.as_u256(),
+                    gas_limit: 50_000,
+                    action: TransactionAction::Call(Address::repeat_byte(0xcc)),
+                    value: 5.as_u256(),
+                    input: Bytes::new(),
+                    access_list,
+                },
+                signature,
+            },
+        ];
+
+        let db = new_mem_database().unwrap();
+        let rwtx = db.begin_mutable().unwrap();
+
+        for (i, original) in txs.iter().enumerate() {
+            let envelope = original.trie_encode();
+            let wrapped = rlp::encode(original);
+
+            for encoded in [&envelope[..], &wrapped[..]] {
+                let decoded = MessageWithSignature::decode_standalone(encoded).unwrap();
+                tx::write(&rwtx, i as u64, &[decoded]).unwrap();
+
+                let stored = tx::read(&rwtx, i as u64, 1).unwrap().remove(0);
+                assert_eq!(&stored, original);
+                assert_eq!(stored.trie_encode(), envelope);
+                assert_eq!(rlp::encode(&stored), wrapped);
+            }
+        }
+    }
 }
@@ -0,0 +1,267 @@
+use crate::{
+    accessors::chain,
+    execution::replay_block,
+    kv::{mdbx::*, tables},
+    models::*,
+    Buffer,
+};
+use anyhow::{bail, format_err};
+
+/// Maximum number of blocks that can be requested at once.
+pub const MAX_BLOCK_COUNT: u64 = 1024;
+
+/// Fee history as returned by `eth_feeHistory`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeeHistory {
+    pub oldest_block: BlockNumber,
+    /// Base fees of the requested blocks, followed by the base fee of the block after the newest one.
+    pub base_fee_per_gas: Vec<U256>,
+    pub gas_used_ratio: Vec<f64>,
+    /// Effective priority fees at the requested percentiles of gas used, for each block.
+    pub reward: Option<Vec<Vec<U256>>>,
+}
+
+/// Effective priority fees at `percentiles` of the block's gas, weighted by gas used of each transaction.
+fn rewards_at_percentiles(
+    block_gas_used: u64,
+    mut transactions: Vec<(U256, u64)>,
+    percentiles: &[f64],
+) -> Vec<U256> {
+    if transactions.is_empty() {
+        return vec![U256::ZERO; percentiles.len()];
+    }
+
+    transactions.sort_by_key(|&(reward, _)| reward);
+
+    let mut idx = 0;
+    let mut sum_gas_used = transactions[0].1;
+    percentiles
+        .iter()
+        .map(|p| {
+            let threshold = (block_gas_used as f64 * p / 100.0) as u64;
+            while sum_gas_used < threshold && idx < transactions.len() - 1 {
+                idx += 1;
+                sum_gas_used += transactions[idx].1;
+            }
+            transactions[idx].0
+        })
+        .collect()
+}
+
+/// Reads fee history for `block_count` blocks up to and including `newest_block`.
+///
+/// Rewards require replaying the blocks, so they are only calculated if `reward_percentiles` is not empty.
+pub fn read<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    block_count: u64,
+    newest_block: BlockNumber,
+    reward_percentiles: &[f64],
+) -> anyhow::Result<FeeHistory> {
+    if reward_percentiles
+        .iter()
+        .any(|p| !(0.0..=100.0).contains(p))
+        || reward_percentiles.windows(2).any(|w| w[0] > w[1])
+    {
+        bail!("Reward percentiles must be increasing values between 0 and 100");
+    }
+
+    let block_count = block_count.min(MAX_BLOCK_COUNT).min(newest_block.0 + 1);
+    if block_count == 0 {
+        return Ok(FeeHistory {
+            oldest_block: newest_block,
+            ..Default::default()
+        });
+    }
+
+    let chain_spec = tx
+        .get(tables::Config, Default::default())?
+        .ok_or_else(|| format_err!("No chain specification set"))?;
+
+    let oldest_block = BlockNumber(newest_block.0 + 1 - block_count);
+
+    let mut history = FeeHistory {
+        oldest_block,
+        reward: if reward_percentiles.is_empty() {
+            None
+        } else {
+            Some(vec![])
+        },
+        ..Default::default()
+    };
+
+    let mut newest_header = None;
+    for block_number in oldest_block..=newest_block {
+        let hash = tx
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("No canonical block {}", block_number))?;
+        let header = tx
+            .get(tables::Header, (block_number, hash))?
+            .ok_or_else(|| format_err!("No header for block {}", block_number))?;
+
+        let base_fee_per_gas = header.base_fee_per_gas.unwrap_or(U256::ZERO);
+        history.base_fee_per_gas.push(base_fee_per_gas);
+        history.gas_used_ratio.push(if header.gas_limit > 0 {
+            header.gas_used as f64 / header.gas_limit as f64
+        } else {
+            0.0
+        });
+
+        if let Some(reward) = &mut history.reward {
+            let body = chain::block_body::read_with_senders(tx, hash, block_number)?
+                .ok_or_else(|| format_err!("No body for block {}", block_number))?;
+
+            let transactions = if body.transactions.is_empty() {
+                vec![]
+            } else {
+                // State at the end of the previous block
+                let mut state = Buffer::new(
+                    tx,
+                    BlockNumber(0),
+                    Some(BlockNumber(block_number.0.saturating_sub(1))),
+                );
+                let receipts =
+                    replay_block(&mut state, &chain_spec, &header.clone().into(), &body)?;
+
+                let mut last_cumulative_gas_used = 0;
+                body.transactions
+                    .iter()
+                    .zip(receipts)
+                    .map(|(txn, receipt)| {
+                        let gas_used = receipt.cumulative_gas_used - last_cumulative_gas_used;
+                        last_cumulative_gas_used = receipt.cumulative_gas_used;
+                        (txn.priority_fee_per_gas(base_fee_per_gas), gas_used)
+                    })
+                    .collect()
+            };
+
+            reward.push(rewards_at_percentiles(
+                header.gas_used,
+                transactions,
+                reward_percentiles,
+            ));
+        }
+
+        newest_header = Some(header);
+    }
+
+    history.base_fee_per_gas.push(
+        chain_spec
+            .base_fee_per_gas(newest_block + 1, newest_header.as_ref())
+            .unwrap_or(U256::ZERO),
+    );
+
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        chain::protocol_param::param, consensus::next_base_fee_per_gas, kv::new_mem_database,
+        res::chainspec::MAINNET,
+    };
+
+    #[test]
+    fn rewards_weighted_by_gas_used() {
+        assert_eq!(
+            rewards_at_percentiles(
+                100_000,
+                vec![
+                    (10.as_u256(), 21_000),
+                    (1.as_u256(), 50_000),
+                    (5.as_u256(), 29_000)
+                ],
+                &[0.0, 25.0, 50.0, 75.0, 100.0]
+            ),
+            vec![
+                1.as_u256(),
+                1.as_u256(),
+                1.as_u256(),
+                5.as_u256(),
+                10.as_u256()
+            ]
+        );
+        assert_eq!(rewards_at_percentiles(0, vec![], &[50.0]), vec![U256::ZERO]);
+    }
+
+    #[test]
+    fn fee_history_of_synthetic_blocks() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.london = Some(BlockNumber(0));
+        tx.set(tables::Config, Default::default(), chain_spec.clone())
+            .unwrap();
+
+        let gas_limit = 30_000_000;
+        // Full, half full, empty
+        let gas_used = [30_000_000, 15_000_000, 0];
+        let mut base_fee_per_gas = 1_000_000_000.as_u256();
+        let mut expected_base_fees = vec![];
+        for (i, &gas_used) in gas_used.iter().enumerate() {
+            let header = BlockHeader {
+                number: BlockNumber(i as u64),
+                gas_limit,
+                gas_used,
+                base_fee_per_gas: Some(base_fee_per_gas),
+                ..BlockHeader::empty()
+            };
+            expected_base_fees.push(base_fee_per_gas);
+            base_fee_per_gas = next_base_fee_per_gas(&header).unwrap();
+
+            let hash = header.hash();
+            tx.set(tables::CanonicalHeader, header.number, hash)
+                .unwrap();
+            tx.set(tables::Header, (header.number, hash), header)
+                .unwrap();
+        }
+        expected_base_fees.push(base_fee_per_gas);
+
+        // Full block raises the base fee by 1/8, half full keeps it, empty lowers it by 1/8.
+        assert_eq!(
+            expected_base_fees,
+            vec![
+                1_000_000_000.as_u256(),
+                1_125_000_000.as_u256(),
+                1_125_000_000.as_u256(),
+                984_375_000.as_u256()
+            ]
+        );
+
+        let history = read(&tx, 10, BlockNumber(2), &[]).unwrap();
+        assert_eq!(
+            history,
+            FeeHistory {
+                oldest_block: BlockNumber(0),
+                base_fee_per_gas: expected_base_fees.clone(),
+                gas_used_ratio: vec![1.0, 0.5, 0.0],
+                reward: None,
+            }
+        );
+
+        let history = read(&tx, 2, BlockNumber(1), &[]).unwrap();
+        assert_eq!(history.oldest_block, BlockNumber(0));
+        assert_eq!(history.base_fee_per_gas, expected_base_fees[..3].to_vec());
+        assert_eq!(history.gas_used_ratio, vec![1.0, 0.5]);
+
+        assert!(read(&tx, 2, BlockNumber(2), &[50.0, 10.0]).is_err());
+
+        // The block after the newest one is the London fork block, or comes before it.
+        chain_spec.upgrades.london = Some(BlockNumber(3));
+        tx.set(tables::Config, Default::default(), chain_spec.clone())
+            .unwrap();
+        assert_eq!(
+            read(&tx, 1, BlockNumber(2), &[]).unwrap().base_fee_per_gas,
+            vec![expected_base_fees[2], param::INITIAL_BASE_FEE.as_u256()]
+        );
+
+        chain_spec.upgrades.london = Some(BlockNumber(4));
+        tx.set(tables::Config, Default::default(), chain_spec)
+            .unwrap();
+        assert_eq!(
+            read(&tx, 1, BlockNumber(2), &[]).unwrap().base_fee_per_gas,
+            vec![expected_base_fees[2], U256::ZERO]
+        );
+    }
+}
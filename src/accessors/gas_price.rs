@@ -0,0 +1,247 @@
+use crate::{
+    accessors::chain,
+    kv::{mdbx::*, tables},
+    models::*,
+};
+use anyhow::format_err;
+
+/// Number of latest blocks to sample.
+pub const SAMPLE_BLOCKS: u64 = 20;
+/// Percentile of transaction gas prices suggested for blocks without base fee.
+pub const GAS_PRICE_PERCENTILE: usize = 60;
+/// Tip added on top of the median base fee, in percent.
+pub const TIP_PERCENT: u64 = 10;
+/// Number of latest blocks to sample for priority fee suggestion.
+pub const TIP_SAMPLE_BLOCKS: u64 = 3;
+/// Priority fee suggested if there are no transactions to sample, 1 Gwei.
+pub const DEFAULT_TIP: u64 = 1_000_000_000;
+
+/// Value at `percentile` of values sorted in ascending order.
+fn percentile(sorted: &[U256], percentile: usize) -> U256 {
+    if sorted.is_empty() {
+        return U256::ZERO;
+    }
+
+    sorted[(sorted.len() - 1) * percentile / 100]
+}
+
+/// Suggests a legacy gas price based on blocks up to and including `latest_block`.
+///
+/// After London this is the median base fee plus a tip, before it the 60th percentile of transaction gas prices.
+pub fn suggest<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    latest_block: BlockNumber,
+) -> anyhow::Result<U256> {
+    let oldest_block = BlockNumber(latest_block.0.saturating_sub(SAMPLE_BLOCKS - 1));
+
+    let mut base_fees = vec![];
+    let mut gas_prices = vec![];
+    for block_number in oldest_block..=latest_block {
+        let hash = tx
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("No canonical block {}", block_number))?;
+        let header = tx
+            .get(tables::Header, (block_number, hash))?
+            .ok_or_else(|| format_err!("No header for block {}", block_number))?;
+
+        if let Some(base_fee_per_gas) = header.base_fee_per_gas {
+            base_fees.push(base_fee_per_gas);
+        } else {
+            let body = chain::block_body::read_without_senders(tx, hash, block_number)?
+                .ok_or_else(|| format_err!("No body for block {}", block_number))?;
+            gas_prices.extend(body.transactions.iter().map(|tx| tx.max_fee_per_gas()));
+        }
+    }
+
+    Ok(if base_fees.is_empty() {
+        gas_prices.sort_unstable();
+        percentile(&gas_prices, GAS_PRICE_PERCENTILE)
+    } else {
+        base_fees.sort_unstable();
+        let median = percentile(&base_fees, 50);
+        median + median * TIP_PERCENT.as_u256() / 100
+    })
+}
+
+/// Suggests `maxPriorityFeePerGas` as the 60th percentile of effective priority fees paid in blocks up to and including `latest_block`.
+pub fn suggest_tip<K: TransactionKind, E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, K, E>,
+    latest_block: BlockNumber,
+) -> anyhow::Result<U256> {
+    let oldest_block = BlockNumber(latest_block.0.saturating_sub(TIP_SAMPLE_BLOCKS - 1));
+
+    let mut tips = vec![];
+    for block_number in oldest_block..=latest_block {
+        let hash = tx
+            .get(tables::CanonicalHeader, block_number)?
+            .ok_or_else(|| format_err!("No canonical block {}", block_number))?;
+        let header = tx
+            .get(tables::Header, (block_number, hash))?
+            .ok_or_else(|| format_err!("No header for block {}", block_number))?;
+        let base_fee_per_gas = header.base_fee_per_gas.unwrap_or(U256::ZERO);
+
+        let body = chain::block_body::read_without_senders(tx, hash, block_number)?
+            .ok_or_else(|| format_err!("No body for block {}", block_number))?;
+        tips.extend(
+            body.transactions
+                .iter()
+                .map(|tx| tx.priority_fee_per_gas(base_fee_per_gas)),
+        );
+    }
+
+    if tips.is_empty() {
+        return Ok(DEFAULT_TIP.as_u256());
+    }
+
+    tips.sort_unstable();
+    Ok(percentile(&tips, GAS_PRICE_PERCENTILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+    use bytes::Bytes;
+
+    fn write_block<E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, RW, E>,
+        number: u64,
+        base_fee_per_gas: Option<U256>,
+        messages: Vec<Message>,
+        base_tx_id: u64,
+    ) {
+        let header = BlockHeader {
+            number: BlockNumber(number),
+            base_fee_per_gas,
+            ..BlockHeader::empty()
+        };
+        let hash = header.hash();
+        tx.set(tables::CanonicalHeader, header.number, hash)
+            .unwrap();
+        tx.set(tables::Header, (header.number, hash), header)
+            .unwrap();
+
+        let txs = messages
+            .into_iter()
+            .map(|message| MessageWithSignature {
+                message,
+                signature: MessageSignature::new(false, H256::repeat_byte(2), H256::repeat_byte(3))
+                    .unwrap(),
+            })
+            .collect::<Vec<_>>();
+        chain::storage_body::write(
+            tx,
+            hash,
+            number,
+            &BodyForStorage {
+                base_tx_id: base_tx_id.into(),
+                tx_amount: txs.len() as u64,
+                uncles: vec![],
+                withdrawals: None,
+            },
+        )
+        .unwrap();
+        chain::tx::write(tx, base_tx_id, &txs).unwrap();
+    }
+
+    fn legacy(gas_price: u64) -> Message {
+        Message::Legacy {
+            chain_id: None,
+            nonce: 0,
+            gas_price: gas_price.as_u256(),
+            gas_limit: 21_000,
+            action: TransactionAction::Create,
+            value: U256::ZERO,
+            input: Bytes::new(),
+        }
+    }
+
+    fn eip1559(max_priority_fee_per_gas: u64, max_fee_per_gas: u64) -> Message {
+        Message::EIP1559 {
+            chain_id: ChainId(1),
+            nonce: 0,
+            max_priority_fee_per_gas: max_priority_fee_per_gas.as_u256(),
+            max_fee_per_gas: max_fee_per_gas.as_u256(),
+            gas_limit: 21_000,
+            action: TransactionAction::Create,
+            value: U256::ZERO,
+            input: Bytes::new(),
+            access_list: vec![],
+        }
+    }
+
+    #[test]
+    fn legacy_gas_price_percentile() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        // Block 0 is outside of the sampled range.
+        write_block(&tx, 0, None, vec![legacy(1_000_000)], 0);
+        // Blocks 1..=20 contain gas prices 1..=100.
+        for i in 0..20 {
+            let messages = (1..=5).map(|j| legacy(i * 5 + j)).collect();
+            write_block(&tx, i + 1, None, messages, 1 + i * 5);
+        }
+
+        // 60th percentile of 1..=100
+        assert_eq!(suggest(&tx, BlockNumber(20)).unwrap(), 60.as_u256());
+    }
+
+    #[test]
+    fn base_fee_median_with_tip() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        for i in 0..20 {
+            write_block(&tx, i, Some(((i + 1) * 100).as_u256()), vec![], 0);
+        }
+
+        // Median of 100..=2000 is 1000, plus 10%
+        assert_eq!(suggest(&tx, BlockNumber(19)).unwrap(), 1100.as_u256());
+    }
+
+    #[test]
+    fn tip_percentile() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        // Block 0 is outside of the sampled range.
+        write_block(&tx, 0, Some(100.as_u256()), vec![eip1559(1_000, 2_000)], 0);
+        // Effective tips: 5, 20, 10
+        write_block(
+            &tx,
+            1,
+            Some(100.as_u256()),
+            vec![eip1559(5, 1_000), eip1559(50, 120), legacy(110)],
+            1,
+        );
+        write_block(&tx, 2, Some(100.as_u256()), vec![], 4);
+        // Effective tips: 1, 30
+        write_block(
+            &tx,
+            3,
+            Some(200.as_u256()),
+            vec![eip1559(1, 300), eip1559(40, 230)],
+            4,
+        );
+
+        // 60th percentile of 1, 5, 10, 20, 30
+        assert_eq!(suggest_tip(&tx, BlockNumber(3)).unwrap(), 10.as_u256());
+
+        // 60th percentile of 5, 10, 20, 1000
+        assert_eq!(suggest_tip(&tx, BlockNumber(2)).unwrap(), 10.as_u256());
+    }
+
+    #[test]
+    fn default_tip_without_transactions() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        write_block(&tx, 0, Some(100.as_u256()), vec![], 0);
+
+        assert_eq!(
+            suggest_tip(&tx, BlockNumber(0)).unwrap(),
+            DEFAULT_TIP.as_u256()
+        );
+    }
+}
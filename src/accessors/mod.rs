@@ -1,2 +1,4 @@
 pub mod chain;
+pub mod fee_history;
+pub mod gas_price;
 pub mod state;
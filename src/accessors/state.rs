@@ -2,6 +2,8 @@ use crate::{
     kv::{mdbx::*, tables, traits::*},
     models::*,
 };
+use anyhow::format_err;
+use bytes::Bytes;
 
 pub mod account {
     use super::*;
@@ -31,6 +33,37 @@ pub mod account {
 
         tx.get(tables::Account, address_to_find)
     }
+
+    /// Current accounts in address order, starting at `start` if given.
+    pub fn walk<'db: 'tx, 'tx, K: TransactionKind, E: EnvironmentKind>(
+        tx: &'tx MdbxTransaction<'db, K, E>,
+        start: Option<Address>,
+    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<(Address, Account)>> + 'tx> {
+        Ok(tx.cursor(tables::Account)?.walk(start))
+    }
+}
+
+pub mod code {
+    use super::*;
+
+    /// Code of the account at the end of `block_number`, or empty if there is no such account.
+    ///
+    /// Fails if the account has code which is not stored.
+    pub fn read<K: TransactionKind, E: EnvironmentKind>(
+        tx: &MdbxTransaction<'_, K, E>,
+        address: Address,
+        block_number: Option<BlockNumber>,
+    ) -> anyhow::Result<Bytes> {
+        if let Some(account) = super::account::read(tx, address, block_number)? {
+            if account.code_hash != EMPTY_HASH {
+                return tx
+                    .get(tables::Code, account.code_hash)?
+                    .ok_or_else(|| format_err!("missing code {:?}", account.code_hash));
+            }
+        }
+
+        Ok(Bytes::new())
+    }
 }
 
 pub mod storage {
@@ -74,6 +107,17 @@ pub mod storage {
             .map(|(_, v)| v)
             .unwrap_or(U256::ZERO))
     }
+
+    /// Current storage slots in address and then location order, starting at the storage of `start` if given.
+    pub fn walk<'db: 'tx, 'tx, K: TransactionKind, E: EnvironmentKind>(
+        tx: &'tx MdbxTransaction<'db, K, E>,
+        start: Option<Address>,
+    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<(Address, H256, U256)>> + 'tx> {
+        Ok(tx
+            .cursor(tables::Storage)?
+            .walk(start)
+            .map(|res| res.map(|(address, (location, value))| (address, location, value))))
+    }
 }
 
 pub mod history_index {
@@ -115,11 +159,59 @@ pub mod history_index {
 pub mod tests {
     use super::*;
     use crate::{
+        crypto::keccak256,
         h256_to_u256,
         kv::{new_mem_database, tables},
     };
     use hex_literal::hex;
 
+    #[test]
+    fn read_code() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let contract = hex!("b000000000000000000000000000000000000008").into();
+        let eoa = hex!("b000000000000000000000000000000000000009").into();
+        let code = Bytes::from_static(&hex!("600035600055"));
+        let code_hash = keccak256(&code);
+
+        txn.set(tables::Code, code_hash, code.clone()).unwrap();
+        txn.set(
+            tables::Account,
+            contract,
+            Account {
+                nonce: 1,
+                balance: U256::ZERO,
+                code_hash,
+            },
+        )
+        .unwrap();
+        txn.set(
+            tables::Account,
+            eoa,
+            Account {
+                nonce: 5,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(super::code::read(&txn, contract, None).unwrap(), code);
+        assert_eq!(super::code::read(&txn, eoa, None).unwrap(), Bytes::new());
+        assert_eq!(
+            super::code::read(
+                &txn,
+                hex!("b00000000000000000000000000000000000000a").into(),
+                None
+            )
+            .unwrap(),
+            Bytes::new()
+        );
+
+        txn.del(tables::Code, code_hash, None).unwrap();
+        assert!(super::code::read(&txn, contract, None).is_err());
+    }
+
     #[test]
     fn read_storage() {
         let db = new_mem_database().unwrap();
@@ -160,4 +252,92 @@ pub mod tests {
             0.as_u256()
         );
     }
+
+    #[test]
+    fn walk_state() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let address1: Address = hex!("b000000000000000000000000000000000000008").into();
+        let address2: Address = hex!("0000000000000000000000000000000000000001").into();
+        let address3: Address = hex!("5000000000000000000000000000000000000000").into();
+
+        for (i, address) in [address1, address2, address3].into_iter().enumerate() {
+            txn.set(
+                tables::Account,
+                address,
+                Account {
+                    nonce: i as u64 + 1,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            super::account::walk(&txn, None)
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap(),
+            vec![
+                (
+                    address2,
+                    Account {
+                        nonce: 2,
+                        ..Default::default()
+                    }
+                ),
+                (
+                    address3,
+                    Account {
+                        nonce: 3,
+                        ..Default::default()
+                    }
+                ),
+                (
+                    address1,
+                    Account {
+                        nonce: 1,
+                        ..Default::default()
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            super::account::walk(&txn, Some(address3))
+                .unwrap()
+                .map(|res| res.map(|(address, _)| address))
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap(),
+            vec![address3, address1]
+        );
+
+        let loc1 = H256::from_low_u64_be(2);
+        let loc2 = H256::from_low_u64_be(1);
+        let val1 = 0x17_u64.as_u256();
+        let val2 = 0x23_u64.as_u256();
+
+        txn.set(tables::Storage, address1, (loc1, val1)).unwrap();
+        txn.set(tables::Storage, address1, (loc2, val2)).unwrap();
+        txn.set(tables::Storage, address2, (loc1, val2)).unwrap();
+
+        assert_eq!(
+            super::storage::walk(&txn, None)
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap(),
+            vec![
+                (address2, loc1, val2),
+                (address1, loc2, val2),
+                (address1, loc1, val1),
+            ]
+        );
+        assert_eq!(
+            super::storage::walk(&txn, Some(address1))
+                .unwrap()
+                .collect::<anyhow::Result<Vec<_>>>()
+                .unwrap(),
+            vec![(address1, loc2, val2), (address1, loc1, val1)]
+        );
+    }
 }
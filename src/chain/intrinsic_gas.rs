@@ -1,7 +1,7 @@
 use super::protocol_param::fee;
 use crate::models::*;
 
-pub fn intrinsic_gas(txn: &Message, homestead: bool, istanbul: bool) -> u128 {
+pub fn intrinsic_gas(txn: &Message, homestead: bool, istanbul: bool, shanghai: bool) -> u128 {
     let mut gas = fee::G_TRANSACTION as u128;
 
     if matches!(txn.action(), TransactionAction::Create) && homestead {
@@ -30,5 +30,36 @@ pub fn intrinsic_gas(txn: &Message, homestead: bool, istanbul: bool) -> u128 {
     let zero_bytes = txn.input().len() as u128 - non_zero_bytes;
     gas += zero_bytes * u128::from(fee::G_TX_DATA_ZERO);
 
+    // https://eips.ethereum.org/EIPS/eip-3860
+    if matches!(txn.action(), TransactionAction::Create) && shanghai {
+        let initcode_words = (txn.input().len() as u128 + 31) / 32;
+        gas += initcode_words * u128::from(fee::G_INITCODE_WORD);
+    }
+
     gas
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eip3860_initcode_word_cost() {
+        // 1000 bytes of initcode round up to 32 words.
+        let txn = Message::Legacy {
+            chain_id: None,
+            nonce: 0,
+            gas_price: U256::ZERO,
+            gas_limit: 100_000,
+            action: TransactionAction::Create,
+            value: U256::ZERO,
+            input: vec![0; 1000].into(),
+        };
+
+        let london = intrinsic_gas(&txn, true, true, false);
+        assert_eq!(london, 21_000 + 32_000 + 1000 * 4);
+
+        let shanghai = intrinsic_gas(&txn, true, true, true);
+        assert_eq!(shanghai, london + 32 * 2);
+    }
+}
@@ -23,14 +23,25 @@ pub mod fee {
     pub const G_TX_DATA_NON_ZERO_FRONTIER: u64 = 68;
     pub const G_TX_DATA_NON_ZERO_ISTANBUL: u64 = 16;
     pub const G_TRANSACTION: u64 = 21_000;
+
+    pub const G_INITCODE_WORD: u64 = 2; // EIP-3860
 } // namespace fee
 
 pub mod param {
     use crate::models::*;
 
+    // See [YP] Section 4.3.4 "Block Header Validity", Eq (50)
+    pub const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
+    // Max seconds from current time allowed for blocks, before they're considered future blocks
+    pub const ALLOWED_FUTURE_BLOCK_TIME: u64 = 15;
+
     // https://eips.ethereum.org/EIPS/eip-170
     pub const MAX_CODE_SIZE: usize = 0x6000;
 
+    // https://eips.ethereum.org/EIPS/eip-3860
+    pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
     pub const BLOCK_REWARD_FRONTIER: u128 = 5 * ETHER;
     pub const BLOCK_REWARD_BYZANTIUM: u128 = 3 * ETHER;
     pub const BLOCK_REWARD_CONSTANTINOPLE: u128 = 2 * ETHER;
@@ -46,4 +57,27 @@ pub mod param {
     pub const INITIAL_BASE_FEE: u64 = 1_000_000_000;
     pub const BASE_FEE_MAX_CHANGE_DENOMINATOR: u64 = 8;
     pub const ELASTICITY_MULTIPLIER: u64 = 2;
+
+    // https://eips.ethereum.org/EIPS/eip-4844
+    pub const GAS_PER_BLOB: u64 = 1 << 17;
+    pub const TARGET_BLOB_GAS_PER_BLOCK: u64 = 3 * GAS_PER_BLOB;
+    pub const MAX_BLOB_GAS_PER_BLOCK: u64 = 6 * GAS_PER_BLOB;
+    pub const MAX_BLOBS_PER_BLOCK: usize = (MAX_BLOB_GAS_PER_BLOCK / GAS_PER_BLOB) as usize;
+    pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+    pub const MIN_BLOB_GASPRICE: u64 = 1;
+    pub const BLOB_GASPRICE_UPDATE_FRACTION: u64 = 3_338_477;
+    pub const FIELD_ELEMENTS_PER_BLOB: u64 = 4096;
+    pub const BLS_MODULUS: [u8; 32] =
+        hex_literal::hex!("73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001");
+    pub const POINT_EVALUATION_PRECOMPILE_GAS: u64 = 50_000;
+
+    // https://eips.ethereum.org/EIPS/eip-4788
+    pub const SYSTEM_ADDRESS: Address = H160(hex_literal::hex!(
+        "fffffffffffffffffffffffffffffffffffffffe"
+    ));
+    pub const BEACON_ROOTS_ADDRESS: Address = H160(hex_literal::hex!(
+        "000F3df6D732807Ef1319fB7B8bB8522d0Beac02"
+    ));
+    pub const BEACON_ROOTS_HISTORY_BUFFER_LENGTH: u64 = 8191;
+    pub const SYSTEM_CALL_GAS_LIMIT: u64 = 30_000_000;
 }
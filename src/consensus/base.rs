@@ -1,19 +1,109 @@
 use super::*;
 use crate::{chain::protocol_param::param, models::*, state::*};
 use anyhow::Context;
-use std::time::SystemTime;
+use std::{ops::RangeInclusive, time::SystemTime};
+
+/// Base fee of the block following `parent`, if `parent` is itself past EIP-1559 activation.
+///
+/// See https://eips.ethereum.org/EIPS/eip-1559
+pub fn next_base_fee_per_gas(parent: &BlockHeader) -> Option<U256> {
+    compute_base_fee(
+        parent,
+        param::ELASTICITY_MULTIPLIER,
+        param::BASE_FEE_MAX_CHANGE_DENOMINATOR,
+    )
+}
+
+/// Same as [`next_base_fee_per_gas`], for networks that deviate from the EIP-1559 constants.
+pub fn compute_base_fee(
+    parent: &BlockHeader,
+    elasticity_multiplier: u64,
+    base_fee_max_change_denominator: u64,
+) -> Option<U256> {
+    let parent_base_fee_per_gas = parent.base_fee_per_gas?;
+    let parent_gas_target = parent.gas_limit / elasticity_multiplier;
+
+    if parent.gas_used == parent_gas_target {
+        return Some(parent_base_fee_per_gas);
+    }
+
+    if parent.gas_used > parent_gas_target {
+        let gas_used_delta = parent.gas_used - parent_gas_target;
+        let base_fee_per_gas_delta = std::cmp::max(
+            U256::ONE,
+            parent_base_fee_per_gas * U256::from(gas_used_delta)
+                / U256::from(parent_gas_target)
+                / U256::from(base_fee_max_change_denominator),
+        );
+        Some(parent_base_fee_per_gas + base_fee_per_gas_delta)
+    } else {
+        let gas_used_delta = parent_gas_target - parent.gas_used;
+        let base_fee_per_gas_delta = parent_base_fee_per_gas * U256::from(gas_used_delta)
+            / U256::from(parent_gas_target)
+            / U256::from(base_fee_max_change_denominator);
+
+        Some(parent_base_fee_per_gas.saturating_sub(base_fee_per_gas_delta))
+    }
+}
+
+/// Excess blob gas of the block following `parent`.
+///
+/// See https://eips.ethereum.org/EIPS/eip-4844
+pub fn next_excess_blob_gas(parent: &BlockHeader) -> u64 {
+    (parent.excess_blob_gas.unwrap_or(0) + parent.blob_gas_used.unwrap_or(0))
+        .saturating_sub(param::TARGET_BLOB_GAS_PER_BLOCK)
+}
+
+/// Price of a unit of blob gas in a block with `excess_blob_gas`.
+pub fn blob_gas_price(excess_blob_gas: u64) -> U256 {
+    fake_exponential(
+        param::MIN_BLOB_GASPRICE,
+        excess_blob_gas,
+        param::BLOB_GASPRICE_UPDATE_FRACTION,
+    )
+}
+
+/// Approximates `factor * e ** (numerator / denominator)` using Taylor expansion.
+fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> U256 {
+    let numerator = U256::from(numerator);
+    let denominator = U256::from(denominator);
+
+    let mut output = U256::ZERO;
+    let mut accum = U256::from(factor) * denominator;
+    let mut i = U256::ONE;
+    while accum > U256::ZERO {
+        output += accum;
+        accum = accum * numerator / (denominator * i);
+        i += U256::ONE;
+    }
+    output / denominator
+}
 
 #[derive(Debug)]
 pub struct ConsensusEngineBase {
     chain_id: ChainId,
+    max_extra_data_size: usize,
+    min_gas_limit: u64,
     eip1559_block: Option<BlockNumber>,
+    cancun_block: Option<BlockNumber>,
+    elasticity_multiplier: u64,
+    base_fee_max_change_denominator: u64,
 }
 
 impl ConsensusEngineBase {
-    pub fn new(chain_id: ChainId, eip1559_block: Option<BlockNumber>) -> Self {
+    pub fn new(
+        params: &Params,
+        eip1559_block: Option<BlockNumber>,
+        cancun_block: Option<BlockNumber>,
+    ) -> Self {
         Self {
-            chain_id,
+            chain_id: params.chain_id,
+            max_extra_data_size: params.maximum_extra_data_size,
+            min_gas_limit: params.min_gas_limit,
             eip1559_block,
+            cancun_block,
+            elasticity_multiplier: params.elasticity_multiplier(),
+            base_fee_max_change_denominator: params.base_fee_max_change_denominator(),
         }
     }
 
@@ -22,13 +112,23 @@ impl ConsensusEngineBase {
         header: &BlockHeader,
         parent: &BlockHeader,
         with_future_timestamp_check: bool,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), DuoError> {
+        let expected_number = parent.number + 1;
+        if header.number != expected_number {
+            return Err(ValidationError::WrongBlockNumber {
+                expected: expected_number,
+                got: header.number,
+            }
+            .into());
+        }
+
         if with_future_timestamp_check {
             let now = SystemTime::now()
-                .duration_since(SystemTime::UNIX_EPOCH)?
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .context("system clock is before Unix epoch")?
                 .as_secs();
-            if header.timestamp > now {
-                return Err(ValidationError::FutureBlock {
+            if header.timestamp > now + param::ALLOWED_FUTURE_BLOCK_TIME {
+                return Err(ValidationError::TimestampTooFarInFuture {
                     now,
                     got: header.timestamp,
                 }
@@ -44,24 +144,18 @@ impl ConsensusEngineBase {
             .into());
         }
 
-        if header.gas_limit < 5000 {
-            return Err(ValidationError::InvalidGasLimit.into());
-        }
-
-        // https://github.com/ethereum/go-ethereum/blob/v1.9.25/consensus/ethash/consensus.go#L267
-        // https://eips.ethereum.org/EIPS/eip-1985
-        if header.gas_limit > i64::MAX.try_into().unwrap() {
-            return Err(ValidationError::InvalidGasLimit.into());
-        }
-
-        if header.extra_data.len() > 32 {
-            return Err(ValidationError::ExtraDataTooLong.into());
+        if header.extra_data.len() > self.max_extra_data_size {
+            return Err(ValidationError::ExtraDataTooLong {
+                max: self.max_extra_data_size,
+                got: header.extra_data.len(),
+            }
+            .into());
         }
 
         if header.timestamp <= parent.timestamp {
-            return Err(ValidationError::InvalidTimestamp {
+            return Err(ValidationError::TimestampTooOld {
                 parent: parent.timestamp,
-                current: header.timestamp,
+                got: header.timestamp,
             }
             .into());
         }
@@ -69,17 +163,17 @@ impl ConsensusEngineBase {
         let mut parent_gas_limit = parent.gas_limit;
         if let Some(fork_block) = self.eip1559_block {
             if fork_block == header.number {
-                parent_gas_limit = parent.gas_limit * param::ELASTICITY_MULTIPLIER;
+                parent_gas_limit = parent.gas_limit * self.elasticity_multiplier;
             }
         }
 
-        let gas_delta = if header.gas_limit > parent_gas_limit {
-            header.gas_limit - parent_gas_limit
-        } else {
-            parent_gas_limit - header.gas_limit
-        };
-        if gas_delta >= parent_gas_limit / 1024 {
-            return Err(ValidationError::InvalidGasLimit.into());
+        let expected_range = gas_limit_range(parent_gas_limit, self.min_gas_limit);
+        if !expected_range.contains(&header.gas_limit) {
+            return Err(ValidationError::InvalidGasLimit {
+                expected_range,
+                got: header.gas_limit,
+            }
+            .into());
         }
 
         let expected_base_fee_per_gas = self.expected_base_fee_per_gas(header, parent);
@@ -91,9 +185,36 @@ impl ConsensusEngineBase {
             .into());
         }
 
+        let expected_excess_blob_gas = self
+            .is_cancun(header.number)
+            .then(|| next_excess_blob_gas(parent));
+        if header.excess_blob_gas != expected_excess_blob_gas {
+            return Err(ValidationError::WrongExcessBlobGas {
+                expected: expected_excess_blob_gas,
+                got: header.excess_blob_gas,
+            }
+            .into());
+        }
+
+        match (
+            self.is_cancun(header.number),
+            header.parent_beacon_block_root,
+        ) {
+            (true, None) => return Err(ValidationError::MissingParentBeaconBlockRoot.into()),
+            (false, Some(_)) => return Err(ValidationError::UnexpectedParentBeaconBlockRoot.into()),
+            _ => {}
+        }
+
         Ok(())
     }
 
+    /// Whether blocks at `number` follow EIP-4844.
+    fn is_cancun(&self, number: BlockNumber) -> bool {
+        self.cancun_block
+            .map(|cancun_block| number >= cancun_block)
+            .unwrap_or(false)
+    }
+
     pub fn get_parent_header(
         &self,
         state: &mut dyn State,
@@ -161,32 +282,11 @@ impl ConsensusEngineBase {
                     return Some(param::INITIAL_BASE_FEE.into());
                 }
 
-                let parent_gas_target = parent.gas_limit / param::ELASTICITY_MULTIPLIER;
-
-                let parent_base_fee_per_gas = parent.base_fee_per_gas.unwrap();
-
-                if parent.gas_used == parent_gas_target {
-                    return Some(parent_base_fee_per_gas);
-                }
-
-                if parent.gas_used > parent_gas_target {
-                    let gas_used_delta = parent.gas_used - parent_gas_target;
-                    let base_fee_per_gas_delta = std::cmp::max(
-                        U256::ONE,
-                        parent_base_fee_per_gas * U256::from(gas_used_delta)
-                            / U256::from(parent_gas_target)
-                            / U256::from(param::BASE_FEE_MAX_CHANGE_DENOMINATOR),
-                    );
-                    return Some(parent_base_fee_per_gas + base_fee_per_gas_delta);
-                } else {
-                    let gas_used_delta = parent_gas_target - parent.gas_used;
-                    let base_fee_per_gas_delta = parent_base_fee_per_gas
-                        * U256::from(gas_used_delta)
-                        / U256::from(parent_gas_target)
-                        / U256::from(param::BASE_FEE_MAX_CHANGE_DENOMINATOR);
-
-                    return Some(parent_base_fee_per_gas.saturating_sub(base_fee_per_gas_delta));
-                }
+                return compute_base_fee(
+                    parent,
+                    self.elasticity_multiplier,
+                    self.base_fee_max_change_denominator,
+                );
             }
         }
 
@@ -212,6 +312,33 @@ impl ConsensusEngineBase {
             .into());
         }
 
+        validate_withdrawals_root(block)?;
+
+        let expected_blob_gas_used = self.is_cancun(block.header.number).then(|| {
+            block
+                .transactions
+                .iter()
+                .map(|txn| txn.blob_gas())
+                .sum::<u64>()
+        });
+        if block.header.blob_gas_used != expected_blob_gas_used {
+            return Err(ValidationError::WrongBlobGasUsed {
+                expected: expected_blob_gas_used,
+                got: block.header.blob_gas_used,
+            }
+            .into());
+        }
+
+        if let Some(blob_gas_used) = expected_blob_gas_used {
+            if blob_gas_used > param::MAX_BLOB_GAS_PER_BLOCK {
+                return Err(ValidationError::BlobGasLimitExceeded {
+                    max: param::MAX_BLOB_GAS_PER_BLOCK,
+                    got: blob_gas_used,
+                }
+                .into());
+            }
+        }
+
         if block.ommers.len() > 2 {
             return Err(ValidationError::TooManyOmmers.into());
         }
@@ -257,10 +384,211 @@ impl ConsensusEngineBase {
     }
 }
 
+/// Checks that the header commits to the withdrawals carried in the block body, see EIP-4895.
+pub fn validate_withdrawals_root(block: &Block) -> Result<(), ValidationError> {
+    let expected = block.withdrawals.as_deref().map(Block::withdrawals_root);
+    if block.header.withdrawals_root != expected {
+        return Err(ValidationError::WrongWithdrawalsRoot {
+            expected,
+            got: block.header.withdrawals_root,
+        });
+    }
+
+    Ok(())
+}
+
+/// Range of gas limits a child block may have given its parent's gas limit.
+///
+/// See [YP] Section 4.3.4 "Block Header Validity", Eq (50).
+pub fn gas_limit_range(parent_gas_limit: u64, min_gas_limit: u64) -> RangeInclusive<u64> {
+    let max_delta = parent_gas_limit / param::GAS_LIMIT_BOUND_DIVISOR;
+
+    let lower = std::cmp::max(
+        min_gas_limit,
+        parent_gas_limit.saturating_sub(max_delta) + 1,
+    );
+    // https://github.com/ethereum/go-ethereum/blob/v1.9.25/consensus/ethash/consensus.go#L267
+    // https://eips.ethereum.org/EIPS/eip-1985
+    let upper = std::cmp::min(
+        i64::MAX as u64,
+        parent_gas_limit.saturating_add(max_delta).saturating_sub(1),
+    );
+
+    lower..=upper
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::res::chainspec::MAINNET;
+    use bytes::Bytes;
+
+    #[test]
+    fn base_fee_overrides() {
+        let parent = |gas_used| BlockHeader {
+            gas_limit: 30_000_000,
+            gas_used,
+            base_fee_per_gas: Some(U256::from(1_000_000_000_u64)),
+            ..BlockHeader::empty()
+        };
+
+        // Full parent: +12.5% by the book, +6.25% with a denominator of 16.
+        assert_eq!(
+            next_base_fee_per_gas(&parent(30_000_000)),
+            Some(U256::from(1_125_000_000_u64))
+        );
+        assert_eq!(
+            compute_base_fee(&parent(30_000_000), 2, 16),
+            Some(U256::from(1_062_500_000_u64))
+        );
+
+        // Half full parent sits at the standard target, but above the target of a multiplier of 4.
+        assert_eq!(
+            next_base_fee_per_gas(&parent(15_000_000)),
+            Some(U256::from(1_000_000_000_u64))
+        );
+        assert_eq!(
+            compute_base_fee(&parent(15_000_000), 4, 8),
+            Some(U256::from(1_125_000_000_u64))
+        );
+        assert_eq!(
+            compute_base_fee(&parent(7_500_000), 4, 8),
+            Some(U256::from(1_000_000_000_u64))
+        );
+
+        // Empty parent: -12.5% by the book, -6.25% with a denominator of 16.
+        assert_eq!(
+            next_base_fee_per_gas(&parent(0)),
+            Some(U256::from(875_000_000_u64))
+        );
+        assert_eq!(
+            compute_base_fee(&parent(0), 2, 16),
+            Some(U256::from(937_500_000_u64))
+        );
+
+        // Defaults of the chain spec match the EIP.
+        let params = &crate::res::chainspec::MAINNET.params;
+        assert_eq!(
+            compute_base_fee(
+                &parent(30_000_000),
+                params.elasticity_multiplier(),
+                params.base_fee_max_change_denominator()
+            ),
+            next_base_fee_per_gas(&parent(30_000_000))
+        );
+    }
+
+    #[test]
+    fn blob_gas_formula() {
+        // Test vectors from the EIP-4844 reference implementation.
+        for (factor, numerator, denominator, expected) in [
+            (1, 0, 1, 1_u64),
+            (38493, 0, 1000, 38493),
+            (0, 1234, 2345, 0),
+            (1, 2, 1, 6),
+            (1, 4, 2, 6),
+            (1, 3, 1, 16),
+            (1, 6, 2, 18),
+            (1, 8, 2, 50),
+            (2, 5, 2, 23),
+            (1, 50_000_000, 2_225_652, 5_709_098_764),
+        ] {
+            assert_eq!(
+                fake_exponential(factor, numerator, denominator),
+                U256::from(expected)
+            );
+        }
+
+        assert_eq!(blob_gas_price(0), U256::ONE);
+
+        let parent = |excess_blob_gas, blobs| BlockHeader {
+            excess_blob_gas: Some(excess_blob_gas),
+            blob_gas_used: Some(blobs * param::GAS_PER_BLOB),
+            ..BlockHeader::empty()
+        };
+        // Usage at or below target does not accumulate excess.
+        assert_eq!(next_excess_blob_gas(&parent(0, 3)), 0);
+        assert_eq!(next_excess_blob_gas(&parent(0, 1)), 0);
+        // Full blocks push the excess up by the distance to the target.
+        assert_eq!(
+            next_excess_blob_gas(&parent(0, 6)),
+            param::TARGET_BLOB_GAS_PER_BLOCK
+        );
+        assert_eq!(
+            next_excess_blob_gas(&parent(param::TARGET_BLOB_GAS_PER_BLOCK, 0)),
+            0
+        );
+        // Pre-Cancun parents count as zero.
+        assert_eq!(next_excess_blob_gas(&BlockHeader::empty()), 0);
+    }
+
+    #[test]
+    fn blob_transaction_constraints() {
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.cancun = Some(BlockNumber(20_000_000));
+        let london = chain_spec.collect_block_spec(19_999_999, None);
+        let cancun = chain_spec.collect_block_spec(20_000_000, None);
+
+        let txn = |blobs: usize, version: u8| Message::EIP4844 {
+            chain_id: ChainId(1),
+            nonce: 0,
+            max_priority_fee_per_gas: U256::ZERO,
+            max_fee_per_gas: U256::ZERO,
+            gas_limit: 21_000,
+            to: Address::repeat_byte(0xaa),
+            value: U256::ZERO,
+            input: Bytes::new(),
+            access_list: vec![],
+            max_fee_per_blob_gas: U256::ONE,
+            blob_versioned_hashes: (0..blobs)
+                .map(|i| {
+                    let mut hash = H256::repeat_byte(i as u8);
+                    hash[0] = version;
+                    hash
+                })
+                .collect(),
+        };
+
+        validate_blob_transaction(&txn(1, 0x01), &cancun, U256::ONE).unwrap();
+        validate_blob_transaction(&txn(6, 0x01), &cancun, U256::ONE).unwrap();
+        assert_eq!(txn(6, 0x01).blob_gas(), param::MAX_BLOB_GAS_PER_BLOCK);
+
+        for (txn, spec, error) in [
+            (
+                txn(1, 0x01),
+                &london,
+                ValidationError::UnsupportedTransactionType,
+            ),
+            (
+                txn(0, 0x01),
+                &cancun,
+                ValidationError::WrongBlobCount { max: 6, got: 0 },
+            ),
+            (
+                txn(7, 0x01),
+                &cancun,
+                ValidationError::WrongBlobCount { max: 6, got: 7 },
+            ),
+            (
+                txn(2, 0x00),
+                &cancun,
+                ValidationError::WrongBlobVersionedHashVersion { got: 0x00 },
+            ),
+        ] {
+            assert_eq!(validate_blob_transaction(&txn, spec, U256::ONE), Err(error));
+        }
+
+        // The blob fee cap must cover the blob gas price of the block.
+        let excess_blob_gas = 10 * param::TARGET_BLOB_GAS_PER_BLOCK;
+        assert!(blob_gas_price(excess_blob_gas) > U256::ONE);
+        assert_eq!(
+            validate_blob_transaction(&txn(1, 0x01), &cancun, blob_gas_price(excess_blob_gas)),
+            Err(ValidationError::MaxFeePerBlobGasLessThanPrice {
+                max_fee_per_blob_gas: U256::ONE,
+                blob_gas_price: blob_gas_price(excess_blob_gas),
+            })
+        );
+    }
 
     #[test]
     fn validate_max_fee_per_gas() {
@@ -317,4 +645,458 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn validate_gas_limit() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, None);
+
+        let parent = BlockHeader {
+            gas_limit: 1_024_000,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+        let max_delta = parent.gas_limit / param::GAS_LIMIT_BOUND_DIVISOR;
+
+        for (gas_limit, valid) in [
+            (parent.gas_limit, true),
+            (parent.gas_limit + max_delta - 1, true),
+            (parent.gas_limit + max_delta, false),
+            (parent.gas_limit - max_delta + 1, true),
+            (parent.gas_limit - max_delta, false),
+        ] {
+            let header = BlockHeader {
+                number: BlockNumber(1),
+                gas_limit,
+                timestamp: parent.timestamp + 1,
+                ..BlockHeader::empty()
+            };
+
+            let res = engine
+                .validate_block_header(&header, &parent, false)
+                .map_err(|e| e.validation().unwrap());
+
+            if valid {
+                assert!(res.is_ok(), "gas limit {} rejected: {:?}", gas_limit, res);
+            } else {
+                assert_eq!(
+                    res.unwrap_err(),
+                    ValidationError::InvalidGasLimit {
+                        expected_range: parent.gas_limit - max_delta + 1
+                            ..=parent.gas_limit + max_delta - 1,
+                        got: gas_limit,
+                    }
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn validate_base_fee_overrides() {
+        let params = Params {
+            eip1559_elasticity_multiplier: Some(4),
+            eip1559_base_fee_max_change_denominator: Some(16),
+            ..MAINNET.params.clone()
+        };
+        let engine = ConsensusEngineBase::new(&params, Some(BlockNumber(1)), None);
+
+        // The fork block may raise the gas limit by the elasticity multiplier of the network.
+        let genesis = BlockHeader {
+            gas_limit: 7_500_000,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+        let fork_block = BlockHeader {
+            number: BlockNumber(1),
+            gas_limit: 30_000_000,
+            gas_used: 30_000_000,
+            timestamp: 1001,
+            base_fee_per_gas: Some(param::INITIAL_BASE_FEE.as_u256()),
+            ..BlockHeader::empty()
+        };
+        engine
+            .validate_block_header(&fork_block, &genesis, false)
+            .unwrap();
+
+        // Full parent: +18.75% with a target of a quarter of the gas limit and a denominator of 16.
+        let expected = Some(U256::from(1_187_500_000_u64));
+        let header = |base_fee_per_gas| BlockHeader {
+            number: BlockNumber(2),
+            gas_limit: 30_000_000,
+            timestamp: 1002,
+            base_fee_per_gas,
+            ..BlockHeader::empty()
+        };
+        engine
+            .validate_block_header(&header(expected), &fork_block, false)
+            .unwrap();
+
+        let by_the_book = next_base_fee_per_gas(&fork_block);
+        assert_eq!(
+            engine
+                .validate_block_header(&header(by_the_book), &fork_block, false)
+                .map_err(|e| e.validation().unwrap())
+                .unwrap_err(),
+            ValidationError::WrongBaseFee {
+                expected,
+                got: by_the_book,
+            }
+        );
+    }
+
+    #[test]
+    fn validate_blob_gas() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, Some(BlockNumber(2)));
+
+        let pre_cancun = BlockHeader {
+            number: BlockNumber(1),
+            gas_limit: 30_000_000,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+        let header = |parent: &BlockHeader, blob_gas_used, excess_blob_gas| BlockHeader {
+            number: parent.number + 1,
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp + 12,
+            blob_gas_used,
+            excess_blob_gas,
+            parent_beacon_block_root: Some(H256::repeat_byte(0xbe)),
+            ..BlockHeader::empty()
+        };
+        let validate = |header: &BlockHeader, parent: &BlockHeader| {
+            engine
+                .validate_block_header(header, parent, false)
+                .map_err(|e| e.validation().unwrap())
+        };
+
+        // Blob gas fields appear with Cancun.
+        let genesis = BlockHeader {
+            gas_limit: 30_000_000,
+            timestamp: 988,
+            ..BlockHeader::empty()
+        };
+        assert_eq!(
+            validate(&header(&genesis, Some(0), Some(0)), &genesis),
+            Err(ValidationError::WrongExcessBlobGas {
+                expected: None,
+                got: Some(0),
+            })
+        );
+
+        let fork_block = header(&pre_cancun, Some(param::MAX_BLOB_GAS_PER_BLOCK), Some(0));
+        validate(&fork_block, &pre_cancun).unwrap();
+        assert_eq!(
+            validate(&header(&pre_cancun, None, None), &pre_cancun),
+            Err(ValidationError::WrongExcessBlobGas {
+                expected: Some(0),
+                got: None,
+            })
+        );
+
+        // So does the parent beacon block root.
+        assert_eq!(
+            validate(
+                &BlockHeader {
+                    parent_beacon_block_root: None,
+                    ..fork_block.clone()
+                },
+                &pre_cancun
+            ),
+            Err(ValidationError::MissingParentBeaconBlockRoot)
+        );
+        assert_eq!(
+            validate(
+                &BlockHeader {
+                    blob_gas_used: None,
+                    excess_blob_gas: None,
+                    ..header(&genesis, None, None)
+                },
+                &genesis
+            ),
+            Err(ValidationError::UnexpectedParentBeaconBlockRoot)
+        );
+
+        // Blob gas used above the target carries over.
+        let expected = Some(param::MAX_BLOB_GAS_PER_BLOCK - param::TARGET_BLOB_GAS_PER_BLOCK);
+        validate(&header(&fork_block, Some(0), expected), &fork_block).unwrap();
+        assert_eq!(
+            validate(&header(&fork_block, Some(0), Some(0)), &fork_block),
+            Err(ValidationError::WrongExcessBlobGas {
+                expected,
+                got: Some(0),
+            })
+        );
+
+        // Blob gas used is what the blob transactions of the block consume.
+        let blob_txn = MessageWithSignature {
+            message: Message::EIP4844 {
+                chain_id: ChainId(1),
+                nonce: 0,
+                max_priority_fee_per_gas: U256::ZERO,
+                max_fee_per_gas: U256::ZERO,
+                gas_limit: 21_000,
+                to: Address::repeat_byte(0xaa),
+                value: U256::ZERO,
+                input: Bytes::new(),
+                access_list: vec![],
+                max_fee_per_blob_gas: U256::ONE,
+                blob_versioned_hashes: vec![H256::repeat_byte(0x01); 2],
+            },
+            signature: MessageSignature::new(
+                false,
+                H256::repeat_byte(0x01),
+                H256::repeat_byte(0x01),
+            )
+            .unwrap(),
+        };
+        let block = |txns, blob_gas_used| {
+            Block::new(
+                PartialHeader {
+                    number: BlockNumber(2),
+                    blob_gas_used,
+                    excess_blob_gas: Some(0),
+                    ..PartialHeader::empty()
+                },
+                vec![blob_txn.clone(); txns],
+                vec![],
+                None,
+            )
+        };
+        for blob_gas_used in [None, Some(param::GAS_PER_BLOB)] {
+            assert_eq!(
+                engine
+                    .pre_validate_block(
+                        &block(1, blob_gas_used),
+                        &mut crate::InMemoryState::default()
+                    )
+                    .unwrap_err()
+                    .downcast::<ValidationError>()
+                    .unwrap(),
+                ValidationError::WrongBlobGasUsed {
+                    expected: Some(2 * param::GAS_PER_BLOB),
+                    got: blob_gas_used,
+                }
+            );
+        }
+        // The block passes the blob gas check and fails on its missing parent.
+        assert_eq!(
+            engine
+                .pre_validate_block(
+                    &block(1, Some(2 * param::GAS_PER_BLOB)),
+                    &mut crate::InMemoryState::default()
+                )
+                .unwrap_err()
+                .downcast::<ValidationError>()
+                .unwrap(),
+            ValidationError::UnknownParent
+        );
+
+        // Blob transactions that each fit may not exceed the limit together.
+        assert_eq!(
+            engine
+                .pre_validate_block(
+                    &block(4, Some(8 * param::GAS_PER_BLOB)),
+                    &mut crate::InMemoryState::default()
+                )
+                .unwrap_err()
+                .downcast::<ValidationError>()
+                .unwrap(),
+            ValidationError::BlobGasLimitExceeded {
+                max: param::MAX_BLOB_GAS_PER_BLOCK,
+                got: 8 * param::GAS_PER_BLOB,
+            }
+        );
+    }
+
+    #[test]
+    fn validate_ommers_hash() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, None);
+
+        let ommers = vec![
+            BlockHeader {
+                number: BlockNumber(9),
+                ..BlockHeader::empty()
+            },
+            BlockHeader {
+                number: BlockNumber(8),
+                ..BlockHeader::empty()
+            },
+        ];
+        let mut block = Block::new(
+            PartialHeader {
+                number: BlockNumber(10),
+                ..PartialHeader::empty()
+            },
+            vec![],
+            ommers,
+            None,
+        );
+        let expected = block.header.ommers_hash;
+        block.header.ommers_hash = EMPTY_LIST_HASH;
+
+        assert_eq!(
+            engine
+                .pre_validate_block(&block, &mut crate::InMemoryState::default())
+                .unwrap_err()
+                .downcast::<ValidationError>()
+                .unwrap(),
+            ValidationError::WrongOmmersHash {
+                expected,
+                got: EMPTY_LIST_HASH,
+            }
+        );
+    }
+
+    #[test]
+    fn validate_min_gas_limit() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, None);
+
+        let parent = BlockHeader {
+            gas_limit: MAINNET.params.min_gas_limit,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+
+        let header = BlockHeader {
+            number: BlockNumber(1),
+            gas_limit: MAINNET.params.min_gas_limit - 1,
+            timestamp: parent.timestamp + 1,
+            ..BlockHeader::empty()
+        };
+
+        assert_eq!(
+            engine
+                .validate_block_header(&header, &parent, false)
+                .unwrap_err()
+                .validation()
+                .unwrap(),
+            ValidationError::InvalidGasLimit {
+                expected_range: gas_limit_range(parent.gas_limit, MAINNET.params.min_gas_limit),
+                got: header.gas_limit,
+            }
+        );
+    }
+
+    #[test]
+    fn gas_limit_range_saturates() {
+        assert_eq!(*gas_limit_range(u64::MAX, 5000).end(), i64::MAX as u64);
+    }
+
+    #[test]
+    fn validate_extra_data_size() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, None);
+
+        let parent = BlockHeader {
+            gas_limit: 1_024_000,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+
+        let mut header = BlockHeader {
+            number: BlockNumber(1),
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp + 1,
+            extra_data: vec![0xab; 32].into(),
+            ..BlockHeader::empty()
+        };
+        engine
+            .validate_block_header(&header, &parent, false)
+            .unwrap();
+
+        header.extra_data = vec![0xab; 33].into();
+        assert_eq!(
+            engine
+                .validate_block_header(&header, &parent, false)
+                .unwrap_err()
+                .validation()
+                .unwrap(),
+            ValidationError::ExtraDataTooLong { max: 32, got: 33 }
+        );
+    }
+
+    #[test]
+    fn validate_timestamp() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, None);
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let parent = BlockHeader {
+            gas_limit: 1_024_000,
+            timestamp: now - 100,
+            ..BlockHeader::empty()
+        };
+
+        let mut header = BlockHeader {
+            number: BlockNumber(1),
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp,
+            ..BlockHeader::empty()
+        };
+        assert_eq!(
+            engine
+                .validate_block_header(&header, &parent, false)
+                .unwrap_err()
+                .validation()
+                .unwrap(),
+            ValidationError::TimestampTooOld {
+                parent: parent.timestamp,
+                got: header.timestamp,
+            }
+        );
+
+        header.timestamp = parent.timestamp + 1;
+        engine
+            .validate_block_header(&header, &parent, true)
+            .unwrap();
+
+        // Far future blocks are only rejected during live sync.
+        header.timestamp = now + 3600;
+        engine
+            .validate_block_header(&header, &parent, false)
+            .unwrap();
+        assert!(matches!(
+            engine
+                .validate_block_header(&header, &parent, true)
+                .unwrap_err()
+                .validation()
+                .unwrap(),
+            ValidationError::TimestampTooFarInFuture { got, .. } if got == header.timestamp
+        ));
+    }
+
+    #[test]
+    fn validate_block_number() {
+        let engine = ConsensusEngineBase::new(&MAINNET.params, None, None);
+
+        let parent = BlockHeader {
+            number: BlockNumber(10),
+            gas_limit: 1_024_000,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+
+        for (number, valid) in [(9, false), (10, false), (11, true), (12, false)] {
+            let header = BlockHeader {
+                number: BlockNumber(number),
+                gas_limit: parent.gas_limit,
+                timestamp: parent.timestamp + 1,
+                ..BlockHeader::empty()
+            };
+
+            let res = engine.validate_block_header(&header, &parent, false);
+            if valid {
+                res.unwrap();
+            } else {
+                assert_eq!(
+                    res.unwrap_err().validation().unwrap(),
+                    ValidationError::WrongBlockNumber {
+                        expected: BlockNumber(11),
+                        got: BlockNumber(number),
+                    }
+                );
+            }
+        }
+    }
 }
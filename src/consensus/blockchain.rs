@@ -11,7 +11,7 @@ use std::{collections::HashMap, convert::TryFrom};
 pub struct Blockchain<'state> {
     state: &'state mut InMemoryState,
     config: ChainSpec,
-    engine: Box<dyn Consensus>,
+    validator: BlockValidator,
     bad_blocks: HashMap<H256, ValidationError>,
     receipts: Vec<Receipt>,
 }
@@ -22,12 +22,7 @@ impl<'state> Blockchain<'state> {
         config: ChainSpec,
         genesis_block: Block,
     ) -> anyhow::Result<Blockchain<'state>> {
-        Self::new_with_consensus(
-            state,
-            engine_factory(config.clone())?,
-            config,
-            genesis_block,
-        )
+        Self::new_with_consensus(state, engine_factory(&config)?, config, genesis_block)
     }
 
     pub fn new_with_consensus(
@@ -43,7 +38,7 @@ impl<'state> Blockchain<'state> {
 
         Ok(Self {
             state,
-            engine,
+            validator: BlockValidator::new(engine),
             config,
             bad_blocks: Default::default(),
             receipts: Default::default(),
@@ -51,9 +46,8 @@ impl<'state> Blockchain<'state> {
     }
 
     pub fn insert_block(&mut self, block: Block, check_state_root: bool) -> anyhow::Result<()> {
-        self.engine
-            .validate_block_header(&block.header, &mut self.state, true)?;
-        self.engine.pre_validate_block(&block, &mut self.state)?;
+        self.validator
+            .validate_block(&block, &mut self.state, true)?;
 
         let hash = block.header.hash();
         if let Some(error) = self.bad_blocks.get(&hash) {
@@ -130,9 +124,18 @@ impl<'state> Blockchain<'state> {
         let body = BlockBodyWithSenders {
             transactions: block.transactions.clone(),
             ommers: block.ommers.clone(),
+            withdrawals: block.withdrawals.clone(),
         };
 
-        let block_spec = self.config.collect_block_spec(block.header.number);
+        let parent = match block.header.number.0.checked_sub(1) {
+            Some(parent_number) => self
+                .state
+                .read_header(BlockNumber(parent_number), block.header.parent_hash)?,
+            None => None,
+        };
+        let block_spec = self
+            .config
+            .collect_block_spec(block.header.number, parent.as_ref());
 
         let mut analysis_cache = AnalysisCache::default();
         let mut tracer = NoopTracer;
@@ -140,7 +143,7 @@ impl<'state> Blockchain<'state> {
             self.state,
             &mut tracer,
             &mut analysis_cache,
-            &mut *self.engine,
+            self.validator.engine_mut(),
             &block.header,
             &body,
             &block_spec,
@@ -183,6 +186,7 @@ impl<'state> Blockchain<'state> {
                 header: header.into(),
                 transactions: body.transactions,
                 ommers: body.ommers,
+                withdrawals: body.withdrawals,
             };
 
             let _ = self.execute_block(&block, false).unwrap();
@@ -228,6 +232,7 @@ impl<'state> Blockchain<'state> {
                     header,
                     transactions: body.transactions,
                     ommers: body.ommers,
+                    withdrawals: body.withdrawals,
                 },
                 hash,
             };
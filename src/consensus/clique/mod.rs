@@ -0,0 +1,744 @@
+use super::{base::ConsensusEngineBase, *};
+use crate::crypto::keccak256;
+use parking_lot::Mutex;
+use secp256k1::{
+    ecdsa::{RecoverableSignature, RecoveryId},
+    Message as SecpMessage, SECP256K1,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::Duration,
+};
+
+/// Fixed number of extra data prefix bytes reserved for signer vanity.
+pub const EXTRA_VANITY: usize = 32;
+/// Fixed number of extra data suffix bytes reserved for signer seal.
+pub const EXTRA_SEAL: usize = 65;
+
+/// Nonce of a block voting to add the beneficiary to the signers.
+pub const NONCE_AUTH: H64 = H64([0xff; 8]);
+/// Nonce of a block voting to remove the beneficiary from the signers.
+pub const NONCE_DROP: H64 = H64([0x00; 8]);
+
+/// Difficulty of a block sealed by the signer whose turn it is.
+pub const DIFF_IN_TURN: u8 = 2;
+/// Difficulty of a block sealed out of turn.
+pub const DIFF_NO_TURN: u8 = 1;
+
+/// Hash of the header that the signer seals: the full header with the seal removed from extra data.
+pub fn seal_hash(header: &BlockHeader) -> Option<H256> {
+    let unsealed_len = header.extra_data.len().checked_sub(EXTRA_SEAL)?;
+
+    let mut header = header.clone();
+    header.extra_data = header.extra_data.slice(..unsealed_len);
+
+    Some(keccak256(&rlp::encode(&header)[..]))
+}
+
+/// Recovers the address of the signer that sealed the header.
+pub fn recover_signer(header: &BlockHeader) -> Result<Address, DuoError> {
+    if header.extra_data.len() < EXTRA_VANITY + EXTRA_SEAL {
+        return Err(ValidationError::InvalidSeal.into());
+    }
+
+    let hash = seal_hash(header).ok_or(ValidationError::InvalidSeal)?;
+    let seal = &header.extra_data[header.extra_data.len() - EXTRA_SEAL..];
+
+    let signature = RecoveryId::from_i32(seal[64] as i32)
+        .and_then(|rec| RecoverableSignature::from_compact(&seal[..64], rec))
+        .map_err(|_| ValidationError::InvalidSeal)?;
+    let public = SECP256K1
+        .recover_ecdsa(
+            &SecpMessage::from_slice(hash.as_bytes()).map_err(anyhow::Error::from)?,
+            &signature,
+        )
+        .map_err(|_| ValidationError::InvalidSeal)?;
+
+    Ok(Address::from_slice(
+        &keccak256(&public.serialize_uncompressed()[1..]).as_bytes()[12..],
+    ))
+}
+
+/// Signers listed in the extra data of an epoch checkpoint block.
+pub fn checkpoint_signers(header: &BlockHeader) -> Result<BTreeSet<Address>, ValidationError> {
+    let signers = header
+        .extra_data
+        .len()
+        .checked_sub(EXTRA_VANITY + EXTRA_SEAL)
+        .map(|len| &header.extra_data[EXTRA_VANITY..EXTRA_VANITY + len])
+        .filter(|signers| signers.len() % ADDRESS_LENGTH == 0)
+        .ok_or(ValidationError::InvalidCheckpointSigners)?;
+
+    Ok(signers
+        .chunks(ADDRESS_LENGTH)
+        .map(Address::from_slice)
+        .collect())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vote {
+    pub block: BlockNumber,
+    pub address: Address,
+    pub authorize: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tally {
+    pub authorize: bool,
+    pub votes: usize,
+}
+
+/// Authorized signers and pending votes at some block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CliqueState {
+    pub signers: BTreeSet<Address>,
+    /// Votes by signer.
+    pub votes: HashMap<Address, Vec<Vote>>,
+    /// Running tallies by voted address.
+    pub tally: HashMap<Address, Tally>,
+    /// Signers of the latest blocks, they have to wait before sealing again.
+    pub recents: BTreeMap<BlockNumber, Address>,
+}
+
+impl CliqueState {
+    pub fn new(signers: BTreeSet<Address>) -> Self {
+        Self {
+            signers,
+            ..Default::default()
+        }
+    }
+
+    /// Whether it is `signer`'s turn to seal block `number`, signers take turns in address order.
+    pub fn in_turn(&self, number: BlockNumber, signer: Address) -> bool {
+        let len = self.signers.len() as u64;
+        len > 0 && self.signers.iter().nth((number.0 % len) as usize) == Some(&signer)
+    }
+
+    /// Number of consecutive blocks in which a signer may seal only once.
+    fn recent_window(&self) -> u64 {
+        self.signers.len() as u64 / 2 + 1
+    }
+
+    fn forget_old_recents(&mut self, number: BlockNumber) {
+        let window = self.recent_window();
+        self.recents
+            .retain(|recent, _| recent.0 + window > number.0);
+    }
+
+    /// Counts a vote, unless it would not change the signers.
+    fn cast(&mut self, address: Address, authorize: bool) -> bool {
+        if self.signers.contains(&address) == authorize {
+            return false;
+        }
+
+        let tally = self.tally.entry(address).or_insert(Tally {
+            authorize,
+            votes: 0,
+        });
+        tally.votes += 1;
+
+        true
+    }
+
+    fn uncast(&mut self, address: Address, authorize: bool) {
+        if let Some(tally) = self.tally.get_mut(&address) {
+            if tally.authorize == authorize {
+                tally.votes -= 1;
+                if tally.votes == 0 {
+                    self.tally.remove(&address);
+                }
+            }
+        }
+    }
+
+    fn discard_votes(&mut self, predicate: impl Fn(&Address, &Vote) -> bool) {
+        let mut discarded = vec![];
+        for (signer, votes) in &mut self.votes {
+            votes.retain(|vote| {
+                let discard = predicate(signer, vote);
+                if discard {
+                    discarded.push(*vote);
+                }
+                !discard
+            });
+        }
+        self.votes.retain(|_, votes| !votes.is_empty());
+
+        for vote in discarded {
+            self.uncast(vote.address, vote.authorize);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Clique {
+    base: ConsensusEngineBase,
+    period: Duration,
+    epoch: u64,
+    /// State after the last validated header, carried over to its child.
+    snapshot: Mutex<Option<(H256, CliqueState)>>,
+}
+
+impl Clique {
+    pub fn new(
+        params: &Params,
+        eip1559_block: Option<BlockNumber>,
+        cancun_block: Option<BlockNumber>,
+        period: Duration,
+        epoch: u64,
+    ) -> Self {
+        Self {
+            base: ConsensusEngineBase::new(params, eip1559_block, cancun_block),
+            period,
+            epoch,
+            snapshot: Mutex::new(None),
+        }
+    }
+
+    /// Advances `state` past `header`.
+    ///
+    /// Every block but genesis must be sealed by an authorized signer that did not seal any of the recent blocks.
+    /// Checkpoint blocks at epoch boundaries then reset the signers to the ones they list and drop all votes.
+    /// Other blocks count the signer's vote for the beneficiary.
+    pub fn apply_block(
+        &self,
+        state: &mut CliqueState,
+        header: &BlockHeader,
+    ) -> Result<(), DuoError> {
+        if header.number.0 == 0 {
+            *state = CliqueState::new(checkpoint_signers(header)?);
+            return Ok(());
+        }
+
+        let signer = recover_signer(header)?;
+        if !state.signers.contains(&signer) {
+            return Err(ValidationError::UnauthorizedSigner { signer }.into());
+        }
+
+        state.forget_old_recents(header.number);
+        if state.recents.values().any(|recent| *recent == signer) {
+            return Err(ValidationError::RecentlySigned { signer }.into());
+        }
+
+        if header.number.0 % self.epoch == 0 {
+            state.recents.insert(header.number, signer);
+            *state = CliqueState {
+                recents: std::mem::take(&mut state.recents),
+                ..CliqueState::new(checkpoint_signers(header)?)
+            };
+            state.forget_old_recents(header.number);
+            return Ok(());
+        }
+
+        let authorize = if header.nonce == NONCE_AUTH {
+            true
+        } else if header.nonce == NONCE_DROP {
+            false
+        } else {
+            return Err(ValidationError::InvalidVote.into());
+        };
+        let address = header.beneficiary;
+        state.recents.insert(header.number, signer);
+
+        // A signer has at most one vote per address, the latest one counts.
+        state.discard_votes(|voter, vote| *voter == signer && vote.address == address);
+        if state.cast(address, authorize) {
+            state.votes.entry(signer).or_default().push(Vote {
+                block: header.number,
+                address,
+                authorize,
+            });
+        }
+
+        if let Some(tally) = state.tally.get(&address).copied() {
+            if tally.votes > state.signers.len() / 2 {
+                if tally.authorize {
+                    state.signers.insert(address);
+                } else {
+                    state.signers.remove(&address);
+                    // Votes of the removed signer no longer count.
+                    state.discard_votes(|voter, _| *voter == address);
+                    // The recent window shrinks with the signers.
+                    state.forget_old_recents(header.number);
+                }
+
+                state.discard_votes(|_, vote| vote.address == address);
+                state.tally.remove(&address);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// State after `parent`: carried over from its validation, or rebuilt from the last checkpoint before it.
+    fn snapshot(
+        &self,
+        state: &mut dyn State,
+        parent: &BlockHeader,
+    ) -> Result<CliqueState, DuoError> {
+        let parent_hash = parent.hash();
+        if let Some((hash, snapshot)) = &*self.snapshot.lock() {
+            if *hash == parent_hash {
+                return Ok(snapshot.clone());
+            }
+        }
+
+        let mut headers = vec![];
+        let mut checkpoint = parent.clone();
+        while checkpoint.number.0 % self.epoch != 0 {
+            let header = self
+                .base
+                .get_parent_header(state, &checkpoint)?
+                .ok_or(ValidationError::UnknownParent)?;
+            headers.push(std::mem::replace(&mut checkpoint, header));
+        }
+
+        // Checkpoints list the signers, but not who sealed the blocks right before them.
+        let mut snapshot = CliqueState::new(checkpoint_signers(&checkpoint)?);
+        let mut recent = checkpoint;
+        while recent.number.0 > 0 && recent.number.0 + snapshot.recent_window() > parent.number.0 {
+            snapshot
+                .recents
+                .insert(recent.number, recover_signer(&recent)?);
+            recent = self
+                .base
+                .get_parent_header(state, &recent)?
+                .ok_or(ValidationError::UnknownParent)?;
+        }
+
+        for header in headers.iter().rev() {
+            self.apply_block(&mut snapshot, header)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    fn validate_header_with_parent(
+        &self,
+        header: &BlockHeader,
+        parent: &BlockHeader,
+        with_future_timestamp_check: bool,
+    ) -> Result<(), DuoError> {
+        self.base
+            .validate_block_header(header, parent, with_future_timestamp_check)?;
+
+        // Signers seal no sooner than one period after the parent.
+        let allowed_at = parent.timestamp + self.period.as_secs();
+        if header.timestamp < allowed_at {
+            return Err(ValidationError::TooSoon {
+                allowed_at,
+                got: header.timestamp,
+            }
+            .into());
+        }
+
+        // Checkpoints do not vote.
+        if header.number.0 % self.epoch == 0
+            && (header.beneficiary != Address::zero() || header.nonce != NONCE_DROP)
+        {
+            return Err(ValidationError::InvalidVote.into());
+        }
+
+        if header.mix_hash != H256::zero() {
+            return Err(ValidationError::InvalidSeal.into());
+        }
+
+        if header.ommers_hash != EMPTY_LIST_HASH {
+            return Err(ValidationError::WrongOmmersHash {
+                expected: EMPTY_LIST_HASH,
+                got: header.ommers_hash,
+            }
+            .into());
+        }
+
+        if header.difficulty != DIFF_IN_TURN.as_u256()
+            && header.difficulty != DIFF_NO_TURN.as_u256()
+        {
+            return Err(ValidationError::WrongDifficulty.into());
+        }
+
+        Ok(())
+    }
+
+    /// Checks the seal of `header` against the state after its parent and advances the state past it.
+    fn validate_signer(
+        &self,
+        snapshot: &mut CliqueState,
+        header: &BlockHeader,
+    ) -> Result<(), DuoError> {
+        let signer = recover_signer(header)?;
+        let expected_difficulty = if snapshot.in_turn(header.number, signer) {
+            DIFF_IN_TURN
+        } else {
+            DIFF_NO_TURN
+        };
+        if header.difficulty != expected_difficulty.as_u256() {
+            return Err(ValidationError::WrongDifficulty.into());
+        }
+
+        self.apply_block(snapshot, header)
+    }
+}
+
+impl Consensus for Clique {
+    fn pre_validate_block(&self, block: &Block, state: &mut dyn State) -> anyhow::Result<()> {
+        if !block.ommers.is_empty() {
+            return Err(ValidationError::TooManyOmmers.into());
+        }
+
+        self.base.pre_validate_block(block, state)
+    }
+
+    fn validate_block_header(
+        &self,
+        header: &BlockHeader,
+        state: &mut dyn State,
+        with_future_timestamp_check: bool,
+    ) -> Result<(), DuoError> {
+        let parent = self
+            .base
+            .get_parent_header(state, header)?
+            .ok_or(ValidationError::UnknownParent)?;
+
+        self.validate_header_with_parent(header, &parent, with_future_timestamp_check)?;
+
+        let mut snapshot = self.snapshot(state, &parent)?;
+        self.validate_signer(&mut snapshot, header)?;
+        *self.snapshot.lock() = Some((header.hash(), snapshot));
+
+        Ok(())
+    }
+
+    fn validate_seal(&self, header: &BlockHeader) -> Result<(), DuoError> {
+        recover_signer(header)?;
+
+        Ok(())
+    }
+
+    fn finalize(
+        &self,
+        _: &PartialHeader,
+        _: &[BlockHeader],
+        _: Revision,
+    ) -> anyhow::Result<Vec<FinalizationChange>> {
+        // Clique does not reward signers.
+        Ok(vec![])
+    }
+
+    fn get_beneficiary(&self, header: &BlockHeader) -> anyhow::Result<Address> {
+        // Coinbase is used for voting, transaction fees go to the signer.
+        Ok(recover_signer(header)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{res::chainspec::RINKEBY, InMemoryState};
+    use bytes::Bytes;
+    use secp256k1::SecretKey;
+
+    fn engine() -> Clique {
+        Clique::new(&RINKEBY.params, None, None, Duration::from_secs(15), 30_000)
+    }
+
+    fn address(key: &SecretKey) -> Address {
+        Address::from_slice(
+            &keccak256(
+                &secp256k1::PublicKey::from_secret_key(SECP256K1, key).serialize_uncompressed()
+                    [1..],
+            )
+            .as_bytes()[12..],
+        )
+    }
+
+    fn sign(mut header: BlockHeader, key: &SecretKey) -> BlockHeader {
+        let mut extra_data = header.extra_data.to_vec();
+        extra_data.resize(extra_data.len() + EXTRA_SEAL, 0);
+        header.extra_data = extra_data.clone().into();
+
+        let seal = extra_data.len() - EXTRA_SEAL;
+        let (rec, sig) = SECP256K1
+            .sign_ecdsa_recoverable(
+                &SecpMessage::from_slice(seal_hash(&header).unwrap().as_bytes()).unwrap(),
+                key,
+            )
+            .serialize_compact();
+        extra_data[seal..seal + 64].copy_from_slice(&sig);
+        extra_data[seal + 64] = rec.to_i32() as u8;
+        header.extra_data = Bytes::from(extra_data);
+
+        header
+    }
+
+    #[test]
+    fn period() {
+        let engine = engine();
+        let parent = BlockHeader {
+            number: BlockNumber(10),
+            gas_limit: 1_024_000,
+            timestamp: 1000,
+            ..BlockHeader::empty()
+        };
+
+        let mut header = BlockHeader {
+            number: BlockNumber(11),
+            gas_limit: parent.gas_limit,
+            timestamp: parent.timestamp + 15,
+            ommers_hash: EMPTY_LIST_HASH,
+            difficulty: DIFF_NO_TURN.as_u256(),
+            ..BlockHeader::empty()
+        };
+        engine
+            .validate_header_with_parent(&header, &parent, false)
+            .unwrap();
+
+        header.timestamp = parent.timestamp + 14;
+        assert_eq!(
+            engine
+                .validate_header_with_parent(&header, &parent, false)
+                .unwrap_err()
+                .validation()
+                .unwrap(),
+            ValidationError::TooSoon {
+                allowed_at: parent.timestamp + 15,
+                got: header.timestamp,
+            }
+        );
+    }
+
+    #[test]
+    fn signer() {
+        let key = SecretKey::from_slice(&[0x42; 32]).unwrap();
+        let address = address(&key);
+
+        let header = sign(
+            BlockHeader {
+                number: BlockNumber(1),
+                extra_data: vec![0; EXTRA_VANITY].into(),
+                ..BlockHeader::empty()
+            },
+            &key,
+        );
+        assert_eq!(recover_signer(&header).unwrap(), address);
+        assert_eq!(engine().get_beneficiary(&header).unwrap(), address);
+        engine().validate_seal(&header).unwrap();
+
+        let mut tampered = header;
+        tampered.timestamp += 1;
+        assert_ne!(recover_signer(&tampered).unwrap(), address);
+
+        tampered.extra_data = Bytes::from(vec![0; EXTRA_VANITY]);
+        assert_eq!(
+            recover_signer(&tampered).unwrap_err().validation(),
+            Some(ValidationError::InvalidSeal)
+        );
+    }
+
+    #[test]
+    fn votes() {
+        let engine = Clique::new(&RINKEBY.params, None, None, Duration::from_secs(15), 100);
+        let keys = (1..=4)
+            .map(|i| SecretKey::from_slice(&[i; 32]).unwrap())
+            .collect::<Vec<_>>();
+        let [a, b, c, d] = [0, 1, 2, 3].map(|i| address(&keys[i]));
+
+        let block = |number: u64, signer: usize, beneficiary: Address, nonce: H64| {
+            sign(
+                BlockHeader {
+                    number: BlockNumber(number),
+                    beneficiary,
+                    nonce,
+                    extra_data: vec![0; EXTRA_VANITY].into(),
+                    ..BlockHeader::empty()
+                },
+                &keys[signer],
+            )
+        };
+
+        let mut state = CliqueState::new([a, b, c].into_iter().collect());
+
+        // Two of three signers are a quorum to add D.
+        engine
+            .apply_block(&mut state, &block(1, 0, d, NONCE_AUTH))
+            .unwrap();
+        // Repeated votes by the same signer do not add up.
+        engine
+            .apply_block(&mut state, &block(11, 0, d, NONCE_AUTH))
+            .unwrap();
+        assert_eq!(state.tally[&d].votes, 1);
+        assert!(!state.signers.contains(&d));
+        engine
+            .apply_block(&mut state, &block(21, 1, d, NONCE_AUTH))
+            .unwrap();
+        assert!(state.signers.contains(&d));
+        assert!(state.tally.is_empty());
+        assert!(state.votes.is_empty());
+
+        // Removing C out of four signers takes three votes.
+        for (number, signer) in [(31, 0), (41, 1)] {
+            engine
+                .apply_block(&mut state, &block(number, signer, c, NONCE_DROP))
+                .unwrap();
+            assert!(state.signers.contains(&c));
+        }
+        // C's own pending vote goes away with it.
+        engine
+            .apply_block(&mut state, &block(51, 2, b, NONCE_DROP))
+            .unwrap();
+        engine
+            .apply_block(&mut state, &block(61, 3, c, NONCE_DROP))
+            .unwrap();
+        assert_eq!(state.signers, [a, b, d].into_iter().collect());
+        assert!(state.tally.is_empty());
+
+        // Removed signers can not seal.
+        assert_eq!(
+            engine
+                .apply_block(&mut state, &block(71, 2, Address::zero(), NONCE_DROP))
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::UnauthorizedSigner { signer: c })
+        );
+        assert_eq!(
+            engine
+                .apply_block(&mut state, &block(71, 0, c, H64::repeat_byte(1)))
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::InvalidVote)
+        );
+
+        // Pending votes and the signers are reset at the epoch boundary.
+        engine
+            .apply_block(&mut state, &block(81, 0, c, NONCE_AUTH))
+            .unwrap();
+        assert!(!state.tally.is_empty());
+        let mut extra_data = vec![0; EXTRA_VANITY];
+        extra_data.extend_from_slice(c.as_bytes());
+        extra_data.extend_from_slice(d.as_bytes());
+        let checkpoint = BlockHeader {
+            number: BlockNumber(100),
+            extra_data: extra_data.into(),
+            ..BlockHeader::empty()
+        };
+        // Checkpoints are sealed by an authorized signer too.
+        let forged = sign(checkpoint.clone(), &keys[2]);
+        let checkpoint = sign(checkpoint, &keys[0]);
+        assert_eq!(
+            engine
+                .apply_block(&mut state, &forged)
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::UnauthorizedSigner { signer: c })
+        );
+        engine.apply_block(&mut state, &checkpoint).unwrap();
+        assert_eq!(state.signers, [c, d].into_iter().collect());
+        assert!(state.votes.is_empty());
+        assert!(state.tally.is_empty());
+        // Recent signers carry over the checkpoint.
+        assert_eq!(state.recents.get(&BlockNumber(100)), Some(&a));
+    }
+
+    #[test]
+    fn unauthorized_signer() {
+        let keys = (1..=3)
+            .map(|i| SecretKey::from_slice(&[i; 32]).unwrap())
+            .collect::<Vec<_>>();
+        let [a, b, c] = [0, 1, 2].map(|i| address(&keys[i]));
+        let signers = CliqueState::new([a, b].into_iter().collect());
+
+        let mut extra_data = vec![0; EXTRA_VANITY];
+        extra_data.extend_from_slice(a.as_bytes());
+        extra_data.extend_from_slice(b.as_bytes());
+        extra_data.resize(extra_data.len() + EXTRA_SEAL, 0);
+        let genesis = BlockHeader {
+            gas_limit: 1_024_000,
+            timestamp: 1000,
+            extra_data: extra_data.into(),
+            ..BlockHeader::empty()
+        };
+
+        let mut state = InMemoryState::new();
+        let block = |parent: &BlockHeader, signer: usize, difficulty: Option<u8>| {
+            let number = parent.number + 1;
+            let address = address(&keys[signer]);
+            let difficulty = difficulty.unwrap_or(if signers.in_turn(number, address) {
+                DIFF_IN_TURN
+            } else {
+                DIFF_NO_TURN
+            });
+            sign(
+                BlockHeader {
+                    parent_hash: parent.hash(),
+                    ommers_hash: EMPTY_LIST_HASH,
+                    difficulty: difficulty.as_u256(),
+                    number,
+                    gas_limit: parent.gas_limit,
+                    timestamp: parent.timestamp + 15,
+                    extra_data: vec![0; EXTRA_VANITY].into(),
+                    ..BlockHeader::empty()
+                },
+                &keys[signer],
+            )
+        };
+        let insert = |state: &mut InMemoryState, header: &BlockHeader| {
+            state.insert_block(
+                Block {
+                    header: header.clone(),
+                    transactions: vec![],
+                    ommers: vec![],
+                    withdrawals: None,
+                },
+                header.hash(),
+            )
+        };
+        insert(&mut state, &genesis);
+
+        let engine = engine();
+        let first = block(&genesis, 0, None);
+        engine
+            .validate_block_header(&first, &mut state, false)
+            .unwrap();
+        insert(&mut state, &first);
+
+        let validate = |engine: &Clique, state: &mut InMemoryState, header: &BlockHeader| {
+            engine
+                .validate_block_header(header, state, false)
+                .unwrap_err()
+                .validation()
+        };
+        assert_eq!(
+            validate(&engine, &mut state, &block(&first, 2, None)),
+            Some(ValidationError::UnauthorizedSigner { signer: c })
+        );
+        assert_eq!(
+            validate(&engine, &mut state, &block(&first, 0, None)),
+            Some(ValidationError::RecentlySigned { signer: a })
+        );
+        let wrong_difficulty = if signers.in_turn(BlockNumber(2), b) {
+            DIFF_NO_TURN
+        } else {
+            DIFF_IN_TURN
+        };
+        assert_eq!(
+            validate(
+                &engine,
+                &mut state,
+                &block(&first, 1, Some(wrong_difficulty))
+            ),
+            Some(ValidationError::WrongDifficulty)
+        );
+
+        // Without the carried over snapshot it is rebuilt from the checkpoint.
+        assert_eq!(
+            validate(&engine(), &mut state, &block(&first, 0, None)),
+            Some(ValidationError::RecentlySigned { signer: a })
+        );
+        engine()
+            .validate_block_header(&block(&first, 1, None), &mut state, false)
+            .unwrap();
+        engine
+            .validate_block_header(&block(&first, 1, None), &mut state, false)
+            .unwrap();
+    }
+}
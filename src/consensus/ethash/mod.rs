@@ -20,8 +20,9 @@ pub struct Ethash {
 impl Ethash {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
-        chain_id: ChainId,
+        params: &Params,
         eip1559_block: Option<BlockNumber>,
+        cancun_block: Option<BlockNumber>,
         duration_limit: u64,
         block_reward: BTreeMap<BlockNumber, U256>,
         homestead_formula: Option<BlockNumber>,
@@ -30,7 +31,7 @@ impl Ethash {
         skip_pow_verification: bool,
     ) -> Self {
         Self {
-            base: ConsensusEngineBase::new(chain_id, eip1559_block),
+            base: ConsensusEngineBase::new(params, eip1559_block, cancun_block),
             duration_limit,
             block_reward,
             homestead_formula,
@@ -51,7 +52,7 @@ impl Consensus for Ethash {
         header: &BlockHeader,
         state: &mut dyn State,
         with_future_timestamp_check: bool,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), DuoError> {
         let parent = self
             .base
             .get_parent_header(state, header)?
@@ -81,7 +82,7 @@ impl Consensus for Ethash {
 
         Ok(())
     }
-    fn validate_seal(&self, header: &BlockHeader) -> anyhow::Result<()> {
+    fn validate_seal(&self, header: &BlockHeader) -> Result<(), DuoError> {
         if !self.skip_pow_verification {
             type Dag = LightDAG;
             let light_dag = Dag::new(header.number.0.into());
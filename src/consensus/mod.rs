@@ -1,12 +1,27 @@
 mod base;
 mod blockchain;
+mod clique;
 mod ethash;
+mod pos;
+mod validator;
 
-pub use self::{blockchain::*, ethash::*};
-use crate::{models::*, State};
-use anyhow::bail;
+pub use self::{
+    base::{
+        blob_gas_price, compute_base_fee, next_base_fee_per_gas, next_excess_blob_gas,
+        validate_withdrawals_root,
+    },
+    blockchain::*,
+    clique::*,
+    ethash::*,
+    pos::*,
+    validator::*,
+};
+use crate::{chain::protocol_param::param, models::*, State};
 use derive_more::{Display, From};
-use std::fmt::{Debug, Display};
+use std::{
+    fmt::{Debug, Display},
+    ops::RangeInclusive,
+};
 
 #[derive(Debug)]
 pub enum FinalizationChange {
@@ -28,10 +43,10 @@ pub trait Consensus: Debug + Send + Sync + 'static {
         header: &BlockHeader,
         state: &mut dyn State,
         with_future_timestamp_check: bool,
-    ) -> anyhow::Result<()>;
+    ) -> Result<(), DuoError>;
 
     /// Validates the seal of the header
-    fn validate_seal(&self, header: &BlockHeader) -> anyhow::Result<()>;
+    fn validate_seal(&self, header: &BlockHeader) -> Result<(), DuoError>;
 
     /// Finalizes block execution by applying changes in the state of accounts or of the consensus itself
     ///
@@ -50,10 +65,10 @@ pub trait Consensus: Debug + Send + Sync + 'static {
 #[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug, PartialEq)]
 pub enum ValidationError {
-    FutureBlock {
+    TimestampTooFarInFuture {
         now: u64,
         got: u64,
-    }, // Block has a timestamp in the future
+    }, // Block has a timestamp too far in the future
 
     // See [YP] Section 4.3.2 "Holistic Validity", Eq (31)
     WrongStateRoot {
@@ -76,26 +91,60 @@ pub enum ValidationError {
         expected: Bloom,
         got: Bloom,
     }, // wrong Hb
+    WrongWithdrawalsRoot {
+        expected: Option<H256>,
+        got: Option<H256>,
+    }, // see EIP-4895
+    WrongBlobGasUsed {
+        expected: Option<u64>,
+        got: Option<u64>,
+    }, // see EIP-4844
 
     // See [YP] Section 4.3.4 "Block Header Validity", Eq (50)
-    UnknownParent,   // P(H) = ∅ ∨ Hi ≠ P(H)Hi + 1
+    UnknownParent, // P(H) = ∅
+    WrongBlockNumber {
+        expected: BlockNumber,
+        got: BlockNumber,
+    }, // Hi ≠ P(H)Hi + 1
     WrongDifficulty, // Hd ≠ D(H)
     GasAboveLimit {
         used: u64,
         limit: u64,
     }, // Hg > Hl
-    InvalidGasLimit, // |Hl-P(H)Hl|≥P(H)Hl/1024 ∨ Hl<5000
-    InvalidTimestamp {
+    InvalidGasLimit {
+        expected_range: RangeInclusive<u64>,
+        got: u64,
+    }, // |Hl-P(H)Hl|≥P(H)Hl/1024 ∨ Hl<5000
+    TimestampTooOld {
         parent: u64,
-        current: u64,
+        got: u64,
     }, // Hs ≤ P(H)Hs
-    ExtraDataTooLong, // ‖Hx‖ > 32
+    ExtraDataTooLong {
+        max: usize,
+        got: usize,
+    }, // ‖Hx‖ > maximum_extra_data_size
     WrongDaoExtraData, // see EIP-779
     WrongBaseFee {
         expected: Option<U256>,
         got: Option<U256>,
     }, // see EIP-1559
-    InvalidSeal,     // Nonce or mix_hash
+    WrongExcessBlobGas {
+        expected: Option<u64>,
+        got: Option<u64>,
+    }, // see EIP-4844
+    InvalidSeal,   // Nonce or mix_hash
+    TooSoon {
+        allowed_at: u64,
+        got: u64,
+    }, // Clique: Hs < P(H)Hs + period
+    UnauthorizedSigner {
+        signer: Address,
+    }, // Clique: seal by a non-signer
+    RecentlySigned {
+        signer: Address,
+    }, // Clique: signer sealed one of the recent blocks
+    InvalidVote,   // Clique: nonce is neither auth nor drop
+    InvalidCheckpointSigners, // Clique: malformed signer list in epoch checkpoint
 
     // See [YP] Section 6.2 "Execution", Eq (58)
     MissingSender, // S(T) = ∅
@@ -119,6 +168,10 @@ pub enum ValidationError {
     }, // Tg > BHl - l(BR)u
     MaxFeeLessThanBase, // max_fee_per_gas < base_fee_per_gas (EIP-1559)
     MaxPriorityFeeGreaterThanMax, // max_priority_fee_per_gas > max_fee_per_gas (EIP-1559)
+    MaxInitcodeSizeExceeded {
+        max: usize,
+        got: usize,
+    }, // ‖Ti‖ > MAX_INITCODE_SIZE (EIP-3860)
 
     // See [YP] Section 11.1 "Ommer Validation", Eq (157)
     TooManyOmmers,      // ‖BU‖ > 2
@@ -138,6 +191,27 @@ pub enum ValidationError {
     WrongChainId, // EIP-155
 
     UnsupportedTransactionType, // EIP-2718
+
+    // See EIP-4844
+    WrongBlobCount {
+        max: usize,
+        got: usize,
+    }, // 0 < ‖Tb‖ ≤ MAX_BLOBS_PER_BLOCK
+    WrongBlobVersionedHashVersion {
+        got: u8,
+    }, // Tb[i][0] ≠ VERSIONED_HASH_VERSION_KZG
+    MaxFeePerBlobGasLessThanPrice {
+        max_fee_per_blob_gas: U256,
+        blob_gas_price: U256,
+    }, // max_fee_per_blob_gas < blob gas price of the block
+    BlobGasLimitExceeded {
+        max: u64,
+        got: u64,
+    }, // blob gas of the block > MAX_BLOB_GAS_PER_BLOCK
+
+    // See EIP-4788
+    MissingParentBeaconBlockRoot,
+    UnexpectedParentBeaconBlockRoot,
 }
 
 impl Display for ValidationError {
@@ -156,6 +230,22 @@ pub enum DuoError {
 
 impl std::error::Error for DuoError {}
 
+impl From<ValidationError> for DuoError {
+    fn from(e: ValidationError) -> Self {
+        Self::Validation(Box::new(e))
+    }
+}
+
+impl DuoError {
+    /// The validation failure, unless the error is internal.
+    pub fn validation(self) -> Option<ValidationError> {
+        match self {
+            Self::Validation(e) => Some(*e),
+            Self::Internal(_) => None,
+        }
+    }
+}
+
 pub fn pre_validate_transaction(
     txn: &Message,
     canonical_chain_id: ChainId,
@@ -181,8 +271,49 @@ pub fn pre_validate_transaction(
     Ok(())
 }
 
-pub fn engine_factory(chain_config: ChainSpec) -> anyhow::Result<Box<dyn Consensus>> {
-    Ok(match chain_config.consensus.seal_verification {
+/// Checks the transaction-level constraints of EIP-4844 blob transactions.
+///
+/// `blob_gas_price` is the price in the including block, as given by [`blob_gas_price`] from its excess blob gas,
+/// itself derived from the parent. Balance sufficiency, including the blob fee, is checked against state during
+/// execution.
+pub fn validate_blob_transaction(
+    txn: &Message,
+    block_spec: &BlockExecutionSpec,
+    blob_gas_price: U256,
+) -> Result<(), ValidationError> {
+    if block_spec.revision < Revision::Cancun {
+        return Err(ValidationError::UnsupportedTransactionType);
+    }
+
+    let blob_versioned_hashes = txn.blob_versioned_hashes();
+    if blob_versioned_hashes.is_empty() || blob_versioned_hashes.len() > param::MAX_BLOBS_PER_BLOCK
+    {
+        return Err(ValidationError::WrongBlobCount {
+            max: param::MAX_BLOBS_PER_BLOCK,
+            got: blob_versioned_hashes.len(),
+        });
+    }
+
+    for hash in blob_versioned_hashes {
+        if hash[0] != param::VERSIONED_HASH_VERSION_KZG {
+            return Err(ValidationError::WrongBlobVersionedHashVersion { got: hash[0] });
+        }
+    }
+
+    let max_fee_per_blob_gas = txn.max_fee_per_blob_gas().unwrap_or(U256::ZERO);
+    if max_fee_per_blob_gas < blob_gas_price {
+        return Err(ValidationError::MaxFeePerBlobGasLessThanPrice {
+            max_fee_per_blob_gas,
+            blob_gas_price,
+        });
+    }
+
+    Ok(())
+}
+
+/// Consensus engine described by the chain spec.
+pub fn engine_factory(chain_spec: &ChainSpec) -> anyhow::Result<Box<dyn Consensus>> {
+    Ok(match &chain_spec.consensus.seal_verification {
         SealVerificationParams::Ethash {
             duration_limit,
             block_reward,
@@ -191,15 +322,44 @@ pub fn engine_factory(chain_config: ChainSpec) -> anyhow::Result<Box<dyn Consens
             difficulty_bomb,
             skip_pow_verification,
         } => Box::new(Ethash::new(
-            chain_config.params.chain_id,
-            chain_config.consensus.eip1559_block,
-            duration_limit,
-            block_reward,
-            homestead_formula,
-            byzantium_formula,
-            difficulty_bomb,
-            skip_pow_verification,
+            &chain_spec.params,
+            chain_spec.consensus.eip1559_block,
+            chain_spec.upgrades.cancun,
+            *duration_limit,
+            block_reward.clone(),
+            *homestead_formula,
+            *byzantium_formula,
+            difficulty_bomb.clone(),
+            *skip_pow_verification,
+        )),
+        SealVerificationParams::Clique { period, epoch } => Box::new(Clique::new(
+            &chain_spec.params,
+            chain_spec.consensus.eip1559_block,
+            chain_spec.upgrades.cancun,
+            *period,
+            *epoch,
         )),
-        _ => bail!("unsupported consensus engine"),
     })
 }
+
+/// Consensus engine of blocks following a parent with `total_difficulty`.
+///
+/// Once the terminal total difficulty is reached the chain is past the merge and
+/// the engine described by the chain spec gives way to proof of stake.
+pub fn engine_for_total_difficulty(
+    chain_spec: &ChainSpec,
+    total_difficulty: U256,
+) -> anyhow::Result<Box<dyn Consensus>> {
+    if chain_spec
+        .consensus
+        .is_terminal_total_difficulty_reached(total_difficulty)
+    {
+        return Ok(Box::new(ProofOfStake::new(
+            &chain_spec.params,
+            chain_spec.consensus.eip1559_block,
+            chain_spec.upgrades.cancun,
+        )));
+    }
+
+    engine_factory(chain_spec)
+}
@@ -0,0 +1,144 @@
+use super::{base::ConsensusEngineBase, *};
+
+/// Consensus of blocks past the terminal total difficulty, see EIP-3675.
+///
+/// Block production is driven by the beacon chain, so execution blocks carry no proof of work,
+/// have no ommers and earn no block rewards.
+#[derive(Debug)]
+pub struct ProofOfStake {
+    base: ConsensusEngineBase,
+}
+
+impl ProofOfStake {
+    pub fn new(
+        params: &Params,
+        eip1559_block: Option<BlockNumber>,
+        cancun_block: Option<BlockNumber>,
+    ) -> Self {
+        Self {
+            base: ConsensusEngineBase::new(params, eip1559_block, cancun_block),
+        }
+    }
+}
+
+impl Consensus for ProofOfStake {
+    fn pre_validate_block(&self, block: &Block, state: &mut dyn State) -> anyhow::Result<()> {
+        if !block.ommers.is_empty() {
+            return Err(ValidationError::TooManyOmmers.into());
+        }
+
+        self.base.pre_validate_block(block, state)
+    }
+
+    fn validate_block_header(
+        &self,
+        header: &BlockHeader,
+        state: &mut dyn State,
+        with_future_timestamp_check: bool,
+    ) -> Result<(), DuoError> {
+        let parent = self
+            .base
+            .get_parent_header(state, header)?
+            .ok_or(ValidationError::UnknownParent)?;
+
+        self.base
+            .validate_block_header(header, &parent, with_future_timestamp_check)?;
+
+        if header.difficulty != U256::ZERO {
+            return Err(ValidationError::WrongDifficulty.into());
+        }
+
+        if header.ommers_hash != EMPTY_LIST_HASH {
+            return Err(ValidationError::WrongOmmersHash {
+                expected: EMPTY_LIST_HASH,
+                got: header.ommers_hash,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn validate_seal(&self, header: &BlockHeader) -> Result<(), DuoError> {
+        if header.nonce != H64::zero() {
+            return Err(ValidationError::InvalidSeal.into());
+        }
+
+        Ok(())
+    }
+
+    fn finalize(
+        &self,
+        _: &PartialHeader,
+        _: &[BlockHeader],
+        _: Revision,
+    ) -> anyhow::Result<Vec<FinalizationChange>> {
+        // Validators are rewarded on the beacon chain.
+        Ok(vec![])
+    }
+
+    fn get_beneficiary(&self, header: &BlockHeader) -> anyhow::Result<Address> {
+        Ok(header.beneficiary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::res::chainspec::MAINNET;
+
+    #[test]
+    fn terminal_total_difficulty() {
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.consensus.terminal_total_difficulty = Some(U256::from(25_u64));
+
+        let header = PartialHeader {
+            number: 3.into(),
+            beneficiary: Address::repeat_byte(0xaa),
+            ..PartialHeader::empty()
+        };
+
+        // Blocks of difficulty 10 reach the terminal total difficulty with block 3,
+        // so block 4 is the first one under proof of stake.
+        let mut total_difficulty = U256::ZERO;
+        let mut engines = vec![];
+        for _ in 1..=4 {
+            engines.push(engine_for_total_difficulty(&chain_spec, total_difficulty).unwrap());
+            total_difficulty += U256::from(10_u64);
+        }
+
+        let rewards = engines
+            .iter()
+            .map(|engine| {
+                engine
+                    .finalize(&header, &[], Revision::London)
+                    .unwrap()
+                    .len()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rewards, [1, 1, 1, 0]);
+
+        let pos = engines.last().unwrap();
+        assert_eq!(
+            pos.validate_seal(&BlockHeader {
+                nonce: H64::repeat_byte(1),
+                ..BlockHeader::empty()
+            })
+            .unwrap_err()
+            .validation(),
+            Some(ValidationError::InvalidSeal)
+        );
+        pos.validate_seal(&BlockHeader::empty()).unwrap();
+
+        // Without a terminal total difficulty the chain never leaves proof of work.
+        chain_spec.consensus.terminal_total_difficulty = None;
+        let engine = engine_for_total_difficulty(&chain_spec, U256::MAX).unwrap();
+        assert_eq!(
+            engine
+                .finalize(&header, &[], Revision::London)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
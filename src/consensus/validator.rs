@@ -0,0 +1,149 @@
+use super::*;
+
+/// Single entry point for validating a block prior to execution.
+///
+/// Runs in order the header checks against the parent, which include the base fee and the engine-specific
+/// rules, then the seal, then the body checks: transactions, ommers and withdrawals roots and ommer validity.
+#[derive(Debug)]
+pub struct BlockValidator {
+    engine: Box<dyn Consensus>,
+}
+
+impl BlockValidator {
+    pub fn new(engine: Box<dyn Consensus>) -> Self {
+        Self { engine }
+    }
+
+    pub fn engine(&self) -> &dyn Consensus {
+        &*self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut dyn Consensus {
+        &mut *self.engine
+    }
+
+    /// NOTE: Shouldn't be used for genesis block.
+    pub fn validate_block(
+        &self,
+        block: &Block,
+        state: &mut dyn State,
+        with_future_timestamp_check: bool,
+    ) -> Result<(), DuoError> {
+        self.engine
+            .validate_block_header(&block.header, state, with_future_timestamp_check)?;
+        self.engine.validate_seal(&block.header)?;
+        self.engine.pre_validate_block(block, state).map_err(|e| {
+            match e.downcast::<ValidationError>() {
+                Ok(e) => e.into(),
+                Err(e) => DuoError::Internal(e),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{res::chainspec::MAINNET, InMemoryState};
+
+    #[test]
+    fn validate_block() {
+        let validator =
+            BlockValidator::new(Box::new(ProofOfStake::new(&MAINNET.params, None, None)));
+
+        let genesis = Block::new(
+            PartialHeader {
+                gas_limit: 30_000_000,
+                ..PartialHeader::empty()
+            },
+            vec![],
+            vec![],
+            None,
+        );
+        let genesis_hash = genesis.header.hash();
+
+        let mut state = InMemoryState::default();
+        state.insert_block(genesis, genesis_hash);
+        state.canonize_block(BlockNumber(0), genesis_hash);
+
+        let child = PartialHeader {
+            parent_hash: genesis_hash,
+            number: BlockNumber(1),
+            gas_limit: 30_000_000,
+            timestamp: 12,
+            ..PartialHeader::empty()
+        };
+
+        validator
+            .validate_block(
+                &Block::new(child.clone(), vec![], vec![], None),
+                &mut state,
+                true,
+            )
+            .unwrap();
+
+        let mut block = Block::new(
+            PartialHeader {
+                parent_hash: H256::repeat_byte(0xaa),
+                ..child.clone()
+            },
+            vec![],
+            vec![],
+            None,
+        );
+        assert_eq!(
+            validator
+                .validate_block(&block, &mut state, true)
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::UnknownParent)
+        );
+
+        block = Block::new(
+            PartialHeader {
+                timestamp: 0,
+                ..child.clone()
+            },
+            vec![],
+            vec![],
+            None,
+        );
+        assert_eq!(
+            validator
+                .validate_block(&block, &mut state, true)
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::TimestampTooOld { parent: 0, got: 0 })
+        );
+
+        block = Block::new(
+            PartialHeader {
+                nonce: H64::repeat_byte(1),
+                ..child.clone()
+            },
+            vec![],
+            vec![],
+            None,
+        );
+        assert_eq!(
+            validator
+                .validate_block(&block, &mut state, true)
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::InvalidSeal)
+        );
+
+        block = Block::new(child, vec![], vec![], None);
+        block.header.transactions_root = H256::repeat_byte(0xbb);
+        assert_eq!(
+            validator
+                .validate_block(&block, &mut state, true)
+                .unwrap_err()
+                .validation(),
+            Some(ValidationError::WrongTransactionsRoot {
+                expected: EMPTY_ROOT,
+                got: H256::repeat_byte(0xbb),
+            })
+        );
+    }
+}
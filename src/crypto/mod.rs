@@ -88,6 +88,29 @@ mod tests {
     use hex_literal::hex;
     use secp256k1::SECP256K1;
 
+    #[test]
+    fn ordered_trie_root_keys() {
+        // Keys are RLP-encoded indices: 0 is the empty string 0x80, 0x7f is itself, 0x80 is 0x8180.
+        let values = (0..200_u64)
+            .map(|i| i.to_be_bytes().to_vec())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ordered_trie_root(&values),
+            trie_root(
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| (rlp::encode(&i).to_vec(), v))
+            )
+        );
+        assert_eq!(rlp::encode(&0_usize).to_vec(), vec![0x80]);
+
+        assert_ne!(
+            ordered_trie_root(&values[..1]),
+            trie_root([(vec![0x00], &values[0])])
+        );
+    }
+
     #[test]
     fn generate_address() {
         assert_eq!(
@@ -0,0 +1,171 @@
+use super::headers::*;
+use crate::{
+    models::*,
+    sentry_connector::{
+        block_id::BlockId, messages::*, sentry_client::PeerFilter, sentry_client_reactor::*,
+    },
+};
+use anyhow::{bail, format_err};
+use std::time::Duration;
+use tokio::{
+    sync::{mpsc, watch},
+    time::timeout,
+};
+use tokio_stream::StreamExt;
+use tracing::*;
+
+/// Blocks requested from a peer at once, walking back from the missing one.
+const BACKFILL_BATCH_SIZE: u64 = 128;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Fetches from peers the blocks [`EngineHeaders`] misses on the way to the fork choice head, which the consensus
+/// layer does not send when the node is behind, and queues them like the payloads it does send.
+#[derive(Debug)]
+pub struct EngineBackfill {
+    sentry: SentryClientReactorShared,
+    missing: watch::Receiver<Option<H256>>,
+    sender: mpsc::UnboundedSender<EngineMessage>,
+    last_request_id: u64,
+}
+
+impl EngineBackfill {
+    pub fn new(sentry: SentryClientReactorShared, headers: &EngineHeaders) -> Self {
+        Self {
+            sentry,
+            missing: headers.missing_blocks(),
+            sender: headers.sender(),
+            last_request_id: 0,
+        }
+    }
+
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        loop {
+            let missing = *self.missing.borrow();
+            if let Some(hash) = missing {
+                match self.fetch(hash).await {
+                    Ok(blocks) => {
+                        debug!("Fetched {} blocks down from {:?}", blocks.len(), hash);
+                        for block in blocks {
+                            self.sender
+                                .send(EngineMessage::NewPayload(block))
+                                .map_err(|_| format_err!("Staged sync is not running"))?;
+                        }
+                    }
+                    Err(e) => {
+                        debug!("Failed to fetch block {:?}: {}", hash, e);
+                        tokio::time::sleep(RETRY_DELAY).await;
+                        continue;
+                    }
+                }
+            }
+
+            if self.missing.changed().await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn next_request_id(&mut self) -> u64 {
+        self.last_request_id += 1;
+        self.last_request_id
+    }
+
+    /// Block `hash` along with up to [`BACKFILL_BATCH_SIZE`] of its ancestors, highest first.
+    async fn fetch(&mut self, hash: H256) -> anyhow::Result<Vec<Block>> {
+        let headers_request_id = self.next_request_id();
+        let bodies_request_id = self.next_request_id();
+        let sentry = self.sentry.read().await;
+
+        let mut headers_stream = sentry.receive_messages(EthMessageId::BlockHeaders)?;
+        sentry
+            .send_message(
+                Message::GetBlockHeaders(GetBlockHeadersMessage {
+                    request_id: headers_request_id,
+                    params: GetBlockHeadersMessageParams {
+                        start_block: BlockId::Hash(hash),
+                        limit: BACKFILL_BATCH_SIZE,
+                        skip: 0,
+                        reverse: 1,
+                    },
+                }),
+                PeerFilter::Random(1),
+            )
+            .await?;
+
+        let (mut headers, peer_id) = timeout(REQUEST_TIMEOUT, async {
+            while let Some(message) = headers_stream.next().await {
+                if let Message::BlockHeaders(response) = message.message {
+                    if response.request_id == headers_request_id {
+                        return Some((response.headers, message.from_peer_id));
+                    }
+                }
+            }
+            None
+        })
+        .await?
+        .ok_or_else(|| format_err!("Sentry stopped"))?;
+
+        // Keep the chain linked to the requested block.
+        let mut expected_hash = hash;
+        let linked = headers
+            .iter()
+            .take_while(|header| {
+                let linked = header.hash() == expected_hash;
+                expected_hash = header.parent_hash;
+                linked
+            })
+            .count();
+        headers.truncate(linked);
+        if headers.is_empty() {
+            bail!("Peer did not return the block");
+        }
+
+        let mut bodies_stream = sentry.receive_messages(EthMessageId::BlockBodies)?;
+        sentry
+            .send_message(
+                Message::GetBlockBodies(GetBlockBodiesMessage {
+                    request_id: bodies_request_id,
+                    block_hashes: headers.iter().map(|header| header.hash()).collect(),
+                }),
+                peer_id
+                    .map(PeerFilter::PeerId)
+                    .unwrap_or(PeerFilter::Random(1)),
+            )
+            .await?;
+
+        let bodies = timeout(REQUEST_TIMEOUT, async {
+            while let Some(message) = bodies_stream.next().await {
+                if let Message::BlockBodies(response) = message.message {
+                    if response.request_id == bodies_request_id {
+                        return Some(response.block_bodies);
+                    }
+                }
+            }
+            None
+        })
+        .await?
+        .ok_or_else(|| format_err!("Sentry stopped"))?;
+
+        // Bodies may be cut short, and those not matching their header are dropped along with the rest.
+        let mut blocks = vec![];
+        for (header, body) in headers.into_iter().zip(bodies) {
+            let block = Block::new(
+                header.clone().into(),
+                body.transactions,
+                body.ommers,
+                header.withdrawals_root.map(|_| vec![]),
+            );
+            if block.header != header {
+                break;
+            }
+            blocks.push(block);
+        }
+
+        if blocks.is_empty() {
+            bail!("Peer did not return the block body");
+        }
+
+        Ok(blocks)
+    }
+}
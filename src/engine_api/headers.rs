@@ -0,0 +1,522 @@
+use super::types::*;
+use crate::{
+    accessors,
+    consensus::*,
+    kv::{mdbx::*, tables},
+    models::*,
+    stagedsync::{stage::*, stages::*},
+    Buffer, StageId,
+};
+use anyhow::format_err;
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::{mpsc, watch};
+use tracing::*;
+
+/// Payloads kept around while waiting for fork choice to point at them, the lowest ones are dropped first.
+const MAX_BUFFERED_PAYLOADS: usize = 1024;
+
+/// Messages passed from the Engine API handlers to [`EngineHeaders`].
+#[derive(Debug)]
+pub enum EngineMessage {
+    /// Payload that passed hash verification.
+    NewPayload(Block),
+    /// Head block hash of the latest fork choice update.
+    ForkchoiceUpdated(H256),
+}
+
+/// Verdicts on payloads rejected by [`EngineHeaders`], keyed by block hash.
+pub type InvalidBlocks = Arc<Mutex<HashMap<H256, PayloadStatus>>>;
+
+/// Header source for staged sync when the chain is driven by the consensus layer.
+///
+/// Buffers the payloads queued by the Engine API handlers and, once fork choice names a head, inserts the
+/// branch leading to it as the canonical chain, leaving the rest of the work to the following stages.
+/// Reorgs below the current head are carried out by requesting an unwind to the fork point first.
+/// The first block missing on the way to the head is published for [`EngineBackfill`] to fetch from peers.
+#[derive(Debug)]
+pub struct EngineHeaders {
+    sender: mpsc::UnboundedSender<EngineMessage>,
+    receiver: mpsc::UnboundedReceiver<EngineMessage>,
+    missing: watch::Sender<Option<H256>>,
+    missing_receiver: watch::Receiver<Option<H256>>,
+    invalid_blocks: InvalidBlocks,
+    validator: BlockValidator,
+    buffer: HashMap<H256, Block>,
+    target: Option<H256>,
+}
+
+impl EngineHeaders {
+    pub fn new(config: &ChainSpec) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (missing, missing_receiver) = watch::channel(None);
+        let validator = BlockValidator::new(Box::new(ProofOfStake::new(
+            &config.params,
+            config.consensus.eip1559_block,
+            config.upgrades.cancun,
+        )));
+
+        Self {
+            sender,
+            receiver,
+            missing,
+            missing_receiver,
+            invalid_blocks: Default::default(),
+            validator,
+            buffer: Default::default(),
+            target: None,
+        }
+    }
+
+    pub fn sender(&self) -> mpsc::UnboundedSender<EngineMessage> {
+        self.sender.clone()
+    }
+
+    pub fn invalid_blocks(&self) -> InvalidBlocks {
+        self.invalid_blocks.clone()
+    }
+
+    /// Hash of the block holding back the walk to the fork choice head, if any.
+    pub fn missing_blocks(&self) -> watch::Receiver<Option<H256>> {
+        self.missing_receiver.clone()
+    }
+
+    fn set_missing(&self, hash: Option<H256>) {
+        if *self.missing.borrow() != hash {
+            // Never fails, a receiver is held for the lifetime of the stage.
+            let _ = self.missing.send(hash);
+        }
+    }
+
+    fn wait_for(&self, hash: H256, target: H256) {
+        debug!("Waiting for block {:?} to reach head {:?}", hash, target);
+        self.set_missing(Some(hash));
+    }
+
+    fn receive_messages(&mut self) {
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                EngineMessage::NewPayload(block) => {
+                    self.buffer.insert(block.header.hash(), block);
+                }
+                EngineMessage::ForkchoiceUpdated(head) => {
+                    self.target = Some(head);
+                }
+            }
+        }
+
+        while self.buffer.len() > MAX_BUFFERED_PAYLOADS {
+            let lowest = *self
+                .buffer
+                .iter()
+                .min_by_key(|(_, block)| block.header.number)
+                .unwrap()
+                .0;
+            self.buffer.remove(&lowest);
+        }
+    }
+
+    fn mark_invalid(&mut self, hashes: &[H256], latest_valid_hash: H256, error: ValidationError) {
+        let mut invalid_blocks = self.invalid_blocks.lock();
+        for hash in hashes {
+            self.buffer.remove(hash);
+            invalid_blocks.insert(
+                *hash,
+                PayloadStatus::invalid(Some(latest_valid_hash), &error),
+            );
+        }
+    }
+}
+
+fn is_canonical<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    number: BlockNumber,
+    hash: H256,
+) -> anyhow::Result<bool> {
+    Ok(tx.get(tables::CanonicalHeader, number)? == Some(hash))
+}
+
+fn insert_block<E: EnvironmentKind>(
+    tx: &MdbxTransaction<'_, RW, E>,
+    block: &Block,
+    hash: H256,
+) -> anyhow::Result<()> {
+    let number = block.header.number;
+    let parent_number = BlockNumber(number.0 - 1);
+    let parent_hash = block.header.parent_hash;
+
+    let total_difficulty = accessors::chain::td::read(tx, parent_hash, parent_number)?
+        .ok_or_else(|| format_err!("No total difficulty for block {}", parent_number))?
+        + block.header.difficulty;
+    let base_tx_id = tx
+        .get(tables::BlockBody, (parent_number, parent_hash))?
+        .ok_or_else(|| format_err!("No body for block {}", parent_number))?
+        .tx_id_range()
+        .end;
+
+    tx.set(tables::Header, (number, hash), block.header.clone())?;
+    tx.set(tables::CanonicalHeader, number, hash)?;
+    tx.set(
+        tables::HeadersTotalDifficulty,
+        (number, hash),
+        total_difficulty,
+    )?;
+    tx.set(tables::LastHeader, Default::default(), hash)?;
+
+    accessors::chain::storage_body::write(
+        tx,
+        hash,
+        number,
+        &BodyForStorage {
+            base_tx_id,
+            tx_amount: block.transactions.len().try_into()?,
+            uncles: vec![],
+            withdrawals: block.withdrawals.clone(),
+        },
+    )?;
+    accessors::chain::tx::write(tx, base_tx_id, &block.transactions)?;
+
+    Ok(())
+}
+
+#[async_trait]
+impl<'db, E> Stage<'db, E> for EngineHeaders
+where
+    E: EnvironmentKind,
+{
+    fn id(&self) -> StageId {
+        HEADERS
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> anyhow::Result<ExecOutput>
+    where
+        'db: 'tx,
+    {
+        let progress = input.stage_progress.unwrap_or_default();
+
+        self.receive_messages();
+
+        let unchanged = ExecOutput::Progress {
+            stage_progress: progress,
+            done: true,
+            must_commit: false,
+        };
+
+        let target = match self.target {
+            Some(target) => target,
+            None => return Ok(unchanged),
+        };
+
+        // Walk back from the fork choice head through the buffered payloads until the canonical chain is met.
+        let mut branch = vec![];
+        let mut hash = target;
+        let fork_point = loop {
+            // Payloads are known by their number, older blocks through the index built by block hashes.
+            let number = match self.buffer.get(&hash) {
+                Some(block) => block.header.number,
+                None => match tx.get(tables::HeaderNumber, hash)? {
+                    Some(number) => number,
+                    None => {
+                        self.wait_for(hash, target);
+                        return Ok(unchanged);
+                    }
+                },
+            };
+
+            if is_canonical(tx, number, hash)? {
+                break number;
+            }
+
+            match self.buffer.get(&hash) {
+                Some(block) => {
+                    branch.push(hash);
+                    hash = block.header.parent_hash;
+                }
+                None => {
+                    self.wait_for(hash, target);
+                    return Ok(unchanged);
+                }
+            }
+        };
+
+        self.set_missing(None);
+
+        if fork_point < progress {
+            return Ok(ExecOutput::Unwind {
+                unwind_to: fork_point,
+            });
+        }
+
+        if branch.is_empty() {
+            return Ok(unchanged);
+        }
+
+        let mut stage_progress = progress;
+        let mut latest_valid_hash = hash;
+        while let Some(hash) = branch.pop() {
+            let block = self.buffer.remove(&hash).unwrap();
+
+            match self.validator.validate_block(
+                &block,
+                &mut Buffer::new(tx, BlockNumber(0), None),
+                true,
+            ) {
+                Ok(()) => {}
+                Err(DuoError::Validation(e)) => {
+                    warn!("Rejecting payload {:?}: {}", hash, e);
+                    branch.push(hash);
+                    self.mark_invalid(&branch, latest_valid_hash, e);
+                    break;
+                }
+                Err(DuoError::Internal(e)) => return Err(e),
+            }
+
+            insert_block(tx, &block, hash)?;
+            stage_progress = block.header.number;
+            latest_valid_hash = hash;
+        }
+
+        Ok(ExecOutput::Progress {
+            stage_progress,
+            done: true,
+            must_commit: stage_progress > progress,
+        })
+    }
+
+    async fn unwind<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> anyhow::Result<UnwindOutput>
+    where
+        'db: 'tx,
+    {
+        let mut canonical = tx.cursor(tables::CanonicalHeader)?;
+        let mut e = canonical.last()?;
+        while let Some((number, _)) = e {
+            if number <= input.unwind_to {
+                break;
+            }
+
+            canonical.delete_current()?;
+            e = canonical.prev()?;
+        }
+
+        let hash = tx
+            .get(tables::CanonicalHeader, input.unwind_to)?
+            .ok_or_else(|| format_err!("No canonical hash for block {}", input.unwind_to))?;
+        tx.set(tables::LastHeader, Default::default(), hash)?;
+
+        Ok(UnwindOutput {
+            stage_progress: input.unwind_to,
+        })
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{
+        chain::protocol_param::param, kv::new_mem_database, res::chainspec::MAINNET,
+        state::genesis::initialize_genesis,
+    };
+    use std::time::Instant;
+
+    /// Mainnet genesis followed by proof of stake blocks with a base fee.
+    pub fn chain_spec() -> ChainSpec {
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.consensus.eip1559_block = Some(BlockNumber(1));
+        chain_spec.consensus.terminal_total_difficulty = Some(U256::ZERO);
+        chain_spec
+    }
+
+    /// Empty block on top of `parent`, `seed` telling apart siblings.
+    pub fn child(parent: &BlockHeader, seed: u8) -> Block {
+        let (gas_limit, base_fee_per_gas) = if parent.number == BlockNumber(0) {
+            (parent.gas_limit * 2, param::INITIAL_BASE_FEE.as_u256())
+        } else {
+            (parent.gas_limit, next_base_fee_per_gas(parent).unwrap())
+        };
+
+        Block::new(
+            PartialHeader {
+                parent_hash: parent.hash(),
+                number: parent.number + 1,
+                difficulty: U256::ZERO,
+                nonce: H64::zero(),
+                gas_limit,
+                gas_used: 0,
+                timestamp: parent.timestamp + 12,
+                extra_data: vec![seed].into(),
+                mix_hash: H256::repeat_byte(seed),
+                base_fee_per_gas: Some(base_fee_per_gas),
+                receipts_root: EMPTY_ROOT,
+                logs_bloom: Bloom::zero(),
+                ..parent.clone().into()
+            },
+            vec![],
+            vec![],
+            None,
+        )
+    }
+
+    fn input(stage_progress: BlockNumber) -> StageInput {
+        StageInput {
+            restarted: false,
+            is_first_run: false,
+            first_started_at: (Instant::now(), None),
+            previous_stage: None,
+            stage_progress: Some(stage_progress),
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_fork_choice() {
+        let db = new_mem_database().unwrap();
+        let etl_temp_dir = tempfile::tempdir().unwrap();
+        let mut tx = db.begin_mutable().unwrap();
+        initialize_genesis(&tx, &etl_temp_dir, chain_spec()).unwrap();
+
+        let genesis_hash = tx
+            .get(tables::CanonicalHeader, BlockNumber(0))
+            .unwrap()
+            .unwrap();
+        let genesis = tx
+            .get(tables::Header, (BlockNumber(0), genesis_hash))
+            .unwrap()
+            .unwrap();
+
+        let mut stage = EngineHeaders::new(&chain_spec());
+        let sender = stage.sender();
+
+        let a = child(&genesis, 1);
+        let b = child(&genesis, 2);
+        sender.send(EngineMessage::NewPayload(a.clone())).unwrap();
+        sender.send(EngineMessage::NewPayload(b.clone())).unwrap();
+
+        // Nothing to do before fork choice names a head.
+        assert_eq!(
+            stage.execute(&mut tx, input(BlockNumber(0))).await.unwrap(),
+            ExecOutput::Progress {
+                stage_progress: BlockNumber(0),
+                done: true,
+                must_commit: false,
+            }
+        );
+
+        sender
+            .send(EngineMessage::ForkchoiceUpdated(a.header.hash()))
+            .unwrap();
+        assert_eq!(
+            stage.execute(&mut tx, input(BlockNumber(0))).await.unwrap(),
+            ExecOutput::Progress {
+                stage_progress: BlockNumber(1),
+                done: true,
+                must_commit: true,
+            }
+        );
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(1)).unwrap(),
+            Some(a.header.hash())
+        );
+        assert!(tx
+            .get(tables::BlockBody, (BlockNumber(1), a.header.hash()))
+            .unwrap()
+            .is_some());
+
+        // Switching to the sibling takes an unwind first.
+        sender
+            .send(EngineMessage::ForkchoiceUpdated(b.header.hash()))
+            .unwrap();
+        assert_eq!(
+            stage.execute(&mut tx, input(BlockNumber(1))).await.unwrap(),
+            ExecOutput::Unwind {
+                unwind_to: BlockNumber(0)
+            }
+        );
+        stage
+            .unwind(
+                &mut tx,
+                UnwindInput {
+                    stage_progress: BlockNumber(1),
+                    unwind_to: BlockNumber(0),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(1)).unwrap(),
+            None
+        );
+
+        assert_eq!(
+            stage.execute(&mut tx, input(BlockNumber(0))).await.unwrap(),
+            ExecOutput::Progress {
+                stage_progress: BlockNumber(1),
+                done: true,
+                must_commit: true,
+            }
+        );
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(1)).unwrap(),
+            Some(b.header.hash())
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_payload() {
+        let db = new_mem_database().unwrap();
+        let etl_temp_dir = tempfile::tempdir().unwrap();
+        let mut tx = db.begin_mutable().unwrap();
+        initialize_genesis(&tx, &etl_temp_dir, chain_spec()).unwrap();
+
+        let genesis_hash = tx
+            .get(tables::CanonicalHeader, BlockNumber(0))
+            .unwrap()
+            .unwrap();
+        let genesis = tx
+            .get(tables::Header, (BlockNumber(0), genesis_hash))
+            .unwrap()
+            .unwrap();
+
+        let mut stage = EngineHeaders::new(&chain_spec());
+        let sender = stage.sender();
+
+        let mut bad = child(&genesis, 1);
+        bad.header.difficulty = U256::ONE;
+        let descendant = child(&bad.header, 2);
+        sender.send(EngineMessage::NewPayload(bad.clone())).unwrap();
+        sender
+            .send(EngineMessage::NewPayload(descendant.clone()))
+            .unwrap();
+        sender
+            .send(EngineMessage::ForkchoiceUpdated(descendant.header.hash()))
+            .unwrap();
+
+        assert_eq!(
+            stage.execute(&mut tx, input(BlockNumber(0))).await.unwrap(),
+            ExecOutput::Progress {
+                stage_progress: BlockNumber(0),
+                done: true,
+                must_commit: false,
+            }
+        );
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(1)).unwrap(),
+            None
+        );
+
+        let invalid_blocks = stage.invalid_blocks();
+        let invalid_blocks = invalid_blocks.lock();
+        for hash in [bad.header.hash(), descendant.header.hash()] {
+            let status = &invalid_blocks[&hash];
+            assert_eq!(status.status, PayloadValidationStatus::Invalid);
+            assert_eq!(status.latest_valid_hash, Some(genesis_hash));
+        }
+    }
+}
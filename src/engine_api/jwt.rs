@@ -0,0 +1,131 @@
+use anyhow::{bail, format_err, Context};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::*;
+use std::{
+    fmt::{self, Debug},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// How far, in seconds, the issue time of a token may stray from the local clock.
+pub const JWT_IAT_WINDOW: u64 = 60;
+
+#[derive(Deserialize, Serialize)]
+struct JwtClaims {
+    iat: u64,
+}
+
+/// Secret shared with the consensus layer, used to authenticate Engine API calls with HS256 tokens.
+#[derive(Clone, PartialEq, Eq)]
+pub struct JwtSecret([u8; 32]);
+
+impl Debug for JwtSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("JwtSecret(..)")
+    }
+}
+
+impl JwtSecret {
+    pub fn new(secret: [u8; 32]) -> Self {
+        Self(secret)
+    }
+
+    pub fn random() -> Self {
+        Self(rand::random())
+    }
+
+    /// Parses the hex encoding of the secret, as found in the secret file shared with the consensus client.
+    pub fn from_hex(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s))?;
+
+        Ok(Self(bytes.try_into().map_err(|bytes: Vec<u8>| {
+            format_err!("JWT secret must be 32 bytes long, got {}", bytes.len())
+        })?))
+    }
+
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
+    /// Reads the secret from a file holding its hex encoding, as passed with `--jwt-secret-file`.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        Self::from_hex(
+            &std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read JWT secret from {}", path.display()))?,
+        )
+    }
+
+    /// Token issued at `iat` seconds since the Unix epoch.
+    pub fn encode(&self, iat: u64) -> String {
+        jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &JwtClaims { iat },
+            &EncodingKey::from_secret(&self.0),
+        )
+        .unwrap()
+    }
+
+    /// Checks the signature of the token and that it was issued around the current time.
+    pub fn validate(&self, token: &str) -> anyhow::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.validate_at(token, now)
+    }
+
+    fn validate_at(&self, token: &str, now: u64) -> anyhow::Result<()> {
+        // Expiration is not used by the Engine API, freshness is judged by the issue time alone.
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = false;
+        validation.required_spec_claims.clear();
+
+        let JwtClaims { iat } = jsonwebtoken::decode::<JwtClaims>(
+            token,
+            &DecodingKey::from_secret(&self.0),
+            &validation,
+        )?
+        .claims;
+        if iat.max(now) - iat.min(now) > JWT_IAT_WINDOW {
+            bail!("JWT issued at {}, which is too far from now ({})", iat, now);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jwt() {
+        let secret = JwtSecret::from_hex(
+            "0x7365637265747365637265747365637265747365637265747365637265747365",
+        )
+        .unwrap();
+        assert_eq!(JwtSecret::from_hex(&secret.to_hex()).unwrap(), secret);
+        assert!(JwtSecret::from_hex("0x0102").is_err());
+
+        let token = secret.encode(1_000_000);
+        secret.validate_at(&token, 1_000_000).unwrap();
+        secret
+            .validate_at(&token, 1_000_000 + JWT_IAT_WINDOW)
+            .unwrap();
+        secret
+            .validate_at(&token, 1_000_000 - JWT_IAT_WINDOW)
+            .unwrap();
+        assert!(secret
+            .validate_at(&token, 1_000_000 + JWT_IAT_WINDOW + 1)
+            .is_err());
+        assert!(secret
+            .validate_at(&token, 1_000_000 - JWT_IAT_WINDOW - 1)
+            .is_err());
+
+        assert!(JwtSecret::random().validate_at(&token, 1_000_000).is_err());
+
+        let (message, _) = token.rsplit_once('.').unwrap();
+        let forged = format!("{}.{}", message, "A".repeat(43));
+        assert!(secret.validate_at(&forged, 1_000_000).is_err());
+        assert!(secret.validate_at("", 1_000_000).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+//! Engine API through which the consensus layer drives the chain after the merge.
+//!
+//! See https://github.com/ethereum/execution-apis/tree/main/src/engine
+mod backfill;
+mod headers;
+mod jwt;
+mod new_payload;
+pub mod opts;
+mod server;
+mod types;
+
+pub use self::{backfill::*, headers::*, jwt::*, new_payload::*, server::*, types::*};
@@ -0,0 +1,404 @@
+use super::{headers::*, types::*};
+use crate::{
+    kv::{mdbx::*, tables, MdbxWithDirHandle},
+    models::*,
+    stagedsync::stages::*,
+};
+use ::mdbx::WriteMap;
+use anyhow::format_err;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+/// Answers the consensus layer about payloads and fork choice, leaving their processing to staged sync.
+///
+/// Payloads are queued to [`EngineHeaders`], which inserts them once fork choice points at them, and are
+/// reported valid once staged sync has verified their state root. Nothing is ever written from here.
+#[derive(Debug)]
+pub struct EngineNewPayload {
+    db: Arc<MdbxWithDirHandle>,
+    sender: mpsc::UnboundedSender<EngineMessage>,
+    invalid_blocks: InvalidBlocks,
+}
+
+impl EngineNewPayload {
+    pub fn new(db: Arc<MdbxWithDirHandle>, headers: &EngineHeaders) -> Self {
+        Self {
+            db,
+            sender: headers.sender(),
+            invalid_blocks: headers.invalid_blocks(),
+        }
+    }
+
+    /// Number and hash of the last block with a verified state root.
+    pub fn head<K: TransactionKind>(
+        &self,
+        tx: &MdbxTransaction<'_, K, WriteMap>,
+    ) -> anyhow::Result<Option<(BlockNumber, H256)>> {
+        let number = INTERMEDIATE_HASHES.get_progress(tx)?.unwrap_or_default();
+
+        Ok(tx
+            .get(tables::CanonicalHeader, number)?
+            .map(|hash| (number, hash)))
+    }
+
+    fn is_valid<K: TransactionKind>(
+        &self,
+        tx: &MdbxTransaction<'_, K, WriteMap>,
+        number: BlockNumber,
+        hash: H256,
+    ) -> anyhow::Result<bool> {
+        Ok(match self.head(tx)? {
+            Some((head_number, _)) => {
+                number <= head_number && tx.get(tables::CanonicalHeader, number)? == Some(hash)
+            }
+            None => false,
+        })
+    }
+
+    fn send(&self, message: EngineMessage) -> anyhow::Result<()> {
+        self.sender
+            .send(message)
+            .map_err(|_| format_err!("Staged sync is not running"))
+    }
+
+    /// `engine_newPayload`
+    ///
+    /// Since V3 the consensus layer also passes the blob versioned hashes it expects in the payload and the parent
+    /// beacon block root.
+    pub async fn new_payload(
+        &mut self,
+        payload: ExecutionPayload,
+        expected_blob_versioned_hashes: Option<Vec<H256>>,
+        parent_beacon_block_root: Option<H256>,
+    ) -> anyhow::Result<PayloadStatus> {
+        let expected_hash = payload.block_hash;
+        let block = match payload.into_block(parent_beacon_block_root) {
+            Ok(block) => block,
+            Err(e) => return Ok(PayloadStatus::invalid(None, e)),
+        };
+
+        let hash = block.header.hash();
+        if hash != expected_hash {
+            return Ok(PayloadStatus::invalid_block_hash(format!(
+                "expected {:?}, got {:?}",
+                expected_hash, hash
+            )));
+        }
+
+        if let Some(expected_blob_versioned_hashes) = expected_blob_versioned_hashes {
+            let blob_versioned_hashes = block
+                .transactions
+                .iter()
+                .flat_map(|txn| txn.blob_versioned_hashes())
+                .copied()
+                .collect::<Vec<_>>();
+            if blob_versioned_hashes != expected_blob_versioned_hashes {
+                return Ok(PayloadStatus::invalid(
+                    None,
+                    format!(
+                        "expected blob versioned hashes {:?}, got {:?}",
+                        expected_blob_versioned_hashes, blob_versioned_hashes
+                    ),
+                ));
+            }
+        }
+
+        let parent_hash = block.header.parent_hash;
+        {
+            let invalid_blocks = self.invalid_blocks.lock();
+            if let Some(status) = invalid_blocks.get(&hash) {
+                return Ok(status.clone());
+            }
+            if let Some(status) = invalid_blocks.get(&parent_hash) {
+                return Ok(PayloadStatus::invalid(
+                    status.latest_valid_hash,
+                    format!("descends from invalid block {:?}", parent_hash),
+                ));
+            }
+        }
+
+        let tx = self.db.begin()?;
+
+        let number = block.header.number;
+        if self.is_valid(&tx, number, hash)? {
+            return Ok(PayloadStatus::valid(hash));
+        }
+
+        let parent_known = match number.0.checked_sub(1) {
+            Some(parent_number) => tx
+                .get(tables::Header, (BlockNumber(parent_number), parent_hash))?
+                .is_some(),
+            None => false,
+        };
+
+        self.send(EngineMessage::NewPayload(block))?;
+
+        Ok(if parent_known {
+            PayloadStatus::accepted()
+        } else {
+            PayloadStatus::syncing()
+        })
+    }
+
+    /// `engine_forkchoiceUpdated`
+    ///
+    /// Block building is not supported, so no payload is ever started.
+    pub fn forkchoice_updated(
+        &self,
+        fork_choice_state: ForkchoiceState,
+    ) -> anyhow::Result<ForkchoiceUpdatedResult> {
+        let head_hash = fork_choice_state.head_block_hash;
+
+        if let Some(status) = self.invalid_blocks.lock().get(&head_hash) {
+            return Ok(ForkchoiceUpdatedResult {
+                payload_status: status.clone(),
+                payload_id: None,
+            });
+        }
+
+        self.send(EngineMessage::ForkchoiceUpdated(head_hash))?;
+
+        let tx = self.db.begin()?;
+        let is_valid = match tx.get(tables::HeaderNumber, head_hash)? {
+            Some(number) => self.is_valid(&tx, number, head_hash)?,
+            None => false,
+        };
+
+        Ok(ForkchoiceUpdatedResult {
+            payload_status: if is_valid {
+                PayloadStatus::valid(head_hash)
+            } else {
+                PayloadStatus::syncing()
+            },
+            payload_id: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        engine_api::headers::tests::{chain_spec, child},
+        kv::new_mem_database,
+        res::chainspec::MAINNET,
+        stagedsync::StagedSync,
+        stages::*,
+        state::genesis::initialize_genesis,
+    };
+
+    fn payload(header: &BlockHeader) -> ExecutionPayload {
+        ExecutionPayload {
+            parent_hash: header.parent_hash,
+            fee_recipient: header.beneficiary,
+            state_root: header.state_root,
+            receipts_root: header.receipts_root,
+            logs_bloom: header.logs_bloom,
+            prev_randao: header.mix_hash,
+            block_number: header.number.0.into(),
+            gas_limit: header.gas_limit.into(),
+            gas_used: header.gas_used.into(),
+            timestamp: header.timestamp.into(),
+            extra_data: header.extra_data.clone(),
+            base_fee_per_gas: header.base_fee_per_gas.unwrap_or(U256::ZERO),
+            block_hash: header.hash(),
+            transactions: vec![],
+            withdrawals: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn payloads_against_database() {
+        let db = Arc::new(new_mem_database().unwrap());
+        let etl_temp_dir = Arc::new(tempfile::tempdir().unwrap());
+        {
+            let tx = db.begin_mutable().unwrap();
+            initialize_genesis(&tx, &etl_temp_dir, MAINNET.clone()).unwrap();
+            tx.commit().unwrap();
+        }
+
+        let headers = EngineHeaders::new(&MAINNET);
+        let mut engine = EngineNewPayload::new(db.clone(), &headers);
+
+        let tx = db.begin().unwrap();
+        let (head_number, head_hash) = engine.head(&tx).unwrap().unwrap();
+        let genesis = tx
+            .get(tables::Header, (head_number, head_hash))
+            .unwrap()
+            .unwrap();
+        drop(tx);
+        assert_eq!(head_number, BlockNumber(0));
+
+        let mut genesis_payload = payload(&genesis);
+        genesis_payload.block_hash = H256::repeat_byte(0xaa);
+        assert_eq!(
+            engine
+                .new_payload(genesis_payload, None, None)
+                .await
+                .unwrap()
+                .status,
+            PayloadValidationStatus::InvalidBlockHash
+        );
+
+        let orphan = BlockHeader {
+            parent_hash: H256::repeat_byte(0xbb),
+            number: BlockNumber(1),
+            difficulty: U256::ZERO,
+            nonce: H64::zero(),
+            base_fee_per_gas: Some(U256::ZERO),
+            ..genesis.clone()
+        };
+        assert_eq!(
+            engine
+                .new_payload(payload(&orphan), None, None)
+                .await
+                .unwrap(),
+            PayloadStatus::syncing()
+        );
+
+        let fork_choice = |head_block_hash| ForkchoiceState {
+            head_block_hash,
+            safe_block_hash: head_block_hash,
+            finalized_block_hash: head_block_hash,
+        };
+        assert_eq!(
+            engine
+                .forkchoice_updated(fork_choice(head_hash))
+                .unwrap()
+                .payload_status,
+            PayloadStatus::valid(head_hash)
+        );
+        assert_eq!(
+            engine
+                .forkchoice_updated(fork_choice(orphan.hash()))
+                .unwrap()
+                .payload_status,
+            PayloadStatus::syncing()
+        );
+
+        let next = BlockHeader {
+            parent_hash: head_hash,
+            ..orphan
+        };
+        assert_eq!(
+            engine
+                .new_payload(payload(&next), None, None)
+                .await
+                .unwrap(),
+            PayloadStatus::accepted()
+        );
+
+        // Payloads are left to staged sync.
+        assert_eq!(HEADERS.get_progress(&db.begin().unwrap()).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn missing_parent_backfilled() {
+        let chain_spec = chain_spec();
+        let db = Arc::new(new_mem_database().unwrap());
+        let etl_temp_dir = Arc::new(tempfile::tempdir().unwrap());
+        let genesis = {
+            let tx = db.begin_mutable().unwrap();
+            initialize_genesis(&tx, &etl_temp_dir, chain_spec.clone()).unwrap();
+            let hash = tx
+                .get(tables::CanonicalHeader, BlockNumber(0))
+                .unwrap()
+                .unwrap();
+            let genesis = tx
+                .get(tables::Header, (BlockNumber(0), hash))
+                .unwrap()
+                .unwrap();
+            tx.commit().unwrap();
+            genesis
+        };
+
+        let headers = EngineHeaders::new(&chain_spec);
+        let mut engine = EngineNewPayload::new(db.clone(), &headers);
+        let missing_blocks = headers.missing_blocks();
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(headers);
+        staged_sync.push(TotalGasIndex);
+        staged_sync.push(BlockHashes {
+            temp_dir: etl_temp_dir.clone(),
+        });
+        staged_sync.push(TotalTxIndex);
+        staged_sync.push(SenderRecovery {
+            batch_size: 1024,
+            shutdown: Default::default(),
+        });
+        staged_sync.push(Execution {
+            batch_size: u64::MAX,
+            history_batch_size: u64::MAX,
+            exit_after_batch: false,
+            batch_until: None,
+            commit_every: None,
+            prune_from: BlockNumber(0),
+            shutdown: Default::default(),
+        });
+        staged_sync.push(HashState::new(
+            etl_temp_dir.clone(),
+            None,
+            Default::default(),
+        ));
+        staged_sync.push(Interhashes::new(etl_temp_dir.clone(), None));
+
+        let parent = child(&genesis, 1).header;
+        let block = child(&parent, 2).header;
+        let fork_choice = ForkchoiceState {
+            head_block_hash: block.hash(),
+            safe_block_hash: genesis.hash(),
+            finalized_block_hash: genesis.hash(),
+        };
+
+        assert_eq!(
+            engine
+                .new_payload(payload(&block), None, None)
+                .await
+                .unwrap(),
+            PayloadStatus::syncing()
+        );
+        assert_eq!(
+            engine
+                .forkchoice_updated(fork_choice)
+                .unwrap()
+                .payload_status,
+            PayloadStatus::syncing()
+        );
+
+        // Staged sync waits for the parent.
+        staged_sync.run_once(&db).await.unwrap();
+        assert_eq!(*missing_blocks.borrow(), Some(parent.hash()));
+        assert_eq!(
+            HEADERS.get_progress(&db.begin().unwrap()).unwrap(),
+            Some(BlockNumber(0))
+        );
+
+        assert_eq!(
+            engine
+                .new_payload(payload(&parent), None, None)
+                .await
+                .unwrap(),
+            PayloadStatus::accepted()
+        );
+        staged_sync.run_once(&db).await.unwrap();
+        assert_eq!(*missing_blocks.borrow(), None);
+
+        assert_eq!(
+            engine
+                .forkchoice_updated(fork_choice)
+                .unwrap()
+                .payload_status,
+            PayloadStatus::valid(block.hash())
+        );
+        assert_eq!(
+            engine
+                .new_payload(payload(&block), None, None)
+                .await
+                .unwrap(),
+            PayloadStatus::valid(block.hash())
+        );
+    }
+}
@@ -0,0 +1,30 @@
+use super::JwtSecret;
+use anyhow::format_err;
+use clap::Parser;
+use std::{net::SocketAddr, path::PathBuf};
+
+#[derive(Parser, Debug)]
+pub struct Opts {
+    #[clap(
+        long = "engine.addr",
+        help = "Address the Engine API listens on.",
+        default_value = "127.0.0.1:8551"
+    )]
+    pub listen_address: SocketAddr,
+    #[clap(
+        long = "jwt-secret-file",
+        help = "File with the hex encoded secret shared with the consensus client.",
+        parse(from_os_str)
+    )]
+    pub jwt_secret_file: Option<PathBuf>,
+}
+
+impl Opts {
+    pub fn jwt_secret(&self) -> anyhow::Result<JwtSecret> {
+        JwtSecret::from_file(
+            self.jwt_secret_file.as_ref().ok_or_else(|| {
+                format_err!("--jwt-secret-file is required to serve the Engine API")
+            })?,
+        )
+    }
+}
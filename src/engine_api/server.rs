@@ -0,0 +1,273 @@
+use super::{jwt::*, new_payload::*, types::*};
+use crate::models::*;
+use anyhow::format_err;
+use async_trait::async_trait;
+use hyper::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, HeaderMap, Method, Request, Response, StatusCode,
+};
+use jsonrpsee::{
+    core::{server::rpc_module::Methods, RpcResult},
+    proc_macros::rpc,
+};
+use std::{convert::Infallible, net::SocketAddr, sync::Arc};
+use tokio::sync::Mutex;
+use tracing::*;
+
+/// Port the consensus layer connects to by default.
+pub const DEFAULT_ENGINE_API_PORT: u16 = 8551;
+
+#[rpc(server, namespace = "engine")]
+pub trait EngineApi {
+    #[method(name = "newPayloadV1")]
+    async fn new_payload_v1(&self, payload: ExecutionPayload) -> RpcResult<PayloadStatus>;
+    #[method(name = "newPayloadV3")]
+    async fn new_payload_v3(
+        &self,
+        payload: ExecutionPayload,
+        expected_blob_versioned_hashes: Vec<H256>,
+        parent_beacon_block_root: H256,
+    ) -> RpcResult<PayloadStatus>;
+    #[method(name = "forkchoiceUpdatedV1")]
+    async fn forkchoice_updated_v1(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        payload_attributes: Option<PayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdatedResult>;
+    #[method(name = "getPayloadV1")]
+    async fn get_payload_v1(&self, payload_id: H64) -> RpcResult<ExecutionPayload>;
+}
+
+#[derive(Clone, Debug)]
+pub struct EngineApiServerImpl {
+    inner: Arc<Mutex<EngineNewPayload>>,
+}
+
+impl EngineApiServerImpl {
+    pub fn new(inner: EngineNewPayload) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+}
+
+#[async_trait]
+impl EngineApiServer for EngineApiServerImpl {
+    async fn new_payload_v1(&self, payload: ExecutionPayload) -> RpcResult<PayloadStatus> {
+        Ok(self
+            .inner
+            .lock()
+            .await
+            .new_payload(payload, None, None)
+            .await?)
+    }
+
+    async fn new_payload_v3(
+        &self,
+        payload: ExecutionPayload,
+        expected_blob_versioned_hashes: Vec<H256>,
+        parent_beacon_block_root: H256,
+    ) -> RpcResult<PayloadStatus> {
+        Ok(self
+            .inner
+            .lock()
+            .await
+            .new_payload(
+                payload,
+                Some(expected_blob_versioned_hashes),
+                Some(parent_beacon_block_root),
+            )
+            .await?)
+    }
+
+    async fn forkchoice_updated_v1(
+        &self,
+        fork_choice_state: ForkchoiceState,
+        _: Option<PayloadAttributes>,
+    ) -> RpcResult<ForkchoiceUpdatedResult> {
+        Ok(self
+            .inner
+            .lock()
+            .await
+            .forkchoice_updated(fork_choice_state)?)
+    }
+
+    async fn get_payload_v1(&self, payload_id: H64) -> RpcResult<ExecutionPayload> {
+        Err(format_err!("Unknown payload {:?}", payload_id).into())
+    }
+}
+
+fn authenticate(secret: &JwtSecret, headers: &HeaderMap) -> anyhow::Result<()> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .ok_or_else(|| format_err!("Missing authorization header"))?
+        .to_str()?
+        .strip_prefix("Bearer ")
+        .ok_or_else(|| format_err!("Authorization is not a bearer token"))?;
+
+    secret.validate(token)
+}
+
+fn respond(status: StatusCode, body: impl Into<Body>) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(body.into())
+        .unwrap()
+}
+
+async fn authenticate_and_handle(
+    req: Request<Body>,
+    methods: Methods,
+    secret: JwtSecret,
+) -> Result<Response<Body>, hyper::Error> {
+    if let Err(e) = authenticate(&secret, req.headers()) {
+        debug!("Rejecting Engine API request: {}", e);
+        return Ok(respond(StatusCode::UNAUTHORIZED, e.to_string()));
+    }
+
+    if req.method() != Method::POST {
+        return Ok(respond(StatusCode::METHOD_NOT_ALLOWED, Body::empty()));
+    }
+
+    let body = hyper::body::to_bytes(req.into_body()).await?;
+    let call = match std::str::from_utf8(&body) {
+        Ok(call) => call,
+        Err(e) => return Ok(respond(StatusCode::BAD_REQUEST, e.to_string())),
+    };
+
+    Ok(match methods.raw_json_request(call).await {
+        Ok((response, _)) => Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(response))
+            .unwrap(),
+        Err(e) => respond(StatusCode::BAD_REQUEST, e.to_string()),
+    })
+}
+
+/// Serves the Engine API on `listen_address`, turning away requests without a valid JWT signed with `secret`.
+pub async fn run_engine_api_server(
+    listen_address: SocketAddr,
+    secret: JwtSecret,
+    api: EngineApiServerImpl,
+) -> anyhow::Result<()> {
+    let methods = Methods::from(api.into_rpc());
+    let make_service = make_service_fn(move |_| {
+        let methods = methods.clone();
+        let secret = secret.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                authenticate_and_handle(req, methods.clone(), secret.clone())
+            }))
+        }
+    });
+
+    info!("Engine API listening on {}", listen_address);
+
+    hyper::Server::try_bind(&listen_address)?
+        .serve(make_service)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+    use jsonrpsee::RpcModule;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    fn bearer(token: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Bearer {}", token)).unwrap(),
+        );
+        headers
+    }
+
+    #[test]
+    fn jwt_authentication() {
+        let secret = JwtSecret::random();
+
+        // valid
+        authenticate(&secret, &bearer(&secret.encode(now()))).unwrap();
+
+        // expired
+        assert!(authenticate(
+            &secret,
+            &bearer(&secret.encode(now() - JWT_IAT_WINDOW - 10))
+        )
+        .is_err());
+
+        // signed with another secret
+        assert!(authenticate(&secret, &bearer(&JwtSecret::random().encode(now()))).is_err());
+
+        // missing
+        assert!(authenticate(&secret, &HeaderMap::new()).is_err());
+
+        // not a bearer token
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&secret.encode(now())).unwrap(),
+        );
+        assert!(authenticate(&secret, &headers).is_err());
+    }
+
+    fn request(headers: HeaderMap, body: &str) -> Request<Body> {
+        let mut req = Request::post("/")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        *req.headers_mut() = headers;
+        req
+    }
+
+    #[tokio::test]
+    async fn unauthenticated_request_rejected() {
+        let res = authenticate_and_handle(
+            request(HeaderMap::new(), ""),
+            Methods::new(),
+            JwtSecret::random(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn authenticated_request_handled() {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("engine_test", |_, _| Ok("pong"))
+            .unwrap();
+
+        let secret = JwtSecret::random();
+        let res = authenticate_and_handle(
+            request(
+                bearer(&secret.encode(now())),
+                r#"{"jsonrpc":"2.0","id":1,"method":"engine_test","params":[]}"#,
+            ),
+            module.into(),
+            secret,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(res.into_body()).await.unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({"jsonrpc": "2.0", "result": "pong", "id": 1})
+        );
+    }
+}
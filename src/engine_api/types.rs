@@ -0,0 +1,306 @@
+use crate::{models::*, util::*};
+use bytes::Bytes;
+use rlp::DecoderError;
+use serde::*;
+
+/// Execution layer part of a proof of stake block, `ExecutionPayloadV1` of the Engine API.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionPayload {
+    pub parent_hash: H256,
+    pub fee_recipient: Address,
+    pub state_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: Bloom,
+    pub prev_randao: H256,
+    pub block_number: U64,
+    pub gas_limit: U64,
+    pub gas_used: U64,
+    pub timestamp: U64,
+    #[serde(with = "hexbytes")]
+    pub extra_data: Bytes,
+    pub base_fee_per_gas: U256,
+    pub block_hash: H256,
+    #[serde(with = "hexbytes_list")]
+    pub transactions: Vec<Bytes>,
+    /// Since `ExecutionPayloadV2`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawals: Option<Vec<PayloadWithdrawal>>,
+    /// Since `ExecutionPayloadV3`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blob_gas_used: Option<U64>,
+    /// Since `ExecutionPayloadV3`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excess_blob_gas: Option<U64>,
+}
+
+/// Withdrawal as carried by the payload, `WithdrawalV1` of the Engine API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadWithdrawal {
+    pub index: U64,
+    pub validator_index: U64,
+    pub address: Address,
+    pub amount: U64,
+}
+
+impl From<PayloadWithdrawal> for Withdrawal {
+    fn from(withdrawal: PayloadWithdrawal) -> Self {
+        Self {
+            index: withdrawal.index.as_u64(),
+            validator_index: withdrawal.validator_index.as_u64(),
+            address: withdrawal.address,
+            amount: withdrawal.amount.as_u64(),
+        }
+    }
+}
+
+impl From<Withdrawal> for PayloadWithdrawal {
+    fn from(withdrawal: Withdrawal) -> Self {
+        Self {
+            index: withdrawal.index.into(),
+            validator_index: withdrawal.validator_index.into(),
+            address: withdrawal.address,
+            amount: withdrawal.amount.into(),
+        }
+    }
+}
+
+impl ExecutionPayload {
+    /// Block carried by the payload.
+    ///
+    /// Fields retired by the merge are set to their post-merge constants and
+    /// `prevRandao` takes the place of the mix hash.
+    /// `parent_beacon_block_root` is passed next to the payload since `engine_newPayloadV3`.
+    /// The block hash is not checked against `block_hash`.
+    pub fn into_block(self, parent_beacon_block_root: Option<H256>) -> Result<Block, DecoderError> {
+        let transactions = self
+            .transactions
+            .iter()
+            .map(|tx| MessageWithSignature::trie_decode(tx))
+            .collect::<Result<Vec<_>, _>>()?;
+        let withdrawals = self.withdrawals.map(|withdrawals| {
+            withdrawals
+                .into_iter()
+                .map(Withdrawal::from)
+                .collect::<Vec<_>>()
+        });
+
+        Ok(Block::new(
+            PartialHeader {
+                parent_hash: self.parent_hash,
+                beneficiary: self.fee_recipient,
+                state_root: self.state_root,
+                receipts_root: self.receipts_root,
+                logs_bloom: self.logs_bloom,
+                difficulty: U256::ZERO,
+                number: BlockNumber(self.block_number.as_u64()),
+                gas_limit: self.gas_limit.as_u64(),
+                gas_used: self.gas_used.as_u64(),
+                timestamp: self.timestamp.as_u64(),
+                extra_data: self.extra_data,
+                mix_hash: self.prev_randao,
+                nonce: H64::zero(),
+                base_fee_per_gas: Some(self.base_fee_per_gas),
+                withdrawals_root: withdrawals
+                    .as_ref()
+                    .map(|withdrawals| Block::withdrawals_root(withdrawals)),
+                blob_gas_used: self.blob_gas_used.map(|v| v.as_u64()),
+                excess_blob_gas: self.excess_blob_gas.map(|v| v.as_u64()),
+                parent_beacon_block_root,
+            },
+            transactions,
+            vec![],
+            withdrawals,
+        ))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PayloadValidationStatus {
+    Valid,
+    Invalid,
+    Syncing,
+    Accepted,
+    InvalidBlockHash,
+}
+
+/// Outcome of handing a payload or a fork choice to the execution layer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadStatus {
+    pub status: PayloadValidationStatus,
+    pub latest_valid_hash: Option<H256>,
+    pub validation_error: Option<String>,
+}
+
+impl PayloadStatus {
+    pub fn valid(hash: H256) -> Self {
+        Self {
+            status: PayloadValidationStatus::Valid,
+            latest_valid_hash: Some(hash),
+            validation_error: None,
+        }
+    }
+
+    pub fn invalid(latest_valid_hash: Option<H256>, error: impl ToString) -> Self {
+        Self {
+            status: PayloadValidationStatus::Invalid,
+            latest_valid_hash,
+            validation_error: Some(error.to_string()),
+        }
+    }
+
+    pub fn invalid_block_hash(error: impl ToString) -> Self {
+        Self {
+            status: PayloadValidationStatus::InvalidBlockHash,
+            latest_valid_hash: None,
+            validation_error: Some(error.to_string()),
+        }
+    }
+
+    pub fn syncing() -> Self {
+        Self {
+            status: PayloadValidationStatus::Syncing,
+            latest_valid_hash: None,
+            validation_error: None,
+        }
+    }
+
+    pub fn accepted() -> Self {
+        Self {
+            status: PayloadValidationStatus::Accepted,
+            latest_valid_hash: None,
+            validation_error: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoiceState {
+    pub head_block_hash: H256,
+    pub safe_block_hash: H256,
+    pub finalized_block_hash: H256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadAttributes {
+    pub timestamp: U64,
+    pub prev_randao: H256,
+    pub suggested_fee_recipient: Address,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForkchoiceUpdatedResult {
+    pub payload_status: PayloadStatus,
+    pub payload_id: Option<H64>,
+}
+
+mod hexbytes_list {
+    use super::*;
+    use serde::{de, Deserializer, Serializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Bytes>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Vec::<String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|s| Bytes::from_hex_string(&s).map_err(de::Error::custom))
+            .collect()
+    }
+
+    pub fn serialize<S>(list: &[Bytes], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(list.iter().map(EncodeHex::to_hex_string))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+    use serde_json::json;
+
+    #[test]
+    fn payload_into_block() {
+        let payload: ExecutionPayload = serde_json::from_value(json!({
+            "parentHash": "0x3b8fb240d288781d4aac94d3fd16809ee413bc99294a085798a589dae51ddd4a",
+            "feeRecipient": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+            "stateRoot": "0xca3149fa9e37db08d1cd49c9061db1002ef1cd58db2210f2115c8c989b2bdf45",
+            "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "prevRandao": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "blockNumber": "0x1",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x5",
+            "extraData": "0x",
+            "baseFeePerGas": "0x7",
+            "blockHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "transactions": []
+        }))
+        .unwrap();
+
+        let block = payload.clone().into_block(None).unwrap();
+        assert_eq!(
+            block.header.parent_hash,
+            H256(hex!(
+                "3b8fb240d288781d4aac94d3fd16809ee413bc99294a085798a589dae51ddd4a"
+            ))
+        );
+        assert_eq!(block.header.number, BlockNumber(1));
+        assert_eq!(block.header.difficulty, U256::ZERO);
+        assert_eq!(block.header.ommers_hash, EMPTY_LIST_HASH);
+        assert_eq!(block.header.transactions_root, EMPTY_ROOT);
+        assert_eq!(block.header.base_fee_per_gas, Some(7_u64.as_u256()));
+        assert_eq!(block.header.withdrawals_root, None);
+        assert_eq!(block.header.blob_gas_used, None);
+        assert_eq!(block.header.parent_beacon_block_root, None);
+        assert!(block.ommers.is_empty());
+
+        assert_eq!(
+            serde_json::from_value::<ExecutionPayload>(serde_json::to_value(&payload).unwrap())
+                .unwrap(),
+            payload
+        );
+
+        // Cancun payloads carry withdrawals and blob gas, the beacon root comes next to them.
+        let mut json = serde_json::to_value(&payload).unwrap();
+        json["withdrawals"] = json!([{
+            "index": "0x1",
+            "validatorIndex": "0x2",
+            "address": "0xa94f5374fce5edbc8e2a8697c15331677e6ebf0b",
+            "amount": "0x3"
+        }]);
+        json["blobGasUsed"] = json!("0x20000");
+        json["excessBlobGas"] = json!("0x0");
+        let payload: ExecutionPayload = serde_json::from_value(json).unwrap();
+
+        let parent_beacon_block_root = H256::repeat_byte(0xbe);
+        let block = payload.into_block(Some(parent_beacon_block_root)).unwrap();
+        let withdrawals = vec![Withdrawal {
+            index: 1,
+            validator_index: 2,
+            address: hex!("a94f5374fce5edbc8e2a8697c15331677e6ebf0b").into(),
+            amount: 3,
+        }];
+        assert_eq!(
+            block.header.withdrawals_root,
+            Some(Block::withdrawals_root(&withdrawals))
+        );
+        assert_eq!(block.withdrawals, Some(withdrawals));
+        assert_eq!(block.header.blob_gas_used, Some(0x20000));
+        assert_eq!(block.header.excess_blob_gas, Some(0));
+        assert_eq!(
+            block.header.parent_beacon_block_root,
+            Some(parent_beacon_block_root)
+        );
+    }
+}
@@ -0,0 +1,102 @@
+use akula::{
+    execution::{
+        analysis_cache::AnalysisCache, block_hash_cache::BlockHashCache, evmglue,
+        tracer::NoopTracer,
+    },
+    models::*,
+    res::chainspec::MAINNET,
+    InMemoryState, IntraBlockState,
+};
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, Criterion};
+use hex_literal::hex;
+
+/// Blocks in the chain, more than `BLOCKHASH` can reach back.
+const BLOCKS: u64 = 300;
+
+fn block_hash_benchmarks(c: &mut Criterion) {
+    let mut db = InMemoryState::default();
+    let mut parent_hash = H256::zero();
+    for number in 0..BLOCKS {
+        let header = BlockHeader {
+            number: BlockNumber(number),
+            parent_hash,
+            ..BlockHeader::empty()
+        };
+        parent_hash = header.hash();
+        db.insert_block(
+            Block {
+                header,
+                transactions: vec![],
+                ommers: vec![],
+                withdrawals: None,
+            },
+            parent_hash,
+        );
+    }
+
+    let contract = hex!("c000000000000000000000000000000000000001").into();
+    // Stores BLOCKHASH(NUMBER - 256), the oldest one available, into slot 0.
+    let code = Bytes::from_static(&hex!("610100430340600055"));
+
+    let mut state = IntraBlockState::new(&mut db);
+    state.set_code(contract, code).unwrap();
+
+    let header = PartialHeader {
+        number: BlockNumber(BLOCKS),
+        parent_hash,
+        ..PartialHeader::empty()
+    };
+    let block_spec = MAINNET.collect_block_spec(header.number, None);
+    let txn = MessageWithSender {
+        message: Message::Legacy {
+            action: TransactionAction::Call(contract),
+            chain_id: Default::default(),
+            nonce: Default::default(),
+            gas_price: Default::default(),
+            gas_limit: Default::default(),
+            value: Default::default(),
+            input: Default::default(),
+        },
+        sender: hex!("0a6bb546b9208cfab9e8fa2b9b2c042b18df7030").into(),
+    };
+
+    let mut analysis_cache = AnalysisCache::default();
+    let mut block_hash_cache = BlockHashCache::default();
+
+    let mut group = c.benchmark_group("blockhash");
+    group.bench_function("uncached", |b| {
+        b.iter(|| {
+            evmglue::execute(
+                &mut state,
+                &mut NoopTracer,
+                &mut analysis_cache,
+                None,
+                &header,
+                &block_spec,
+                &txn,
+                100_000,
+            )
+            .unwrap()
+        })
+    });
+    group.bench_function("cached", |b| {
+        b.iter(|| {
+            evmglue::execute(
+                &mut state,
+                &mut NoopTracer,
+                &mut analysis_cache,
+                Some(&mut block_hash_cache),
+                &header,
+                &block_spec,
+                &txn,
+                100_000,
+            )
+            .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, block_hash_benchmarks);
+criterion_main!(benches);
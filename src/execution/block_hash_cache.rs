@@ -0,0 +1,74 @@
+use crate::models::*;
+use lru::LruCache;
+
+/// Ancestor hashes for the `BLOCKHASH` opcode, shared across consecutive blocks of the same chain.
+#[derive(Debug)]
+pub struct BlockHashCache {
+    inner: LruCache<BlockNumber, H256>,
+    hits: u64,
+    misses: u64,
+}
+
+impl Default for BlockHashCache {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+impl BlockHashCache {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            inner: LruCache::new(cap),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn get(&mut self, block_number: BlockNumber) -> Option<H256> {
+        let hash = self.inner.get(&block_number).copied();
+        if hash.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        hash
+    }
+
+    /// Inserts the hash, dropping all cached hashes if it belongs to a different chain.
+    pub fn insert(&mut self, block_number: BlockNumber, hash: H256) {
+        if let Some(&cached) = self.inner.peek(&block_number) {
+            if cached != hash {
+                self.inner.clear();
+            }
+        }
+        self.inner.put(block_number, hash);
+    }
+
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_chains_drops_hashes() {
+        let mut cache = BlockHashCache::default();
+        cache.insert(BlockNumber(9), H256::repeat_byte(9));
+        cache.insert(BlockNumber(10), H256::repeat_byte(10));
+
+        assert_eq!(cache.get(BlockNumber(9)), Some(H256::repeat_byte(9)));
+        assert_eq!(cache.get(BlockNumber(8)), None);
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        cache.insert(BlockNumber(10), H256::repeat_byte(0xaa));
+        assert_eq!(cache.get(BlockNumber(9)), None);
+        assert_eq!(cache.get(BlockNumber(10)), Some(H256::repeat_byte(0xaa)));
+    }
+}
@@ -5,7 +5,7 @@ use super::{
     CreateMessage,
 };
 use bytes::Bytes;
-use ethereum_types::Address;
+use ethereum_types::{Address, H256};
 use ethnum::U256;
 
 /// State access status (EIP-2929).
@@ -56,6 +56,10 @@ pub struct TxContext {
     pub chain_id: U256,
     /// The block base fee per gas (EIP-1559, EIP-3198).
     pub block_base_fee: U256,
+    /// The versioned hashes of the transaction's blobs (EIP-4844).
+    pub tx_blob_hashes: Vec<H256>,
+    /// The block price of a unit of blob gas (EIP-4844, EIP-7516).
+    pub block_blob_base_fee: U256,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -150,7 +150,7 @@ pub(crate) fn do_create<H: Host, const REVISION: Revision, const CREATE2: bool>(
     host: &mut H,
 ) -> Result<(), StatusCode> {
     use crate::{
-        execution::evm::{common::*, host::*, CreateMessage},
+        execution::evm::{common::*, host::*, CreateMessage, MAX_INITCODE_SIZE},
         models::*,
     };
     use ethnum::U256;
@@ -166,6 +166,21 @@ pub(crate) fn do_create<H: Host, const REVISION: Revision, const CREATE2: bool>(
     let region = memory::get_memory_region(state, init_code_offset, init_code_size)
         .map_err(|_| StatusCode::OutOfGas)?;
 
+    // https://eips.ethereum.org/EIPS/eip-3860
+    if REVISION >= Revision::Shanghai {
+        if let Some(region) = &region {
+            if region.size.get() > MAX_INITCODE_SIZE {
+                return Err(StatusCode::OutOfGas);
+            }
+
+            let initcode_cost = memory::num_words(region.size.get()) * 2;
+            state.gas_left -= initcode_cost;
+            if state.gas_left < 0 {
+                return Err(StatusCode::OutOfGas);
+            }
+        }
+    }
+
     let salt = if CREATE2 {
         let salt = state.stack.pop();
 
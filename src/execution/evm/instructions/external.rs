@@ -2,6 +2,7 @@ use crate::{
     execution::evm::{
         common::address_to_u256, host::*, instructions::memory, state::ExecutionState, StatusCode,
     },
+    h256_to_u256,
     models::Revision,
 };
 use ethnum::U256;
@@ -148,6 +149,21 @@ pub(crate) fn blockhash<H: Host>(
     Ok(())
 }
 
+/// Versioned hash of the transaction's blob at the index on top of the stack, zero past the last blob.
+///
+/// See https://eips.ethereum.org/EIPS/eip-4844#opcode-to-get-versioned-hashes
+pub(crate) fn blobhash<H: Host>(state: &mut ExecutionState, host: &mut H) {
+    let index = state.stack.pop();
+
+    let blob_hashes = host.get_tx_context().tx_blob_hashes;
+    let mut hash = U256::ZERO;
+    if index < blob_hashes.len() as u128 {
+        hash = h256_to_u256(blob_hashes[index.as_usize()]);
+    }
+
+    state.stack.push(hash);
+}
+
 #[inline]
 #[allow(clippy::collapsible_if)]
 pub(crate) fn do_log<H: Host, const NUM_TOPICS: usize>(
@@ -87,6 +87,8 @@ pub const fn properties<const OPCODE: OpCode>() -> Properties {
         OpCode::CHAINID => Properties::new(0, 1),
         OpCode::SELFBALANCE => Properties::new(0, 1),
         OpCode::BASEFEE => Properties::new(0, 1),
+        OpCode::BLOBHASH => Properties::new(1, 0),
+        OpCode::BLOBBASEFEE => Properties::new(0, 1),
 
         OpCode::POP => Properties::new(1, -1),
         OpCode::MLOAD => Properties::new(1, 0),
@@ -100,6 +102,7 @@ pub const fn properties<const OPCODE: OpCode>() -> Properties {
         OpCode::MSIZE => Properties::new(0, 1),
         OpCode::GAS => Properties::new(0, 1),
         OpCode::JUMPDEST => Properties::new(0, 0),
+        OpCode::PUSH0 => Properties::new(0, 1),
 
         OpCode::PUSH1 => Properties::new(0, 1),
         OpCode::PUSH2 => Properties::new(0, 1),
@@ -385,6 +388,10 @@ const fn gas_costs() -> GasCostTable {
     table[Revision::London as usize][OpCode::BASEFEE.to_usize()] = 2;
 
     table[Revision::Shanghai as usize] = table[Revision::London as usize];
+    table[Revision::Shanghai as usize][OpCode::PUSH0.to_usize()] = 2;
+    table[Revision::Cancun as usize] = table[Revision::Shanghai as usize];
+    table[Revision::Cancun as usize][OpCode::BLOBHASH.to_usize()] = 3;
+    table[Revision::Cancun as usize][OpCode::BLOBBASEFEE.to_usize()] = 2;
 
     table
 }
@@ -476,6 +483,8 @@ const fn property_table() -> [Option<Properties>; 256] {
     table[OpCode::CHAINID.to_usize()] = Some(Properties::new(0, 1));
     table[OpCode::SELFBALANCE.to_usize()] = Some(Properties::new(0, 1));
     table[OpCode::BASEFEE.to_usize()] = Some(Properties::new(0, 1));
+    table[OpCode::BLOBHASH.to_usize()] = Some(Properties::new(1, 0));
+    table[OpCode::BLOBBASEFEE.to_usize()] = Some(Properties::new(0, 1));
 
     table[OpCode::POP.to_usize()] = Some(Properties::new(1, -1));
     table[OpCode::MLOAD.to_usize()] = Some(Properties::new(1, 0));
@@ -489,6 +498,7 @@ const fn property_table() -> [Option<Properties>; 256] {
     table[OpCode::MSIZE.to_usize()] = Some(Properties::new(0, 1));
     table[OpCode::GAS.to_usize()] = Some(Properties::new(0, 1));
     table[OpCode::JUMPDEST.to_usize()] = Some(Properties::new(0, 0));
+    table[OpCode::PUSH0.to_usize()] = Some(Properties::new(0, 1));
 
     table[OpCode::PUSH1.to_usize()] = Some(Properties::new(0, 1));
     table[OpCode::PUSH2.to_usize()] = Some(Properties::new(0, 1));
@@ -160,6 +160,9 @@ impl AnalyzedCode {
             (true, Revision::Shanghai) => {
                 execute_message::<H, true, { Revision::Shanghai }>(self, &mut state, host)
             }
+            (true, Revision::Cancun) => {
+                execute_message::<H, true, { Revision::Cancun }>(self, &mut state, host)
+            }
             (false, Revision::Frontier) => {
                 execute_message::<H, false, { Revision::Frontier }>(self, &mut state, host)
             }
@@ -193,6 +196,9 @@ impl AnalyzedCode {
             (false, Revision::Shanghai) => {
                 execute_message::<H, false, { Revision::Shanghai }>(self, &mut state, host)
             }
+            (false, Revision::Cancun) => {
+                execute_message::<H, false, { Revision::Cancun }>(self, &mut state, host)
+            }
         };
 
         match res {
@@ -393,10 +399,13 @@ where
                 .push(host.get_tx_context().block_gas_limit.into()),
             OpCode::CHAINID => state.stack.push(host.get_tx_context().chain_id),
             OpCode::BASEFEE => state.stack.push(host.get_tx_context().block_base_fee),
+            OpCode::BLOBHASH => external::blobhash(state, host),
+            OpCode::BLOBBASEFEE => state.stack.push(host.get_tx_context().block_blob_base_fee),
             OpCode::SELFBALANCE => {
                 external::selfbalance(state, host);
             }
             OpCode::POP => pop(&mut state.stack),
+            OpCode::PUSH0 => state.stack.push(U256::ZERO),
             OpCode::MLOAD => memory::mload(state)?,
             OpCode::MSTORE => memory::mstore(state)?,
             OpCode::MSTORE8 => memory::mstore8(state)?,
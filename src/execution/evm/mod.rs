@@ -10,6 +10,9 @@ pub use state::{ExecutionState, Stack};
 /// Maximum allowed EVM bytecode size.
 pub const MAX_CODE_SIZE: usize = 0x6000;
 
+/// Maximum allowed EVM initcode size.
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
 mod common;
 pub mod host;
 #[macro_use]
@@ -73,6 +73,8 @@ impl OpCode {
     pub const CHAINID: OpCode = OpCode(0x46);
     pub const SELFBALANCE: OpCode = OpCode(0x47);
     pub const BASEFEE: OpCode = OpCode(0x48);
+    pub const BLOBHASH: OpCode = OpCode(0x49);
+    pub const BLOBBASEFEE: OpCode = OpCode(0x4a);
 
     pub const POP: OpCode = OpCode(0x50);
     pub const MLOAD: OpCode = OpCode(0x51);
@@ -86,6 +88,7 @@ impl OpCode {
     pub const MSIZE: OpCode = OpCode(0x59);
     pub const GAS: OpCode = OpCode(0x5a);
     pub const JUMPDEST: OpCode = OpCode(0x5b);
+    pub const PUSH0: OpCode = OpCode(0x5f);
 
     pub const PUSH1: OpCode = OpCode(0x60);
     pub const PUSH2: OpCode = OpCode(0x61);
@@ -226,6 +229,8 @@ impl OpCode {
             OpCode::CHAINID => "CHAINID",
             OpCode::SELFBALANCE => "SELFBALANCE",
             OpCode::BASEFEE => "BASEFEE",
+            OpCode::BLOBHASH => "BLOBHASH",
+            OpCode::BLOBBASEFEE => "BLOBBASEFEE",
             OpCode::POP => "POP",
             OpCode::MLOAD => "MLOAD",
             OpCode::MSTORE => "MSTORE",
@@ -238,6 +243,7 @@ impl OpCode {
             OpCode::MSIZE => "MSIZE",
             OpCode::GAS => "GAS",
             OpCode::JUMPDEST => "JUMPDEST",
+            OpCode::PUSH0 => "PUSH0",
             OpCode::PUSH1 => "PUSH1",
             OpCode::PUSH2 => "PUSH2",
             OpCode::PUSH3 => "PUSH3",
@@ -0,0 +1,53 @@
+use crate::{
+    execution::evm::{opcode::*, util::*, *},
+    models::*,
+};
+
+#[test]
+fn blob_opcodes_pre_cancun() {
+    for opcode in [OpCode::BLOBHASH, OpCode::BLOBBASEFEE] {
+        EvmTester::new()
+            .revision(Revision::Shanghai)
+            .code(Bytecode::new().pushv(0).opcode(opcode))
+            .status(StatusCode::UndefinedInstruction)
+            .check()
+    }
+}
+
+#[test]
+fn blobhash() {
+    // https://eips.ethereum.org/EIPS/eip-4844#opcode-to-get-versioned-hashes
+    let t = EvmTester::new()
+        .revision(Revision::Cancun)
+        .apply_host_fn(|host, _| {
+            host.tx_context.tx_blob_hashes = vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02)];
+        });
+
+    t.clone()
+        .code(Bytecode::new().pushv(1).opcode(OpCode::BLOBHASH).ret_top())
+        .status(StatusCode::Success)
+        .gas_used(21)
+        .output_data(H256::repeat_byte(0x02).0)
+        .check();
+
+    // Past the last blob.
+    t.code(Bytecode::new().pushv(2).opcode(OpCode::BLOBHASH).ret_top())
+        .status(StatusCode::Success)
+        .output_value(0_u128)
+        .check()
+}
+
+#[test]
+fn blobbasefee() {
+    // https://eips.ethereum.org/EIPS/eip-7516
+    EvmTester::new()
+        .revision(Revision::Cancun)
+        .apply_host_fn(|host, _| {
+            host.tx_context.block_blob_base_fee = 7_u128.into();
+        })
+        .code(Bytecode::new().opcode(OpCode::BLOBBASEFEE).ret_top())
+        .status(StatusCode::Success)
+        .gas_used(17)
+        .output_value(7_u128)
+        .check()
+}
@@ -1,6 +1,8 @@
 mod basefee;
+mod blob;
 mod call;
 mod eip2929;
 mod execute;
 mod other;
+mod push0;
 mod state;
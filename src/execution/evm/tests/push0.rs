@@ -0,0 +1,43 @@
+use crate::{
+    execution::evm::{opcode::*, util::*, *},
+    models::*,
+};
+
+#[test]
+fn push0_pre_shanghai() {
+    EvmTester::new()
+        .revision(Revision::London)
+        .code(Bytecode::new().opcode(OpCode::PUSH0))
+        .status(StatusCode::UndefinedInstruction)
+        .check()
+}
+
+#[test]
+fn push0() {
+    // https://eips.ethereum.org/EIPS/eip-3855#test-cases
+    let t = EvmTester::new().revision(Revision::Shanghai);
+    t.clone()
+        .code(Bytecode::new().opcode(OpCode::PUSH0).opcode(OpCode::STOP))
+        .status(StatusCode::Success)
+        .gas_used(2)
+        .check();
+
+    t.code(Bytecode::new().opcode(OpCode::PUSH0).ret_top())
+        .status(StatusCode::Success)
+        .gas_used(17)
+        .output_value(0_u128)
+        .check()
+}
+
+#[test]
+fn push0_stack_overflow() {
+    let t = EvmTester::new().revision(Revision::Shanghai);
+    t.clone()
+        .code(1024 * OpCode::PUSH0)
+        .status(StatusCode::Success)
+        .check();
+
+    t.code(1025 * OpCode::PUSH0)
+        .status(StatusCode::StackOverflow)
+        .check()
+}
@@ -86,6 +86,8 @@ impl Default for MockedHost {
                 block_difficulty: U256::ZERO,
                 chain_id: U256::ZERO,
                 block_base_fee: U256::ZERO,
+                tx_blob_hashes: vec![],
+                block_blob_base_fee: U256::ZERO,
             },
             block_hash: U256::ZERO,
             call_result: Output {
@@ -1,11 +1,13 @@
 use super::{
     address::*,
     analysis_cache::AnalysisCache,
+    block_hash_cache::BlockHashCache,
     precompiled,
     tracer::{CodeKind, MessageKind, Tracer},
 };
 use crate::{
     chain::protocol_param::{fee, param},
+    consensus::blob_gas_price,
     crypto::keccak256,
     execution::evm::{
         host::*, AnalyzedCode, CallKind, CreateMessage, InterpreterMessage, Output, StatusCode,
@@ -34,6 +36,7 @@ where
     state: &'state mut IntraBlockState<'r, B>,
     tracer: &'tracer mut dyn Tracer,
     analysis_cache: &'analysis mut AnalysisCache,
+    block_hash_cache: Option<&'analysis mut BlockHashCache>,
     header: &'h PartialHeader,
     block_spec: &'c BlockExecutionSpec,
     txn: &'t MessageWithSender,
@@ -44,6 +47,7 @@ pub fn execute<'db, 'tracer, 'analysis, B: State>(
     state: &mut IntraBlockState<'db, B>,
     tracer: &'tracer mut dyn Tracer,
     analysis_cache: &'analysis mut AnalysisCache,
+    block_hash_cache: Option<&'analysis mut BlockHashCache>,
     header: &PartialHeader,
     block_spec: &BlockExecutionSpec,
     txn: &MessageWithSender,
@@ -53,6 +57,7 @@ pub fn execute<'db, 'tracer, 'analysis, B: State>(
         header,
         tracer,
         analysis_cache,
+        block_hash_cache,
         state,
         block_spec,
         txn,
@@ -95,17 +100,13 @@ where
     B: State,
 {
     fn create(&mut self, message: &CreateMessage) -> anyhow::Result<Output> {
-        let mut res = Output {
-            status_code: StatusCode::Success,
-            gas_left: message.gas,
-            output_data: Bytes::new(),
-            create_address: None,
-        };
-
-        let value = message.endowment;
-        if self.state.get_balance(message.sender)? < value {
-            res.status_code = StatusCode::InsufficientBalance;
-            return Ok(res);
+        if self.state.get_balance(message.sender)? < message.endowment {
+            return Ok(Output {
+                status_code: StatusCode::InsufficientBalance,
+                gas_left: message.gas,
+                output_data: Bytes::new(),
+                create_address: None,
+            });
         }
 
         let nonce = self.state.get_nonce(message.sender)?;
@@ -131,6 +132,27 @@ where
             message.endowment,
         );
 
+        let res = self.create_contract(message, contract_addr)?;
+
+        self.tracer.capture_end(&res);
+
+        Ok(res)
+    }
+
+    fn create_contract(
+        &mut self,
+        message: &CreateMessage,
+        contract_addr: Address,
+    ) -> anyhow::Result<Output> {
+        let mut res = Output {
+            status_code: StatusCode::Success,
+            gas_left: message.gas,
+            output_data: Bytes::new(),
+            create_address: None,
+        };
+
+        let value = message.endowment;
+
         if self.state.get_nonce(contract_addr)? != 0
             || self.state.get_code_hash(contract_addr)? != EMPTY_HASH
         {
@@ -238,6 +260,18 @@ where
             message.value,
         );
 
+        let res = self.call_code(message, code_kind)?;
+
+        self.tracer.capture_end(&res);
+
+        Ok(res)
+    }
+
+    fn call_code(
+        &mut self,
+        message: &InterpreterMessage,
+        code_kind: CodeKind,
+    ) -> anyhow::Result<Output> {
         // https://eips.ethereum.org/EIPS/eip-161
         if message.value == 0
             && self.block_spec.revision >= Revision::Spurious
@@ -344,11 +378,7 @@ where
 
         let mut host = EvmHost { inner: self };
 
-        let output = analysis.execute(&mut host, msg, revision);
-
-        self.tracer.capture_end(&output);
-
-        Ok(output)
+        Ok(analysis.execute(&mut host, msg, revision))
     }
 
     fn number_of_precompiles(&self) -> u8 {
@@ -362,6 +392,7 @@ where
             Revision::Istanbul | Revision::Berlin | Revision::London | Revision::Shanghai => {
                 precompiled::NUM_OF_ISTANBUL_CONTRACTS as u8
             }
+            Revision::Cancun => precompiled::NUM_OF_CANCUN_CONTRACTS as u8,
         }
     }
 
@@ -553,7 +584,7 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
             .unwrap();
         self.inner.state.set_balance(address, 0).unwrap();
 
-        self.tracer(|t| t.capture_self_destruct(address, beneficiary));
+        self.tracer(|t| t.capture_self_destruct(address, beneficiary, balance));
     }
 
     fn call(&mut self, msg: Call) -> Output {
@@ -581,9 +612,21 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
         let block_number = self.inner.header.number.0;
         let block_timestamp = self.inner.header.timestamp;
         let block_gas_limit = self.inner.header.gas_limit;
-        let block_difficulty = self.inner.header.difficulty;
+        // Past the merge DIFFICULTY became PREVRANDAO, the beacon chain randomness carried in `mix_hash`.
+        // Proof-of-stake blocks are the only ones with zero difficulty.
+        // https://eips.ethereum.org/EIPS/eip-4399
+        let post_merge = self.inner.block_spec.revision >= Revision::Shanghai
+            || (self.inner.block_spec.terminal_total_difficulty.is_some()
+                && self.inner.header.difficulty == U256::ZERO);
+        let block_difficulty = if post_merge {
+            h256_to_u256(self.inner.header.mix_hash)
+        } else {
+            self.inner.header.difficulty
+        };
         let chain_id = self.inner.block_spec.params.chain_id.0.into();
         let block_base_fee = base_fee_per_gas;
+        let tx_blob_hashes = self.inner.txn.blob_versioned_hashes().to_vec();
+        let block_blob_base_fee = blob_gas_price(self.inner.header.excess_blob_gas.unwrap_or(0));
 
         TxContext {
             tx_gas_price,
@@ -595,6 +638,8 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
             block_difficulty,
             chain_id,
             block_base_fee,
+            tx_blob_hashes,
+            block_blob_base_fee,
         }
     }
 
@@ -605,6 +650,13 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
 
         let mut hash = self.inner.header.parent_hash;
 
+        if let Some(cache) = self.inner.block_hash_cache.as_mut() {
+            cache.insert(BlockNumber(base_number.0 - 1), hash);
+            if let Some(hash) = cache.get(BlockNumber(block_number)) {
+                return h256_to_u256(hash);
+            }
+        }
+
         for i in 1..distance {
             hash = self
                 .inner
@@ -615,6 +667,10 @@ impl<'r, 'state, 'tracer, 'analysis, 'h, 'c, 't, 'a, B: State> Host
                 .context("no header")
                 .unwrap()
                 .parent_hash;
+
+            if let Some(cache) = self.inner.block_hash_cache.as_mut() {
+                cache.insert(BlockNumber(base_number.0 - i - 1), hash);
+            }
         }
 
         h256_to_u256(hash)
@@ -659,8 +715,9 @@ mod tests {
             state,
             &mut tracer,
             &mut AnalysisCache::default(),
+            None,
             header,
-            &MAINNET.collect_block_spec(header.number),
+            &MAINNET.collect_block_spec(header.number, None),
             txn,
             gas,
         )
@@ -1132,4 +1189,126 @@ mod tests {
             StatusCode::Success
         );
     }
+
+    #[test]
+    fn prevrandao() {
+        // DIFFICULTY (PREVRANDAO), PUSH1 0, MSTORE, PUSH1 32, PUSH1 0, RETURN
+        let code = hex!("4460005260206000f3");
+        let contract = hex!("2000000000000000000000000000000000000000").into();
+        let caller = hex!("1000000000000000000000000000000000000000").into();
+        let mix_hash = H256::repeat_byte(0xab);
+
+        let mut db = InMemoryState::default();
+        let mut state = IntraBlockState::new(&mut db);
+        state.set_code(contract, code.to_vec().into()).unwrap();
+
+        let txn = MessageWithSender {
+            message: Message::Legacy {
+                action: TransactionAction::Call(contract),
+
+                chain_id: Default::default(),
+                nonce: Default::default(),
+                gas_price: Default::default(),
+                gas_limit: Default::default(),
+                value: Default::default(),
+                input: Default::default(),
+            },
+            sender: caller,
+        };
+
+        // First proof-of-stake block on mainnet.
+        let header = PartialHeader {
+            number: 15_537_394.into(),
+            difficulty: U256::ZERO,
+            mix_hash,
+            ..PartialHeader::empty()
+        };
+        let res = execute(&mut state, &header, &txn, 100_000);
+        assert_eq!(res.status_code, StatusCode::Success);
+        assert_eq!(res.output_data, mix_hash.as_bytes());
+
+        // Proof-of-work blocks still return the difficulty.
+        let header = PartialHeader {
+            number: 15_537_393.into(),
+            difficulty: 0x2000.as_u256(),
+            mix_hash,
+            ..PartialHeader::empty()
+        };
+        let res = execute(&mut state, &header, &txn, 100_000);
+        assert_eq!(res.status_code, StatusCode::Success);
+        assert_eq!(res.output_data, u256_to_h256(0x2000.as_u256()).as_bytes());
+    }
+
+    #[test]
+    fn block_hash_cache() {
+        let mut db = InMemoryState::default();
+        let mut parent_hash = H256::zero();
+        let mut hashes = vec![];
+        for number in 0..=10 {
+            let header = BlockHeader {
+                number: BlockNumber(number),
+                parent_hash,
+                ..BlockHeader::empty()
+            };
+            parent_hash = header.hash();
+            hashes.push(parent_hash);
+            db.insert_block(
+                Block {
+                    header,
+                    transactions: vec![],
+                    ommers: vec![],
+                    withdrawals: None,
+                },
+                parent_hash,
+            );
+        }
+
+        let contract = hex!("c000000000000000000000000000000000000001").into();
+        // Stores BLOCKHASH(5) into slot 0.
+        let code = Bytes::from_static(&hex!("600540600055"));
+
+        let mut state = IntraBlockState::new(&mut db);
+        state.set_code(contract, code).unwrap();
+
+        let header = PartialHeader {
+            number: 11.into(),
+            parent_hash,
+            ..PartialHeader::empty()
+        };
+        let txn = MessageWithSender {
+            message: Message::Legacy {
+                action: TransactionAction::Call(contract),
+                chain_id: Default::default(),
+                nonce: Default::default(),
+                gas_price: Default::default(),
+                gas_limit: Default::default(),
+                value: Default::default(),
+                input: Default::default(),
+            },
+            sender: hex!("0a6bb546b9208cfab9e8fa2b9b2c042b18df7030").into(),
+        };
+
+        let mut cache = BlockHashCache::default();
+        for _ in 0..3 {
+            let res = super::execute(
+                &mut state,
+                &mut NoopTracer,
+                &mut AnalysisCache::default(),
+                Some(&mut cache),
+                &header,
+                &MAINNET.collect_block_spec(header.number, None),
+                &txn,
+                50_000,
+            )
+            .unwrap();
+            assert_eq!(res.status_code, StatusCode::Success);
+            assert_eq!(
+                state.get_current_storage(contract, U256::ZERO).unwrap(),
+                h256_to_u256(hashes[5])
+            );
+        }
+
+        // Only the first lookup walks the headers.
+        assert_eq!((cache.hits(), cache.misses()), (2, 1));
+    }
 }
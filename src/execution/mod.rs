@@ -1,8 +1,13 @@
-use self::{analysis_cache::AnalysisCache, processor::ExecutionProcessor, tracer::NoopTracer};
+use self::{
+    analysis_cache::AnalysisCache,
+    processor::ExecutionProcessor,
+    tracer::{BlockTrace, CallTrace, CallTreeTracer, NoopTracer, RewardType, Tracer},
+};
 use crate::{consensus, crypto::*, models::*, State};
 
 pub mod address;
 pub mod analysis_cache;
+pub mod block_hash_cache;
 pub mod evm;
 pub mod evmglue;
 pub mod precompiled;
@@ -16,9 +21,9 @@ pub fn execute_block<S: State>(
     block: &BlockBodyWithSenders,
 ) -> anyhow::Result<Vec<Receipt>> {
     let mut analysis_cache = AnalysisCache::default();
-    let mut engine = consensus::engine_factory(config.clone())?;
+    let mut engine = consensus::engine_factory(&config)?;
     let mut tracer = NoopTracer;
-    let config = config.collect_block_spec(header.number);
+    let config = config.collect_header_spec(header);
     ExecutionProcessor::new(
         state,
         &mut tracer,
@@ -31,12 +36,115 @@ pub fn execute_block<S: State>(
     .execute_and_write_block()
 }
 
+/// Executes the block without post validation, returning receipts.
+///
+/// Nothing is written to `state`.
+pub fn replay_block<S: State>(
+    state: &mut S,
+    config: &ChainSpec,
+    header: &PartialHeader,
+    block: &BlockBodyWithSenders,
+) -> anyhow::Result<Vec<Receipt>> {
+    let mut analysis_cache = AnalysisCache::default();
+    let mut engine = consensus::engine_factory(&config)?;
+    let mut tracer = NoopTracer;
+    let block_spec = config.collect_header_spec(header);
+    ExecutionProcessor::new(
+        state,
+        &mut tracer,
+        &mut analysis_cache,
+        &mut *engine,
+        header,
+        block,
+        &block_spec,
+    )
+    .execute_block_no_post_validation()
+}
+
+/// Executes the block recording call traces of every transaction and the rewards.
+///
+/// Nothing is written to `state`.
+pub fn trace_block<S: State>(
+    state: &mut S,
+    config: &ChainSpec,
+    header: &PartialHeader,
+    block: &BlockBodyWithSenders,
+) -> anyhow::Result<BlockTrace> {
+    let mut analysis_cache = AnalysisCache::default();
+    let mut engine = consensus::engine_factory(&config)?;
+    let mut tracer = CallTreeTracer::default();
+    let block_spec = config.collect_header_spec(header);
+    ExecutionProcessor::new(
+        state,
+        &mut tracer,
+        &mut analysis_cache,
+        &mut *engine,
+        header,
+        block,
+        &block_spec,
+    )
+    .execute_block_no_post_validation()?;
+
+    // Ommer rewards come first, in the order of ommers.
+    let mut rewards = engine
+        .finalize(header, &block.ommers, block_spec.revision)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, change)| match change {
+            consensus::FinalizationChange::Reward { address, amount } => CallTrace::reward(
+                address,
+                if i < block.ommers.len() {
+                    RewardType::Uncle
+                } else {
+                    RewardType::Block
+                },
+                amount,
+            ),
+        })
+        .collect::<Vec<_>>();
+    rewards.rotate_left(block.ommers.len().min(rewards.len()));
+
+    Ok(BlockTrace {
+        transactions: tracer.into_traces(),
+        rewards,
+    })
+}
+
+/// Executes transaction at `index` in the block with `tracer`, on top of the state before the block.
+///
+/// Returns the receipt and gas used by the transaction. Nothing is written to `state`.
+pub fn trace_transaction<S: State>(
+    state: &mut S,
+    config: &ChainSpec,
+    header: &PartialHeader,
+    block: &BlockBodyWithSenders,
+    index: usize,
+    tracer: &mut dyn Tracer,
+) -> anyhow::Result<(Receipt, u64)> {
+    let mut analysis_cache = AnalysisCache::default();
+    let mut engine = consensus::engine_factory(&config)?;
+    let mut noop_tracer = NoopTracer;
+    let config = config.collect_header_spec(header);
+    ExecutionProcessor::new(
+        state,
+        &mut noop_tracer,
+        &mut analysis_cache,
+        &mut *engine,
+        header,
+        block,
+        &config,
+    )
+    .trace_transaction(index, tracer)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{address::create_address, *};
+    use super::{address::create_address, tracer::StructLogger, *};
     use crate::{
-        chain::protocol_param::param, crypto::root_hash, res::chainspec::MAINNET, InMemoryState,
+        chain::protocol_param::param, crypto::root_hash, res::chainspec::MAINNET, u256_to_h256,
+        InMemoryState,
     };
+    use bytes::Bytes;
     use hex_literal::hex;
     use sha3::{Digest, Keccak256};
 
@@ -142,6 +250,7 @@ mod tests {
             &BlockBodyWithSenders {
                 transactions: vec![tx],
                 ommers: vec![],
+                withdrawals: None,
             },
         )
         .unwrap();
@@ -193,6 +302,7 @@ mod tests {
             &BlockBodyWithSenders {
                 transactions: vec![tx],
                 ommers: vec![],
+                withdrawals: None,
             },
         )
         .unwrap();
@@ -204,4 +314,304 @@ mod tests {
         assert!(miner_account.balance > 2 * param::BLOCK_REWARD_CONSTANTINOPLE);
         assert!(miner_account.balance < 3 * param::BLOCK_REWARD_CONSTANTINOPLE);
     }
+
+    #[test]
+    fn trace_second_transaction() {
+        let header = PartialHeader {
+            number: 13_500_001.into(),
+            gas_limit: 100_000,
+            ..PartialHeader::empty()
+        };
+
+        let sender = hex!("b685342b8c54347aad148e1f22eff3eb3eb29391").into();
+        let contract = hex!("c000000000000000000000000000000000000001").into();
+
+        // Stores the first word of the input into slot 0.
+        let code = Bytes::from_static(&hex!("60003560005500"));
+        let code_hash = keccak256(&code);
+
+        let mut state = InMemoryState::default();
+        state.update_account(
+            sender,
+            None,
+            Some(Account {
+                balance: ETHER.into(),
+                ..Default::default()
+            }),
+        );
+        state.update_account(
+            contract,
+            None,
+            Some(Account {
+                code_hash,
+                ..Default::default()
+            }),
+        );
+        state.update_code(code_hash, code).unwrap();
+
+        let t = |nonce, input| MessageWithSender {
+            message: Message::EIP1559 {
+                chain_id: ChainId(1),
+                nonce,
+                max_priority_fee_per_gas: U256::ZERO,
+                max_fee_per_gas: U256::from(20 * GIGA),
+                gas_limit: 50_000,
+                action: TransactionAction::Call(contract),
+                value: U256::ZERO,
+                input,
+                access_list: Default::default(),
+            },
+            sender,
+        };
+
+        let block = BlockBodyWithSenders {
+            transactions: vec![
+                (t)(0, 0x2a.as_u256().to_be_bytes().to_vec().into()),
+                (t)(1, 0x3e.as_u256().to_be_bytes().to_vec().into()),
+            ],
+            ommers: vec![],
+            withdrawals: None,
+        };
+
+        let mut tracer = StructLogger::new(Default::default());
+        let (receipt, gas_used) =
+            trace_transaction(&mut state, &MAINNET, &header, &block, 1, &mut tracer).unwrap();
+        assert!(receipt.success);
+        assert!(gas_used < receipt.cumulative_gas_used);
+
+        let logs = tracer.logs();
+        assert_eq!(
+            logs.iter().map(|log| (log.pc, log.op)).collect::<Vec<_>>(),
+            vec![
+                (0, "PUSH1"),
+                (2, "CALLDATALOAD"),
+                (3, "PUSH1"),
+                (5, "SSTORE"),
+                (6, "STOP")
+            ]
+        );
+        assert!(logs.iter().all(|log| log.depth == 1));
+        assert_eq!(logs[3].stack, Some(vec![0x3e.as_u256(), U256::ZERO]));
+        assert_eq!(
+            logs[4].storage,
+            Some([(H256::zero(), u256_to_h256(0x3e.as_u256()))].into())
+        );
+
+        // Nothing is written to the state.
+        assert_eq!(state.read_storage(contract, U256::ZERO).unwrap(), 0);
+        assert_eq!(state.read_account(sender).unwrap().unwrap().nonce, 0);
+    }
+
+    #[test]
+    fn trace_nested_calls() {
+        let miner = hex!("5a0b54d5dc17e0aadc383d2db43b0a0d3e029c4c").into();
+        let header = PartialHeader {
+            number: 13_500_001.into(),
+            beneficiary: miner,
+            gas_limit: 100_000,
+            ..PartialHeader::empty()
+        };
+
+        let sender = hex!("b685342b8c54347aad148e1f22eff3eb3eb29391").into();
+        let caller = hex!("c000000000000000000000000000000000000001").into();
+        let callee = hex!("c000000000000000000000000000000000000002").into();
+        let refund = hex!("c000000000000000000000000000000000000003").into();
+
+        // Calls the callee with 10000 gas, then self-destructs to the refund address.
+        let caller_code = Bytes::from_static(&hex!(
+            "6000600060006000600073c000000000000000000000000000000000000002612710f15073c000000000000000000000000000000000000003ff"
+        ));
+        // Returns 0x2a as a 32 byte word.
+        let callee_code = Bytes::from_static(&hex!("602a60005260206000f3"));
+
+        let mut state = InMemoryState::default();
+        state.update_account(
+            sender,
+            None,
+            Some(Account {
+                balance: ETHER.into(),
+                ..Default::default()
+            }),
+        );
+        for (address, code, balance) in [
+            (caller, caller_code, 1000.as_u256()),
+            (callee, callee_code, U256::ZERO),
+        ] {
+            let code_hash = keccak256(&code);
+            state.update_account(
+                address,
+                None,
+                Some(Account {
+                    balance,
+                    code_hash,
+                    ..Default::default()
+                }),
+            );
+            state.update_code(code_hash, code).unwrap();
+        }
+
+        let block = BlockBodyWithSenders {
+            transactions: vec![MessageWithSender {
+                message: Message::EIP1559 {
+                    chain_id: ChainId(1),
+                    nonce: 0,
+                    max_priority_fee_per_gas: U256::ZERO,
+                    max_fee_per_gas: U256::from(20 * GIGA),
+                    gas_limit: 100_000,
+                    action: TransactionAction::Call(caller),
+                    value: U256::ZERO,
+                    input: Bytes::new(),
+                    access_list: Default::default(),
+                },
+                sender,
+            }],
+            ommers: vec![],
+            withdrawals: None,
+        };
+
+        let trace = trace_block(&mut state, &MAINNET, &header, &block).unwrap();
+
+        assert_eq!(trace.transactions.len(), 1);
+        let traces = &trace.transactions[0];
+        assert_eq!(traces.len(), 3);
+
+        assert_eq!(traces[0].trace_address, Vec::<usize>::new());
+        assert_eq!(traces[0].subtraces, 2);
+        assert_eq!(
+            traces[0].action,
+            tracer::call_tree::Action::Call(tracer::call_tree::CallAction {
+                call_type: tracer::CallKind::Call,
+                from: sender,
+                to: caller,
+                gas: 79_000.into(),
+                input: Bytes::new(),
+                value: U256::ZERO,
+            })
+        );
+        assert!(traces[0].error.is_none());
+
+        assert_eq!(traces[1].trace_address, vec![0]);
+        assert_eq!(traces[1].subtraces, 0);
+        match &traces[1].action {
+            tracer::call_tree::Action::Call(action) => {
+                assert_eq!(action.from, caller);
+                assert_eq!(action.to, callee);
+                assert_eq!(action.gas, 10_000.into());
+            }
+            other => panic!("unexpected action {:?}", other),
+        }
+        match &traces[1].result {
+            Some(tracer::call_tree::TraceOutput::Call(output)) => {
+                assert_eq!(
+                    output.output,
+                    Bytes::from(0x2a.as_u256().to_be_bytes().to_vec())
+                );
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        assert_eq!(traces[2].trace_address, vec![1]);
+        assert_eq!(
+            traces[2].action,
+            tracer::call_tree::Action::SelfDestruct(tracer::call_tree::SelfDestructAction {
+                address: caller,
+                refund_address: refund,
+                balance: 1000.as_u256(),
+            })
+        );
+
+        assert_eq!(
+            trace.rewards,
+            vec![CallTrace::reward(
+                miner,
+                RewardType::Block,
+                param::BLOCK_REWARD_CONSTANTINOPLE.into()
+            )]
+        );
+
+        // Nothing is written to the state.
+        assert!(state.read_account(refund).unwrap().is_none());
+    }
+
+    #[test]
+    fn trace_cancun_block() {
+        let header = PartialHeader {
+            number: 19_426_587.into(),
+            gas_limit: 30_000_000,
+            timestamp: 1_710_338_135,
+            parent_beacon_block_root: Some(H256::repeat_byte(0xbe)),
+            ..PartialHeader::empty()
+        };
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.shanghai = Some(17_034_870.into());
+        chain_spec.upgrades.cancun = Some(header.number);
+
+        let sender = hex!("b685342b8c54347aad148e1f22eff3eb3eb29391").into();
+        let contract = hex!("c000000000000000000000000000000000000001").into();
+
+        let mut state = InMemoryState::default();
+        state.update_account(
+            sender,
+            None,
+            Some(Account {
+                balance: ETHER.into(),
+                ..Default::default()
+            }),
+        );
+        for (address, code) in [
+            (
+                param::BEACON_ROOTS_ADDRESS,
+                Bytes::from_static(&hex!("3373fffffffffffffffffffffffffffffffffffffffe14604d57602036146024575f5ffd5b5f35801560495762001fff810690815414603c575f5ffd5b62001fff01545f5260205ff35b5f5ffd5b62001fff42064281555f359062001fff015500")),
+            ),
+            // Stores the first word of the input into slot 0.
+            (contract, Bytes::from_static(&hex!("60003560005500"))),
+        ] {
+            let code_hash = keccak256(&code);
+            state.update_account(
+                address,
+                None,
+                Some(Account {
+                    code_hash,
+                    ..Default::default()
+                }),
+            );
+            state.update_code(code_hash, code).unwrap();
+        }
+
+        let t = |nonce| MessageWithSender {
+            message: Message::EIP1559 {
+                chain_id: ChainId(1),
+                nonce,
+                max_priority_fee_per_gas: U256::ZERO,
+                max_fee_per_gas: U256::from(20 * GIGA),
+                gas_limit: 50_000,
+                action: TransactionAction::Call(contract),
+                value: U256::ZERO,
+                input: 0x2a.as_u256().to_be_bytes().to_vec().into(),
+                access_list: Default::default(),
+            },
+            sender,
+        };
+        let block = BlockBodyWithSenders {
+            transactions: vec![(t)(0), (t)(1)],
+            ommers: vec![],
+            withdrawals: None,
+        };
+
+        let trace = trace_block(&mut state, &chain_spec, &header, &block).unwrap();
+
+        // The beacon roots system call is not traced.
+        assert_eq!(trace.transactions.len(), 2);
+        for traces in &trace.transactions {
+            assert_eq!(traces.len(), 1);
+            match &traces[0].action {
+                tracer::call_tree::Action::Call(action) => {
+                    assert_eq!(action.from, sender);
+                    assert_eq!(action.to, contract);
+                }
+                other => panic!("unexpected action {:?}", other),
+            }
+        }
+    }
 }
@@ -1,6 +1,7 @@
 use crate::{chain::protocol_param::param, crypto::*, models::*, util::*};
 use arrayref::array_ref;
 use bytes::{Buf, Bytes};
+use c_kzg::{ethereum_kzg_settings, Bytes32, Bytes48, KzgProof};
 use num_bigint::BigUint;
 use num_traits::Zero;
 use ripemd::*;
@@ -25,7 +26,7 @@ pub struct Contract {
     pub run: RunFunction,
 }
 
-pub const CONTRACTS: [Contract; NUM_OF_ISTANBUL_CONTRACTS] = [
+pub const CONTRACTS: [Contract; NUM_OF_CANCUN_CONTRACTS] = [
     Contract {
         gas: ecrecover_gas,
         run: ecrecover_run,
@@ -62,11 +63,16 @@ pub const CONTRACTS: [Contract; NUM_OF_ISTANBUL_CONTRACTS] = [
         gas: blake2_f_gas,
         run: blake2_f_run,
     },
+    Contract {
+        gas: point_evaluation_gas,
+        run: point_evaluation_run,
+    },
 ];
 
 pub const NUM_OF_FRONTIER_CONTRACTS: usize = 4;
 pub const NUM_OF_BYZANTIUM_CONTRACTS: usize = 8;
 pub const NUM_OF_ISTANBUL_CONTRACTS: usize = 9;
+pub const NUM_OF_CANCUN_CONTRACTS: usize = 10;
 
 fn ecrecover_gas(_: Bytes, _: Revision) -> Option<u64> {
     Some(3_000)
@@ -447,6 +453,42 @@ fn blake2_f_run(input: Bytes) -> Option<Bytes> {
     Some(output_buf.to_vec().into())
 }
 
+fn point_evaluation_gas(_: Bytes, _: Revision) -> Option<u64> {
+    Some(param::POINT_EVALUATION_PRECOMPILE_GAS)
+}
+
+/// Verifies that the blob committed to by `commitment` evaluates to `y` at `z`.
+///
+/// See https://eips.ethereum.org/EIPS/eip-4844#point-evaluation-precompile
+fn point_evaluation_run(input: Bytes) -> Option<Bytes> {
+    if input.len() != 192 {
+        return None;
+    }
+
+    let versioned_hash = &input[..32];
+    let z = Bytes32::from_bytes(&input[32..64]).ok()?;
+    let y = Bytes32::from_bytes(&input[64..96]).ok()?;
+    let commitment = &input[96..144];
+    let proof = Bytes48::from_bytes(&input[144..192]).ok()?;
+
+    let mut expected_versioned_hash = Sha256::digest(commitment);
+    expected_versioned_hash[0] = param::VERSIONED_HASH_VERSION_KZG;
+    if versioned_hash != &expected_versioned_hash[..] {
+        return None;
+    }
+
+    let commitment = Bytes48::from_bytes(commitment).ok()?;
+    if !KzgProof::verify_kzg_proof(&commitment, &z, &y, &proof, ethereum_kzg_settings()).ok()? {
+        return None;
+    }
+
+    let mut output = [0; 64];
+    output[24..32].copy_from_slice(&param::FIELD_ELEMENTS_PER_BLOB.to_be_bytes());
+    output[32..].copy_from_slice(&param::BLS_MODULUS);
+
+    Some(output.to_vec().into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -713,4 +755,29 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn point_evaluation() {
+        let input = hex!(
+            "01e798154708fe7789429634053cbf9f99b619f9f084048927333fce637f549b564c0a11a0f704f4fc3e8acfe0f8245f0ad1347b378fbf96e206da11a5d3630624d25032e67a7e6a4910df5834b8fe70e6bcfeeac0352434196bdf4b2485d5a18f59a8d2a1a625a17f3fea0fe5eb8c896db3764f3185481bc22f91b4aaffcca25f26936857bc3a7c2539ea8ec3a952b7873033e038326e87ed3e1276fd140253fa08e9fc25fb2d9a98527fc22a2c9612fbeafdad446cbc7bcdbdcd780af2c16a"
+        );
+        assert_eq!(
+            point_evaluation_run(input.to_vec().into()).unwrap(),
+            bytes!(
+                "000000000000000000000000000000000000000000000000000000000000100073eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001"
+            )
+        );
+
+        // The commitment does not match the versioned hash.
+        let mut wrong_hash = input;
+        wrong_hash[1] ^= 1;
+        assert_eq!(point_evaluation_run(wrong_hash.to_vec().into()), None);
+
+        // The proof does not open the commitment at z to y.
+        let mut wrong_value = input;
+        wrong_value[95] ^= 1;
+        assert_eq!(point_evaluation_run(wrong_value.to_vec().into()), None);
+
+        assert_eq!(point_evaluation_run(input[..191].to_vec().into()), None);
+    }
 }
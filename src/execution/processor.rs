@@ -1,4 +1,9 @@
-use super::{analysis_cache::AnalysisCache, root_hash, tracer::Tracer};
+use super::{
+    analysis_cache::AnalysisCache,
+    block_hash_cache::BlockHashCache,
+    root_hash,
+    tracer::{NoopTracer, Tracer},
+};
 use crate::{
     chain::{
         intrinsic_gas::*,
@@ -22,6 +27,7 @@ where
     state: IntraBlockState<'r, S>,
     tracer: &'tracer mut dyn Tracer,
     analysis_cache: &'analysis mut AnalysisCache,
+    block_hash_cache: Option<&'analysis mut BlockHashCache>,
     engine: &'e mut dyn Consensus,
     header: &'h PartialHeader,
     block: &'b BlockBodyWithSenders,
@@ -47,6 +53,7 @@ where
             state: IntraBlockState::new(state),
             tracer,
             analysis_cache,
+            block_hash_cache: None,
             engine,
             header,
             block,
@@ -55,6 +62,15 @@ where
         }
     }
 
+    /// Reuses ancestor hashes across blocks. The cache must only be shared between blocks of the same chain.
+    pub fn with_block_hash_cache(
+        mut self,
+        block_hash_cache: &'analysis mut BlockHashCache,
+    ) -> Self {
+        self.block_hash_cache = Some(block_hash_cache);
+        self
+    }
+
     fn available_gas(&self) -> u64 {
         self.header.gas_limit - self.cumulative_gas_used
     }
@@ -63,7 +79,7 @@ where
         &mut self.state
     }
 
-    pub(crate) fn into_state(self) -> IntraBlockState<'r, S> {
+    pub fn into_state(self) -> IntraBlockState<'r, S> {
         self.state
     }
 
@@ -75,6 +91,26 @@ where
         )
         .expect("Tx must have been prevalidated");
 
+        if tx.tx_type() == TxType::EIP4844 {
+            validate_blob_transaction(
+                tx,
+                self.block_spec,
+                blob_gas_price(self.header.excess_blob_gas.unwrap_or(0)),
+            )?;
+        }
+
+        // https://eips.ethereum.org/EIPS/eip-3860
+        if self.block_spec.revision >= Revision::Shanghai
+            && matches!(tx.action(), TransactionAction::Create)
+            && tx.input().len() > param::MAX_INITCODE_SIZE
+        {
+            return Err(ValidationError::MaxInitcodeSizeExceeded {
+                max: param::MAX_INITCODE_SIZE,
+                got: tx.input().len(),
+            }
+            .into());
+        }
+
         if self.state.get_code_hash(tx.sender)? != EMPTY_HASH {
             return Err(ValidationError::SenderNoEOA { sender: tx.sender }.into());
         }
@@ -94,8 +130,17 @@ where
             * U512::from(ethereum_types::U256::from(
                 tx.max_fee_per_gas().to_be_bytes(),
             ));
+        // https://eips.ethereum.org/EIPS/eip-4844
+        let max_blob_gas_cost = U512::from(tx.blob_gas())
+            * U512::from(ethereum_types::U256::from(
+                tx.max_fee_per_blob_gas()
+                    .unwrap_or(U256::ZERO)
+                    .to_be_bytes(),
+            ));
         // See YP, Eq (57) in Section 6.2 "Execution"
-        let v0 = max_gas_cost + U512::from(ethereum_types::U256::from(tx.value().to_be_bytes()));
+        let v0 = max_gas_cost
+            + max_blob_gas_cost
+            + U512::from(ethereum_types::U256::from(tx.value().to_be_bytes()));
         let available_balance =
             ethereum_types::U256::from(self.state.get_balance(tx.sender)?.to_be_bytes()).into();
         if available_balance < v0 {
@@ -136,12 +181,26 @@ where
             U256::from(txn.gas_limit()) * effective_gas_price,
         )?;
 
+        // Blob gas is paid upfront at the block's blob gas price and burned, it is never refunded.
+        // https://eips.ethereum.org/EIPS/eip-4844#gas-accounting
+        let blob_gas = txn.blob_gas();
+        if blob_gas > 0 {
+            let blob_gas_price = blob_gas_price(self.header.excess_blob_gas.unwrap_or(0));
+            self.state
+                .subtract_from_balance(txn.sender, U256::from(blob_gas) * blob_gas_price)?;
+        }
+
         if let TransactionAction::Call(to) = txn.action() {
             self.state.access_account(to);
             // EVM itself increments the nonce for contract creation
             self.state.set_nonce(txn.sender, txn.nonce() + 1)?;
         }
 
+        // https://eips.ethereum.org/EIPS/eip-3651
+        if rev >= Revision::Shanghai {
+            self.state.access_account(self.header.beneficiary);
+        }
+
         for entry in &*txn.access_list() {
             self.state.access_account(entry.address);
             for &key in &entry.slots {
@@ -149,7 +208,12 @@ where
             }
         }
 
-        let g0 = intrinsic_gas(txn, rev >= Revision::Homestead, rev >= Revision::Istanbul);
+        let g0 = intrinsic_gas(
+            txn,
+            rev >= Revision::Homestead,
+            rev >= Revision::Istanbul,
+            rev >= Revision::Shanghai,
+        );
         let gas = u128::from(txn.gas_limit())
             .checked_sub(g0)
             .ok_or(ValidationError::IntrinsicGas)?
@@ -162,6 +226,7 @@ where
             #[allow(clippy::needless_option_as_deref)]
             self.tracer,
             self.analysis_cache,
+            self.block_hash_cache.as_deref_mut(),
             self.header,
             self.block_spec,
             txn,
@@ -195,19 +260,121 @@ where
         })
     }
 
-    pub fn execute_block_no_post_validation(&mut self) -> anyhow::Result<Vec<Receipt>> {
-        let mut receipts = Vec::with_capacity(self.block.transactions.len());
+    /// Store the parent beacon block root in the beacon roots contract by calling it from the system address.
+    ///
+    /// The call is not a transaction: it pays no fees, does not count towards the block gas and produces no receipt.
+    /// See <https://eips.ethereum.org/EIPS/eip-4788>.
+    fn process_beacon_block_root(&mut self) -> anyhow::Result<()> {
+        if self.block_spec.revision < Revision::Cancun {
+            return Ok(());
+        }
+
+        let parent_beacon_block_root = self
+            .header
+            .parent_beacon_block_root
+            .ok_or(ValidationError::MissingParentBeaconBlockRoot)?;
+
+        if self.state.get_code(param::BEACON_ROOTS_ADDRESS)?.is_none() {
+            return Ok(());
+        }
+
+        self.state.clear_journal_and_substate();
 
+        let message = MessageWithSender {
+            message: Message::Legacy {
+                chain_id: None,
+                nonce: 0,
+                gas_price: U256::ZERO,
+                gas_limit: param::SYSTEM_CALL_GAS_LIMIT,
+                action: TransactionAction::Call(param::BEACON_ROOTS_ADDRESS),
+                value: U256::ZERO,
+                input: parent_beacon_block_root.0.to_vec().into(),
+            },
+            sender: param::SYSTEM_ADDRESS,
+        };
+
+        // System calls are not part of any transaction, so they are kept out of traces.
+        evmglue::execute(
+            &mut self.state,
+            &mut NoopTracer,
+            self.analysis_cache,
+            self.block_hash_cache.as_deref_mut(),
+            self.header,
+            self.block_spec,
+            &message,
+            param::SYSTEM_CALL_GAS_LIMIT,
+        )?;
+
+        // The system address must not be left behind as an empty account.
+        self.state.destruct_touched_dead()?;
+
+        self.state.finalize_transaction();
+
+        Ok(())
+    }
+
+    fn apply_balance_changes(&mut self) -> anyhow::Result<()> {
         for (&address, &balance) in &self.block_spec.balance_changes {
             self.state.set_balance(address, balance)?;
         }
 
+        Ok(())
+    }
+
+    /// Replays transactions preceding `index` and executes the transaction at `index` with `tracer`.
+    ///
+    /// Returns the receipt and gas used by the traced transaction alone.
+    pub fn trace_transaction(
+        &mut self,
+        index: usize,
+        tracer: &'tracer mut dyn Tracer,
+    ) -> anyhow::Result<(Receipt, u64)> {
+        let block = self.block;
+        let txn = block
+            .transactions
+            .get(index)
+            .ok_or_else(|| anyhow::format_err!("No tx #{} in block", index))?;
+
+        self.apply_balance_changes()?;
+        self.process_beacon_block_root()?;
+
+        for (i, txn) in block.transactions[..index].iter().enumerate() {
+            self.validate_transaction(txn)
+                .with_context(|| format!("Failed to validate tx #{}", i))?;
+            self.execute_transaction(txn)?;
+        }
+
+        let gas_used_before = self.cumulative_gas_used;
+        self.tracer = tracer;
+        self.validate_transaction(txn)
+            .with_context(|| format!("Failed to validate tx #{}", index))?;
+        let receipt = self.execute_transaction(txn)?;
+        let gas_used = receipt.cumulative_gas_used - gas_used_before;
+
+        Ok((receipt, gas_used))
+    }
+
+    pub fn execute_block_no_post_validation(&mut self) -> anyhow::Result<Vec<Receipt>> {
+        let mut receipts = Vec::with_capacity(self.block.transactions.len());
+
+        self.apply_balance_changes()?;
+
+        self.process_beacon_block_root()?;
+
         for (i, txn) in self.block.transactions.iter().enumerate() {
             self.validate_transaction(txn)
                 .with_context(|| format!("Failed to validate tx #{}", i))?;
             receipts.push(self.execute_transaction(txn)?);
         }
 
+        // https://eips.ethereum.org/EIPS/eip-4895
+        if let Some(withdrawals) = &self.block.withdrawals {
+            for withdrawal in withdrawals {
+                self.state
+                    .add_to_balance(withdrawal.address, withdrawal.amount_wei())?;
+            }
+        }
+
         for change in
             self.engine
                 .finalize(self.header, &self.block.ommers, self.block_spec.revision)?
@@ -341,8 +508,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
         let mut tracer = NoopTracer;
         let mut processor = ExecutionProcessor::new(
             &mut state,
@@ -358,6 +525,479 @@ mod tests {
         assert!(receipt.success);
     }
 
+    #[test]
+    fn block_and_ommer_rewards() {
+        let miner = Address::repeat_byte(0xaa);
+        let ommer_miner = Address::repeat_byte(0xbb);
+
+        let header = PartialHeader {
+            number: 13_000_000.into(),
+            gas_limit: 30_000_000,
+            beneficiary: miner,
+            ..PartialHeader::empty()
+        };
+        let block = BlockBodyWithSenders {
+            transactions: vec![],
+            ommers: vec![BlockHeader {
+                number: 12_999_999.into(),
+                beneficiary: ommer_miner,
+                ..BlockHeader::empty()
+            }],
+            withdrawals: None,
+        };
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
+        let mut tracer = NoopTracer;
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            &mut tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        let receipts = processor.execute_block_no_post_validation().unwrap();
+        assert!(receipts.is_empty());
+        processor.into_state().write_to_db(header.number).unwrap();
+
+        let block_reward = param::BLOCK_REWARD_CONSTANTINOPLE;
+        assert_eq!(
+            state.read_account(miner).unwrap().unwrap().balance,
+            (block_reward + block_reward / 32).as_u256()
+        );
+        assert_eq!(
+            state.read_account(ommer_miner).unwrap().unwrap().balance,
+            (block_reward * 7 / 8).as_u256()
+        );
+    }
+
+    #[test]
+    fn eip161_empty_account_deletion() {
+        let sender = Address::repeat_byte(0xaa);
+        let recipient = Address::repeat_byte(0xbb);
+
+        let has_recipient_after_block = |number: u64| {
+            let header = PartialHeader {
+                number: number.into(),
+                gas_limit: 1_000_000,
+                ..PartialHeader::empty()
+            };
+            let block = Default::default();
+
+            let mut state = InMemoryState::default();
+            state.update_account(
+                sender,
+                None,
+                Some(Account {
+                    balance: ETHER.into(),
+                    ..Default::default()
+                }),
+            );
+
+            let txn = MessageWithSender {
+                message: Message::Legacy {
+                    chain_id: None,
+                    nonce: 0,
+                    gas_price: U256::ZERO,
+                    gas_limit: 21_000,
+                    action: TransactionAction::Call(recipient),
+                    value: U256::ZERO,
+                    input: Bytes::new(),
+                },
+                sender,
+            };
+
+            let mut analysis_cache = AnalysisCache::default();
+            let mut engine = engine_factory(&MAINNET).unwrap();
+            let block_spec = MAINNET.collect_block_spec(header.number, None);
+            let mut tracer = NoopTracer;
+            let mut processor = ExecutionProcessor::new(
+                &mut state,
+                &mut tracer,
+                &mut analysis_cache,
+                &mut *engine,
+                &header,
+                &block,
+                &block_spec,
+            );
+
+            let receipt = processor.execute_transaction(&txn).unwrap();
+            assert!(receipt.success);
+            processor.into_state().write_to_db(header.number).unwrap();
+
+            state.read_account(recipient).unwrap().is_some()
+        };
+
+        // Homestead keeps the touched empty account.
+        assert!(has_recipient_after_block(2_000_000));
+        // Spurious Dragon deletes it.
+        assert!(!has_recipient_after_block(2_675_000));
+    }
+
+    #[test]
+    fn eip3651_warm_coinbase() {
+        let sender = Address::repeat_byte(0xaa);
+        let contract = Address::repeat_byte(0xcc);
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.shanghai = Some(17_034_870.into());
+
+        let gas_used_at = |number: u64| {
+            let header = PartialHeader {
+                number: number.into(),
+                gas_limit: 30_000_000,
+                beneficiary: Address::repeat_byte(0xbb),
+                ..PartialHeader::empty()
+            };
+            let block = Default::default();
+
+            let txn = MessageWithSender {
+                message: Message::Legacy {
+                    chain_id: None,
+                    nonce: 0,
+                    gas_price: U256::ZERO,
+                    gas_limit: 100_000,
+                    action: TransactionAction::Call(contract),
+                    value: U256::ZERO,
+                    input: Bytes::new(),
+                },
+                sender,
+            };
+
+            let mut state = InMemoryState::default();
+            let mut analysis_cache = AnalysisCache::default();
+            let mut engine = engine_factory(&chain_spec).unwrap();
+            let block_spec = chain_spec.collect_block_spec(header.number, None);
+            let mut tracer = NoopTracer;
+            let mut processor = ExecutionProcessor::new(
+                &mut state,
+                &mut tracer,
+                &mut analysis_cache,
+                &mut *engine,
+                &header,
+                &block,
+                &block_spec,
+            );
+
+            // COINBASE BALANCE POP STOP
+            processor
+                .state
+                .set_code(contract, bytes!("41315000"))
+                .unwrap();
+
+            let receipt = processor.execute_transaction(&txn).unwrap();
+            assert!(receipt.success);
+            receipt.cumulative_gas_used
+        };
+
+        // Cold account access before Shanghai.
+        assert_eq!(gas_used_at(17_000_000), 21_000 + 2 + 2_600 + 2);
+        // Warm coinbase from Shanghai on.
+        assert_eq!(gas_used_at(17_034_870), 21_000 + 2 + 100 + 2);
+    }
+
+    #[test]
+    fn eip4895_withdrawals() {
+        let header = PartialHeader {
+            number: 17_034_870.into(),
+            gas_limit: 30_000_000,
+            ..PartialHeader::empty()
+        };
+
+        let validator = Address::repeat_byte(0xaa);
+        let block = BlockBodyWithSenders {
+            transactions: vec![],
+            ommers: vec![],
+            withdrawals: Some(vec![
+                Withdrawal {
+                    index: 0,
+                    validator_index: 1,
+                    address: validator,
+                    amount: 32 * GIGA,
+                },
+                Withdrawal {
+                    index: 1,
+                    validator_index: 2,
+                    address: validator,
+                    amount: 5,
+                },
+            ]),
+        };
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
+        let mut tracer = NoopTracer;
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            &mut tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        processor.execute_block_no_post_validation().unwrap();
+
+        assert_eq!(
+            processor.state.get_balance(validator).unwrap(),
+            U256::from(32 * ETHER + 5 * u128::from(GIGA))
+        );
+    }
+
+    #[test]
+    fn eip4788_beacon_block_root() {
+        let timestamp = 1_710_338_135;
+        let parent_beacon_block_root = H256::repeat_byte(0xbe);
+        let header = PartialHeader {
+            number: 19_426_587.into(),
+            gas_limit: 30_000_000,
+            timestamp,
+            parent_beacon_block_root: Some(parent_beacon_block_root),
+            ..PartialHeader::empty()
+        };
+        let block = Default::default();
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.shanghai = Some(17_034_870.into());
+        chain_spec.upgrades.cancun = Some(header.number);
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&chain_spec).unwrap();
+        let block_spec = chain_spec.collect_block_spec(header.number, None);
+        let mut tracer = NoopTracer;
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            &mut tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        processor
+            .state
+            .set_code(
+                param::BEACON_ROOTS_ADDRESS,
+                bytes!("3373fffffffffffffffffffffffffffffffffffffffe14604d57602036146024575f5ffd5b5f35801560495762001fff810690815414603c575f5ffd5b62001fff01545f5260205ff35b5f5ffd5b62001fff42064281555f359062001fff015500"),
+            )
+            .unwrap();
+
+        let receipts = processor.execute_block_no_post_validation().unwrap();
+        assert!(receipts.is_empty());
+
+        let timestamp_idx = timestamp % param::BEACON_ROOTS_HISTORY_BUFFER_LENGTH;
+        let root_idx = timestamp_idx + param::BEACON_ROOTS_HISTORY_BUFFER_LENGTH;
+        assert_eq!(
+            processor
+                .state
+                .get_current_storage(param::BEACON_ROOTS_ADDRESS, timestamp_idx.as_u256())
+                .unwrap(),
+            timestamp.as_u256()
+        );
+        assert_eq!(
+            processor
+                .state
+                .get_current_storage(param::BEACON_ROOTS_ADDRESS, root_idx.as_u256())
+                .unwrap(),
+            h256_to_u256(parent_beacon_block_root)
+        );
+        assert!(!processor.state.exists(param::SYSTEM_ADDRESS).unwrap());
+    }
+
+    #[test]
+    fn eip4788_missing_beacon_block_root() {
+        let header = PartialHeader {
+            number: 19_426_587.into(),
+            gas_limit: 30_000_000,
+            ..PartialHeader::empty()
+        };
+        let block = Default::default();
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.shanghai = Some(17_034_870.into());
+        chain_spec.upgrades.cancun = Some(header.number);
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&chain_spec).unwrap();
+        let block_spec = chain_spec.collect_block_spec(header.number, None);
+        let mut tracer = NoopTracer;
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            &mut tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        assert_eq!(
+            processor
+                .execute_block_no_post_validation()
+                .unwrap_err()
+                .downcast::<ValidationError>()
+                .unwrap(),
+            ValidationError::MissingParentBeaconBlockRoot
+        );
+    }
+
+    #[test]
+    fn eip4844_blob_gas_is_burned() {
+        let miner = Address::repeat_byte(0xbb);
+        let excess_blob_gas = 10 * param::TARGET_BLOB_GAS_PER_BLOCK;
+        let header = PartialHeader {
+            number: 19_426_587.into(),
+            gas_limit: 30_000_000,
+            beneficiary: miner,
+            base_fee_per_gas: Some(U256::ONE),
+            blob_gas_used: Some(2 * param::GAS_PER_BLOB),
+            excess_blob_gas: Some(excess_blob_gas),
+            ..PartialHeader::empty()
+        };
+        let block = Default::default();
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.shanghai = Some(17_034_870.into());
+        chain_spec.upgrades.cancun = Some(header.number);
+
+        let sender = Address::repeat_byte(0xaa);
+        let blob_gas_price = blob_gas_price(excess_blob_gas);
+        assert!(blob_gas_price > U256::ONE);
+        let txn = MessageWithSender {
+            message: Message::EIP4844 {
+                chain_id: ChainId(1),
+                nonce: 0,
+                max_priority_fee_per_gas: U256::ONE,
+                max_fee_per_gas: 2_u8.as_u256(),
+                gas_limit: 21_000,
+                to: Address::repeat_byte(0xcc),
+                value: U256::ZERO,
+                input: Bytes::new(),
+                access_list: vec![],
+                max_fee_per_blob_gas: blob_gas_price,
+                blob_versioned_hashes: vec![H256::repeat_byte(0x01), H256::repeat_byte(0x02)]
+                    .into_iter()
+                    .map(|mut hash| {
+                        hash[0] = param::VERSIONED_HASH_VERSION_KZG;
+                        hash
+                    })
+                    .collect(),
+            },
+            sender,
+        };
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&chain_spec).unwrap();
+        let block_spec = chain_spec.collect_block_spec(header.number, None);
+        let mut tracer = NoopTracer;
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            &mut tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        processor
+            .state
+            .add_to_balance(sender, U256::from(ETHER))
+            .unwrap();
+
+        processor.validate_transaction(&txn).unwrap();
+        let receipt = processor.execute_transaction(&txn).unwrap();
+        assert!(receipt.success);
+        assert_eq!(receipt.cumulative_gas_used, 21_000);
+
+        // The sender pays the base fee, the tip and the blob fee, the miner only gets the tip.
+        assert_eq!(
+            processor.state.get_balance(sender).unwrap(),
+            U256::from(ETHER)
+                - 2_u8.as_u256() * 21_000_u64.as_u256()
+                - U256::from(2 * param::GAS_PER_BLOB) * blob_gas_price
+        );
+        assert_eq!(
+            processor.state.get_balance(miner).unwrap(),
+            21_000_u64.as_u256()
+        );
+    }
+
+    #[test]
+    fn eip3860_initcode_size_limit() {
+        let header = PartialHeader {
+            number: 17_034_870.into(),
+            gas_limit: 30_000_000,
+            ..PartialHeader::empty()
+        };
+        let block = Default::default();
+
+        let sender = Address::repeat_byte(0xaa);
+
+        let mut chain_spec = MAINNET.clone();
+        chain_spec.upgrades.shanghai = Some(17_034_870.into());
+
+        let mut state = InMemoryState::default();
+        let mut analysis_cache = AnalysisCache::default();
+        let mut engine = engine_factory(&chain_spec).unwrap();
+        let block_spec = chain_spec.collect_block_spec(header.number, None);
+        let mut tracer = NoopTracer;
+        let mut processor = ExecutionProcessor::new(
+            &mut state,
+            &mut tracer,
+            &mut analysis_cache,
+            &mut *engine,
+            &header,
+            &block,
+            &block_spec,
+        );
+
+        processor
+            .state
+            .add_to_balance(sender, U256::from(ETHER))
+            .unwrap();
+
+        let create = |initcode_len: usize| MessageWithSender {
+            message: Message::Legacy {
+                chain_id: None,
+                nonce: 0,
+                gas_price: U256::ZERO,
+                gas_limit: 10_000_000,
+                action: TransactionAction::Create,
+                value: U256::ZERO,
+                input: vec![0; initcode_len].into(),
+            },
+            sender,
+        };
+
+        processor
+            .validate_transaction(&create(param::MAX_INITCODE_SIZE))
+            .unwrap();
+
+        let err = processor
+            .validate_transaction(&create(param::MAX_INITCODE_SIZE + 1))
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ValidationError>(),
+            Some(&ValidationError::MaxInitcodeSizeExceeded {
+                max: param::MAX_INITCODE_SIZE,
+                got: param::MAX_INITCODE_SIZE + 1,
+            })
+        );
+    }
+
     #[test]
     fn eip3607_reject_transactions_from_senders_with_deployed_code() {
         let header = PartialHeader {
@@ -387,8 +1027,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
         let mut tracer = NoopTracer;
         let mut processor = ExecutionProcessor::new(
             &mut state,
@@ -448,8 +1088,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
         let mut tracer = NoopTracer;
         let mut processor = ExecutionProcessor::new(
             &mut state,
@@ -567,8 +1207,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
         let mut tracer = NoopTracer;
         let mut processor = ExecutionProcessor::new(
             &mut state,
@@ -677,8 +1317,8 @@ mod tests {
             };
 
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
         let mut tracer = NoopTracer;
         let mut processor = ExecutionProcessor::new(
             &mut state,
@@ -733,8 +1373,8 @@ mod tests {
 
         let mut state = InMemoryState::default();
         let mut analysis_cache = AnalysisCache::default();
-        let mut engine = engine_factory(MAINNET.clone()).unwrap();
-        let block_spec = MAINNET.collect_block_spec(header.number);
+        let mut engine = engine_factory(&MAINNET).unwrap();
+        let block_spec = MAINNET.collect_block_spec(header.number, None);
         let mut tracer = NoopTracer;
         let mut processor = ExecutionProcessor::new(
             &mut state,
@@ -0,0 +1,254 @@
+use super::*;
+use crate::{
+    execution::evm::{Output, StatusCode},
+    models::*,
+    util::hexbytes,
+};
+use bytes::Bytes;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallAction {
+    pub call_type: CallKind,
+    pub from: Address,
+    pub to: Address,
+    pub gas: U64,
+    #[serde(with = "hexbytes")]
+    pub input: Bytes,
+    pub value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateAction {
+    pub from: Address,
+    pub gas: U64,
+    #[serde(with = "hexbytes")]
+    pub init: Bytes,
+    pub value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfDestructAction {
+    pub address: Address,
+    pub refund_address: Address,
+    pub balance: U256,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RewardType {
+    Block,
+    Uncle,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardAction {
+    pub author: Address,
+    pub reward_type: RewardType,
+    pub value: U256,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Action {
+    Call(CallAction),
+    Create(CreateAction),
+    SelfDestruct(SelfDestructAction),
+    Reward(RewardAction),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceType {
+    Call,
+    Create,
+    Suicide,
+    Reward,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallOutput {
+    pub gas_used: U64,
+    #[serde(with = "hexbytes")]
+    pub output: Bytes,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateOutput {
+    pub gas_used: U64,
+    #[serde(with = "hexbytes")]
+    pub code: Bytes,
+    pub address: Address,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum TraceOutput {
+    Call(CallOutput),
+    Create(CreateOutput),
+}
+
+/// Single action trace in OpenEthereum's `trace_block` format, without block and transaction location.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallTrace {
+    #[serde(rename = "type")]
+    pub trace_type: TraceType,
+    pub action: Action,
+    pub result: Option<TraceOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub subtraces: usize,
+    pub trace_address: Vec<usize>,
+}
+
+impl CallTrace {
+    pub fn reward(author: Address, reward_type: RewardType, value: U256) -> Self {
+        Self {
+            trace_type: TraceType::Reward,
+            action: Action::Reward(RewardAction {
+                author,
+                reward_type,
+                value,
+            }),
+            result: None,
+            error: None,
+            subtraces: 0,
+            trace_address: vec![],
+        }
+    }
+}
+
+/// Traces of all transactions in the block, followed by block and uncle rewards.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BlockTrace {
+    pub transactions: Vec<Vec<CallTrace>>,
+    pub rewards: Vec<CallTrace>,
+}
+
+/// Tracer which records the tree of calls, creates and self-destructs of each transaction.
+#[derive(Debug, Default)]
+pub struct CallTreeTracer {
+    transactions: Vec<Vec<CallTrace>>,
+    /// Position of each unfinished trace in the current transaction and the gas it was given.
+    stack: Vec<(usize, u64)>,
+}
+
+impl CallTreeTracer {
+    /// Traces of each transaction, in execution order.
+    pub fn into_traces(self) -> Vec<Vec<CallTrace>> {
+        self.transactions
+    }
+
+    fn push_trace(&mut self, trace_type: TraceType, action: Action) -> usize {
+        let traces = self.transactions.last_mut().unwrap();
+        let trace_address = if let Some(&(parent, _)) = self.stack.last() {
+            let parent = &mut traces[parent];
+            let mut trace_address = parent.trace_address.clone();
+            trace_address.push(parent.subtraces);
+            parent.subtraces += 1;
+            trace_address
+        } else {
+            vec![]
+        };
+
+        traces.push(CallTrace {
+            trace_type,
+            action,
+            result: None,
+            error: None,
+            subtraces: 0,
+            trace_address,
+        });
+
+        traces.len() - 1
+    }
+}
+
+impl Tracer for CallTreeTracer {
+    fn capture_start(
+        &mut self,
+        _: u16,
+        from: Address,
+        to: Address,
+        call_type: MessageKind,
+        input: Bytes,
+        gas: u64,
+        value: U256,
+    ) {
+        if self.stack.is_empty() {
+            self.transactions.push(vec![]);
+        }
+
+        let (trace_type, action) = match call_type {
+            MessageKind::Create => (
+                TraceType::Create,
+                Action::Create(CreateAction {
+                    from,
+                    gas: gas.into(),
+                    init: input,
+                    value,
+                }),
+            ),
+            MessageKind::Call { call_kind, .. } => (
+                TraceType::Call,
+                Action::Call(CallAction {
+                    call_type: call_kind,
+                    from,
+                    to,
+                    gas: gas.into(),
+                    input,
+                    value,
+                }),
+            ),
+        };
+
+        let idx = self.push_trace(trace_type, action);
+        self.stack.push((idx, gas));
+    }
+
+    fn capture_end(&mut self, output: &Output) {
+        let (idx, gas) = self.stack.pop().unwrap();
+        let trace = &mut self.transactions.last_mut().unwrap()[idx];
+
+        match output.status_code {
+            StatusCode::Success => {
+                let gas_used = (gas - output.gas_left as u64).into();
+                trace.result = Some(match output.create_address {
+                    Some(address) => TraceOutput::Create(CreateOutput {
+                        gas_used,
+                        code: output.output_data.clone(),
+                        address,
+                    }),
+                    None => TraceOutput::Call(CallOutput {
+                        gas_used,
+                        output: output.output_data.clone(),
+                    }),
+                });
+            }
+            StatusCode::Revert => trace.error = Some("Reverted".to_string()),
+            other => trace.error = Some(other.to_string()),
+        }
+    }
+
+    fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address, balance: U256) {
+        if self.stack.is_empty() {
+            return;
+        }
+
+        self.push_trace(
+            TraceType::Suicide,
+            Action::SelfDestruct(SelfDestructAction {
+                address: caller,
+                refund_address: beneficiary,
+                balance,
+            }),
+        );
+    }
+}
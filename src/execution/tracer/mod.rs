@@ -1,13 +1,18 @@
+pub mod call_tree;
 pub mod eip3155_tracer;
+pub mod struct_logger;
 
 use auto_impl::auto_impl;
+pub use call_tree::{BlockTrace, CallTrace, CallTreeTracer, RewardType};
 pub use eip3155_tracer::StdoutTracer;
+pub use struct_logger::{StructLog, StructLogger, StructLoggerConfig};
 
 use crate::{
     execution::evm::{ExecutionState, OpCode},
     models::*,
 };
 use bytes::Bytes;
+use serde::Serialize;
 use std::{
     collections::{BTreeMap, HashMap},
     fmt::Debug,
@@ -21,7 +26,8 @@ pub enum CodeKind {
     Bytecode(Option<Bytes>),
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CallKind {
     Call,
     CallCode,
@@ -65,7 +71,7 @@ pub trait Tracer: Debug + Send {
     ) {
     }
     fn capture_end(&mut self, output: &Output) {}
-    fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address) {}
+    fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address, balance: U256) {}
     fn capture_account_read(&mut self, account: Address) {}
     fn capture_account_write(&mut self, account: Address) {}
 }
@@ -102,7 +108,7 @@ impl Tracer for CallTracer {
         self.addresses.entry(to).or_default().to = true;
     }
 
-    fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address) {
+    fn capture_self_destruct(&mut self, caller: Address, beneficiary: Address, _: U256) {
         self.addresses.entry(caller).or_default().from = true;
         self.addresses.entry(beneficiary).or_default().to = true;
     }
@@ -0,0 +1,112 @@
+use super::*;
+use crate::{
+    execution::evm::{ExecutionState, OpCode, Output},
+    models::*,
+    u256_to_h256,
+};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StructLoggerConfig {
+    pub disable_storage: bool,
+    pub disable_memory: bool,
+    pub disable_stack: bool,
+}
+
+/// Single step of execution, as reported by Geth's `debug_traceTransaction`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructLog {
+    pub pc: usize,
+    pub op: &'static str,
+    pub gas: u64,
+    pub gas_cost: u64,
+    pub depth: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Vec<U256>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memory: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub storage: Option<BTreeMap<H256, H256>>,
+}
+
+/// Tracer which records every executed instruction.
+///
+/// Storage only reflects the slots written by the traced transaction.
+#[derive(Debug, Default)]
+pub struct StructLogger {
+    config: StructLoggerConfig,
+    logs: Vec<StructLog>,
+    storage: HashMap<Address, BTreeMap<H256, H256>>,
+    return_value: Bytes,
+}
+
+impl StructLogger {
+    pub fn new(config: StructLoggerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn logs(&self) -> &[StructLog] {
+        &self.logs
+    }
+
+    /// Output of the top level call.
+    pub fn return_value(&self) -> &Bytes {
+        &self.return_value
+    }
+
+    pub fn into_logs(self) -> Vec<StructLog> {
+        self.logs
+    }
+}
+
+impl Tracer for StructLogger {
+    fn trace_instructions(&self) -> bool {
+        true
+    }
+
+    fn capture_state(
+        &mut self,
+        env: &ExecutionState,
+        pc: usize,
+        op: OpCode,
+        cost: u64,
+        depth: u16,
+    ) {
+        let storage = if self.config.disable_storage {
+            None
+        } else {
+            let storage = self.storage.entry(env.message.recipient).or_default();
+            if op == OpCode::SSTORE && env.stack.len() >= 2 {
+                storage.insert(
+                    u256_to_h256(*env.stack.get(0)),
+                    u256_to_h256(*env.stack.get(1)),
+                );
+            }
+            Some(storage.clone())
+        };
+
+        self.logs.push(StructLog {
+            pc,
+            op: op.name(),
+            gas: env.gas_left as u64,
+            gas_cost: cost,
+            depth: depth + 1,
+            stack: (!self.config.disable_stack).then(|| env.stack.0.to_vec()),
+            memory: (!self.config.disable_memory)
+                .then(|| env.memory.chunks(32).map(hex::encode).collect()),
+            storage,
+        });
+    }
+
+    fn capture_end(&mut self, output: &Output) {
+        // Top level call always ends last.
+        self.return_value = output.output_data.clone();
+    }
+}
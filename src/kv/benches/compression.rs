@@ -0,0 +1,69 @@
+use akula::kv::{compression::*, traits::*};
+use bytes::Bytes;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use hex_literal::hex;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Synthetic contract bytecode: Solidity preamble, function dispatcher and random constants.
+fn bytecode(rng: &mut impl Rng) -> Bytes {
+    let mut code =
+        hex!("608060405234801561001057600080fd5b50600436106100415760003560e01c").to_vec();
+    for _ in 0..rng.gen_range(8..64) {
+        match rng.gen_range(0..3) {
+            0 => {
+                code.push(0x63);
+                code.extend_from_slice(&rng.gen::<[u8; 4]>());
+                code.extend_from_slice(&hex!("14610046578063"));
+            }
+            1 => {
+                code.push(0x7f);
+                code.extend_from_slice(&rng.gen::<[u8; 32]>());
+            }
+            _ => code.extend_from_slice(&hex!(
+                "5b6000805460018101825590805260209020015550565b600080fd5b"
+            )),
+        }
+    }
+    code.into()
+}
+
+fn compression_benchmarks(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let values = (0..1000).map(|_| bytecode(&mut rng)).collect::<Vec<_>>();
+
+    let uncompressed_size = values.iter().map(|v| v.len()).sum::<usize>();
+    let compressed = values
+        .iter()
+        .map(|v| Compressed(v.clone()).encode())
+        .collect::<Vec<_>>();
+    println!(
+        "uncompressed: {} bytes, compressed: {} bytes",
+        uncompressed_size,
+        compressed.iter().map(|v| v.len()).sum::<usize>(),
+    );
+
+    let mut group = c.benchmark_group("bytecode decode");
+    group.throughput(Throughput::Bytes(uncompressed_size as u64));
+    group.bench_function("uncompressed", |b| {
+        b.iter_batched(
+            || values.clone(),
+            |values| {
+                for v in values {
+                    Bytes::decode(&v).unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("compressed", |b| {
+        b.iter(|| {
+            for v in &compressed {
+                Compressed::<Bytes>::decode(v).unwrap();
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compression_benchmarks);
+criterion_main!(benches);
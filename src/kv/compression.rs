@@ -0,0 +1,87 @@
+use super::traits::*;
+use bytes::Bytes;
+
+pub const COMPRESSION_LEVEL: i32 = 3;
+
+/// Value which is stored zstd-compressed.
+///
+/// Values are compressed without a trained dictionary: decoding one would need the dictionary the value was
+/// written with, and no table records which one that was.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Compressed<V>(pub V);
+
+impl<V> TableEncode for Compressed<V>
+where
+    V: TableEncode,
+{
+    type Encoded = Vec<u8>;
+
+    fn encode(self) -> Self::Encoded {
+        zstd::bulk::compress(self.0.encode().as_ref(), COMPRESSION_LEVEL)
+            .expect("in-memory compression never fails")
+    }
+}
+
+impl<V> TableDecode for Compressed<V>
+where
+    V: TableDecode,
+{
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        V::decode(&zstd::stream::decode_all(b)?).map(Self)
+    }
+}
+
+/// Table with the same name and keys as `T`, but with zstd-compressed values.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CompressedTable<T>(pub T)
+where
+    T: Table;
+
+impl<T> Table for CompressedTable<T>
+where
+    T: Table,
+{
+    type Key = T::Key;
+    type Value = Compressed<T::Value>;
+    type SeekKey = T::SeekKey;
+
+    fn db_name(&self) -> string::String<Bytes> {
+        self.0.db_name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::{new_mem_database, tables};
+    use ethereum_types::H256;
+    use hex_literal::hex;
+
+    fn bytecode(i: usize) -> Bytes {
+        let mut code =
+            hex!("608060405234801561001057600080fd5b50600436106100415760003560e01c").to_vec();
+        for j in 0..16 {
+            code.extend_from_slice(&hex!("63"));
+            code.extend_from_slice(&((i * 31 + j) as u32).to_be_bytes());
+            code.extend_from_slice(&hex!("14610046578063"));
+        }
+        code.into()
+    }
+
+    #[test]
+    fn compressed_table_roundtrip() {
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+
+        let table = CompressedTable(tables::Code);
+        let code = bytecode(0);
+        tx.set(table, H256::zero(), Compressed(code.clone()))
+            .unwrap();
+
+        assert_eq!(
+            tx.get(table, H256::zero()).unwrap(),
+            Some(Compressed(code.clone()))
+        );
+        assert!(tx.get(tables::Code, H256::zero()).unwrap().unwrap().len() < code.len());
+    }
+}
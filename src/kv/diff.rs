@@ -0,0 +1,177 @@
+use super::{mdbx::*, CustomTable};
+use std::cmp::Ordering;
+
+/// All differences between the entries of a table in two databases.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableDiff {
+    /// Key and value.
+    pub only_in_left: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Key and value.
+    pub only_in_right: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Key, left value and right value.
+    pub differing_values: Vec<(Vec<u8>, Vec<u8>, Vec<u8>)>,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_left.is_empty()
+            && self.only_in_right.is_empty()
+            && self.differing_values.is_empty()
+    }
+}
+
+/// Compare `table` in two databases key by key, collecting every difference.
+///
+/// Entries of dupsort tables are compared as key/value pairs, so a duplicate present on one side only
+/// lands in the `only_in_*` lists and `differing_values` stays empty.
+pub fn diff_tables<K1, E1, K2, E2>(
+    tx1: &MdbxTransaction<'_, K1, E1>,
+    tx2: &MdbxTransaction<'_, K2, E2>,
+    table: &str,
+    dup_sort: bool,
+) -> anyhow::Result<TableDiff>
+where
+    K1: TransactionKind,
+    E1: EnvironmentKind,
+    K2: TransactionKind,
+    E2: EnvironmentKind,
+{
+    let mut left = tx1
+        .cursor(CustomTable::from(table.to_string()))?
+        .walk(None)
+        .peekable();
+    let mut right = tx2
+        .cursor(CustomTable::from(table.to_string()))?
+        .walk(None)
+        .peekable();
+
+    let mut diff = TableDiff::default();
+    loop {
+        let ordering = match (left.peek(), right.peek()) {
+            (None, None) => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(Ok((k1, v1))), Some(Ok((k2, v2)))) => {
+                if dup_sort {
+                    (k1, v1).cmp(&(k2, v2))
+                } else {
+                    k1.cmp(k2)
+                }
+            }
+            // Surface the error below.
+            (Some(Err(_)), _) => Ordering::Less,
+            (_, Some(Err(_))) => Ordering::Greater,
+        };
+
+        match ordering {
+            Ordering::Less => {
+                diff.only_in_left.push(left.next().unwrap()?);
+            }
+            Ordering::Greater => {
+                diff.only_in_right.push(right.next().unwrap()?);
+            }
+            Ordering::Equal => {
+                let (k, v1) = left.next().unwrap()?;
+                let (_, v2) = right.next().unwrap()?;
+                if v1 != v2 {
+                    diff.differing_values.push((k, v1, v2));
+                }
+            }
+        }
+    }
+
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kv::{new_mem_database, tables, traits::*},
+        models::*,
+    };
+
+    fn encoded<T: TableEncode>(v: T) -> Vec<u8> {
+        v.encode().as_ref().to_vec()
+    }
+
+    #[test]
+    fn diff() {
+        let db1 = new_mem_database().unwrap();
+        let db2 = new_mem_database().unwrap();
+
+        let tx1 = db1.begin_mutable().unwrap();
+        let tx2 = db2.begin_mutable().unwrap();
+        for i in 0..5 {
+            tx1.set(
+                tables::CanonicalHeader,
+                BlockNumber(i),
+                H256::repeat_byte(i as u8),
+            )
+            .unwrap();
+        }
+        for i in 1..7 {
+            let hash = if i == 3 {
+                H256::repeat_byte(0xff)
+            } else {
+                H256::repeat_byte(i as u8)
+            };
+            tx2.set(tables::CanonicalHeader, BlockNumber(i), hash)
+                .unwrap();
+        }
+
+        assert!(diff_tables(&tx1, &tx1, "CanonicalHeader", false)
+            .unwrap()
+            .is_empty());
+
+        let diff = diff_tables(&tx1, &tx2, "CanonicalHeader", false).unwrap();
+        assert_eq!(
+            diff.only_in_left,
+            vec![(encoded(BlockNumber(0)), encoded(H256::repeat_byte(0)))]
+        );
+        assert_eq!(
+            diff.only_in_right,
+            vec![
+                (encoded(BlockNumber(5)), encoded(H256::repeat_byte(5))),
+                (encoded(BlockNumber(6)), encoded(H256::repeat_byte(6)))
+            ]
+        );
+        assert_eq!(
+            diff.differing_values,
+            vec![(
+                encoded(BlockNumber(3)),
+                encoded(H256::repeat_byte(3)),
+                encoded(H256::repeat_byte(0xff))
+            )]
+        );
+    }
+
+    #[test]
+    fn diff_dupsort() {
+        let db1 = new_mem_database().unwrap();
+        let db2 = new_mem_database().unwrap();
+
+        let address = Address::repeat_byte(0xaa);
+        let entry = |i: u8| (H256::repeat_byte(i), U256::from(i));
+
+        let tx1 = db1.begin_mutable().unwrap();
+        let tx2 = db2.begin_mutable().unwrap();
+        for i in [1, 2, 3] {
+            tx1.set(tables::Storage, address, entry(i)).unwrap();
+        }
+        for i in [1, 3, 4] {
+            tx2.set(tables::Storage, address, entry(i)).unwrap();
+        }
+
+        let diff = diff_tables(&tx1, &tx2, "Storage", true).unwrap();
+        assert_eq!(
+            diff.only_in_left,
+            vec![(encoded(address), encoded(entry(2)))]
+        );
+        assert_eq!(
+            diff.only_in_right,
+            vec![(encoded(address), encoded(entry(4)))]
+        );
+        assert!(diff.differing_values.is_empty());
+    }
+}
@@ -1,9 +1,19 @@
 use crate::kv::{traits::*, *};
 use ::mdbx::{DatabaseFlags, WriteFlags};
 pub use ::mdbx::{EnvironmentKind, TransactionKind, RO, RW};
-use anyhow::Context;
-use std::{collections::HashMap, marker::PhantomData, ops::Deref, path::Path};
+use anyhow::{bail, Context};
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    ops::Deref,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 use tables::*;
+use tracing::*;
 
 #[derive(Clone, Debug)]
 struct TableObjectWrapper<T>(T);
@@ -100,14 +110,63 @@ impl<E: EnvironmentKind> MdbxEnvironment<E> {
     pub fn begin(&self) -> anyhow::Result<MdbxTransaction<'_, RO, E>> {
         Ok(MdbxTransaction {
             inner: self.inner.begin_ro_txn()?,
+            writes: Default::default(),
         })
     }
 
     pub fn begin_mutable(&self) -> anyhow::Result<MdbxTransaction<'_, RW, E>> {
         Ok(MdbxTransaction {
             inner: self.inner.begin_rw_txn()?,
+            writes: Default::default(),
         })
     }
+
+    /// Names of all tables in the environment, in sorted order.
+    pub fn table_names(&self) -> anyhow::Result<Vec<String>> {
+        self.begin()?.table_names()
+    }
+}
+
+impl<E> MdbxEnvironment<E>
+where
+    E: EnvironmentKind + 'static,
+{
+    /// Sizes of all tables, collected on the blocking thread pool since stat calls may take long on a large database.
+    pub async fn table_sizes(self: Arc<Self>) -> anyhow::Result<HashMap<String, u64>> {
+        tokio::task::spawn_blocking(move || self.begin()?.table_sizes()).await?
+    }
+}
+
+/// Writes made through a transaction and its cursors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WriteStats {
+    /// Puts with the `APPEND` or `APPEND_DUP` flag.
+    pub appends: u64,
+    /// Total size of the keys and values put.
+    pub bytes_written: u64,
+}
+
+#[derive(Debug, Default)]
+struct WriteCounters {
+    appends: AtomicU64,
+    bytes_written: AtomicU64,
+}
+
+impl WriteCounters {
+    fn record(&self, key: &[u8], value: &[u8], flags: WriteFlags) {
+        if flags.intersects(WriteFlags::APPEND | WriteFlags::APPEND_DUP) {
+            self.appends.fetch_add(1, Ordering::Relaxed);
+        }
+        self.bytes_written
+            .fetch_add((key.len() + value.len()) as u64, Ordering::Relaxed);
+    }
+
+    fn stats(&self) -> WriteStats {
+        WriteStats {
+            appends: self.appends.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -117,18 +176,28 @@ where
     E: EnvironmentKind,
 {
     inner: ::mdbx::Transaction<'env, K, E>,
+    writes: WriteCounters,
 }
 
 impl<'env, E> MdbxTransaction<'env, RO, E>
 where
     E: EnvironmentKind,
 {
-    pub fn table_sizes(&self) -> anyhow::Result<HashMap<String, u64>> {
-        let mut out = HashMap::new();
+    /// Names of all tables, as stored in the unnamed database, in sorted order.
+    pub fn table_names(&self) -> anyhow::Result<Vec<String>> {
+        let mut out = Vec::new();
         let main_db = self.inner.open_db(None)?;
         let mut cursor = self.inner.cursor(&main_db)?;
         while let Some((table, _)) = cursor.next_nodup::<Vec<u8>, ()>()? {
-            let table = String::from_utf8(table)?;
+            out.push(String::from_utf8(table)?);
+        }
+
+        Ok(out)
+    }
+
+    pub fn table_sizes(&self) -> anyhow::Result<HashMap<String, u64>> {
+        let mut out = HashMap::new();
+        for table in self.table_names()? {
             let db = self
                 .inner
                 .open_db(Some(&table))
@@ -162,6 +231,11 @@ where
         self.inner.id()
     }
 
+    /// Writes made so far through this transaction and its cursors.
+    pub fn write_stats(&self) -> WriteStats {
+        self.writes.stats()
+    }
+
     pub fn cursor<'tx, T>(&'tx self, table: T) -> anyhow::Result<MdbxCursor<'tx, K, T>>
     where
         'env: 'tx,
@@ -173,6 +247,7 @@ where
                 .inner
                 .cursor(&self.inner.open_db(Some(table_name.as_ref()))?)?,
             t: table.db_name(),
+            writes: &self.writes,
             _marker: PhantomData,
         })
     }
@@ -193,12 +268,17 @@ impl<'env, E: EnvironmentKind> MdbxTransaction<'env, RW, E> {
     where
         T: Table,
     {
-        Ok(self.inner.put(
+        let (k, v) = (k.encode(), v.encode());
+        self.inner.put(
             &self.inner.open_db(Some(table.db_name().as_ref()))?,
-            &k.encode(),
-            &v.encode(),
+            &k,
+            &v,
             WriteFlags::UPSERT,
-        )?)
+        )?;
+        self.writes
+            .record(k.as_ref(), v.as_ref(), WriteFlags::UPSERT);
+
+        Ok(())
     }
 
     pub fn del<T>(&self, table: T, key: T::Key, value: Option<T::Value>) -> anyhow::Result<bool>
@@ -233,6 +313,17 @@ impl<'env, E: EnvironmentKind> MdbxTransaction<'env, RW, E> {
 
         Ok(())
     }
+
+    /// Discard all changes made in this transaction.
+    ///
+    /// The binding aborts a transaction that is dropped uncommitted with `mdbx_txn_abort`, on its
+    /// transaction manager thread for read-write transactions, so dropping it here is the abort.
+    pub fn abort(self) -> anyhow::Result<()> {
+        debug!("Aborting read-write transaction {}", self.inner.id());
+        drop(self.inner);
+
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -243,9 +334,17 @@ where
 {
     inner: ::mdbx::Cursor<'txn, K>,
     t: string::String<Bytes>,
+    writes: &'txn WriteCounters,
     _marker: PhantomData<T>,
 }
 
+/// Position of a cursor which outlives its transaction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CursorCheckpoint {
+    pub table_name: String,
+    pub last_key: Vec<u8>,
+}
+
 fn map_res_inner<T, E>(
     v: Result<Option<(TableObjectWrapper<T::Key>, TableObjectWrapper<T::Value>)>, E>,
 ) -> anyhow::Result<Option<(T::Key, T::Value)>>
@@ -316,6 +415,40 @@ where
         map_res_inner::<T, _>(self.inner.get_current())
     }
 
+    /// Save the current position, so that it can be restored in another transaction.
+    ///
+    /// Returns `None` if the cursor is not positioned.
+    pub fn save_position(&mut self) -> anyhow::Result<Option<CursorCheckpoint>>
+    where
+        T::Key: TableDecode,
+    {
+        Ok(self.current()?.map(|(key, _)| CursorCheckpoint {
+            table_name: String::from(&*self.t),
+            last_key: key.encode().as_ref().to_vec(),
+        }))
+    }
+
+    /// Re-seek to the position saved in `checkpoint`.
+    ///
+    /// If the saved key has since been deleted, the cursor is positioned at the key following it.
+    pub fn restore_position(
+        &mut self,
+        checkpoint: &CursorCheckpoint,
+    ) -> anyhow::Result<Option<(T::Key, T::Value)>>
+    where
+        T::Key: TableDecode,
+    {
+        if checkpoint.table_name != *self.t {
+            bail!(
+                "Cursor checkpoint for table {} cannot be restored in table {}",
+                checkpoint.table_name,
+                &*self.t
+            );
+        }
+
+        map_res_inner::<T, _>(self.inner.set_range(&checkpoint.last_key))
+    }
+
     pub fn walk(
         mut self,
         start_key: Option<T::SeekKey>,
@@ -473,27 +606,15 @@ where
     T: Table,
 {
     pub fn put(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
-        Ok(self.inner.put(
-            key.encode().as_ref(),
-            value.encode().as_ref(),
-            WriteFlags::default(),
-        )?)
+        self.write(key, value, WriteFlags::default())
     }
 
     pub fn upsert(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
-        Ok(self.inner.put(
-            key.encode().as_ref(),
-            value.encode().as_ref(),
-            WriteFlags::UPSERT,
-        )?)
+        self.write(key, value, WriteFlags::UPSERT)
     }
 
     pub fn append(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
-        Ok(self.inner.put(
-            key.encode().as_ref(),
-            value.encode().as_ref(),
-            WriteFlags::APPEND,
-        )?)
+        self.write(key, value, WriteFlags::APPEND)
     }
 
     pub fn delete_current(&mut self) -> anyhow::Result<()> {
@@ -501,6 +622,14 @@ where
 
         Ok(())
     }
+
+    fn write(&mut self, key: T::Key, value: T::Value, flags: WriteFlags) -> anyhow::Result<()> {
+        let (key, value) = (key.encode(), value.encode());
+        self.inner.put(key.as_ref(), value.as_ref(), flags)?;
+        self.writes.record(key.as_ref(), value.as_ref(), flags);
+
+        Ok(())
+    }
 }
 
 impl<'txn, T> MdbxCursor<'txn, RW, T>
@@ -511,10 +640,141 @@ where
         Ok(self.inner.del(WriteFlags::NO_DUP_DATA)?)
     }
     pub fn append_dup(&mut self, key: T::Key, value: T::Value) -> anyhow::Result<()> {
-        Ok(self.inner.put(
-            key.encode().as_ref(),
-            value.encode().as_ref(),
-            WriteFlags::APPEND_DUP,
-        )?)
+        self.write(key, value, WriteFlags::APPEND_DUP)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kv::new_mem_database, models::*};
+
+    #[test]
+    fn aborted_changes_are_discarded() {
+        let db = new_mem_database().unwrap();
+
+        let tx = db.begin_mutable().unwrap();
+        tx.set(tables::TotalTx, BlockNumber(1), 1).unwrap();
+        tx.cursor(tables::TotalTx)
+            .unwrap()
+            .append(BlockNumber(2), 2)
+            .unwrap();
+        tx.abort().unwrap();
+
+        let tx = db.begin().unwrap();
+        assert_eq!(tx.get(tables::TotalTx, BlockNumber(1)).unwrap(), None);
+        assert_eq!(tx.get(tables::TotalTx, BlockNumber(2)).unwrap(), None);
+
+        let tx = db.begin_mutable().unwrap();
+        tx.set(tables::TotalTx, BlockNumber(3), 3).unwrap();
+        tx.commit().unwrap();
+
+        let tx = db.begin().unwrap();
+        assert_eq!(tx.get(tables::TotalTx, BlockNumber(3)).unwrap(), Some(3));
+    }
+
+    #[test]
+    fn write_stats() {
+        let db = new_mem_database().unwrap();
+
+        let tx = db.begin_mutable().unwrap();
+        tx.set(tables::TotalTx, BlockNumber(1), 1).unwrap();
+        let mut cursor = tx.cursor(tables::TotalTx).unwrap();
+        cursor.append(BlockNumber(2), 2).unwrap();
+        cursor.append(BlockNumber(3), 3).unwrap();
+
+        assert_eq!(
+            tx.write_stats(),
+            WriteStats {
+                appends: 2,
+                bytes_written: 3 * (8 + 8),
+            }
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn async_table_sizes() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let db = Arc::new(
+            MdbxEnvironment::<::mdbx::NoWriteMap>::open_rw(
+                ::mdbx::Environment::new(),
+                tmpdir.path(),
+                CHAINDATA_TABLES.clone(),
+            )
+            .unwrap(),
+        );
+
+        let tx = db.begin_mutable().unwrap();
+        for i in 0..10_000 {
+            tx.set(tables::TotalTx, BlockNumber(i), i).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let sizes = db.clone().table_sizes().await.unwrap();
+        assert_eq!(sizes.len(), CHAINDATA_TABLES.len());
+        assert!(sizes[tables::TotalTx::const_db_name()] > 0);
+        assert_eq!(sizes[tables::TotalGas::const_db_name()], 0);
+    }
+
+    #[test]
+    fn table_names() {
+        let tmpdir = tempfile::tempdir().unwrap();
+        let db = MdbxEnvironment::<::mdbx::NoWriteMap>::open_rw(
+            ::mdbx::Environment::new(),
+            tmpdir.path(),
+            Arc::new(
+                [("Gamma", false), ("Alpha", true), ("Beta", false)]
+                    .into_iter()
+                    .map(|(name, dup_sort)| (name, TableInfo { dup_sort }))
+                    .collect(),
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(db.table_names().unwrap(), vec!["Alpha", "Beta", "Gamma"]);
+    }
+
+    #[test]
+    fn cursor_position_survives_commit() {
+        let db = new_mem_database().unwrap();
+
+        let tx = db.begin_mutable().unwrap();
+        for i in 0..10 {
+            tx.set(tables::TotalTx, BlockNumber(i), i * 10).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = db.begin().unwrap();
+        let mut cursor = tx.cursor(tables::TotalTx).unwrap();
+        assert_eq!(cursor.save_position().unwrap(), None);
+        cursor.seek(BlockNumber(4)).unwrap();
+        let checkpoint = cursor.save_position().unwrap().unwrap();
+        drop(cursor);
+        drop(tx);
+
+        let tx = db.begin_mutable().unwrap();
+        tx.del(tables::TotalTx, BlockNumber(0), None).unwrap();
+        tx.commit().unwrap();
+
+        let tx = db.begin().unwrap();
+        let mut cursor = tx.cursor(tables::TotalTx).unwrap();
+        assert_eq!(
+            cursor.restore_position(&checkpoint).unwrap(),
+            Some((BlockNumber(4), 40))
+        );
+        assert_eq!(cursor.next().unwrap(), Some((BlockNumber(5), 50)));
+        drop(cursor);
+        drop(tx);
+
+        let tx = db.begin_mutable().unwrap();
+        tx.del(tables::TotalTx, BlockNumber(4), None).unwrap();
+        let mut cursor = tx.cursor(tables::TotalTx).unwrap();
+        assert_eq!(
+            cursor.restore_position(&checkpoint).unwrap(),
+            Some((BlockNumber(5), 50))
+        );
+
+        let mut other = tx.cursor(tables::TotalGas).unwrap();
+        assert!(other.restore_position(&checkpoint).is_err());
     }
 }
@@ -1,10 +1,13 @@
+pub mod compression;
+pub mod diff;
 pub mod mdbx;
 pub mod tables;
 pub mod traits;
 
 use self::traits::*;
 use crate::kv::tables::CHAINDATA_TABLES;
-use ::mdbx::{Geometry, WriteMap};
+use ::mdbx::{EnvironmentKind, Geometry, TransactionKind, WriteMap, RW};
+use anyhow::ensure;
 use byte_unit::*;
 use bytes::Bytes;
 use derive_more::Deref;
@@ -49,12 +52,65 @@ pub fn new_mem_database() -> anyhow::Result<MdbxWithDirHandle> {
 }
 
 pub fn new_database(path: &std::path::Path) -> anyhow::Result<MdbxWithDirHandle> {
+    let inner = new_environment(path, n_tib_bytes!(4), Some(n_gib_bytes!(4) as usize))?;
+    {
+        let tx = inner.begin_mutable()?;
+        init_schema_version(&tx)?;
+        tx.commit()?;
+    }
+
     Ok(MdbxWithDirHandle {
-        inner: new_environment(path, n_tib_bytes!(4), Some(n_gib_bytes!(4) as usize))?,
+        inner,
         _tmpdir: None,
     })
 }
 
+/// Version of the layout of stored data, bumped whenever data written by older versions can no longer be read.
+///
+/// Version 1 introduced withdrawals in block bodies and the post-Shanghai fields of block headers.
+pub const SCHEMA_VERSION: u64 = 1;
+
+const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+
+/// Stamps a database without chain data with the current [`SCHEMA_VERSION`], then checks it.
+fn init_schema_version<E: EnvironmentKind>(
+    tx: &mdbx::MdbxTransaction<'_, RW, E>,
+) -> anyhow::Result<()> {
+    if tx
+        .get(tables::Migration, SCHEMA_VERSION_KEY.to_vec())?
+        .is_none()
+        && tx.get(tables::Config, Default::default())?.is_none()
+    {
+        tx.set(
+            tables::Migration,
+            SCHEMA_VERSION_KEY.to_vec(),
+            SCHEMA_VERSION.to_be_bytes().to_vec(),
+        )?;
+    }
+
+    check_schema_version(tx)
+}
+
+/// Fails unless the database was written with the current [`SCHEMA_VERSION`].
+pub fn check_schema_version<K: TransactionKind, E: EnvironmentKind>(
+    tx: &mdbx::MdbxTransaction<'_, K, E>,
+) -> anyhow::Result<()> {
+    let version = tx
+        .get(tables::Migration, SCHEMA_VERSION_KEY.to_vec())?
+        .map(|v| -> anyhow::Result<u64> { Ok(u64::from_be_bytes(v.as_slice().try_into()?)) })
+        .transpose()?
+        .unwrap_or(0);
+
+    ensure!(
+        version == SCHEMA_VERSION,
+        "Database schema version {} is not supported, version {} is required. Please resync into a new data directory.",
+        version,
+        SCHEMA_VERSION
+    );
+
+    Ok(())
+}
+
 fn new_environment(
     path: &std::path::Path,
     size_upper_limit: u128,
@@ -71,3 +127,42 @@ fn new_environment(
     builder.set_rp_augment_limit(16 * 256 * 1024);
     mdbx::MdbxEnvironment::open_rw(builder, path, CHAINDATA_TABLES.deref().clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_version() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Fresh databases are stamped and open again.
+        drop(new_database(dir.path()).unwrap());
+        let db = new_database(dir.path()).unwrap();
+        check_schema_version(&db.begin().unwrap()).unwrap();
+
+        // Chain data written by other versions is refused.
+        for version in [None, Some(SCHEMA_VERSION + 1)] {
+            let tx = db.begin_mutable().unwrap();
+            tx.set(
+                tables::Config,
+                Default::default(),
+                crate::res::chainspec::MAINNET.clone(),
+            )
+            .unwrap();
+            if let Some(version) = version {
+                tx.set(
+                    tables::Migration,
+                    SCHEMA_VERSION_KEY.to_vec(),
+                    version.to_be_bytes().to_vec(),
+                )
+                .unwrap();
+            } else {
+                tx.del(tables::Migration, SCHEMA_VERSION_KEY.to_vec(), None)
+                    .unwrap();
+            }
+            assert!(check_schema_version(&tx).is_err());
+            assert!(init_schema_version(&tx).is_err());
+        }
+    }
+}
@@ -1,3 +1,11 @@
+//! Database tables.
+//!
+//! Every table is a zero-sized type implementing [`Table`], which ties together its name in the database,
+//! the type of its keys, the type of its values and the type used to seek in it.
+//! Tables are declared with [`define_table!`](crate::define_table), or its shorthand [`decl_table!`](crate::decl_table).
+//!
+//! All tables of the chain database are listed in [`CHAINDATA_TABLE_LIST`].
+
 use super::*;
 use crate::{models::*, zeroless_view, StageId};
 use anyhow::{bail, format_err};
@@ -6,7 +14,6 @@ use arrayvec::ArrayVec;
 use bytes::Bytes;
 use croaring::{treemap::NativeSerializer, Treemap as RoaringTreemap};
 use derive_more::*;
-use maplit::hashmap;
 use modular_bitfield::prelude::*;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, *};
@@ -58,10 +65,13 @@ where
     }
 }
 
+/// Declares a table: a zero-sized struct named after the table, implementing [`Table`].
+///
+/// `SeekKey` defaults to `Key`.
 #[macro_export]
-macro_rules! decl_table {
-    ($name:ident => $key:ty => $value:ty => $seek_key:ty) => {
-        #[derive(Clone, Copy, Debug, Default)]
+macro_rules! define_table {
+    ($name:ident, Key = $key:ty, Value = $value:ty, SeekKey = $seek_key:ty) => {
+        #[derive(Clone, Copy, Default)]
         pub struct $name;
 
         impl $crate::kv::traits::Table for $name {
@@ -88,14 +98,31 @@ macro_rules! decl_table {
             }
         }
 
+        impl std::fmt::Debug for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.write_str(Self::const_db_name())
+            }
+        }
+
         impl std::fmt::Display for $name {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 write!(f, "{}", Self::const_db_name())
             }
         }
     };
+    ($name:ident, Key = $key:ty, Value = $value:ty) => {
+        $crate::define_table!($name, Key = $key, Value = $value, SeekKey = $key);
+    };
+}
+
+/// Shorthand for [`define_table!`]: `decl_table!(Name => Key => Value [=> SeekKey])`.
+#[macro_export]
+macro_rules! decl_table {
+    ($name:ident => $key:ty => $value:ty => $seek_key:ty) => {
+        $crate::define_table!($name, Key = $key, Value = $value, SeekKey = $seek_key);
+    };
     ($name:ident => $key:ty => $value:ty) => {
-        decl_table!($name => $key => $value => $key);
+        $crate::define_table!($name, Key = $key, Value = $value);
     };
 }
 
@@ -417,6 +444,84 @@ impl TableDecode for U256 {
     }
 }
 
+/// `U256` encoded as exactly 32 big-endian bytes, so that encoded keys sort in numeric order.
+///
+/// Plain `U256` is stored without leading zeroes, as in Erigon.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deref,
+    DerefMut,
+    Default,
+    Display,
+    PartialEq,
+    Eq,
+    From,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct U256Be(pub U256);
+
+impl TableEncode for U256Be {
+    type Encoded = [u8; KECCAK_LENGTH];
+
+    fn encode(self) -> Self::Encoded {
+        self.0.to_be_bytes()
+    }
+}
+
+impl TableDecode for U256Be {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match b.len() {
+            KECCAK_LENGTH => Ok(Self(U256::from_be_bytes(*array_ref!(b, 0, KECCAK_LENGTH)))),
+            other => Err(InvalidLength::<KECCAK_LENGTH> { got: other }.into()),
+        }
+    }
+}
+
+/// `U256` encoded as exactly 32 little-endian bytes, for tables that use this layout.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deref,
+    DerefMut,
+    Default,
+    Display,
+    PartialEq,
+    Eq,
+    From,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(transparent)]
+pub struct U256Le(pub U256);
+
+impl TableEncode for U256Le {
+    type Encoded = [u8; KECCAK_LENGTH];
+
+    fn encode(self) -> Self::Encoded {
+        self.0.to_le_bytes()
+    }
+}
+
+impl TableDecode for U256Le {
+    fn decode(b: &[u8]) -> anyhow::Result<Self> {
+        match b.len() {
+            KECCAK_LENGTH => Ok(Self(U256::from_le_bytes(*array_ref!(b, 0, KECCAK_LENGTH)))),
+            other => Err(InvalidLength::<KECCAK_LENGTH> { got: other }.into()),
+        }
+    }
+}
+
 impl TableEncode for (H256, U256) {
     type Encoded = VariableVec<{ KECCAK_LENGTH + KECCAK_LENGTH }>;
 
@@ -759,11 +864,25 @@ decl_table!(DbInfo => Vec<u8> => Vec<u8>);
 decl_table!(SnapshotInfo => Vec<u8> => Vec<u8>);
 decl_table!(BittorrentInfo => Vec<u8> => Vec<u8>);
 decl_table!(HeaderNumber => H256 => BlockNumber);
-decl_table!(CanonicalHeader => BlockNumber => H256);
-decl_table!(Header => HeaderKey => BlockHeader => BlockNumber);
+define_table!(CanonicalHeader, Key = BlockNumber, Value = H256);
+define_table!(
+    Header,
+    Key = HeaderKey,
+    Value = BlockHeader,
+    SeekKey = BlockNumber
+);
 decl_table!(HeadersTotalDifficulty => HeaderKey => U256);
-decl_table!(BlockBody => HeaderKey => BodyForStorage => BlockNumber);
-decl_table!(BlockTransaction => TxIndex => MessageWithSignature);
+define_table!(
+    BlockBody,
+    Key = HeaderKey,
+    Value = BodyForStorage,
+    SeekKey = BlockNumber
+);
+define_table!(
+    BlockTransaction,
+    Key = TxIndex,
+    Value = MessageWithSignature
+);
 decl_table!(TotalGas => BlockNumber => u64);
 decl_table!(TotalTx => BlockNumber => u64);
 decl_table!(Log => (BlockNumber, TxIndex) => Vec<crate::models::Log>);
@@ -775,7 +894,7 @@ decl_table!(CallToIndex => BitmapKey<Address> => RoaringTreemap);
 decl_table!(BlockTransactionLookup => H256 => TruncateStart<BlockNumber>);
 decl_table!(Config => VariableVec<0> => ChainSpec);
 decl_table!(SyncStage => StageId => BlockNumber);
-decl_table!(TxSender => HeaderKey => Vec<Address>);
+define_table!(TxSender, Key = HeaderKey, Value = Vec<Address>);
 decl_table!(LastBlock => Vec<u8> => Vec<u8>);
 decl_table!(Migration => Vec<u8> => Vec<u8>);
 decl_table!(Sequence => Vec<u8> => Vec<u8>);
@@ -784,62 +903,110 @@ decl_table!(Issuance => Vec<u8> => Vec<u8>);
 
 pub type DatabaseChart = Arc<HashMap<&'static str, TableInfo>>;
 
+/// All tables of the chain database.
+pub const CHAINDATA_TABLE_LIST: &[(&str, TableInfo)] = &[
+    (Account::const_db_name(), TableInfo { dup_sort: false }),
+    (Storage::const_db_name(), TableInfo { dup_sort: true }),
+    (
+        AccountChangeSet::const_db_name(),
+        TableInfo { dup_sort: true },
+    ),
+    (
+        StorageChangeSet::const_db_name(),
+        TableInfo { dup_sort: true },
+    ),
+    (
+        HashedAccount::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (HashedStorage::const_db_name(), TableInfo { dup_sort: true }),
+    (
+        AccountHistory::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (
+        StorageHistory::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (Code::const_db_name(), TableInfo { dup_sort: false }),
+    (TrieAccount::const_db_name(), TableInfo { dup_sort: false }),
+    (TrieStorage::const_db_name(), TableInfo { dup_sort: false }),
+    (DbInfo::const_db_name(), TableInfo { dup_sort: false }),
+    (SnapshotInfo::const_db_name(), TableInfo { dup_sort: false }),
+    (
+        BittorrentInfo::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (HeaderNumber::const_db_name(), TableInfo { dup_sort: false }),
+    (
+        CanonicalHeader::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (Header::const_db_name(), TableInfo { dup_sort: false }),
+    (
+        HeadersTotalDifficulty::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (BlockBody::const_db_name(), TableInfo { dup_sort: false }),
+    (
+        BlockTransaction::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (TotalGas::const_db_name(), TableInfo { dup_sort: false }),
+    (TotalTx::const_db_name(), TableInfo { dup_sort: false }),
+    (Log::const_db_name(), TableInfo { dup_sort: false }),
+    (
+        LogTopicIndex::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (
+        LogAddressIndex::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (CallTraceSet::const_db_name(), TableInfo { dup_sort: true }),
+    (
+        CallFromIndex::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (CallToIndex::const_db_name(), TableInfo { dup_sort: false }),
+    (
+        BlockTransactionLookup::const_db_name(),
+        TableInfo { dup_sort: false },
+    ),
+    (Config::const_db_name(), TableInfo { dup_sort: false }),
+    (SyncStage::const_db_name(), TableInfo { dup_sort: false }),
+    (TxSender::const_db_name(), TableInfo { dup_sort: false }),
+    (LastBlock::const_db_name(), TableInfo { dup_sort: false }),
+    (Migration::const_db_name(), TableInfo { dup_sort: false }),
+    (Sequence::const_db_name(), TableInfo { dup_sort: false }),
+    (LastHeader::const_db_name(), TableInfo { dup_sort: false }),
+    (Issuance::const_db_name(), TableInfo { dup_sort: false }),
+];
+
 pub static CHAINDATA_TABLES: Lazy<Arc<HashMap<&'static str, TableInfo>>> = Lazy::new(|| {
-    Arc::new(hashmap! {
-        Account::const_db_name() => TableInfo::default(),
-        Storage::const_db_name() => TableInfo {
-            dup_sort: true,
-        },
-        AccountChangeSet::const_db_name() => TableInfo {
-            dup_sort: true,
-        },
-        StorageChangeSet::const_db_name() => TableInfo {
-            dup_sort: true,
-        },
-        HashedAccount::const_db_name() => TableInfo::default(),
-        HashedStorage::const_db_name() => TableInfo {
-            dup_sort: true,
-        },
-        AccountHistory::const_db_name() => TableInfo::default(),
-        StorageHistory::const_db_name() => TableInfo::default(),
-        Code::const_db_name() => TableInfo::default(),
-        TrieAccount::const_db_name() => TableInfo::default(),
-        TrieStorage::const_db_name() => TableInfo::default(),
-        DbInfo::const_db_name() => TableInfo::default(),
-        SnapshotInfo::const_db_name() => TableInfo::default(),
-        BittorrentInfo::const_db_name() => TableInfo::default(),
-        HeaderNumber::const_db_name() => TableInfo::default(),
-        CanonicalHeader::const_db_name() => TableInfo::default(),
-        Header::const_db_name() => TableInfo::default(),
-        HeadersTotalDifficulty::const_db_name() => TableInfo::default(),
-        BlockBody::const_db_name() => TableInfo::default(),
-        BlockTransaction::const_db_name() => TableInfo::default(),
-        TotalGas::const_db_name() => TableInfo::default(),
-        TotalTx::const_db_name() => TableInfo::default(),
-        Log::const_db_name() => TableInfo::default(),
-        LogTopicIndex::const_db_name() => TableInfo::default(),
-        LogAddressIndex::const_db_name() => TableInfo::default(),
-        CallTraceSet::const_db_name() => TableInfo {
-            dup_sort: true,
-        },
-        CallFromIndex::const_db_name() => TableInfo::default(),
-        CallToIndex::const_db_name() => TableInfo::default(),
-        BlockTransactionLookup::const_db_name() => TableInfo::default(),
-        Config::const_db_name() => TableInfo::default(),
-        SyncStage::const_db_name() => TableInfo::default(),
-        TxSender::const_db_name() => TableInfo::default(),
-        LastBlock::const_db_name() => TableInfo::default(),
-        Migration::const_db_name() => TableInfo::default(),
-        Sequence::const_db_name() => TableInfo::default(),
-        LastHeader::const_db_name() => TableInfo::default(),
-        Issuance::const_db_name() => TableInfo::default(),
-    })
+    Arc::new(
+        CHAINDATA_TABLE_LIST
+            .iter()
+            .map(|(name, info)| (*name, info.clone()))
+            .collect(),
+    )
 });
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use hex_literal::hex;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn no_duplicate_tables() {
+        let mut names = HashSet::new();
+        for (name, _) in CHAINDATA_TABLE_LIST {
+            assert!(names.insert(*name), "duplicate table {}", name);
+        }
+        assert_eq!(CHAINDATA_TABLES.len(), CHAINDATA_TABLE_LIST.len());
+    }
 
     #[test]
     fn u256() {
@@ -856,6 +1023,47 @@ mod tests {
         }
     }
 
+    fn u256_fixture() -> impl Strategy<Value = U256> {
+        any::<[u8; 32]>().prop_map(U256::from_be_bytes)
+    }
+
+    proptest! {
+        #[test]
+        fn u256_be_roundtrip(v in u256_fixture()) {
+            let encoded = U256Be(v).encode();
+            prop_assert_eq!(encoded.len(), 32);
+            prop_assert_eq!(U256Be::decode(&encoded).unwrap(), U256Be(v));
+        }
+
+        #[test]
+        fn u256_le_roundtrip(v in u256_fixture()) {
+            let encoded = U256Le(v).encode();
+            prop_assert_eq!(encoded.len(), 32);
+            prop_assert_eq!(U256Le::decode(&encoded).unwrap(), U256Le(v));
+        }
+
+        #[test]
+        fn u256_be_ordering(a in u256_fixture(), b in u256_fixture()) {
+            prop_assert_eq!(U256Be(a).encode().cmp(&U256Be(b).encode()), a.cmp(&b));
+        }
+    }
+
+    #[test]
+    fn u256_fixed_width() {
+        assert_eq!(U256Be(U256::ONE).encode(), {
+            let mut v = [0; 32];
+            v[31] = 1;
+            v
+        });
+        assert_eq!(U256Le(U256::ONE).encode(), {
+            let mut v = [0; 32];
+            v[0] = 1;
+            v
+        });
+        assert!(U256Be::decode(&[1]).is_err());
+        assert!(U256Le::decode(&[0; 33]).is_err());
+    }
+
     #[test]
     fn log() {
         let input = vec![
@@ -29,9 +29,11 @@ pub mod chain;
 pub mod consensus;
 pub mod crypto;
 pub mod downloader;
+pub mod engine_api;
 pub mod etl;
 pub mod execution;
 pub mod kv;
+pub mod mining;
 pub mod models;
 pub mod res;
 pub mod sentry;
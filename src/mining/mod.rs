@@ -0,0 +1,252 @@
+use crate::{
+    chain::protocol_param::param,
+    consensus::{
+        compute_base_fee,
+        difficulty::{canonical_difficulty, BlockDifficultyBombData},
+    },
+    h256_to_u256,
+    models::*,
+};
+use ::ethash::LightDAG;
+use anyhow::bail;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::sync::oneshot;
+
+/// Block to be sealed on top of `parent`.
+///
+/// State root, receipts root, logs bloom and gas used are results of executing `transactions` on top of `parent`.
+#[derive(Clone, Debug)]
+pub struct BlockTemplate {
+    pub parent: BlockHeader,
+    pub transactions: Vec<MessageWithSignature>,
+    pub beneficiary: Address,
+    pub extra_data: Bytes,
+    pub timestamp: u64,
+    pub state_root: H256,
+    pub receipts_root: H256,
+    pub logs_bloom: Bloom,
+    pub gas_used: u64,
+}
+
+/// Ethash miner for development and private networks.
+#[derive(Debug)]
+pub struct Miner {
+    pool: rayon::ThreadPool,
+    /// Stop flag of the running search.
+    stop: Mutex<Arc<AtomicBool>>,
+    homestead_formula: Option<BlockNumber>,
+    byzantium_formula: Option<BlockNumber>,
+    difficulty_bomb: Option<DifficultyBomb>,
+    eip1559_block: Option<BlockNumber>,
+    elasticity_multiplier: u64,
+    base_fee_max_change_denominator: u64,
+}
+
+impl Miner {
+    pub fn new(chain_spec: &ChainSpec, threads: usize) -> anyhow::Result<Self> {
+        let SealVerificationParams::Ethash {
+            homestead_formula,
+            byzantium_formula,
+            difficulty_bomb,
+            ..
+        } = chain_spec.consensus.seal_verification.clone() else {
+            bail!("Mining is only supported for Ethash");
+        };
+
+        Ok(Self {
+            pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .thread_name(|i| format!("miner-{}", i))
+                .build()?,
+            stop: Default::default(),
+            homestead_formula,
+            byzantium_formula,
+            difficulty_bomb,
+            eip1559_block: chain_spec.consensus.eip1559_block,
+            elasticity_multiplier: chain_spec.params.elasticity_multiplier(),
+            base_fee_max_change_denominator: chain_spec.params.base_fee_max_change_denominator(),
+        })
+    }
+
+    /// Header of the block in `template`, without seal.
+    pub fn prepare_header(&self, template: BlockTemplate) -> BlockHeader {
+        let parent = &template.parent;
+        let number = parent.number + 1;
+
+        let difficulty = canonical_difficulty(
+            number,
+            template.timestamp,
+            parent.difficulty,
+            parent.timestamp,
+            parent.ommers_hash != EMPTY_LIST_HASH,
+            switch_is_active(self.byzantium_formula, number),
+            switch_is_active(self.homestead_formula, number),
+            self.difficulty_bomb
+                .as_ref()
+                .map(|b| BlockDifficultyBombData {
+                    delay_to: b.get_delay_to(number),
+                }),
+        );
+
+        // The fork block doubles the gas limit, keeping the gas target where the limit used to be.
+        let gas_limit = match self.eip1559_block {
+            Some(fork_block) if number == fork_block => {
+                parent.gas_limit * self.elasticity_multiplier
+            }
+            _ => parent.gas_limit,
+        };
+
+        let base_fee_per_gas = match self.eip1559_block {
+            Some(fork_block) if number == fork_block => Some(param::INITIAL_BASE_FEE.as_u256()),
+            Some(fork_block) if number > fork_block => compute_base_fee(
+                parent,
+                self.elasticity_multiplier,
+                self.base_fee_max_change_denominator,
+            ),
+            _ => None,
+        };
+
+        BlockHeader {
+            parent_hash: parent.hash(),
+            ommers_hash: EMPTY_LIST_HASH,
+            beneficiary: template.beneficiary,
+            state_root: template.state_root,
+            transactions_root: Block::transactions_root(&template.transactions),
+            receipts_root: template.receipts_root,
+            logs_bloom: template.logs_bloom,
+            difficulty,
+            number,
+            gas_limit,
+            gas_used: template.gas_used,
+            timestamp: template.timestamp,
+            extra_data: template.extra_data,
+            mix_hash: H256::zero(),
+            nonce: H64::zero(),
+            base_fee_per_gas,
+            withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
+        }
+    }
+
+    /// Searches for the seal of the block in background, returning the sealed header once found.
+    ///
+    /// The receiver fails if the search is stopped before that, either by [`Miner::stop`] or by the next search.
+    pub fn mine(&self, template: BlockTemplate) -> oneshot::Receiver<BlockHeader> {
+        let (tx, rx) = oneshot::channel();
+
+        let mut header = self.prepare_header(template);
+        let stop = Arc::new(AtomicBool::new(false));
+        std::mem::replace(&mut *self.stop.lock(), stop.clone()).store(true, Ordering::SeqCst);
+
+        self.pool.spawn(move || {
+            let dag = LightDAG::new(header.number.0.into());
+            let truncated_hash = header.truncated_hash();
+            let boundary = ::ethash::cross_boundary(header.difficulty);
+
+            let seal = (0..u64::MAX).into_par_iter().find_map_any(|nonce| {
+                if stop.load(Ordering::Relaxed) {
+                    return Some(None);
+                }
+
+                let nonce = H64::from_low_u64_be(nonce);
+                let (mix_hash, final_hash) = dag.hashimoto(truncated_hash, nonce);
+                if h256_to_u256(final_hash) <= boundary {
+                    return Some(Some((mix_hash, nonce)));
+                }
+
+                None
+            });
+
+            if let Some(Some((mix_hash, nonce))) = seal {
+                header.mix_hash = mix_hash;
+                header.nonce = nonce;
+                let _ = tx.send(header);
+            }
+        });
+
+        rx
+    }
+
+    /// Halts the running search.
+    pub fn stop(&self) {
+        self.stop.lock().store(true, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::res::chainspec::MAINNET;
+
+    /// Searches are started on top of block 0, so that they use the light cache of epoch 0, which is the smallest.
+    fn template(parent_number: BlockNumber) -> BlockTemplate {
+        BlockTemplate {
+            parent: BlockHeader {
+                number: parent_number,
+                difficulty: 8_000_000_000_000_000_u64.as_u256(),
+                timestamp: 1_000,
+                gas_limit: 30_000_000,
+                gas_used: 15_000_000,
+                ommers_hash: EMPTY_LIST_HASH,
+                ..BlockHeader::empty()
+            },
+            transactions: vec![],
+            beneficiary: Address::repeat_byte(0xaa),
+            extra_data: Bytes::from_static(b"akula"),
+            timestamp: 1_013,
+            state_root: H256::repeat_byte(1),
+            receipts_root: EMPTY_ROOT,
+            logs_bloom: Bloom::zero(),
+            gas_used: 0,
+        }
+    }
+
+    #[test]
+    fn prepare_header() {
+        let miner = Miner::new(&MAINNET, 1).unwrap();
+        let template = template(BlockNumber(12_964_999));
+        let header = miner.prepare_header(template.clone());
+
+        assert_eq!(header.parent_hash, template.parent.hash());
+        assert_eq!(header.number, BlockNumber(12_965_000));
+        assert_eq!(header.beneficiary, template.beneficiary);
+        assert_eq!(header.transactions_root, EMPTY_ROOT);
+        // London activates at this block.
+        assert_eq!(header.gas_limit, template.parent.gas_limit * 2);
+        assert_eq!(
+            header.base_fee_per_gas,
+            Some(param::INITIAL_BASE_FEE.as_u256())
+        );
+        // 13 seconds since parent keep the difficulty apart from the bomb.
+        assert!(header.difficulty >= template.parent.difficulty);
+    }
+
+    #[tokio::test]
+    async fn stop_halts_search() {
+        let miner = Miner::new(&MAINNET, 2).unwrap();
+        let sealed = miner.mine(template(BlockNumber(0)));
+        miner.stop();
+
+        assert!(sealed.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn new_search_supersedes_running_one() {
+        let miner = Miner::new(&MAINNET, 2).unwrap();
+        let first = miner.mine(template(BlockNumber(0)));
+        let second = miner.mine(template(BlockNumber(0)));
+
+        assert!(first.await.is_err());
+
+        miner.stop();
+        assert!(second.await.is_err());
+    }
+}
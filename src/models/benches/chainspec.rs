@@ -0,0 +1,113 @@
+use akula::{
+    models::*,
+    res::chainspec::{MAINNET, RINKEBY},
+};
+use bytes::Bytes;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::collections::HashMap;
+
+/// Number of blocks to collect the spec for in every iteration.
+const BLOCKS: u64 = 1_000_000;
+
+/// Chain with many system contract upgrades and a large genesis allocation.
+fn heavy_chainspec() -> ChainSpec {
+    let mut chainspec = RINKEBY.clone();
+    chainspec.contracts = (0..1000_u64)
+        .map(|i| {
+            (
+                BlockNumber(i * 1000),
+                [(
+                    Address::from_low_u64_be(i % 10),
+                    Contract::Contract {
+                        code: Bytes::from(i.to_be_bytes().to_vec()),
+                    },
+                )]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+            )
+        })
+        .collect();
+    chainspec.balances = [(
+        BlockNumber(0),
+        (0..5000)
+            .map(|i| (Address::from_low_u64_be(i), 1.as_u256()))
+            .collect::<HashMap<_, _>>(),
+    )]
+    .into_iter()
+    .collect();
+    chainspec
+}
+
+/// Lookup of active system contracts as done before switching to a range, kept as a baseline.
+fn contracts_by_fold(
+    chainspec: &ChainSpec,
+    block_number: BlockNumber,
+) -> HashMap<Address, Contract> {
+    chainspec
+        .contracts
+        .iter()
+        .fold(HashMap::new(), |mut acc, (bn, contracts)| {
+            if block_number >= *bn {
+                for (addr, contract) in contracts {
+                    acc.insert(*addr, contract.clone());
+                }
+            }
+
+            acc
+        })
+}
+
+fn contracts_by_range(
+    chainspec: &ChainSpec,
+    block_number: BlockNumber,
+) -> HashMap<Address, Contract> {
+    chainspec
+        .contracts
+        .range(..=block_number)
+        .flat_map(|(_, contracts)| contracts)
+        .map(|(addr, contract)| (*addr, contract.clone()))
+        .collect()
+}
+
+fn chainspec_benchmarks(c: &mut Criterion) {
+    let heavy = heavy_chainspec();
+
+    let mut group = c.benchmark_group("collect_block_spec");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(BLOCKS));
+    for (name, chainspec) in [("mainnet", &*MAINNET), ("heavy", &heavy)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for block_number in 1..=BLOCKS {
+                    black_box(chainspec.collect_block_spec(block_number, None));
+                }
+            })
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("system contracts lookup");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(BLOCKS));
+    for (name, chainspec) in [("mainnet", &*MAINNET), ("heavy", &heavy)] {
+        for (lookup, f) in [
+            (
+                "fold",
+                contracts_by_fold as fn(&ChainSpec, BlockNumber) -> _,
+            ),
+            ("range", contracts_by_range),
+        ] {
+            group.bench_function(format!("{} {}", name, lookup), |b| {
+                b.iter(|| {
+                    for block_number in 1..=BLOCKS {
+                        black_box(f(chainspec, BlockNumber(block_number)));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, chainspec_benchmarks);
+criterion_main!(benches);
@@ -1,16 +1,40 @@
 use super::*;
-use crate::crypto::*;
+use crate::{crypto::*, json_hex_field, optional_json_hex_field, EncodeHex};
 use derive_more::Deref;
 use parity_scale_codec::*;
-use rlp_derive::*;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
 use sha3::*;
-use std::borrow::Borrow;
+use std::{borrow::Borrow, ops::Range};
 
-#[derive(Clone, Debug, PartialEq, Eq, RlpEncodable, RlpDecodable)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Block {
     pub header: BlockHeader,
     pub transactions: Vec<MessageWithSignature>,
     pub ommers: Vec<BlockHeader>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl Encodable for Block {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(3 + self.withdrawals.is_some() as usize);
+        s.append(&self.header);
+        s.append_list(&self.transactions);
+        s.append_list(&self.ommers);
+        if let Some(withdrawals) = &self.withdrawals {
+            s.append_list(withdrawals);
+        }
+    }
+}
+
+impl Decodable for Block {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Self {
+            header: rlp.val_at(0)?,
+            transactions: rlp.list_at(1)?,
+            ommers: rlp.list_at(2)?,
+            withdrawals: decode_withdrawals(rlp, 3)?,
+        })
+    }
 }
 
 impl Block {
@@ -19,6 +43,7 @@ impl Block {
         partial_header: PartialHeader,
         transactions: Vec<MessageWithSignature>,
         ommers: Vec<BlockHeader>,
+        withdrawals: Option<Vec<Withdrawal>>,
     ) -> Self {
         let ommers_hash = Self::ommers_hash(&ommers);
         let transactions_root = Self::transactions_root(&transactions);
@@ -27,6 +52,7 @@ impl Block {
             header: BlockHeader::new(partial_header, ommers_hash, transactions_root),
             transactions,
             ommers,
+            withdrawals,
         }
     }
 
@@ -39,6 +65,19 @@ impl Block {
     ) -> H256 {
         ordered_trie_root(iter.into_iter().map(|r| r.borrow().trie_encode()))
     }
+
+    pub fn withdrawals_root(withdrawals: &[Withdrawal]) -> H256 {
+        ordered_trie_root(withdrawals.iter().map(rlp::encode))
+    }
+}
+
+/// Withdrawals are the optional last item of block (body) lists starting with Shanghai.
+fn decode_withdrawals(rlp: &Rlp, index: usize) -> Result<Option<Vec<Withdrawal>>, DecoderError> {
+    match rlp.item_count()? {
+        n if n == index => Ok(None),
+        n if n == index + 1 => Ok(Some(rlp.list_at(index)?)),
+        _ => Err(DecoderError::RlpIncorrectListLen),
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -46,6 +85,7 @@ pub struct BlockWithSenders {
     pub header: PartialHeader,
     pub transactions: Vec<MessageWithSender>,
     pub ommers: Vec<BlockHeader>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
 }
 
 impl From<Block> for BlockWithSenders {
@@ -67,14 +107,37 @@ impl From<Block> for BlockWithSenders {
             header: block.header.into(),
             transactions,
             ommers: block.ommers,
+            withdrawals: block.withdrawals,
         }
     }
 }
 
-#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct BlockBody {
     pub transactions: Vec<MessageWithSignature>,
     pub ommers: Vec<BlockHeader>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl Encodable for BlockBody {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2 + self.withdrawals.is_some() as usize);
+        s.append_list(&self.transactions);
+        s.append_list(&self.ommers);
+        if let Some(withdrawals) = &self.withdrawals {
+            s.append_list(withdrawals);
+        }
+    }
+}
+
+impl Decodable for BlockBody {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Self {
+            transactions: rlp.list_at(0)?,
+            ommers: rlp.list_at(1)?,
+            withdrawals: decode_withdrawals(rlp, 2)?,
+        })
+    }
 }
 
 impl From<Block> for BlockBody {
@@ -82,6 +145,7 @@ impl From<Block> for BlockBody {
         Self {
             transactions: block.transactions,
             ommers: block.ommers,
+            withdrawals: block.withdrawals,
         }
     }
 }
@@ -90,13 +154,33 @@ impl From<Block> for BlockBody {
 pub struct BlockBodyWithSenders {
     pub transactions: Vec<MessageWithSender>,
     pub ommers: Vec<BlockHeader>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
 }
 
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Encode, Decode, RlpDecodable)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
 pub struct BodyForStorage {
     pub base_tx_id: TxIndex,
     pub tx_amount: u64,
     pub uncles: Vec<BlockHeader>,
+    pub withdrawals: Option<Vec<Withdrawal>>,
+}
+
+impl BodyForStorage {
+    /// IDs of the block's transactions in the `BlockTransaction` table.
+    pub fn tx_id_range(&self) -> Range<TxIndex> {
+        self.base_tx_id..self.base_tx_id + self.tx_amount
+    }
+}
+
+impl Decodable for BodyForStorage {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        Ok(Self {
+            base_tx_id: rlp.val_at(0)?,
+            tx_amount: rlp.val_at(1)?,
+            uncles: rlp.list_at(2)?,
+            withdrawals: decode_withdrawals(rlp, 3)?,
+        })
+    }
 }
 
 #[derive(Clone, Debug, Deref, Default)]
@@ -106,12 +190,183 @@ pub struct WithHash<T> {
     pub hash: H256,
 }
 
+/// Header as returned by `eth_getBlockByNumber`, without the body fields.
+impl From<BlockHeader> for serde_json::Value {
+    fn from(header: BlockHeader) -> Self {
+        let mut map = serde_json::Map::new();
+        let mut field = |name: &str, value: String| {
+            map.insert(name.to_string(), value.into());
+        };
+
+        field("hash", header.hash().to_hex_string());
+        field("parentHash", header.parent_hash.to_hex_string());
+        field("sha3Uncles", header.ommers_hash.to_hex_string());
+        field("miner", header.beneficiary.to_hex_string());
+        field("stateRoot", header.state_root.to_hex_string());
+        field("transactionsRoot", header.transactions_root.to_hex_string());
+        field("receiptsRoot", header.receipts_root.to_hex_string());
+        field("logsBloom", header.logs_bloom.to_hex_string());
+        field("difficulty", header.difficulty.to_hex_string());
+        field("number", header.number.0.to_hex_string());
+        field("gasLimit", header.gas_limit.to_hex_string());
+        field("gasUsed", header.gas_used.to_hex_string());
+        field("timestamp", header.timestamp.to_hex_string());
+        field("extraData", header.extra_data.to_hex_string());
+        field("mixHash", header.mix_hash.to_hex_string());
+        field("nonce", header.nonce.to_hex_string());
+        if let Some(base_fee_per_gas) = header.base_fee_per_gas {
+            field("baseFeePerGas", base_fee_per_gas.to_hex_string());
+        }
+        if let Some(withdrawals_root) = header.withdrawals_root {
+            field("withdrawalsRoot", withdrawals_root.to_hex_string());
+        }
+        if let Some(blob_gas_used) = header.blob_gas_used {
+            field("blobGasUsed", blob_gas_used.to_hex_string());
+        }
+        if let Some(excess_blob_gas) = header.excess_blob_gas {
+            field("excessBlobGas", excess_blob_gas.to_hex_string());
+        }
+        if let Some(parent_beacon_block_root) = header.parent_beacon_block_root {
+            field(
+                "parentBeaconBlockRoot",
+                parent_beacon_block_root.to_hex_string(),
+            );
+        }
+
+        Self::Object(map)
+    }
+}
+
+/// Header from an `eth_getBlockByNumber` response. Body fields and the block hash are ignored.
+impl TryFrom<serde_json::Value> for BlockHeader {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_json::Value) -> anyhow::Result<Self> {
+        Ok(Self {
+            parent_hash: json_hex_field(&value, "parentHash")?,
+            ommers_hash: json_hex_field(&value, "sha3Uncles")?,
+            beneficiary: json_hex_field(&value, "miner")?,
+            state_root: json_hex_field(&value, "stateRoot")?,
+            transactions_root: json_hex_field(&value, "transactionsRoot")?,
+            receipts_root: json_hex_field(&value, "receiptsRoot")?,
+            logs_bloom: json_hex_field(&value, "logsBloom")?,
+            difficulty: json_hex_field(&value, "difficulty")?,
+            number: BlockNumber(json_hex_field(&value, "number")?),
+            gas_limit: json_hex_field(&value, "gasLimit")?,
+            gas_used: json_hex_field(&value, "gasUsed")?,
+            timestamp: json_hex_field(&value, "timestamp")?,
+            extra_data: json_hex_field(&value, "extraData")?,
+            mix_hash: json_hex_field(&value, "mixHash")?,
+            nonce: json_hex_field(&value, "nonce")?,
+            base_fee_per_gas: optional_json_hex_field(&value, "baseFeePerGas")?,
+            withdrawals_root: optional_json_hex_field(&value, "withdrawalsRoot")?,
+            blob_gas_used: optional_json_hex_field(&value, "blobGasUsed")?,
+            excess_blob_gas: optional_json_hex_field(&value, "excessBlobGas")?,
+            parent_beacon_block_root: optional_json_hex_field(&value, "parentBeaconBlockRoot")?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     const CHAIN_ID: ChainId = ChainId(1);
 
+    #[test]
+    fn ommers_hash_of_list_encoding() {
+        let ommers = vec![
+            BlockHeader {
+                number: BlockNumber(9),
+                beneficiary: Address::repeat_byte(1),
+                ..BlockHeader::empty()
+            },
+            BlockHeader {
+                number: BlockNumber(8),
+                beneficiary: Address::repeat_byte(2),
+                ..BlockHeader::empty()
+            },
+        ];
+        let block = Block::new(
+            PartialHeader {
+                number: BlockNumber(10),
+                ..PartialHeader::empty()
+            },
+            vec![],
+            ommers.clone(),
+            None,
+        );
+
+        // The hash covers the ommers list exactly as it appears in the block encoding.
+        let encoded = rlp::encode(&block);
+        let ommers_rlp = Rlp::new(&encoded).at(2).unwrap().as_raw().to_vec();
+        assert_eq!(
+            block.header.ommers_hash,
+            H256::from_slice(&Keccak256::digest(&ommers_rlp))
+        );
+        assert_ne!(block.header.ommers_hash, EMPTY_LIST_HASH);
+        assert_eq!(Block::ommers_hash(&[]), EMPTY_LIST_HASH);
+
+        // Not a hash of the concatenated headers, and order matters.
+        let concatenated = ommers
+            .iter()
+            .flat_map(|ommer| rlp::encode(ommer).to_vec())
+            .collect::<Vec<_>>();
+        assert_ne!(
+            block.header.ommers_hash,
+            H256::from_slice(&Keccak256::digest(&concatenated))
+        );
+        assert_ne!(
+            block.header.ommers_hash,
+            Block::ommers_hash(&[ommers[1].clone(), ommers[0].clone()])
+        );
+    }
+
+    #[test]
+    fn ommers_hash_mainnet() {
+        // Uncle of https://etherscan.io/block/3
+        let ommers_rlp = hex!(
+            "f90215f90212a0d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69a"
+            "ec8c0db1cb8fa3a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413"
+            "f0a142fd40d4934794c8ebccc5f5689fa8659d83713341e5ad19349448a01e6e"
+            "030581fd1873b4784280859cd3b3c04aa85520f08c304cf5ee63d3935adda056"
+            "e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a0"
+            "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+            "b901000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000000000000000000000000000000000000000000000000000000000000000"
+            "0000008503ff80000001821388808455ba42429a59617465732052616e64616c"
+            "6c202d2045746865724e696e6a61a0f8c94dfe61cf26dcdf8cffeda337cf6a90"
+            "3d65c449d7691a022837f6e2d994598868b769c5451a7aea"
+        );
+        let ommers = rlp::decode_list::<BlockHeader>(&ommers_rlp);
+
+        assert_eq!(ommers.len(), 1);
+        assert_eq!(ommers[0].number, BlockNumber(1));
+        assert_eq!(
+            Block::ommers_hash(&ommers),
+            H256(hex!(
+                "6b17b938c6e4ef18b26ad81b9ca3515f27fd9c4e82aac56a1fd8eab288785e41"
+            ))
+        );
+    }
+
+    #[test]
+    fn tx_id_range() {
+        let body = BodyForStorage {
+            base_tx_id: TxIndex(42),
+            tx_amount: 3,
+            uncles: vec![],
+            withdrawals: None,
+        };
+        assert_eq!(body.tx_id_range(), TxIndex(42)..TxIndex(45));
+    }
+
     #[test]
     fn compose_block() {
         // https://etherscan.io/block/13143465
@@ -134,6 +389,10 @@ mod tests {
             mix_hash: hex!("b26583e11ffc5d412b46d1ddb74e78c775fb54b049dc0cf0689e8430a45d9186").into(),
             nonce: hex!("596b98b5d0f8cc56").into(),
             base_fee_per_gas: Some(0x18aac2ec3d_u64.into()),
+            withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
         };
 
         let ommers = vec![];
@@ -255,7 +514,7 @@ mod tests {
             ]
         );
 
-        let block = Block::new(partial_header, transactions, ommers);
+        let block = Block::new(partial_header, transactions, ommers, None);
 
         assert_eq!(
             block.header.transactions_root.0,
@@ -324,6 +583,10 @@ mod tests {
                     .into(),
                 nonce: hex!("68b769c5451a7aea").into(),
                 base_fee_per_gas: None,
+                withdrawals_root: None,
+                blob_gas_used: None,
+                excess_blob_gas: None,
+                parent_beacon_block_root: None,
             }]
         );
 
@@ -399,12 +662,67 @@ mod tests {
                     .into(),
                 nonce: hex!("0000000000000023").into(),
                 base_fee_per_gas: None,
+                withdrawals_root: None,
+                blob_gas_used: None,
+                excess_blob_gas: None,
+                parent_beacon_block_root: None,
             }],
+            withdrawals: None,
         };
 
         assert_eq!(rlp::decode::<BlockBody>(&rlp::encode(&body)).unwrap(), body);
     }
 
+    #[test]
+    fn withdrawals_rlp() {
+        let withdrawals = vec![
+            Withdrawal {
+                index: 0,
+                validator_index: 65_535,
+                address: hex!("0000000000000000000000000000000000000000").into(),
+                amount: 0,
+            },
+            Withdrawal {
+                index: 1,
+                validator_index: 65_536,
+                address: hex!("0000000000000000000000000000000000001000").into(),
+                amount: 32 * GIGA,
+            },
+        ];
+
+        assert_eq!(
+            rlp::encode(&withdrawals[1]).to_vec(),
+            hex!("e00183010000940000000000000000000000000000000000001000850773594000")
+        );
+
+        let body = BlockBody {
+            transactions: vec![],
+            ommers: vec![],
+            withdrawals: Some(withdrawals.clone()),
+        };
+        let encoded = rlp::encode(&body);
+        assert_eq!(rlp::Rlp::new(&encoded).item_count().unwrap(), 3);
+        assert_eq!(rlp::decode::<BlockBody>(&encoded).unwrap(), body);
+
+        let header = PartialHeader {
+            number: 17_034_870.into(),
+            base_fee_per_gas: Some(7_u64.into()),
+            withdrawals_root: Some(Block::withdrawals_root(&withdrawals)),
+            ..PartialHeader::empty()
+        };
+        let block = Block::new(header, vec![], vec![], Some(withdrawals));
+        assert_eq!(rlp::decode::<Block>(&rlp::encode(&block)).unwrap(), block);
+
+        // Pre-Shanghai bodies keep their two-item encoding.
+        let body = BlockBody {
+            withdrawals: None,
+            ..body
+        };
+        let encoded = rlp::encode(&body);
+        assert_eq!(rlp::Rlp::new(&encoded).item_count().unwrap(), 2);
+        assert_eq!(rlp::decode::<BlockBody>(&encoded).unwrap(), body);
+    }
+
     #[test]
     fn invalid_block_rlp() {
         // Consensus test RLP_InputList_TooManyElements_HEADER_DECODEINTO_BLOCK_EXTBLOCK_HEADER
@@ -465,4 +783,78 @@ mod tests {
 
         assert_eq!(rlp::decode::<BlockHeader>(&rlp::encode(&h)).unwrap(), h);
     }
+
+    #[test]
+    fn eip4844_header_rlp() {
+        let h = BlockHeader {
+            number: 19_426_587.into(),
+            base_fee_per_gas: Some(2_700_000_000_u64.into()),
+            withdrawals_root: Some(EMPTY_ROOT),
+            blob_gas_used: Some(393_216),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: None,
+            ..BlockHeader::empty()
+        };
+
+        assert_eq!(rlp::decode::<BlockHeader>(&rlp::encode(&h)).unwrap(), h);
+    }
+
+    #[test]
+    fn header_json_mainnet_genesis() {
+        let json = serde_json::json!({
+            "hash": "0xd4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+            "parentHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+            "miner": "0x0000000000000000000000000000000000000000",
+            "stateRoot": "0xd7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544",
+            "transactionsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "receiptsRoot": "0x56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421",
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "difficulty": "0x400000000",
+            "number": "0x0",
+            "gasLimit": "0x1388",
+            "gasUsed": "0x0",
+            "timestamp": "0x0",
+            "extraData": "0x11bbe8db4e347b4e8c937c1c8370e4b5ed33adb3db69cbdb7a38e1e50b1b82fa",
+            "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "nonce": "0x0000000000000042",
+        });
+
+        let header = BlockHeader::try_from(json.clone()).unwrap();
+        assert_eq!(header.number, BlockNumber(0));
+        assert_eq!(header.gas_limit, 5000);
+        assert_eq!(
+            header.hash(),
+            hex!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3").into()
+        );
+        assert_eq!(serde_json::Value::from(header), json);
+    }
+
+    #[test]
+    fn header_json_roundtrip() {
+        let header = BlockHeader {
+            parent_hash: H256::repeat_byte(1),
+            beneficiary: Address::repeat_byte(2),
+            difficulty: U256::ZERO,
+            number: BlockNumber(19_426_587),
+            gas_limit: 30_000_000,
+            gas_used: 12_345_678,
+            timestamp: 1_710_338_135,
+            extra_data: b"akula".to_vec().into(),
+            base_fee_per_gas: Some(U256::from(30_000_000_000_u64)),
+            withdrawals_root: Some(EMPTY_ROOT),
+            blob_gas_used: Some(393_216),
+            excess_blob_gas: Some(0),
+            parent_beacon_block_root: Some(H256::repeat_byte(3)),
+            ..BlockHeader::empty()
+        };
+
+        let json = serde_json::Value::from(header.clone());
+        assert_eq!(json["gasLimit"], "0x1c9c380");
+        assert_eq!(json["baseFeePerGas"], "0x6fc23ac00");
+        assert_eq!(json["excessBlobGas"], "0x0");
+        assert_eq!(BlockHeader::try_from(json).unwrap(), header);
+
+        assert!(BlockHeader::try_from(serde_json::json!({ "number": "0x1" })).is_err());
+    }
 }
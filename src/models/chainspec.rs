@@ -1,13 +1,16 @@
-use crate::{models::*, util::*};
+use crate::{chain::protocol_param::param, consensus::compute_base_fee, models::*, util::*};
 use bytes::Bytes;
 use serde::*;
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt::{self, Display, Formatter},
     time::Duration,
 };
 
 type NodeUrl = String;
 
+const ETHASH_AVERAGE_BLOCK_TIME: Duration = Duration::from_secs(13);
+
 #[derive(Debug, PartialEq)]
 pub struct BlockExecutionSpec {
     pub revision: Revision,
@@ -15,6 +18,10 @@ pub struct BlockExecutionSpec {
     pub params: Params,
     pub system_contract_changes: HashMap<Address, Contract>,
     pub balance_changes: HashMap<Address, U256>,
+    /// Base fee of the block, `None` before London.
+    pub base_fee_per_gas: Option<U256>,
+    /// Total difficulty at which the chain switches to proof-of-stake, if it is scheduled to.
+    pub terminal_total_difficulty: Option<U256>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -33,31 +40,29 @@ pub struct ChainSpec {
 }
 
 impl ChainSpec {
-    pub fn collect_block_spec(&self, block_number: impl Into<BlockNumber>) -> BlockExecutionSpec {
+    /// Execution parameters of the block.
+    ///
+    /// After London the base fee is derived from `parent`: its gas usage, gas limit and base fee.
+    /// It is left unset if the parent header is not supplied, use [`Self::collect_header_spec`] when the block's
+    /// own header is at hand.
+    pub fn collect_block_spec(
+        &self,
+        block_number: impl Into<BlockNumber>,
+        parent: Option<&BlockHeader>,
+    ) -> BlockExecutionSpec {
         let block_number = block_number.into();
         let mut revision = Revision::Frontier;
         let mut active_transitions = HashSet::new();
-        for (fork, r) in [
-            (self.upgrades.london, Revision::London),
-            (self.upgrades.berlin, Revision::Berlin),
-            (self.upgrades.istanbul, Revision::Istanbul),
-            (self.upgrades.petersburg, Revision::Petersburg),
-            (self.upgrades.constantinople, Revision::Constantinople),
-            (self.upgrades.byzantium, Revision::Byzantium),
-            (self.upgrades.spurious, Revision::Spurious),
-            (self.upgrades.tangerine, Revision::Tangerine),
-            (self.upgrades.homestead, Revision::Homestead),
-        ] {
+        for (fork, r) in self.upgrades.forks().into_iter().rev() {
             if let Some(fork_block) = fork {
                 if block_number >= fork_block {
+                    // Several forks may activate at the same block.
                     if block_number == fork_block {
                         active_transitions.insert(r);
                     }
                     if revision == Revision::Frontier {
                         revision = r;
                     }
-
-                    break;
                 }
             }
         }
@@ -66,23 +71,58 @@ impl ChainSpec {
             revision,
             active_transitions,
             params: self.params.clone(),
-            system_contract_changes: self.contracts.iter().fold(
-                HashMap::new(),
-                |mut acc, (bn, contracts)| {
-                    if block_number >= *bn {
-                        for (addr, contract) in contracts {
-                            acc.insert(*addr, contract.clone());
-                        }
-                    }
-
-                    acc
-                },
-            ),
+            system_contract_changes: self
+                .contracts
+                .range(..=block_number)
+                .flat_map(|(_, contracts)| contracts)
+                .map(|(addr, contract)| (*addr, contract.clone()))
+                .collect(),
             balance_changes: self
                 .balances
                 .get(&block_number)
                 .cloned()
                 .unwrap_or_default(),
+            base_fee_per_gas: self.base_fee_per_gas(block_number, parent),
+            terminal_total_difficulty: self.consensus.terminal_total_difficulty,
+        }
+    }
+
+    /// Execution parameters of the block with `header`, taking the base fee from the header itself.
+    pub fn collect_header_spec(&self, header: &PartialHeader) -> BlockExecutionSpec {
+        BlockExecutionSpec {
+            base_fee_per_gas: header.base_fee_per_gas,
+            ..self.collect_block_spec(header.number, None)
+        }
+    }
+
+    /// Base fee of `block_number`: the initial one at the London fork block, none before it.
+    pub fn base_fee_per_gas(
+        &self,
+        block_number: BlockNumber,
+        parent: Option<&BlockHeader>,
+    ) -> Option<U256> {
+        if Some(block_number) == self.upgrades.london {
+            return Some(param::INITIAL_BASE_FEE.as_u256());
+        }
+
+        if self.is_london_active_at(block_number) {
+            return parent.and_then(|parent| {
+                compute_base_fee(
+                    parent,
+                    self.params.elasticity_multiplier(),
+                    self.params.base_fee_max_change_denominator(),
+                )
+            });
+        }
+
+        None
+    }
+
+    /// Average interval between blocks, used to estimate how far the chain has advanced.
+    pub fn expected_block_time(&self) -> Duration {
+        match &self.consensus.seal_verification {
+            SealVerificationParams::Clique { period, .. } => *period,
+            SealVerificationParams::Ethash { .. } => ETHASH_AVERAGE_BLOCK_TIME,
         }
     }
 
@@ -97,6 +137,8 @@ impl ChainSpec {
             self.upgrades.istanbul,
             self.upgrades.berlin,
             self.upgrades.london,
+            self.upgrades.shanghai,
+            self.upgrades.cancun,
         ]
         .iter()
         .copied()
@@ -113,6 +155,99 @@ impl ChainSpec {
     }
 }
 
+impl Display for ChainSpec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.name)?;
+        writeln!(
+            f,
+            "  Chain ID {}, network ID {}",
+            self.params.chain_id, self.params.network_id
+        )?;
+        match &self.consensus.seal_verification {
+            SealVerificationParams::Clique { period, epoch } => writeln!(
+                f,
+                "  Engine: Clique, period {}s, epoch {}",
+                period.as_secs(),
+                epoch
+            )?,
+            SealVerificationParams::Ethash { .. } => writeln!(f, "  Engine: Ethash")?,
+        }
+
+        writeln!(f, "  Upgrades:")?;
+        for (fork, revision) in self.upgrades.forks() {
+            if let Some(fork_block) = fork {
+                writeln!(f, "    {} at block {}", revision, fork_block)?;
+            }
+        }
+        if let Some(eip1559_block) = self.consensus.eip1559_block {
+            writeln!(f, "    EIP-1559 at block {}", eip1559_block)?;
+        }
+        if let Some(terminal_total_difficulty) = self.consensus.terminal_total_difficulty {
+            writeln!(
+                f,
+                "    Merge at total difficulty {}",
+                terminal_total_difficulty
+            )?;
+        }
+
+        let genesis = &self.genesis;
+        writeln!(
+            f,
+            "  Genesis: block {}, gas limit {}, timestamp {}, author {:?}",
+            genesis.number, genesis.gas_limit, genesis.timestamp, genesis.author
+        )?;
+
+        if !self.contracts.is_empty() {
+            writeln!(f, "  Contracts:")?;
+            for (block, contracts) in &self.contracts {
+                let precompiles = contracts
+                    .values()
+                    .filter(|contract| matches!(contract, Contract::Precompile(_)))
+                    .count();
+                writeln!(
+                    f,
+                    "    at block {}: {} precompiles, {} contracts",
+                    block,
+                    precompiles,
+                    contracts.len() - precompiles
+                )?;
+            }
+        }
+
+        write!(f, "  Balance changes at {} blocks", self.balances.len())
+    }
+}
+
+macro_rules! fork_predicates {
+    ($($fork:ident => $method:ident),* $(,)?) => {
+        impl ChainSpec {
+            $(
+                #[doc = concat!("Whether the ", stringify!($fork), " upgrade is active at `block`.")]
+                pub fn $method(&self, block: BlockNumber) -> bool {
+                    self.upgrades
+                        .$fork
+                        .map(|fork_block| block >= fork_block)
+                        .unwrap_or(false)
+                }
+            )*
+        }
+    };
+}
+
+fork_predicates!(
+    homestead => is_homestead_active_at,
+    tangerine => is_tangerine_active_at,
+    spurious => is_spurious_active_at,
+    byzantium => is_byzantium_active_at,
+    constantinople => is_constantinople_active_at,
+    petersburg => is_petersburg_active_at,
+    istanbul => is_istanbul_active_at,
+    berlin => is_berlin_active_at,
+    london => is_london_active_at,
+    shanghai => is_shanghai_active_at,
+    cancun => is_cancun_active_at,
+);
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct DifficultyBomb {
     pub delays: BTreeMap<BlockNumber, BlockNumber>,
@@ -143,6 +278,22 @@ pub struct ConsensusParams {
         with = "::serde_with::rust::unwrap_or_skip"
     )]
     pub eip1559_block: Option<BlockNumber>,
+    /// Total difficulty at which the chain transitions to proof of stake, see EIP-3675.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub terminal_total_difficulty: Option<U256>,
+}
+
+impl ConsensusParams {
+    /// Whether a chain with `total_difficulty` is past the merge.
+    pub fn is_terminal_total_difficulty_reached(&self, total_difficulty: U256) -> bool {
+        self.terminal_total_difficulty
+            .map(|terminal_total_difficulty| total_difficulty >= terminal_total_difficulty)
+            .unwrap_or(false)
+    }
 }
 
 pub fn switch_is_active(switch: Option<BlockNumber>, block_number: BlockNumber) -> bool {
@@ -154,6 +305,7 @@ pub enum SealVerificationParams {
     Clique {
         #[serde(deserialize_with = "deserialize_period_as_duration")]
         period: Duration,
+        #[serde(deserialize_with = "deserialize_nonzero_epoch")]
         epoch: u64,
     },
     Ethash {
@@ -265,6 +417,37 @@ pub struct Upgrades {
         with = "::serde_with::rust::unwrap_or_skip"
     )]
     pub london: Option<BlockNumber>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub shanghai: Option<BlockNumber>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::unwrap_or_skip"
+    )]
+    pub cancun: Option<BlockNumber>,
+}
+
+impl Upgrades {
+    /// Fork blocks with the revisions they activate, in order of activation.
+    fn forks(&self) -> [(Option<BlockNumber>, Revision); 11] {
+        [
+            (self.homestead, Revision::Homestead),
+            (self.tangerine, Revision::Tangerine),
+            (self.spurious, Revision::Spurious),
+            (self.byzantium, Revision::Byzantium),
+            (self.constantinople, Revision::Constantinople),
+            (self.petersburg, Revision::Petersburg),
+            (self.istanbul, Revision::Istanbul),
+            (self.berlin, Revision::Berlin),
+            (self.london, Revision::London),
+            (self.shanghai, Revision::Shanghai),
+            (self.cancun, Revision::Cancun),
+        ]
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -272,6 +455,33 @@ pub struct Params {
     pub chain_id: ChainId,
     pub network_id: NetworkId,
     pub min_gas_limit: u64,
+    pub maximum_extra_data_size: usize,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "::serde_with::rust::unwrap_or_skip::serialize",
+        deserialize_with = "deserialize_nonzero_override"
+    )]
+    pub eip1559_elasticity_multiplier: Option<u64>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "::serde_with::rust::unwrap_or_skip::serialize",
+        deserialize_with = "deserialize_nonzero_override"
+    )]
+    pub eip1559_base_fee_max_change_denominator: Option<u64>,
+}
+
+impl Params {
+    pub fn elasticity_multiplier(&self) -> u64 {
+        self.eip1559_elasticity_multiplier
+            .unwrap_or(param::ELASTICITY_MULTIPLIER)
+    }
+
+    pub fn base_fee_max_change_denominator(&self) -> u64 {
+        self.eip1559_base_fee_max_change_denominator
+            .unwrap_or(param::BASE_FEE_MAX_CHANGE_DENOMINATOR)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
@@ -394,7 +604,7 @@ impl<'de> de::Visitor<'de> for DeserializePeriodAsDuration {
     where
         E: de::Error,
     {
-        Ok(Duration::from_millis(v))
+        Ok(Duration::from_secs(v))
     }
 }
 
@@ -405,6 +615,29 @@ where
     deserializer.deserialize_any(DeserializePeriodAsDuration)
 }
 
+fn deserialize_nonzero_epoch<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let epoch = u64::deserialize(deserializer)?;
+    if epoch == 0 {
+        return Err(de::Error::custom("Clique epoch must be non-zero"));
+    }
+    Ok(epoch)
+}
+
+/// EIP-1559 overrides divide the base fee computation, so they must be non-zero when set.
+fn deserialize_nonzero_override<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    let value = u64::deserialize(deserializer)?;
+    if value == 0 {
+        return Err(de::Error::custom("EIP-1559 parameter must be non-zero"));
+    }
+    Ok(Some(value))
+}
+
 fn deserialize_str_as_u64<'de, D>(deserializer: D) -> Result<u64, D::Error>
 where
     D: de::Deserializer<'de>,
@@ -419,6 +652,31 @@ mod tests {
     use hex_literal::hex;
     use maplit::*;
 
+    #[test]
+    fn display() {
+        let s = MAINNET.to_string();
+
+        assert!(s.starts_with("Ethereum\n"));
+        assert!(s.contains("Engine: Ethash"));
+        assert!(s.contains("Homestead at block 1150000"));
+        assert!(s.contains("London at block 12965000"));
+        assert!(s.contains("EIP-1559 at block 12965000"));
+        assert!(!s.contains("Cancun"));
+        assert!(!s.contains("Contracts:"));
+
+        let mut spec = MAINNET.clone();
+        spec.contracts.insert(
+            BlockNumber(5),
+            hashmap! {
+                Address::from_low_u64_be(1) => Contract::Precompile(Precompile::EcRecover { base: 3000, word: 0 }),
+                Address::from_low_u64_be(0x100) => Contract::Contract { code: Bytes::from_static(&[0x00]) },
+            },
+        );
+        assert!(spec
+            .to_string()
+            .contains("at block 5: 1 precompiles, 1 contracts"));
+    }
+
     #[test]
     fn load_chainspec() {
         assert_eq!(
@@ -426,10 +684,11 @@ mod tests {
                 name: "Rinkeby".into(),
                 consensus: ConsensusParams {
                     seal_verification: SealVerificationParams::Clique {
-                        period: Duration::from_millis(15),
+                        period: Duration::from_secs(15),
                         epoch: 30_000,
                     },
                     eip1559_block: Some(8897988.into()),
+                    terminal_total_difficulty: None,
                 },
                 upgrades: Upgrades {
                     homestead: Some(1.into()),
@@ -441,11 +700,16 @@ mod tests {
                     istanbul: Some(5435345.into()),
                     berlin: Some(8290928.into()),
                     london: Some(8897988.into()),
+                    shanghai: None,
+                    cancun: None,
                 },
                 params: Params {
                     chain_id: ChainId(4),
                     network_id: NetworkId(4),
                     min_gas_limit: 5000,
+                    maximum_extra_data_size: 65535,
+                    eip1559_elasticity_multiplier: None,
+                    eip1559_base_fee_max_change_denominator: None,
                 },
                 genesis: Genesis {
                     number: BlockNumber(0),
@@ -489,17 +753,207 @@ mod tests {
         );
     }
 
+    #[test]
+    fn zero_clique_epoch() {
+        assert!(
+            ron::from_str::<SealVerificationParams>("Clique(period: 15, epoch: 30000)").is_ok()
+        );
+        assert!(ron::from_str::<SealVerificationParams>("Clique(period: 15, epoch: 0)").is_err());
+    }
+
+    #[test]
+    fn zero_eip1559_overrides() {
+        let params = |overrides: &str| {
+            ron::from_str::<Params>(&format!(
+                "(chain_id: 1, network_id: 1, min_gas_limit: 5000, maximum_extra_data_size: 32{})",
+                overrides
+            ))
+        };
+
+        assert_eq!(params("").unwrap().eip1559_elasticity_multiplier, None);
+        assert_eq!(
+            params(
+                ", eip1559_elasticity_multiplier: 4, eip1559_base_fee_max_change_denominator: 16"
+            )
+            .unwrap()
+            .base_fee_max_change_denominator(),
+            16
+        );
+        assert!(params(", eip1559_elasticity_multiplier: 0").is_err());
+        assert!(params(", eip1559_base_fee_max_change_denominator: 0").is_err());
+    }
+
     #[test]
     fn distinct_block_numbers() {
         assert_eq!(
             MAINNET.gather_forks(),
             vec![
                 1_150_000, 1_920_000, 2_463_000, 2_675_000, 4_370_000, 7_280_000, 9_069_000,
-                9_200_000, 12_244_000, 12_965_000, 13_773_000
+                9_200_000, 12_244_000, 12_965_000, 13_773_000, 15_050_000
             ]
             .into_iter()
             .map(BlockNumber)
             .collect()
         );
     }
+
+    #[test]
+    fn difficulty_bomb_delays() {
+        let bomb = match &MAINNET.consensus.seal_verification {
+            SealVerificationParams::Ethash {
+                difficulty_bomb: Some(bomb),
+                ..
+            } => bomb,
+            other => panic!("unexpected seal verification {:?}", other),
+        };
+
+        for (block_number, expected_delay_to) in [
+            // Frontier ice age and Homestead run the bomb undelayed.
+            (0, 0),
+            (200_000, 0),
+            (1_150_000, 0),
+            (4_369_999, 0),
+            // Byzantium
+            (4_370_000, 3_000_000),
+            (7_279_999, 3_000_000),
+            // Constantinople
+            (7_280_000, 5_000_000),
+            (9_199_999, 5_000_000),
+            // Muir Glacier
+            (9_200_000, 9_000_000),
+            // Berlin leaves the delay alone.
+            (12_244_000, 9_000_000),
+            (12_964_999, 9_000_000),
+            // London
+            (12_965_000, 9_700_000),
+            (13_772_999, 9_700_000),
+            // Arrow Glacier
+            (13_773_000, 10_700_000),
+            (15_049_999, 10_700_000),
+            // Gray Glacier
+            (15_050_000, 11_400_000),
+            (20_000_000, 11_400_000),
+        ] {
+            assert_eq!(
+                bomb.get_delay_to(BlockNumber(block_number)),
+                BlockNumber(expected_delay_to),
+                "block {}",
+                block_number
+            );
+        }
+    }
+
+    #[test]
+    fn fork_predicates() {
+        for (is_active_at, fork_block) in [
+            (
+                ChainSpec::is_homestead_active_at as fn(&ChainSpec, BlockNumber) -> bool,
+                1,
+            ),
+            (ChainSpec::is_tangerine_active_at, 2),
+            (ChainSpec::is_spurious_active_at, 3),
+            (ChainSpec::is_byzantium_active_at, 1_035_301),
+            (ChainSpec::is_constantinople_active_at, 3_660_663),
+            (ChainSpec::is_petersburg_active_at, 4_321_234),
+            (ChainSpec::is_istanbul_active_at, 5_435_345),
+            (ChainSpec::is_berlin_active_at, 8_290_928),
+            (ChainSpec::is_london_active_at, 8_897_988),
+        ] {
+            assert!(!is_active_at(&RINKEBY, BlockNumber(fork_block - 1)));
+            assert!(is_active_at(&RINKEBY, BlockNumber(fork_block)));
+            assert!(is_active_at(&RINKEBY, BlockNumber(fork_block + 1)));
+        }
+
+        let mut no_london = RINKEBY.clone();
+        no_london.upgrades.london = None;
+        assert!(!no_london.is_london_active_at(BlockNumber(u64::MAX)));
+    }
+
+    #[test]
+    fn london_base_fee() {
+        let london = RINKEBY.upgrades.london.unwrap();
+
+        let parent = BlockHeader {
+            number: BlockNumber(london.0 - 2),
+            ..BlockHeader::empty()
+        };
+        assert_eq!(
+            RINKEBY
+                .collect_block_spec(london.0 - 1, Some(&parent))
+                .base_fee_per_gas,
+            None
+        );
+
+        assert_eq!(
+            RINKEBY.collect_block_spec(london, None).base_fee_per_gas,
+            Some(param::INITIAL_BASE_FEE.as_u256())
+        );
+
+        // Parent used more than its gas target of 5M.
+        let parent = BlockHeader {
+            number: london,
+            gas_limit: 10_000_000,
+            gas_used: 10_000_000,
+            base_fee_per_gas: Some(param::INITIAL_BASE_FEE.as_u256()),
+            ..BlockHeader::empty()
+        };
+        assert_eq!(
+            RINKEBY
+                .collect_block_spec(london + 1, Some(&parent))
+                .base_fee_per_gas,
+            Some(1_125_000_000.as_u256())
+        );
+        assert_eq!(
+            RINKEBY
+                .collect_block_spec(london + 1, None)
+                .base_fee_per_gas,
+            None
+        );
+
+        // A known header brings its own base fee.
+        let header = PartialHeader {
+            number: london + 1,
+            base_fee_per_gas: Some(1_125_000_000.as_u256()),
+            ..PartialHeader::empty()
+        };
+        assert_eq!(
+            RINKEBY.collect_header_spec(&header).base_fee_per_gas,
+            Some(1_125_000_000.as_u256())
+        );
+    }
+
+    #[test]
+    fn active_transitions_only_at_fork_blocks() {
+        let transitions = |block: u64| MAINNET.collect_block_spec(block, None).active_transitions;
+
+        assert!(transitions(1_149_999).is_empty());
+        assert_eq!(
+            transitions(1_150_000),
+            [Revision::Homestead].into_iter().collect()
+        );
+        for block in [1_150_001, 1_500_000, 1_919_999, 2_462_999] {
+            assert!(transitions(block).is_empty());
+        }
+        assert_eq!(
+            transitions(2_463_000),
+            [Revision::Tangerine].into_iter().collect()
+        );
+
+        assert_eq!(
+            transitions(7_280_000),
+            [Revision::Constantinople, Revision::Petersburg]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            MAINNET.collect_block_spec(7_280_000, None).revision,
+            Revision::Petersburg
+        );
+    }
+
+    #[test]
+    fn expected_block_time() {
+        assert_eq!(MAINNET.expected_block_time(), Duration::from_secs(13));
+        assert_eq!(RINKEBY.expected_block_time(), Duration::from_secs(15));
+    }
 }
@@ -0,0 +1,38 @@
+use super::*;
+
+// Keccak-256 hash of an empty string, KEC("").
+pub const EMPTY_HASH: H256 = H256(hex!(
+    "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+));
+
+// Keccak-256 hash of the RLP of an empty list, KEC("\xc0").
+pub const EMPTY_LIST_HASH: H256 = H256(hex!(
+    "1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
+));
+
+// Root hash of an empty trie, KEC(RLP("")).
+pub const EMPTY_ROOT: H256 = H256(hex!(
+    "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+));
+
+// Parent hash of the genesis block.
+pub const GENESIS_PARENT_HASH: H256 = H256::zero();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{keccak256, ordered_trie_root};
+
+    #[test]
+    fn constants() {
+        assert_eq!(keccak256(b""), EMPTY_HASH);
+        assert_eq!(keccak256(rlp::EMPTY_LIST_RLP), EMPTY_LIST_HASH);
+        assert_eq!(keccak256(rlp::NULL_RLP), EMPTY_ROOT);
+        assert_eq!(ordered_trie_root(Vec::<Vec<u8>>::new()), EMPTY_ROOT);
+        assert_eq!(
+            keccak256(rlp::encode_list::<BlockHeader, _>(&[])),
+            EMPTY_LIST_HASH
+        );
+        assert!(GENESIS_PARENT_HASH.is_zero());
+    }
+}
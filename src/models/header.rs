@@ -24,17 +24,21 @@ pub struct BlockHeader {
     pub mix_hash: H256,
     pub nonce: H64,
     pub base_fee_per_gas: Option<U256>,
+    pub withdrawals_root: Option<H256>,
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
 }
 
 impl Encodable for BlockHeader {
     fn rlp_append(&self, s: &mut RlpStream) {
-        s.begin_list({
-            if self.base_fee_per_gas.is_some() {
-                16
-            } else {
-                15
-            }
-        });
+        s.begin_list(
+            15 + self.base_fee_per_gas.is_some() as usize
+                + self.withdrawals_root.is_some() as usize
+                + self.blob_gas_used.is_some() as usize
+                + self.excess_blob_gas.is_some() as usize
+                + self.parent_beacon_block_root.is_some() as usize,
+        );
         s.append(&self.parent_hash);
         s.append(&self.ommers_hash);
         s.append(&self.beneficiary);
@@ -53,6 +57,18 @@ impl Encodable for BlockHeader {
         if let Some(base_fee_per_gas) = self.base_fee_per_gas {
             s.append(&base_fee_per_gas);
         }
+        if let Some(withdrawals_root) = self.withdrawals_root {
+            s.append(&withdrawals_root);
+        }
+        if let Some(blob_gas_used) = self.blob_gas_used {
+            s.append(&blob_gas_used);
+        }
+        if let Some(excess_blob_gas) = self.excess_blob_gas {
+            s.append(&excess_blob_gas);
+        }
+        if let Some(parent_beacon_block_root) = self.parent_beacon_block_root {
+            s.append(&parent_beacon_block_root);
+        }
     }
 }
 
@@ -79,6 +95,10 @@ impl Decodable for BlockHeader {
         let mix_hash = rlp.next().ok_or(DecoderError::RlpInvalidLength)?.as_val()?;
         let nonce = rlp.next().ok_or(DecoderError::RlpInvalidLength)?.as_val()?;
         let base_fee_per_gas = rlp.next().map(|rlp| rlp.as_val()).transpose()?;
+        let withdrawals_root = rlp.next().map(|rlp| rlp.as_val()).transpose()?;
+        let blob_gas_used = rlp.next().map(|rlp| rlp.as_val()).transpose()?;
+        let excess_blob_gas = rlp.next().map(|rlp| rlp.as_val()).transpose()?;
+        let parent_beacon_block_root = rlp.next().map(|rlp| rlp.as_val()).transpose()?;
 
         Ok(Self {
             parent_hash,
@@ -97,6 +117,10 @@ impl Decodable for BlockHeader {
             mix_hash,
             nonce,
             base_fee_per_gas,
+            withdrawals_root,
+            blob_gas_used,
+            excess_blob_gas,
+            parent_beacon_block_root,
         })
     }
 }
@@ -121,6 +145,10 @@ impl BlockHeader {
             mix_hash: partial_header.mix_hash,
             nonce: partial_header.nonce,
             base_fee_per_gas: partial_header.base_fee_per_gas,
+            withdrawals_root: partial_header.withdrawals_root,
+            blob_gas_used: partial_header.blob_gas_used,
+            excess_blob_gas: partial_header.excess_blob_gas,
+            parent_beacon_block_root: partial_header.parent_beacon_block_root,
         }
     }
 
@@ -143,6 +171,10 @@ impl BlockHeader {
             mix_hash: H256::zero(),
             nonce: H64::zero(),
             base_fee_per_gas: None,
+            withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
         }
     }
 
@@ -168,17 +200,21 @@ impl BlockHeader {
             timestamp: u64,
             extra_data: Bytes,
             base_fee_per_gas: Option<U256>,
+            withdrawals_root: Option<H256>,
+            blob_gas_used: Option<u64>,
+            excess_blob_gas: Option<u64>,
+            parent_beacon_block_root: Option<H256>,
         }
 
         impl Encodable for TruncatedHeader {
             fn rlp_append(&self, s: &mut RlpStream) {
-                s.begin_list({
-                    if self.base_fee_per_gas.is_some() {
-                        14
-                    } else {
-                        13
-                    }
-                });
+                s.begin_list(
+                    13 + self.base_fee_per_gas.is_some() as usize
+                        + self.withdrawals_root.is_some() as usize
+                        + self.blob_gas_used.is_some() as usize
+                        + self.excess_blob_gas.is_some() as usize
+                        + self.parent_beacon_block_root.is_some() as usize,
+                );
                 s.append(&self.parent_hash);
                 s.append(&self.ommers_hash);
                 s.append(&self.beneficiary);
@@ -195,6 +231,18 @@ impl BlockHeader {
                 if let Some(base_fee_per_gas) = self.base_fee_per_gas {
                     s.append(&base_fee_per_gas);
                 }
+                if let Some(withdrawals_root) = self.withdrawals_root {
+                    s.append(&withdrawals_root);
+                }
+                if let Some(blob_gas_used) = self.blob_gas_used {
+                    s.append(&blob_gas_used);
+                }
+                if let Some(excess_blob_gas) = self.excess_blob_gas {
+                    s.append(&excess_blob_gas);
+                }
+                if let Some(parent_beacon_block_root) = self.parent_beacon_block_root {
+                    s.append(&parent_beacon_block_root);
+                }
             }
         }
 
@@ -214,6 +262,10 @@ impl BlockHeader {
                 timestamp: self.timestamp,
                 extra_data: self.extra_data.clone(),
                 base_fee_per_gas: self.base_fee_per_gas,
+                withdrawals_root: self.withdrawals_root,
+                blob_gas_used: self.blob_gas_used,
+                excess_blob_gas: self.excess_blob_gas,
+                parent_beacon_block_root: self.parent_beacon_block_root,
             })[..],
         )
     }
@@ -236,6 +288,10 @@ pub struct PartialHeader {
     pub mix_hash: H256,
     pub nonce: H64,
     pub base_fee_per_gas: Option<U256>,
+    pub withdrawals_root: Option<H256>,
+    pub blob_gas_used: Option<u64>,
+    pub excess_blob_gas: Option<u64>,
+    pub parent_beacon_block_root: Option<H256>,
 }
 
 impl From<BlockHeader> for PartialHeader {
@@ -255,6 +311,10 @@ impl From<BlockHeader> for PartialHeader {
             mix_hash: header.mix_hash,
             nonce: header.nonce,
             base_fee_per_gas: header.base_fee_per_gas,
+            withdrawals_root: header.withdrawals_root,
+            blob_gas_used: header.blob_gas_used,
+            excess_blob_gas: header.excess_blob_gas,
+            parent_beacon_block_root: header.parent_beacon_block_root,
         }
     }
 }
@@ -277,6 +337,10 @@ impl PartialHeader {
             mix_hash: H256::zero(),
             nonce: H64::zero(),
             base_fee_per_gas: None,
+            withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
         }
     }
 }
@@ -44,3 +44,130 @@ impl Decodable for Log {
         })
     }
 }
+
+/// Single value or a list of alternatives, as accepted by log filters.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ValueOrArray<T> {
+    Value(T),
+    Array(Vec<T>),
+}
+
+impl<T: PartialEq> ValueOrArray<T> {
+    /// Whether `v` is one of the alternatives. Empty list matches anything.
+    pub fn matches(&self, v: &T) -> bool {
+        match self {
+            Self::Value(value) => value == v,
+            Self::Array(values) => values.is_empty() || values.contains(v),
+        }
+    }
+}
+
+/// Filter for logs by emitting address and topics.
+///
+/// Each position in `topics` restricts the topic at the same position, `None` matches any topic.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<ValueOrArray<Address>>,
+    #[serde(default)]
+    pub topics: Vec<Option<ValueOrArray<H256>>>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &Log) -> bool {
+        if let Some(address) = &self.address {
+            if !address.matches(&log.address) {
+                return false;
+            }
+        }
+
+        self.topics
+            .iter()
+            .enumerate()
+            .all(|(i, topic)| match topic {
+                None => true,
+                Some(topic) => log
+                    .topics
+                    .get(i)
+                    .map(|log_topic| topic.matches(log_topic))
+                    .unwrap_or(false),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(address: u8, topics: &[u8]) -> Log {
+        Log {
+            address: Address::repeat_byte(address),
+            topics: topics.iter().map(|&t| H256::repeat_byte(t)).collect(),
+            data: Bytes::new(),
+        }
+    }
+
+    #[test]
+    fn filter_logs() {
+        let blocks = vec![
+            vec![log(1, &[0xaa]), log(2, &[0xbb, 0xaa])],
+            vec![],
+            vec![log(1, &[0xbb]), log(3, &[0xaa, 0xcc]), log(1, &[])],
+        ];
+
+        let matching = |filter: &LogFilter| {
+            blocks
+                .iter()
+                .flatten()
+                .filter(|log| filter.matches(log))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(matching(&LogFilter::default()).len(), 5);
+
+        let filter = LogFilter {
+            topics: vec![Some(ValueOrArray::Value(H256::repeat_byte(0xaa)))],
+            ..Default::default()
+        };
+        assert_eq!(
+            matching(&filter),
+            vec![log(1, &[0xaa]), log(3, &[0xaa, 0xcc])]
+        );
+
+        let filter = LogFilter {
+            address: Some(ValueOrArray::Value(Address::repeat_byte(1))),
+            topics: vec![Some(ValueOrArray::Array(vec![
+                H256::repeat_byte(0xaa),
+                H256::repeat_byte(0xbb),
+            ]))],
+        };
+        assert_eq!(matching(&filter), vec![log(1, &[0xaa]), log(1, &[0xbb])]);
+
+        let filter = LogFilter {
+            topics: vec![None, Some(ValueOrArray::Value(H256::repeat_byte(0xaa)))],
+            ..Default::default()
+        };
+        assert_eq!(matching(&filter), vec![log(2, &[0xbb, 0xaa])]);
+    }
+
+    #[test]
+    fn deserialize_filter() {
+        let filter: LogFilter = serde_json::from_str(
+            r#"{
+                "address": ["0x0101010101010101010101010101010101010101"],
+                "topics": [null, "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            filter,
+            LogFilter {
+                address: Some(ValueOrArray::Array(vec![Address::repeat_byte(1)])),
+                topics: vec![None, Some(ValueOrArray::Value(H256::repeat_byte(0xaa)))],
+            }
+        );
+    }
+}
@@ -2,22 +2,28 @@ mod account;
 mod block;
 mod bloom;
 mod chainspec;
+mod constants;
 mod header;
 mod log;
 mod receipt;
 mod revision;
 mod transaction;
+mod withdrawal;
 
 pub use self::{
-    account::*, block::*, bloom::*, chainspec::*, header::*, log::*, receipt::*, revision::*,
-    transaction::*,
+    account::*, block::*, bloom::*, chainspec::*, constants::*, header::*, log::*, receipt::*,
+    revision::*, transaction::*, withdrawal::*,
 };
 
 use derive_more::*;
 use hex_literal::hex;
 use rlp::{Decodable, Encodable};
 use serde::{Deserialize, Serialize};
-use std::{iter::Step, mem::size_of, ops::Add};
+use std::{
+    iter::Step,
+    mem::size_of,
+    ops::{Add, RangeInclusive},
+};
 
 pub use ethereum_types::{Address, Bloom, H128, H160, H256, H512, H64, U512, U64};
 pub use ethnum::*;
@@ -118,20 +124,77 @@ u64_wrapper!(ChainId);
 u64_wrapper!(NetworkId);
 u64_wrapper!(TxIndex);
 
-// Keccak-256 hash of an empty string, KEC("").
-pub const EMPTY_HASH: H256 = H256(hex!(
-    "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
-));
+/// Range of blocks with both ends included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockRange {
+    pub start: BlockNumber,
+    pub end_inclusive: BlockNumber,
+}
+
+impl BlockRange {
+    pub fn new(start: BlockNumber, end_inclusive: BlockNumber) -> Self {
+        Self {
+            start,
+            end_inclusive,
+        }
+    }
+
+    pub fn contains(&self, n: BlockNumber) -> bool {
+        self.start <= n && n <= self.end_inclusive
+    }
 
-// Keccak-256 hash of the RLP of an empty list, KEC("\xc0").
-pub const EMPTY_LIST_HASH: H256 = H256(hex!(
-    "1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347"
-));
+    /// Number of blocks in the range, saturating at `u64::MAX` for the range of all blocks.
+    pub fn len(&self) -> u64 {
+        if self.end_inclusive < self.start {
+            return 0;
+        }
 
-// Root hash of an empty trie.
-pub const EMPTY_ROOT: H256 = H256(hex!(
-    "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
-));
+        (self.end_inclusive.0 - self.start.0).saturating_add(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn blocks(&self) -> RangeInclusive<BlockNumber> {
+        self.start..=self.end_inclusive
+    }
+}
 
 pub const GIGA: u64 = 1_000_000_000; // = 10^9
 pub const ETHER: u128 = 1_000_000_000_000_000_000; // = 10^18
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_range() {
+        let range = BlockRange::new(BlockNumber(5), BlockNumber(7));
+        assert_eq!(range.len(), 3);
+        assert!(!range.is_empty());
+        assert!(!range.contains(BlockNumber(4)));
+        assert!(range.contains(BlockNumber(5)));
+        assert!(range.contains(BlockNumber(7)));
+        assert!(!range.contains(BlockNumber(8)));
+        assert_eq!(
+            range.blocks().collect::<Vec<_>>(),
+            vec![BlockNumber(5), BlockNumber(6), BlockNumber(7)]
+        );
+
+        let single = BlockRange::new(BlockNumber(5), BlockNumber(5));
+        assert_eq!(single.len(), 1);
+        assert!(single.contains(BlockNumber(5)));
+
+        let empty = BlockRange::new(BlockNumber(6), BlockNumber(5));
+        assert!(empty.is_empty());
+        assert!(!empty.contains(BlockNumber(5)));
+        assert_eq!(empty.blocks().count(), 0);
+
+        let to_end = BlockRange::new(BlockNumber(1), BlockNumber(u64::MAX));
+        assert_eq!(to_end.len(), u64::MAX);
+        let all = BlockRange::new(BlockNumber(0), BlockNumber(u64::MAX));
+        assert_eq!(all.len(), u64::MAX);
+        assert!(!all.is_empty());
+    }
+}
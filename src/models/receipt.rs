@@ -35,7 +35,7 @@ impl Receipt {
                 l.append(&self.bloom);
                 l.append_list(&self.logs);
             }
-            TxType::EIP2930 | TxType::EIP1559 => {
+            TxType::EIP2930 | TxType::EIP1559 | TxType::EIP4844 => {
                 let mut b = BytesMut::with_capacity(1);
                 b.put_u8(self.tx_type as u8);
                 let mut l = RlpStream::new_list_with_buffer(b, 4);
@@ -52,18 +52,21 @@ impl Receipt {
         }
     }
 
-    fn decode_inner(rlp: &rlp::Rlp, is_legacy: bool) -> Result<Self, DecoderError> {
-        Ok(match is_legacy {
-            true => {
-                let inner = UntypedReceipt::decode(rlp)?;
-                inner.into_receipt(TxType::Legacy)
-            }
-            false => {
-                let tx_type = u8::decode(rlp)?;
-                let inner = UntypedReceipt::decode(rlp)?;
-                inner.into_receipt(tx_type.try_into()?)
-            }
-        })
+    /// Decodes the EIP-2718 envelope produced by [`TrieEncode::trie_encode`]:
+    /// either a legacy RLP list, or a type byte followed by the RLP list.
+    pub fn trie_decode(slice: &[u8]) -> Result<Self, DecoderError> {
+        let first = *slice.first().ok_or(DecoderError::Custom("empty slice"))?;
+
+        if first >= 0xc0 {
+            return Ok(UntypedReceipt::decode(&rlp::Rlp::new(slice))?.into_receipt(TxType::Legacy));
+        }
+
+        let tx_type = TxType::try_from(first)?;
+        if tx_type == TxType::Legacy {
+            return Err(DecoderError::Custom("Invalid tx type"));
+        }
+
+        Ok(UntypedReceipt::decode(&rlp::Rlp::new(&slice[1..]))?.into_receipt(tx_type))
     }
 }
 
@@ -83,9 +86,12 @@ impl Encodable for Receipt {
 
 impl Decodable for Receipt {
     fn decode(rlp: &rlp::Rlp) -> Result<Self, rlp::DecoderError> {
-        // FIXME: This check may be incorrect
-        let is_legacy = !rlp.is_data();
-        Self::decode_inner(rlp, is_legacy)
+        // Typed receipts are wrapped into an RLP string, legacy ones are a bare list.
+        if rlp.is_list() {
+            return Ok(UntypedReceipt::decode(rlp)?.into_receipt(TxType::Legacy));
+        }
+
+        Self::trie_decode(rlp.data()?)
     }
 }
 
@@ -108,3 +114,73 @@ impl UntypedReceipt {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hex_literal::hex;
+
+    #[test]
+    fn typed_receipt_roundtrip() {
+        let logs = vec![Log {
+            address: hex!("5a443704dd4b594b382c22a083e2bd3090a6fef3").into(),
+            topics: vec![H256::repeat_byte(0xab)],
+            data: hex!("0102").to_vec().into(),
+        }];
+
+        for tx_type in [
+            TxType::Legacy,
+            TxType::EIP2930,
+            TxType::EIP1559,
+            TxType::EIP4844,
+        ] {
+            let receipt = Receipt::new(tx_type, true, 21_000, logs.clone());
+
+            let encoded = receipt.trie_encode();
+            if tx_type == TxType::Legacy {
+                assert!(encoded[0] >= 0xc0);
+            } else {
+                assert_eq!(encoded[0], tx_type as u8);
+            }
+            assert_eq!(Receipt::trie_decode(&encoded).unwrap(), receipt);
+
+            assert_eq!(
+                rlp::decode::<Receipt>(&rlp::encode(&receipt)).unwrap(),
+                receipt
+            );
+            assert_eq!(
+                rlp::decode_list::<Receipt>(&rlp::encode_list::<Receipt, _>(&[
+                    receipt.clone(),
+                    receipt.clone()
+                ])),
+                vec![receipt.clone(), receipt]
+            );
+        }
+
+        assert!(Receipt::trie_decode(&[]).is_err());
+        assert!(Receipt::trie_decode(&[0x00, 0xc0]).is_err());
+        assert!(Receipt::trie_decode(&[0x04, 0xc0]).is_err());
+    }
+
+    #[test]
+    fn receipts_root() {
+        let receipts = vec![
+            Receipt::new(TxType::Legacy, true, 21_000, vec![]),
+            Receipt::new(TxType::EIP1559, false, 63_000, vec![]),
+        ];
+
+        assert_eq!(root_hash::<Receipt>(&[]), EMPTY_ROOT);
+        assert_eq!(
+            root_hash(&receipts[..1]),
+            H256(hex!(
+                "056b23fbba480696b65fe5a59b8f2148a1299103c4f57df839233af2cf4ca2d2"
+            ))
+        );
+        assert_eq!(
+            root_hash(&receipts),
+            H256(hex!(
+                "ed1a103dd718e9e35b59fddbb8195af90fd863088525d27ad0a0f5cecd134f90"
+            ))
+        );
+    }
+}
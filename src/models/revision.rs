@@ -37,6 +37,9 @@ pub enum Revision {
 
     /// The Shanghai revision.
     Shanghai = 10,
+
+    /// [The Cancun revision.](https://github.com/ethereum/execution-specs/blob/master/network-upgrades/mainnet-upgrades/cancun.md)
+    Cancun = 11,
 }
 
 impl Revision {
@@ -53,11 +56,12 @@ impl Revision {
             Self::Berlin,
             Self::London,
             Self::Shanghai,
+            Self::Cancun,
         ]
     }
 
     pub const fn latest() -> Self {
-        Self::Shanghai
+        Self::Cancun
     }
 
     pub const fn len() -> usize {
@@ -1,8 +1,10 @@
 use crate::{
+    chain::protocol_param::param,
     crypto::{is_valid_signature, TrieEncode},
     models::*,
     util::*,
 };
+use anyhow::{bail, ensure, format_err, Context};
 use bytes::{BufMut, Bytes, BytesMut};
 use derive_more::Deref;
 use educe::Educe;
@@ -22,6 +24,7 @@ pub enum TxType {
     Legacy = 0,
     EIP2930 = 1,
     EIP1559 = 2,
+    EIP4844 = 3,
 }
 
 impl TryFrom<u8> for TxType {
@@ -31,6 +34,7 @@ impl TryFrom<u8> for TxType {
             0 => Ok(TxType::Legacy),
             1 => Ok(TxType::EIP2930),
             2 => Ok(TxType::EIP1559),
+            3 => Ok(TxType::EIP4844),
             _ => Err(DecoderError::Custom("Invalid tx type")),
         }
     }
@@ -281,6 +285,27 @@ pub enum Message {
         input: Bytes,
         access_list: Vec<AccessListItem>,
     },
+    EIP4844 {
+        #[codec(compact)]
+        chain_id: ChainId,
+        #[codec(compact)]
+        nonce: u64,
+        #[codec(compact)]
+        max_priority_fee_per_gas: U256,
+        #[codec(compact)]
+        max_fee_per_gas: U256,
+        #[codec(compact)]
+        gas_limit: u64,
+        to: Address,
+        #[codec(compact)]
+        value: U256,
+        #[educe(Debug(method = "write_hex_string"))]
+        input: Bytes,
+        access_list: Vec<AccessListItem>,
+        #[codec(compact)]
+        max_fee_per_blob_gas: U256,
+        blob_versioned_hashes: Vec<H256>,
+    },
 }
 
 impl Message {
@@ -368,6 +393,36 @@ impl Message {
                 s.append_list(access_list);
                 s.out()
             }
+            Message::EIP4844 {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                gas_limit,
+                to,
+                value,
+                input,
+                access_list,
+                max_fee_per_blob_gas,
+                blob_versioned_hashes,
+            } => {
+                let mut b = BytesMut::with_capacity(1);
+                b.put_u8(3);
+                let mut s = RlpStream::new_with_buffer(b);
+                s.begin_list(11);
+                s.append(chain_id);
+                s.append(nonce);
+                s.append(max_priority_fee_per_gas);
+                s.append(max_fee_per_gas);
+                s.append(gas_limit);
+                s.append(to);
+                s.append(value);
+                s.append(&input.as_ref());
+                s.append_list(access_list);
+                s.append(max_fee_per_blob_gas);
+                s.append_list(blob_versioned_hashes);
+                s.out()
+            }
         };
 
         H256::from_slice(Keccak256::digest(&msg.freeze()).as_slice())
@@ -479,6 +534,42 @@ impl MessageWithSignature {
                     s.append(&s1.out());
                 }
             }
+            Message::EIP4844 {
+                chain_id,
+                nonce,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                gas_limit,
+                to,
+                value,
+                input,
+                access_list,
+                max_fee_per_blob_gas,
+                blob_versioned_hashes,
+            } => {
+                let mut b = BytesMut::with_capacity(1);
+                b.put_u8(3);
+                let mut s1 = RlpStream::new_list_with_buffer(b, 14);
+                s1.append(chain_id);
+                s1.append(nonce);
+                s1.append(max_priority_fee_per_gas);
+                s1.append(max_fee_per_gas);
+                s1.append(gas_limit);
+                s1.append(to);
+                s1.append(value);
+                s1.append(&input.as_ref());
+                s1.append_list(access_list);
+                s1.append(max_fee_per_blob_gas);
+                s1.append_list(blob_versioned_hashes);
+                s1.append(&self.signature.odd_y_parity);
+                s1.append(&U256::from_be_bytes(self.signature.r.0));
+                s1.append(&U256::from_be_bytes(self.signature.s.0));
+                if standalone {
+                    s.append_raw(&*s1.out().freeze(), 1);
+                } else {
+                    s.append(&s1.out());
+                }
+            }
         }
     }
 }
@@ -550,6 +641,36 @@ impl MessageWithSignature {
             });
         }
 
+        if first == 0x03 {
+            let s = slice.get(1..).ok_or(DecoderError::Custom("no tx body"))?;
+            let rlp = Rlp::new(s);
+            if rlp.item_count()? != 14 {
+                return Err(DecoderError::RlpIncorrectListLen);
+            }
+
+            return Ok(Self {
+                message: Message::EIP4844 {
+                    chain_id: rlp.val_at(0)?,
+                    nonce: rlp.val_at(1)?,
+                    max_priority_fee_per_gas: rlp.val_at(2)?,
+                    max_fee_per_gas: rlp.val_at(3)?,
+                    gas_limit: rlp.val_at(4)?,
+                    to: rlp.val_at(5)?,
+                    value: rlp.val_at(6)?,
+                    input: rlp.val_at::<Vec<u8>>(7)?.into(),
+                    access_list: rlp.list_at(8)?,
+                    max_fee_per_blob_gas: rlp.val_at(9)?,
+                    blob_versioned_hashes: rlp.list_at(10)?,
+                },
+                signature: MessageSignature::new(
+                    rlp.val_at(11)?,
+                    H256(rlp.val_at::<U256>(12)?.to_be_bytes()),
+                    H256(rlp.val_at::<U256>(13)?.to_be_bytes()),
+                )
+                .ok_or(DecoderError::Custom("Invalid transaction signature format"))?,
+            });
+        }
+
         let rlp = Rlp::new(slice);
         if rlp.is_list() {
             if rlp.item_count()? != 9 {
@@ -582,6 +703,15 @@ impl MessageWithSignature {
 
         Err(DecoderError::Custom("invalid tx type"))
     }
+
+    /// Decodes a standalone transaction given either as a bare EIP-2718 envelope,
+    /// or in RLP form where typed transactions are wrapped into a string.
+    pub fn decode_standalone(slice: &[u8]) -> Result<MessageWithSignature, DecoderError> {
+        match slice.first() {
+            Some(&first) if first <= 0x7f => Self::trie_decode(slice),
+            _ => rlp::decode(slice),
+        }
+    }
 }
 
 impl Encodable for MessageWithSignature {
@@ -680,6 +810,35 @@ impl Decodable for MessageWithSignature {
             });
         }
 
+        if first == 0x03 {
+            let rlp = Rlp::new(s);
+            if rlp.item_count()? != 14 {
+                return Err(DecoderError::RlpIncorrectListLen);
+            }
+
+            return Ok(Self {
+                message: Message::EIP4844 {
+                    chain_id: rlp.val_at(0)?,
+                    nonce: rlp.val_at(1)?,
+                    max_priority_fee_per_gas: rlp.val_at(2)?,
+                    max_fee_per_gas: rlp.val_at(3)?,
+                    gas_limit: rlp.val_at(4)?,
+                    to: rlp.val_at(5)?,
+                    value: rlp.val_at(6)?,
+                    input: rlp.val_at::<Vec<u8>>(7)?.into(),
+                    access_list: rlp.list_at(8)?,
+                    max_fee_per_blob_gas: rlp.val_at(9)?,
+                    blob_versioned_hashes: rlp.list_at(10)?,
+                },
+                signature: MessageSignature::new(
+                    rlp.val_at(11)?,
+                    H256(rlp.val_at::<U256>(12)?.to_be_bytes()),
+                    H256(rlp.val_at::<U256>(13)?.to_be_bytes()),
+                )
+                .ok_or(DecoderError::Custom("Invalid transaction signature format"))?,
+            });
+        }
+
         Err(DecoderError::Custom("invalid tx type"))
     }
 }
@@ -690,6 +849,7 @@ impl Message {
             Self::Legacy { .. } => TxType::Legacy,
             Self::EIP2930 { .. } => TxType::EIP2930,
             Self::EIP1559 { .. } => TxType::EIP1559,
+            Self::EIP4844 { .. } => TxType::EIP4844,
         }
     }
 
@@ -697,7 +857,7 @@ impl Message {
         match *self {
             Self::Legacy { chain_id, .. } => chain_id,
             Self::EIP2930 { chain_id, .. } => Some(chain_id),
-            Self::EIP1559 { chain_id, .. } => Some(chain_id),
+            Self::EIP1559 { chain_id, .. } | Self::EIP4844 { chain_id, .. } => Some(chain_id),
         }
     }
 
@@ -705,7 +865,8 @@ impl Message {
         match *self {
             Self::Legacy { nonce, .. }
             | Self::EIP2930 { nonce, .. }
-            | Self::EIP1559 { nonce, .. } => nonce,
+            | Self::EIP1559 { nonce, .. }
+            | Self::EIP4844 { nonce, .. } => nonce,
         }
     }
 
@@ -715,6 +876,10 @@ impl Message {
             Self::EIP1559 {
                 max_priority_fee_per_gas,
                 ..
+            }
+            | Self::EIP4844 {
+                max_priority_fee_per_gas,
+                ..
             } => max_priority_fee_per_gas,
         }
     }
@@ -724,6 +889,9 @@ impl Message {
             Self::Legacy { gas_price, .. } | Self::EIP2930 { gas_price, .. } => gas_price,
             Self::EIP1559 {
                 max_fee_per_gas, ..
+            }
+            | Self::EIP4844 {
+                max_fee_per_gas, ..
             } => max_fee_per_gas,
         }
     }
@@ -732,7 +900,8 @@ impl Message {
         match *self {
             Self::Legacy { gas_limit, .. }
             | Self::EIP2930 { gas_limit, .. }
-            | Self::EIP1559 { gas_limit, .. } => gas_limit,
+            | Self::EIP1559 { gas_limit, .. }
+            | Self::EIP4844 { gas_limit, .. } => gas_limit,
         }
     }
 
@@ -741,6 +910,15 @@ impl Message {
             Self::Legacy { action, .. }
             | Self::EIP2930 { action, .. }
             | Self::EIP1559 { action, .. } => action,
+            Self::EIP4844 { to, .. } => TransactionAction::Call(to),
+        }
+    }
+
+    /// Recipient of the message, `None` for contract creation.
+    pub const fn to(&self) -> Option<Address> {
+        match self.action() {
+            TransactionAction::Call(to) => Some(to),
+            TransactionAction::Create => None,
         }
     }
 
@@ -748,7 +926,8 @@ impl Message {
         match *self {
             Self::Legacy { value, .. }
             | Self::EIP2930 { value, .. }
-            | Self::EIP1559 { value, .. } => value,
+            | Self::EIP1559 { value, .. }
+            | Self::EIP4844 { value, .. } => value,
         }
     }
 
@@ -756,19 +935,45 @@ impl Message {
         match self {
             Self::Legacy { input, .. }
             | Self::EIP2930 { input, .. }
-            | Self::EIP1559 { input, .. } => input,
+            | Self::EIP1559 { input, .. }
+            | Self::EIP4844 { input, .. } => input,
         }
     }
 
     pub const fn access_list(&self) -> Cow<'_, AccessList> {
         match self {
             Self::Legacy { .. } => Cow::Owned(AccessList::new()),
-            Self::EIP2930 { access_list, .. } | Self::EIP1559 { access_list, .. } => {
-                Cow::Borrowed(access_list)
-            }
+            Self::EIP2930 { access_list, .. }
+            | Self::EIP1559 { access_list, .. }
+            | Self::EIP4844 { access_list, .. } => Cow::Borrowed(access_list),
+        }
+    }
+
+    pub const fn max_fee_per_blob_gas(&self) -> Option<U256> {
+        match *self {
+            Self::EIP4844 {
+                max_fee_per_blob_gas,
+                ..
+            } => Some(max_fee_per_blob_gas),
+            _ => None,
         }
     }
 
+    pub fn blob_versioned_hashes(&self) -> &[H256] {
+        match self {
+            Self::EIP4844 {
+                blob_versioned_hashes,
+                ..
+            } => blob_versioned_hashes,
+            _ => &[],
+        }
+    }
+
+    /// Blob gas consumed by the transaction, zero for non-blob transactions.
+    pub fn blob_gas(&self) -> u64 {
+        self.blob_versioned_hashes().len() as u64 * param::GAS_PER_BLOB
+    }
+
     pub(crate) fn priority_fee_per_gas(&self, base_fee_per_gas: U256) -> U256 {
         assert!(self.max_fee_per_gas() >= base_fee_per_gas);
         min(
@@ -817,9 +1022,231 @@ impl MessageWithSignature {
     }
 }
 
+fn access_list_to_json(access_list: &[AccessListItem]) -> serde_json::Value {
+    access_list
+        .iter()
+        .map(|item| {
+            serde_json::json!({
+                "address": item.address.to_hex_string(),
+                "storageKeys": item
+                    .slots
+                    .iter()
+                    .map(EncodeHex::to_hex_string)
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+fn json_hex_list<T: DecodeHex>(value: &serde_json::Value, name: &str) -> anyhow::Result<Vec<T>> {
+    match value.get(name) {
+        None | Some(serde_json::Value::Null) => Ok(vec![]),
+        Some(list) => list
+            .as_array()
+            .ok_or_else(|| format_err!("{} is not an array", name))?
+            .iter()
+            .map(|v| {
+                let s = v
+                    .as_str()
+                    .ok_or_else(|| format_err!("{} entry is not a string", name))?;
+                T::from_hex_string(s).with_context(|| format!("invalid {} entry", name))
+            })
+            .collect(),
+    }
+}
+
+fn access_list_from_json(value: &serde_json::Value) -> anyhow::Result<AccessList> {
+    match value.get("accessList") {
+        None | Some(serde_json::Value::Null) => Ok(vec![]),
+        Some(list) => list
+            .as_array()
+            .ok_or_else(|| format_err!("accessList is not an array"))?
+            .iter()
+            .map(|item| {
+                Ok(AccessListItem {
+                    address: json_hex_field(item, "address")?,
+                    slots: json_hex_list(item, "storageKeys")?,
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Transaction object as returned by `eth_getTransactionByHash`, without the block fields.
+///
+/// `from` is recovered from the signature and left out if recovery fails.
+impl From<MessageWithSignature> for serde_json::Value {
+    fn from(tx: MessageWithSignature) -> Self {
+        let mut map = serde_json::Map::new();
+        let mut field = |name: &str, value: serde_json::Value| {
+            map.insert(name.to_string(), value);
+        };
+
+        field("type", (tx.tx_type() as u64).to_hex_string().into());
+        field("hash", tx.hash().to_hex_string().into());
+        if let Ok(sender) = tx.recover_sender() {
+            field("from", sender.to_hex_string().into());
+        }
+        if let Some(chain_id) = tx.chain_id() {
+            field("chainId", chain_id.0.to_hex_string().into());
+        }
+        field("nonce", tx.nonce().to_hex_string().into());
+        field("gas", tx.gas_limit().to_hex_string().into());
+        field(
+            "to",
+            tx.to()
+                .map(|to| to.to_hex_string().into())
+                .unwrap_or(serde_json::Value::Null),
+        );
+        field("value", tx.value().to_hex_string().into());
+        field("input", tx.input().to_hex_string().into());
+
+        let odd_y_parity = tx.signature.odd_y_parity();
+        match &tx.message {
+            Message::Legacy {
+                chain_id,
+                gas_price,
+                ..
+            } => {
+                field("gasPrice", gas_price.to_hex_string().into());
+                let v = YParityAndChainId {
+                    odd_y_parity,
+                    chain_id: *chain_id,
+                }
+                .v();
+                field("v", v.to_hex_string().into());
+            }
+            message => {
+                if let Message::EIP2930 { gas_price, .. } = message {
+                    field("gasPrice", gas_price.to_hex_string().into());
+                } else {
+                    field(
+                        "maxPriorityFeePerGas",
+                        message.max_priority_fee_per_gas().to_hex_string().into(),
+                    );
+                    field(
+                        "maxFeePerGas",
+                        message.max_fee_per_gas().to_hex_string().into(),
+                    );
+                }
+                if let Some(max_fee_per_blob_gas) = message.max_fee_per_blob_gas() {
+                    field(
+                        "maxFeePerBlobGas",
+                        max_fee_per_blob_gas.to_hex_string().into(),
+                    );
+                    field(
+                        "blobVersionedHashes",
+                        message
+                            .blob_versioned_hashes()
+                            .iter()
+                            .map(EncodeHex::to_hex_string)
+                            .collect(),
+                    );
+                }
+                field("accessList", access_list_to_json(&message.access_list()));
+                field("v", (odd_y_parity as u64).to_hex_string().into());
+                field("yParity", (odd_y_parity as u64).to_hex_string().into());
+            }
+        }
+        field("r", h256_to_u256(tx.r()).to_hex_string().into());
+        field("s", h256_to_u256(tx.s()).to_hex_string().into());
+
+        Self::Object(map)
+    }
+}
+
+/// Transaction from a JSON-RPC transaction object. `hash`, `from` and block fields are ignored.
+impl TryFrom<serde_json::Value> for MessageWithSignature {
+    type Error = anyhow::Error;
+
+    fn try_from(value: serde_json::Value) -> anyhow::Result<Self> {
+        let tx_type = optional_json_hex_field::<u64>(&value, "type")?.unwrap_or(0);
+        let nonce = json_hex_field(&value, "nonce")?;
+        let gas_limit = json_hex_field(&value, "gas")?;
+        let to = optional_json_hex_field(&value, "to")?;
+        let action = to
+            .map(TransactionAction::Call)
+            .unwrap_or(TransactionAction::Create);
+        let tx_value = json_hex_field(&value, "value")?;
+        let input = json_hex_field(&value, "input")?;
+        let v = json_hex_field::<u64>(&value, "v")?;
+
+        let (message, odd_y_parity) = if tx_type == 0 {
+            let YParityAndChainId {
+                odd_y_parity,
+                chain_id,
+            } = YParityAndChainId::from_v(v).ok_or_else(|| format_err!("invalid v {}", v))?;
+            (
+                Message::Legacy {
+                    chain_id,
+                    nonce,
+                    gas_price: json_hex_field(&value, "gasPrice")?,
+                    gas_limit,
+                    action,
+                    value: tx_value,
+                    input,
+                },
+                odd_y_parity,
+            )
+        } else {
+            let chain_id = ChainId(json_hex_field(&value, "chainId")?);
+            let access_list = access_list_from_json(&value)?;
+            let message = match tx_type {
+                1 => Message::EIP2930 {
+                    chain_id,
+                    nonce,
+                    gas_price: json_hex_field(&value, "gasPrice")?,
+                    gas_limit,
+                    action,
+                    value: tx_value,
+                    input,
+                    access_list,
+                },
+                2 => Message::EIP1559 {
+                    chain_id,
+                    nonce,
+                    max_priority_fee_per_gas: json_hex_field(&value, "maxPriorityFeePerGas")?,
+                    max_fee_per_gas: json_hex_field(&value, "maxFeePerGas")?,
+                    gas_limit,
+                    action,
+                    value: tx_value,
+                    input,
+                    access_list,
+                },
+                3 => Message::EIP4844 {
+                    chain_id,
+                    nonce,
+                    max_priority_fee_per_gas: json_hex_field(&value, "maxPriorityFeePerGas")?,
+                    max_fee_per_gas: json_hex_field(&value, "maxFeePerGas")?,
+                    gas_limit,
+                    to: to.ok_or_else(|| format_err!("blob transaction without recipient"))?,
+                    value: tx_value,
+                    input,
+                    access_list,
+                    max_fee_per_blob_gas: json_hex_field(&value, "maxFeePerBlobGas")?,
+                    blob_versioned_hashes: json_hex_list(&value, "blobVersionedHashes")?,
+                },
+                other => bail!("unsupported transaction type {}", other),
+            };
+            let y_parity = optional_json_hex_field::<u64>(&value, "yParity")?.unwrap_or(v);
+            ensure!(y_parity <= 1, "invalid y parity {}", y_parity);
+            (message, y_parity == 1)
+        };
+
+        let r = json_hex_field(&value, "r")?;
+        let s = json_hex_field(&value, "s")?;
+        Ok(Self {
+            message,
+            signature: MessageSignature::new(odd_y_parity, u256_to_h256(r), u256_to_h256(s))
+                .ok_or_else(|| format_err!("invalid signature"))?,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::keccak256;
     use hex_literal::hex;
 
     #[test]
@@ -953,6 +1380,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn transaction_eip4844() {
+        let tx = MessageWithSignature {
+            message: Message::EIP4844 {
+                chain_id: ChainId(5),
+                nonce: 7,
+                max_priority_fee_per_gas: 10_000_000_000_u64.into(),
+                max_fee_per_gas: 30_000_000_000_u64.into(),
+                gas_limit: 5_748_100_u64,
+                to: hex!("811a752c8cd697e3cb27279c330ed1ada745a8d7").into(),
+                value: 2.as_u256() * 1_000_000_000 * 1_000_000_000,
+                input: hex!("6ebaf477f83e051589c1188bcc6ddccd").to_vec().into(),
+                access_list: vec![AccessListItem {
+                    address: hex!("bb9bc244d798123fde783fcc1c72d3bb8c189413").into(),
+                    slots: vec![],
+                }],
+                max_fee_per_blob_gas: 1_000_000_000_u64.into(),
+                blob_versioned_hashes: vec![hex!(
+                    "01a915e4d060149eb4365960e6a7a45f334393093061116b197e3240065ff2d8"
+                )
+                .into()],
+            },
+            signature: MessageSignature::new(
+                false,
+                hex!("36b241b061a36a32ab7fe86c7aa9eb592dd59018cd0443adc0903590c16b02b0"),
+                hex!("5edcc541b4741c5cc6dd347c5ed9577ef293a62787b4510465fadbfe39ee4094"),
+            )
+            .unwrap(),
+        };
+
+        assert_eq!(tx.tx_type(), TxType::EIP4844);
+        assert_eq!(tx.trie_encode()[0], 0x03);
+        assert_eq!(
+            tx,
+            rlp::decode::<MessageWithSignature>(&rlp::encode(&tx)).unwrap()
+        );
+        assert_eq!(
+            tx,
+            MessageWithSignature::trie_decode(&tx.trie_encode()).unwrap()
+        );
+    }
+
     #[test]
     fn y_parity_and_chain_id() {
         for range in [0..27, 29..35] {
@@ -1037,4 +1506,145 @@ mod tests {
             38
         );
     }
+
+    #[test]
+    fn json_legacy() {
+        // https://eips.ethereum.org/EIPS/eip-155#example
+        let raw = hex!("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83");
+        let mut json = serde_json::json!({
+            "type": "0x0",
+            "from": "0x9d8a62f656a8d1615c1294fd71e9cfb3e4855a4f",
+            "chainId": "0x1",
+            "nonce": "0x9",
+            "gasPrice": "0x4a817c800",
+            "gas": "0x5208",
+            "to": "0x3535353535353535353535353535353535353535",
+            "value": "0xde0b6b3a7640000",
+            "input": "0x",
+            "v": "0x25",
+            "r": "0x28ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276",
+            "s": "0x67cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83",
+        });
+
+        let tx = MessageWithSignature::try_from(json.clone()).unwrap();
+        assert_eq!(tx, rlp::decode::<MessageWithSignature>(&raw).unwrap());
+        assert_eq!(tx.hash(), keccak256(raw));
+
+        json["hash"] = tx.hash().to_hex_string().into();
+        assert_eq!(serde_json::Value::from(tx), json);
+    }
+
+    #[test]
+    fn json_typed() {
+        let mut json = serde_json::json!({
+            "type": "0x1",
+            "chainId": "0x5",
+            "nonce": "0x7",
+            "gasPrice": "0x6fc23ac00",
+            "gas": "0x57b584",
+            "to": "0x811a752c8cd697e3cb27279c330ed1ada745a8d7",
+            "value": "0x1bc16d674ec80000",
+            "input": "0x6ebaf477f83e051589c1188bcc6ddccd",
+            "accessList": [
+                {
+                    "address": "0xde0b295669a9fd93d5f28d9ec85e40f4cb697bae",
+                    "storageKeys": [
+                        "0x0000000000000000000000000000000000000000000000000000000000000003",
+                        "0x0000000000000000000000000000000000000000000000000000000000000007"
+                    ]
+                },
+                {
+                    "address": "0xbb9bc244d798123fde783fcc1c72d3bb8c189413",
+                    "storageKeys": []
+                }
+            ],
+            "v": "0x0",
+            "yParity": "0x0",
+            "r": "0x36b241b061a36a32ab7fe86c7aa9eb592dd59018cd0443adc0903590c16b02b0",
+            "s": "0x5edcc541b4741c5cc6dd347c5ed9577ef293a62787b4510465fadbfe39ee4094",
+        });
+
+        let access_list = vec![
+            AccessListItem {
+                address: hex!("de0b295669a9fd93d5f28d9ec85e40f4cb697bae").into(),
+                slots: vec![H256::from_low_u64_be(3), H256::from_low_u64_be(7)],
+            },
+            AccessListItem {
+                address: hex!("bb9bc244d798123fde783fcc1c72d3bb8c189413").into(),
+                slots: vec![],
+            },
+        ];
+        let signature = MessageSignature::new(
+            false,
+            hex!("36b241b061a36a32ab7fe86c7aa9eb592dd59018cd0443adc0903590c16b02b0"),
+            hex!("5edcc541b4741c5cc6dd347c5ed9577ef293a62787b4510465fadbfe39ee4094"),
+        )
+        .unwrap();
+        let to = hex!("811a752c8cd697e3cb27279c330ed1ada745a8d7").into();
+        let value = 2.as_u256() * 1_000_000_000 * 1_000_000_000;
+        let input: Bytes = hex!("6ebaf477f83e051589c1188bcc6ddccd").to_vec().into();
+
+        let roundtrip = |json: &serde_json::Value, tx: &MessageWithSignature| {
+            assert_eq!(&MessageWithSignature::try_from(json.clone()).unwrap(), tx);
+
+            let mut out = serde_json::Value::from(tx.clone());
+            let out_map = out.as_object_mut().unwrap();
+            assert_eq!(out_map.remove("hash").unwrap(), tx.hash().to_hex_string());
+            assert_eq!(
+                out_map.remove("from").unwrap(),
+                tx.recover_sender().unwrap().to_hex_string()
+            );
+            assert_eq!(&out, json);
+        };
+
+        roundtrip(
+            &json,
+            &MessageWithSignature {
+                message: Message::EIP2930 {
+                    chain_id: ChainId(5),
+                    nonce: 7,
+                    gas_price: 30_000_000_000_u64.into(),
+                    gas_limit: 5_748_100,
+                    action: TransactionAction::Call(to),
+                    value,
+                    input: input.clone(),
+                    access_list: access_list.clone(),
+                },
+                signature: signature.clone(),
+            },
+        );
+
+        let fields = json.as_object_mut().unwrap();
+        fields.insert("type".to_string(), "0x2".into());
+        fields.remove("gasPrice");
+        fields.insert("maxPriorityFeePerGas".to_string(), "0x2540be400".into());
+        fields.insert("maxFeePerGas".to_string(), "0x6fc23ac00".into());
+        roundtrip(
+            &json,
+            &MessageWithSignature {
+                message: Message::EIP1559 {
+                    chain_id: ChainId(5),
+                    nonce: 7,
+                    max_priority_fee_per_gas: 10_000_000_000_u64.into(),
+                    max_fee_per_gas: 30_000_000_000_u64.into(),
+                    gas_limit: 5_748_100,
+                    action: TransactionAction::Call(to),
+                    value,
+                    input,
+                    access_list,
+                },
+                signature,
+            },
+        );
+
+        json["to"] = serde_json::Value::Null;
+        assert_eq!(
+            MessageWithSignature::try_from(json.clone())
+                .unwrap()
+                .action(),
+            TransactionAction::Create
+        );
+        json["yParity"] = "0x2".into();
+        assert!(MessageWithSignature::try_from(json).is_err());
+    }
 }
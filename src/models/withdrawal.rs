@@ -0,0 +1,32 @@
+use super::*;
+use parity_scale_codec::*;
+use rlp_derive::*;
+
+/// Validator withdrawal pushed from the beacon chain, see EIP-4895.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    RlpEncodable,
+    RlpDecodable,
+)]
+pub struct Withdrawal {
+    pub index: u64,
+    pub validator_index: u64,
+    pub address: Address,
+    /// Withdrawn amount in Gwei.
+    pub amount: u64,
+}
+
+impl Withdrawal {
+    /// Withdrawn amount in wei.
+    pub fn amount_wei(&self) -> U256 {
+        self.amount.as_u256() * GIGA.as_u256()
+    }
+}
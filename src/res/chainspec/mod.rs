@@ -1,5 +1,6 @@
 use crate::models::ChainSpec;
 use once_cell::sync::Lazy;
+use std::collections::HashMap;
 
 pub static MAINNET: Lazy<ChainSpec> =
     Lazy::new(|| ron::from_str(include_str!("ethereum.ron")).unwrap());
@@ -8,5 +9,94 @@ pub static ROPSTEN: Lazy<ChainSpec> =
 pub static RINKEBY: Lazy<ChainSpec> =
     Lazy::new(|| ron::from_str(include_str!("rinkeby.ron")).unwrap());
 
+fn known_chains() -> [&'static ChainSpec; 3] {
+    [&*MAINNET, &*ROPSTEN, &*RINKEBY]
+}
+
+static BY_CHAIN_ID: Lazy<HashMap<u64, &'static ChainSpec>> = Lazy::new(|| {
+    known_chains()
+        .into_iter()
+        .map(|spec| (spec.params.chain_id.0, spec))
+        .collect()
+});
+
+static BY_NETWORK_ID: Lazy<HashMap<u64, &'static ChainSpec>> = Lazy::new(|| {
+    known_chains()
+        .into_iter()
+        .map(|spec| (spec.params.network_id.0, spec))
+        .collect()
+});
+
+impl ChainSpec {
+    /// Bundled chain spec with the given EIP-155 chain ID.
+    pub fn from_chain_id(id: u64) -> Option<&'static ChainSpec> {
+        BY_CHAIN_ID.get(&id).copied()
+    }
+
+    /// Bundled chain spec with the given devp2p network ID.
+    pub fn from_network_id(id: u64) -> Option<&'static ChainSpec> {
+        BY_NETWORK_ID.get(&id).copied()
+    }
+
+    /// Human-readable name of the network with the given EIP-155 chain ID, for display.
+    pub fn chain_name(chain_id: u64) -> &'static str {
+        match chain_id {
+            1 => "Ethereum Mainnet",
+            3 => "Ropsten",
+            4 => "Rinkeby",
+            5 => "Görli",
+            11155111 => "Sepolia",
+            _ => "Unknown",
+        }
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_by_id() {
+        assert!(std::ptr::eq(
+            ChainSpec::from_chain_id(1).unwrap(),
+            &*MAINNET
+        ));
+        assert!(std::ptr::eq(
+            ChainSpec::from_chain_id(3).unwrap(),
+            &*ROPSTEN
+        ));
+        assert!(std::ptr::eq(
+            ChainSpec::from_chain_id(4).unwrap(),
+            &*RINKEBY
+        ));
+        assert!(ChainSpec::from_chain_id(999).is_none());
+
+        assert!(std::ptr::eq(
+            ChainSpec::from_network_id(1).unwrap(),
+            &*MAINNET
+        ));
+        assert!(std::ptr::eq(
+            ChainSpec::from_network_id(4).unwrap(),
+            &*RINKEBY
+        ));
+        assert!(ChainSpec::from_network_id(999).is_none());
+    }
+
+    #[test]
+    fn chain_name() {
+        for (chain_id, name) in [
+            (1, "Ethereum Mainnet"),
+            (3, "Ropsten"),
+            (4, "Rinkeby"),
+            (5, "Görli"),
+            (11155111, "Sepolia"),
+            (999, "Unknown"),
+        ] {
+            assert_eq!(ChainSpec::chain_name(chain_id), name);
+        }
+
+        for spec in known_chains() {
+            assert_ne!(ChainSpec::chain_name(spec.params.chain_id.0), "Unknown");
+        }
+    }
+}
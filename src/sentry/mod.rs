@@ -19,7 +19,9 @@ use std::{
     },
 };
 use tokio::sync::{
-    broadcast::{channel as broadcast_channel, Sender as BroadcastSender},
+    broadcast::{
+        channel as broadcast_channel, Receiver as BroadcastReceiver, Sender as BroadcastSender,
+    },
     mpsc::{channel, Sender},
     Mutex as AsyncMutex,
 };
@@ -30,6 +32,7 @@ pub mod devp2p;
 pub mod eth;
 pub mod grpc;
 pub mod services;
+pub mod snap;
 
 type OutboundSender = Sender<OutboundEvent>;
 type OutboundReceiver = Arc<AsyncMutex<BoxStream<'static, OutboundEvent>>>;
@@ -184,6 +187,22 @@ impl CapabilityServerImpl {
         self.valid_peers.read().len()
     }
 
+    /// Subscribe to peer events, along with a `Connect` event for every peer connected so far.
+    ///
+    /// Events are sent under the lock of the valid peers, so none is missed or duplicated between the two.
+    pub fn subscribe_peers(&self) -> (Vec<PeersReply>, BroadcastReceiver<PeersReply>) {
+        let valid_peers = self.valid_peers.read();
+        let connected = valid_peers
+            .iter()
+            .map(|&peer| PeersReply {
+                peer_id: Some(ethereum_interfaces::types::H512::from(peer)),
+                event: ethereum_interfaces::sentry::peers_reply::PeerEvent::Connect as i32,
+            })
+            .collect();
+
+        (connected, self.peers_status_sender.subscribe())
+    }
+
     pub fn set_status(&self, message: FullStatusData) {
         *self.status_message.write() = Some(message);
         self.no_new_peers.store(false, Ordering::SeqCst);
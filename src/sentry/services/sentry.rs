@@ -152,7 +152,7 @@ impl Sentry for SentryService {
         &self,
         _request: tonic::Request<PeersRequest>,
     ) -> Result<Response<Self::PeersStream>, tonic::Status> {
-        let receiver = self.capability_server.peers_status_sender.subscribe();
+        let (connected, receiver) = self.capability_server.subscribe_peers();
         let stream = BroadcastStream::new(receiver)
             // map BroadcastStreamRecvError to tonic::Status
             .map_err(|error| match error {
@@ -161,6 +161,8 @@ impl Sentry for SentryService {
                     "The receiver lagged too far behind. Some events dropped.",
                 ),
             });
+        // Peers connected so far come first, so subscribers start out with the full set.
+        let stream = tokio_stream::iter(connected.into_iter().map(Ok)).chain(stream);
         Ok(Response::new(Box::pin(stream)))
     }
 
@@ -0,0 +1,469 @@
+use super::devp2p::*;
+use crate::{
+    kv::{mdbx::*, tables},
+    models::*,
+    trie::calculate_storage_root,
+};
+use arrayvec::ArrayString;
+use enum_primitive_derive::*;
+use rlp::{DecoderError, Rlp, RlpStream};
+use rlp_derive::*;
+
+pub fn capability_name() -> CapabilityName {
+    CapabilityName(ArrayString::from("snap").unwrap())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Primitive)]
+pub enum SnapMessageId {
+    GetAccountRange = 0x00,
+    AccountRange = 0x01,
+    GetStorageRanges = 0x02,
+    StorageRanges = 0x03,
+    GetByteCodes = 0x04,
+    ByteCodes = 0x05,
+    GetTrieNodes = 0x06,
+    TrieNodes = 0x07,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct GetAccountRange {
+    pub request_id: u64,
+    pub root_hash: H256,
+    pub starting_hash: H256,
+    pub limit_hash: H256,
+    pub response_bytes: u64,
+}
+
+/// Account in the "slim" snapshot format: empty storage root and code hash are sent as empty strings.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlimAccount {
+    pub nonce: u64,
+    pub balance: U256,
+    pub storage_root: H256,
+    pub code_hash: H256,
+}
+
+impl rlp::Encodable for SlimAccount {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4);
+        s.append(&self.nonce);
+        s.append(&self.balance);
+        if self.storage_root == EMPTY_ROOT {
+            s.append_empty_data();
+        } else {
+            s.append(&self.storage_root);
+        }
+        if self.code_hash == EMPTY_HASH {
+            s.append_empty_data();
+        } else {
+            s.append(&self.code_hash);
+        }
+    }
+}
+
+impl rlp::Decodable for SlimAccount {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        fn hash_or(rlp: &Rlp, index: usize, default: H256) -> Result<H256, DecoderError> {
+            let v = rlp.at(index)?;
+            if v.is_empty() {
+                Ok(default)
+            } else {
+                v.as_val()
+            }
+        }
+
+        if rlp.item_count()? != 4 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+
+        Ok(Self {
+            nonce: rlp.val_at(0)?,
+            balance: rlp.val_at(1)?,
+            storage_root: hash_or(rlp, 2, EMPTY_ROOT)?,
+            code_hash: hash_or(rlp, 3, EMPTY_HASH)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct AccountData {
+    pub hash: H256,
+    pub body: SlimAccount,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct AccountRange {
+    pub request_id: u64,
+    pub accounts: Vec<AccountData>,
+    pub proof: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct GetStorageRanges {
+    pub request_id: u64,
+    pub root_hash: H256,
+    pub account_hashes: Vec<H256>,
+    pub starting_hash: Vec<u8>,
+    pub limit_hash: Vec<u8>,
+    pub response_bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct StorageData {
+    pub hash: H256,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodableWrapper, RlpDecodableWrapper)]
+pub struct AccountStorage {
+    pub slots: Vec<StorageData>,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct StorageRanges {
+    pub request_id: u64,
+    pub slots: Vec<AccountStorage>,
+    pub proof: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct GetByteCodes {
+    pub request_id: u64,
+    pub hashes: Vec<H256>,
+    pub response_bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct ByteCodes {
+    pub request_id: u64,
+    pub codes: Vec<Vec<u8>>,
+}
+
+/// Account trie path optionally followed by storage trie paths of that account.
+#[derive(Clone, Debug, PartialEq, RlpEncodableWrapper, RlpDecodableWrapper)]
+pub struct TrieNodePathSet {
+    pub paths: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct GetTrieNodes {
+    pub request_id: u64,
+    pub root_hash: H256,
+    pub paths: Vec<TrieNodePathSet>,
+    pub response_bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable)]
+pub struct TrieNodes {
+    pub request_id: u64,
+    pub nodes: Vec<Vec<u8>>,
+}
+
+/// Soft cap on response size, regardless of what the peer asks for.
+/// Serving stops once it is reached, so the last item may take the response past it.
+pub const SOFT_RESPONSE_LIMIT: u64 = 2 * 1024 * 1024;
+
+/// Serves snap requests from the current hashed state.
+///
+/// Only the latest state is available, so `root_hash` of incoming requests is not checked yet,
+/// and no range proofs are attached to responses.
+#[derive(Debug)]
+pub struct SnapServer<'env, E: EnvironmentKind> {
+    env: &'env MdbxEnvironment<E>,
+}
+
+impl<'env, E: EnvironmentKind> SnapServer<'env, E> {
+    pub fn new(env: &'env MdbxEnvironment<E>) -> Self {
+        Self { env }
+    }
+
+    pub fn get_account_range(&self, request: &GetAccountRange) -> anyhow::Result<AccountRange> {
+        let txn = self.env.begin()?;
+
+        let response_bytes = request.response_bytes.min(SOFT_RESPONSE_LIMIT);
+
+        let mut accounts = vec![];
+        let mut size = 0;
+        let mut cursor = txn.cursor(tables::HashedAccount)?;
+        let mut entry = cursor.seek(request.starting_hash)?;
+        while let Some((hash, account)) = entry {
+            let body = SlimAccount {
+                nonce: account.nonce,
+                balance: account.balance,
+                storage_root: calculate_storage_root(&txn, hash)?,
+                code_hash: account.code_hash,
+            };
+            let data = AccountData { hash, body };
+            size += rlp::encode(&data).len() as u64;
+            accounts.push(data);
+
+            // The first account past the limit is included to prove there are no more accounts in range.
+            if hash >= request.limit_hash || size >= response_bytes {
+                break;
+            }
+
+            entry = cursor.next()?;
+        }
+
+        Ok(AccountRange {
+            request_id: request.request_id,
+            accounts,
+            proof: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+    use hex_literal::hex;
+
+    // Expected encodings follow the message layouts of the snap/1 spec
+    // (https://github.com/ethereum/devp2p/blob/master/caps/snap.md) and use mainnet genesis data:
+    // its state root and the hashed addresses and balances of two genesis allocations.
+    const GENESIS_ROOT: H256 = H256(hex!(
+        "d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544"
+    ));
+    // keccak256(0x000d836201318ec6899a67540690382780743280)
+    const ACCOUNT_1: H256 = H256(hex!(
+        "cf67b71c90b0d523dd5004cf206f325748da347685071b34812e21801f5270c4"
+    ));
+    // keccak256(0x001762430ea9c3a26e5749afdb70da5f78ddbb8c)
+    const ACCOUNT_2: H256 = H256(hex!(
+        "d298f55ef9ff3d9f5421402fde4480f11be323fb8c7799860893d70a86b2211d"
+    ));
+    // keccak256(0x6000)
+    const CODE_HASH: H256 = H256(hex!(
+        "07ad118d6cc8642c86c03827f276d8b791a65e5c99a3845faf186be720a1455d"
+    ));
+    // keccak256 of storage slot 0
+    const SLOT_0: H256 = H256(hex!(
+        "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+    ));
+    const RESPONSE_BYTES: u64 = 512 * 1024;
+
+    fn genesis_account() -> SlimAccount {
+        SlimAccount {
+            nonce: 0,
+            balance: 0xad78ebc5ac6200000_u128.as_u256(),
+            storage_root: EMPTY_ROOT,
+            code_hash: EMPTY_HASH,
+        }
+    }
+
+    fn check_encoding<T>(v: T, expected: &[u8])
+    where
+        T: rlp::Encodable + rlp::Decodable + PartialEq + std::fmt::Debug,
+    {
+        let encoded = rlp::encode(&v);
+        assert_eq!(&*encoded, expected);
+        assert_eq!(rlp::decode::<T>(expected).unwrap(), v);
+    }
+
+    #[test]
+    fn slim_account_rlp() {
+        // Empty storage root and code hash are sent as empty strings
+        check_encoding(genesis_account(), &hex!("cd80890ad78ebc5ac62000008080"));
+        check_encoding(
+            SlimAccount {
+                nonce: 1,
+                balance: U256::ZERO,
+                storage_root: GENESIS_ROOT,
+                code_hash: CODE_HASH,
+            },
+            &hex!("f8440180a0d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544a007ad118d6cc8642c86c03827f276d8b791a65e5c99a3845faf186be720a1455d"),
+        );
+    }
+
+    #[test]
+    fn get_account_range_rlp() {
+        check_encoding(
+            GetAccountRange {
+                request_id: 1,
+                root_hash: GENESIS_ROOT,
+                starting_hash: H256::zero(),
+                limit_hash: H256::repeat_byte(0xff),
+                response_bytes: RESPONSE_BYTES,
+            },
+            &hex!("f86801a0d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544a00000000000000000000000000000000000000000000000000000000000000000a0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff83080000"),
+        );
+    }
+
+    #[test]
+    fn account_range_rlp() {
+        check_encoding(
+            AccountRange {
+                request_id: 1,
+                accounts: vec![
+                    AccountData {
+                        hash: ACCOUNT_1,
+                        body: genesis_account(),
+                    },
+                    AccountData {
+                        hash: ACCOUNT_2,
+                        body: genesis_account(),
+                    },
+                ],
+                proof: vec![vec![0xde, 0xad]],
+            },
+            &hex!("f86701f860efa0cf67b71c90b0d523dd5004cf206f325748da347685071b34812e21801f5270c4cd80890ad78ebc5ac62000008080efa0d298f55ef9ff3d9f5421402fde4480f11be323fb8c7799860893d70a86b2211dcd80890ad78ebc5ac62000008080c382dead"),
+        );
+    }
+
+    #[test]
+    fn get_storage_ranges_rlp() {
+        check_encoding(
+            GetStorageRanges {
+                request_id: 2,
+                root_hash: GENESIS_ROOT,
+                account_hashes: vec![ACCOUNT_1],
+                starting_hash: vec![],
+                limit_hash: vec![0xff; 32],
+                response_bytes: RESPONSE_BYTES,
+            },
+            &hex!("f86a02a0d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544e1a0cf67b71c90b0d523dd5004cf206f325748da347685071b34812e21801f5270c480a0ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff83080000"),
+        );
+    }
+
+    #[test]
+    fn storage_ranges_rlp() {
+        check_encoding(
+            StorageRanges {
+                request_id: 2,
+                slots: vec![AccountStorage {
+                    slots: vec![StorageData {
+                        hash: SLOT_0,
+                        data: vec![0x01],
+                    }],
+                }],
+                proof: vec![],
+            },
+            &hex!(
+                "e702e4e3e2a0290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e56301c0"
+            ),
+        );
+    }
+
+    #[test]
+    fn get_byte_codes_rlp() {
+        check_encoding(
+            GetByteCodes {
+                request_id: 3,
+                hashes: vec![CODE_HASH],
+                response_bytes: RESPONSE_BYTES,
+            },
+            &hex!(
+                "e703e1a007ad118d6cc8642c86c03827f276d8b791a65e5c99a3845faf186be720a1455d83080000"
+            ),
+        );
+    }
+
+    #[test]
+    fn byte_codes_rlp() {
+        check_encoding(
+            ByteCodes {
+                request_id: 3,
+                codes: vec![vec![0x60, 0x00]],
+            },
+            &hex!("c503c3826000"),
+        );
+    }
+
+    #[test]
+    fn get_trie_nodes_rlp() {
+        check_encoding(
+            GetTrieNodes {
+                request_id: 4,
+                root_hash: GENESIS_ROOT,
+                paths: vec![
+                    TrieNodePathSet {
+                        paths: vec![vec![0x01]],
+                    },
+                    TrieNodePathSet {
+                        paths: vec![vec![0x12, 0x34], vec![]],
+                    },
+                ],
+                response_bytes: RESPONSE_BYTES,
+            },
+            &hex!("ee04a0d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544c7c101c48212348083080000"),
+        );
+    }
+
+    #[test]
+    fn trie_nodes_rlp() {
+        check_encoding(
+            TrieNodes {
+                request_id: 4,
+                nodes: vec![vec![0xc0]],
+            },
+            &hex!("c404c281c0"),
+        );
+    }
+
+    #[test]
+    fn serve_account_range() {
+        let db = new_mem_database().unwrap();
+
+        let hashes = [
+            H256::repeat_byte(0x10),
+            H256::repeat_byte(0x20),
+            H256::repeat_byte(0x30),
+            H256::repeat_byte(0x40),
+        ];
+
+        let txn = db.begin_mutable().unwrap();
+        for (i, hash) in hashes.iter().enumerate() {
+            txn.set(
+                tables::HashedAccount,
+                *hash,
+                Account {
+                    nonce: i as u64,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+        txn.set(
+            tables::HashedStorage,
+            hashes[1],
+            (H256::repeat_byte(0x01), 1.as_u256()),
+        )
+        .unwrap();
+        txn.commit().unwrap();
+
+        let server = SnapServer::new(&db);
+
+        let response = server
+            .get_account_range(&GetAccountRange {
+                request_id: 42,
+                root_hash: H256::zero(),
+                starting_hash: H256::repeat_byte(0x11),
+                limit_hash: H256::repeat_byte(0x35),
+                response_bytes: SOFT_RESPONSE_LIMIT,
+            })
+            .unwrap();
+
+        assert_eq!(response.request_id, 42);
+        assert_eq!(
+            response.accounts.iter().map(|a| a.hash).collect::<Vec<_>>(),
+            hashes[1..].to_vec()
+        );
+        assert_eq!(response.accounts[0].body.nonce, 1);
+        assert_ne!(response.accounts[0].body.storage_root, EMPTY_ROOT);
+        assert_eq!(response.accounts[1].body.storage_root, EMPTY_ROOT);
+
+        // Budget of a single account
+        let response = server
+            .get_account_range(&GetAccountRange {
+                request_id: 43,
+                root_hash: H256::zero(),
+                starting_hash: H256::zero(),
+                limit_hash: H256::repeat_byte(0xff),
+                response_bytes: 1,
+            })
+            .unwrap();
+        assert_eq!(response.accounts.len(), 1);
+        assert_eq!(response.accounts[0].hash, hashes[0]);
+    }
+}
@@ -13,15 +13,15 @@ pub struct ChainConfig {
 }
 
 impl ChainConfig {
-    fn new(chain_spec: ChainSpec) -> Self {
+    fn new(chain_spec: ChainSpec) -> anyhow::Result<Self> {
         let genesis = GenesisState::new(chain_spec.clone());
-        let genesis_header = genesis.header(&genesis.initial_state());
+        let genesis_header = genesis.header(&genesis.initial_state()?);
         let genesis_block_hash = genesis_header.hash();
 
-        Self {
+        Ok(Self {
             chain_spec,
             genesis_block_hash,
-        }
+        })
     }
 
     pub fn network_id(&self) -> NetworkId {
@@ -50,19 +50,19 @@ impl ChainsConfig {
         let mut configs = HashMap::<String, ChainConfig>::new();
         configs.insert(
             String::from("mainnet"),
-            ChainConfig::new(crate::res::chainspec::MAINNET.clone()),
+            ChainConfig::new(crate::res::chainspec::MAINNET.clone())?,
         );
         configs.insert(
             String::from("ethereum"),
-            ChainConfig::new(crate::res::chainspec::MAINNET.clone()),
+            ChainConfig::new(crate::res::chainspec::MAINNET.clone())?,
         );
         configs.insert(
             String::from("ropsten"),
-            ChainConfig::new(crate::res::chainspec::ROPSTEN.clone()),
+            ChainConfig::new(crate::res::chainspec::ROPSTEN.clone())?,
         );
         configs.insert(
             String::from("rinkeby"),
-            ChainConfig::new(crate::res::chainspec::RINKEBY.clone()),
+            ChainConfig::new(crate::res::chainspec::RINKEBY.clone())?,
         );
         Ok(ChainsConfig(configs))
     }
@@ -214,6 +214,10 @@ mod tests {
                         mix_hash: H256(hex!("0000000000000000000000000000000000000000000000000000000000000000")),
                         nonce: H64(hex!("0000000000000000")),
                         base_fee_per_gas: None,
+                        withdrawals_root: None,
+                        blob_gas_used: None,
+                        excess_blob_gas: None,
+                        parent_beacon_block_root: None,
                     }
                 ]
             })
@@ -296,8 +300,13 @@ mod tests {
                         extra_data: vec![0x77, 0x88].into(),
                         mix_hash: H256(hex!("0000000000000000000000000000000000000000000000000000000000000000")),
                         nonce: H64(hex!("0000000000000000")),
-                        base_fee_per_gas: None
-                    }]
+                        base_fee_per_gas: None,
+                        withdrawals_root: None,
+                        blob_gas_used: None,
+                        excess_blob_gas: None,
+                        parent_beacon_block_root: None
+                    }],
+                    withdrawals: None,
                 }]
             })
         );
@@ -0,0 +1,272 @@
+use super::{stage::*, stages::StageId};
+use crate::{kv::mdbx::*, models::*};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BarrierId(pub usize);
+
+impl BarrierId {
+    /// ID of the barrier's stage, `Barrier<N>`.
+    ///
+    /// Names are interned, so that each one is allocated once however many staged syncs are built.
+    pub fn stage_id(self) -> StageId {
+        static NAMES: Mutex<Vec<&'static str>> = parking_lot::const_mutex(Vec::new());
+
+        let mut names = NAMES.lock();
+        while names.len() <= self.0 {
+            let name = format!("Barrier{}", names.len());
+            names.push(Box::leak(name.into_boxed_str()));
+        }
+        StageId(names[self.0])
+    }
+}
+
+/// Progress reported by the stages a barrier waits for.
+#[derive(Debug)]
+pub struct BarrierState {
+    after: Vec<StageId>,
+    progress: HashMap<&'static str, BlockNumber>,
+}
+
+impl BarrierState {
+    pub fn new(after: &[StageId]) -> Self {
+        Self {
+            after: after.to_vec(),
+            progress: HashMap::new(),
+        }
+    }
+
+    pub fn waits_for(&self, stage_id: StageId) -> bool {
+        self.after.iter().any(|id| id.0 == stage_id.0)
+    }
+
+    pub fn record(&mut self, stage_id: StageId, progress: BlockNumber) {
+        if self.waits_for(stage_id) {
+            self.progress.insert(stage_id.0, progress);
+        }
+    }
+
+    /// Block number all awaited stages have reached, if they are at the same one.
+    pub fn synced_progress(&self) -> Option<BlockNumber> {
+        let mut progress = self.after.iter().map(|id| self.progress.get(id.0));
+        let first = *progress.next()??;
+        for p in progress {
+            if *p? != first {
+                return None;
+            }
+        }
+        Some(first)
+    }
+}
+
+/// Pseudo-stage which holds back the following stages until all awaited stages are at the same block.
+///
+/// Until then it reports its previous progress, which the next stage sees as its upper bound.
+#[derive(Debug)]
+pub struct Barrier {
+    id: StageId,
+    state: Arc<Mutex<BarrierState>>,
+}
+
+impl Barrier {
+    pub fn new(id: StageId, state: Arc<Mutex<BarrierState>>) -> Self {
+        Self { id, state }
+    }
+}
+
+#[async_trait]
+impl<'db, E> Stage<'db, E> for Barrier
+where
+    E: EnvironmentKind,
+{
+    fn id(&self) -> StageId {
+        self.id
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        _: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> anyhow::Result<ExecOutput>
+    where
+        'db: 'tx,
+    {
+        let stage_progress = self
+            .state
+            .lock()
+            .synced_progress()
+            .or(input.stage_progress)
+            .unwrap_or_default();
+
+        Ok(ExecOutput::Progress {
+            stage_progress,
+            done: true,
+            must_commit: false,
+        })
+    }
+
+    async fn unwind<'tx>(
+        &mut self,
+        _: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> anyhow::Result<UnwindOutput>
+    where
+        'db: 'tx,
+    {
+        Ok(UnwindOutput {
+            stage_progress: input.unwind_to,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kv::new_mem_database, stagedsync::StagedSync};
+
+    #[derive(Debug)]
+    struct Advance {
+        id: StageId,
+        step: u64,
+        target: u64,
+    }
+
+    #[async_trait]
+    impl<'db, E> Stage<'db, E> for Advance
+    where
+        E: EnvironmentKind,
+    {
+        fn id(&self) -> StageId {
+            self.id
+        }
+
+        async fn execute<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: StageInput,
+        ) -> anyhow::Result<ExecOutput>
+        where
+            'db: 'tx,
+        {
+            let progress = input.stage_progress.unwrap_or_default().0;
+            Ok(ExecOutput::Progress {
+                stage_progress: BlockNumber((progress + self.step).min(self.target)),
+                done: true,
+                must_commit: false,
+            })
+        }
+
+        async fn unwind<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: UnwindInput,
+        ) -> anyhow::Result<UnwindOutput>
+        where
+            'db: 'tx,
+        {
+            Ok(UnwindOutput {
+                stage_progress: input.unwind_to,
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct Follow {
+        seen: Arc<Mutex<Vec<BlockNumber>>>,
+    }
+
+    #[async_trait]
+    impl<'db, E> Stage<'db, E> for Follow
+    where
+        E: EnvironmentKind,
+    {
+        fn id(&self) -> StageId {
+            StageId("Follow")
+        }
+
+        async fn execute<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: StageInput,
+        ) -> anyhow::Result<ExecOutput>
+        where
+            'db: 'tx,
+        {
+            let (_, previous_progress) = input.previous_stage.unwrap();
+            self.seen.lock().push(previous_progress);
+            Ok(ExecOutput::Progress {
+                stage_progress: previous_progress,
+                done: true,
+                must_commit: false,
+            })
+        }
+
+        async fn unwind<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: UnwindInput,
+        ) -> anyhow::Result<UnwindOutput>
+        where
+            'db: 'tx,
+        {
+            Ok(UnwindOutput {
+                stage_progress: input.unwind_to,
+            })
+        }
+    }
+
+    #[test]
+    fn stage_ids_are_interned() {
+        let id = BarrierId(2).stage_id();
+        assert_eq!(id.0, "Barrier2");
+        assert!(std::ptr::eq(id.0, BarrierId(2).stage_id().0));
+        assert_eq!(BarrierId(0).stage_id().0, "Barrier0");
+    }
+
+    #[test]
+    fn synced_progress_requires_all_stages() {
+        let mut state = BarrierState::new(&[StageId("A"), StageId("B")]);
+        assert_eq!(state.synced_progress(), None);
+
+        state.record(StageId("A"), BlockNumber(5));
+        state.record(StageId("C"), BlockNumber(5));
+        assert_eq!(state.synced_progress(), None);
+
+        state.record(StageId("B"), BlockNumber(3));
+        assert_eq!(state.synced_progress(), None);
+
+        state.record(StageId("B"), BlockNumber(5));
+        assert_eq!(state.synced_progress(), Some(BlockNumber(5)));
+    }
+
+    #[tokio::test]
+    async fn following_stage_waits_for_barrier() {
+        let db = new_mem_database().unwrap();
+        let seen = Arc::new(Mutex::new(vec![]));
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(Advance {
+            id: StageId("A"),
+            step: 2,
+            target: 6,
+        });
+        staged_sync.push(Advance {
+            id: StageId("B"),
+            step: 3,
+            target: 6,
+        });
+        staged_sync.with_barrier(&[StageId("A"), StageId("B")]);
+        staged_sync.push(Follow { seen: seen.clone() });
+        staged_sync.set_max_block(Some(BlockNumber(6)));
+
+        staged_sync.run(&db).await.unwrap();
+
+        // A and B are at 2/3, 4/6 and finally 6/6.
+        assert_eq!(
+            *seen.lock(),
+            vec![BlockNumber(0), BlockNumber(0), BlockNumber(6)]
+        );
+    }
+}
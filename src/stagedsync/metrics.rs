@@ -0,0 +1,163 @@
+//! Staged sync metrics, recorded through the [`metrics`] facade and exported in the Prometheus format.
+//!
+//! Recording is a no-op until [`Registry::install`] is called.
+
+use super::stages::StageId;
+use crate::kv::mdbx::WriteStats;
+use ::metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use anyhow::format_err;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use std::{convert::Infallible, fmt::Debug, net::SocketAddr, time::Duration};
+use tracing::*;
+
+/// Upper bounds of the stage duration histogram buckets, in seconds.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0,
+];
+
+const EXECUTE_BLOCKS: &str = "akula_stage_execute_blocks_total";
+const EXECUTE_DURATION: &str = "akula_stage_execute_duration_seconds";
+const UNWIND_BLOCKS: &str = "akula_stage_unwind_blocks_total";
+const STAGE_PROGRESS: &str = "akula_stage_progress";
+const STAGE_APPENDS: &str = "akula_stage_appends_total";
+const STAGE_BYTES_WRITTEN: &str = "akula_stage_bytes_written_total";
+const MDBX_COMMITS: &str = "akula_mdbx_commits_total";
+
+/// Record one invocation of a stage which advanced by `blocks`.
+pub fn record_execute(stage: StageId, blocks: u64, duration: Duration) {
+    counter!(EXECUTE_BLOCKS, blocks, "stage" => stage.0);
+    histogram!(EXECUTE_DURATION, duration, "stage" => stage.0);
+}
+
+pub fn record_unwind(stage: StageId, blocks: u64) {
+    counter!(UNWIND_BLOCKS, blocks, "stage" => stage.0);
+}
+
+/// Record writes made by a stage wrapped in [`ProfilingStage`](super::profiling::ProfilingStage).
+pub fn record_writes(stage: StageId, writes: WriteStats) {
+    counter!(STAGE_APPENDS, writes.appends, "stage" => stage.0);
+    counter!(STAGE_BYTES_WRITTEN, writes.bytes_written, "stage" => stage.0);
+}
+
+/// Record the committed progress of a stage.
+pub fn record_progress(stage: StageId, progress: u64) {
+    gauge!(STAGE_PROGRESS, progress as f64, "stage" => stage.0);
+}
+
+pub fn record_commit() {
+    counter!(MDBX_COMMITS, 1);
+}
+
+/// Handle to the metrics recorder, rendering everything recorded so far.
+#[derive(Clone)]
+pub struct Registry {
+    handle: PrometheusHandle,
+}
+
+impl Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry").finish_non_exhaustive()
+    }
+}
+
+impl Registry {
+    /// Install the Prometheus recorder as the global recorder of the process, which can only happen once.
+    pub fn install() -> anyhow::Result<Self> {
+        let recorder = PrometheusBuilder::new()
+            .set_buckets(&DURATION_BUCKETS)?
+            .build_recorder();
+        let handle = recorder.handle();
+        ::metrics::set_boxed_recorder(Box::new(recorder))
+            .map_err(|e| format_err!("Failed to install metrics recorder: {}", e))?;
+
+        describe_counter!(EXECUTE_BLOCKS, "Blocks processed by a stage.");
+        describe_histogram!(EXECUTE_DURATION, "Duration of a single stage invocation.");
+        describe_counter!(UNWIND_BLOCKS, "Blocks unwound by a stage.");
+        describe_counter!(STAGE_APPENDS, "Cursor appends made by a profiled stage.");
+        describe_counter!(
+            STAGE_BYTES_WRITTEN,
+            "Key and value bytes written by a profiled stage."
+        );
+        describe_gauge!(STAGE_PROGRESS, "Committed progress of a stage.");
+        describe_counter!(
+            MDBX_COMMITS,
+            "Database transactions committed by the staged sync."
+        );
+
+        Ok(Self { handle })
+    }
+
+    /// Render all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        self.handle.render()
+    }
+}
+
+async fn serve_metrics(
+    req: Request<Body>,
+    registry: Registry,
+) -> Result<Response<Body>, Infallible> {
+    Ok(match (req.method(), req.uri().path()) {
+        (&Method::GET, "/metrics") => Response::builder()
+            .header("Content-Type", "text/plain; version=0.0.4")
+            .body(Body::from(registry.render()))
+            .unwrap(),
+        _ => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    })
+}
+
+/// Serves the metrics of `registry` for Prometheus to scrape at `/metrics` on `listen_address`.
+pub async fn run_metrics_server(
+    listen_address: SocketAddr,
+    registry: Registry,
+) -> anyhow::Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let registry = registry.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |req| serve_metrics(req, registry.clone()))) }
+    });
+
+    info!("Metrics endpoint listening on {}", listen_address);
+
+    hyper::Server::try_bind(&listen_address)?
+        .serve(make_service)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{test_util::Sleepy, StagedSync},
+        *,
+    };
+    use crate::{kv::new_mem_database, models::*};
+
+    #[tokio::test]
+    async fn stage_metrics() {
+        let db = new_mem_database().unwrap();
+        let registry = Registry::install().unwrap();
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(Sleepy(StageId("Metered")));
+        staged_sync.set_max_block(Some(BlockNumber(20)));
+
+        staged_sync.run(&db).await.unwrap();
+
+        let rendered = registry.render();
+        assert!(rendered.contains("akula_stage_execute_blocks_total{stage=\"Metered\"} 20\n"));
+        assert!(
+            rendered.contains("akula_stage_execute_duration_seconds_count{stage=\"Metered\"} 2\n")
+        );
+        assert!(rendered.contains("akula_stage_progress{stage=\"Metered\"} 20\n"));
+        assert!(rendered.contains("akula_mdbx_commits_total "));
+        assert!(!rendered.contains("akula_stage_unwind_blocks_total{stage=\"Metered\"}"));
+    }
+}
@@ -1,9 +1,33 @@
+pub mod barrier;
+pub mod metrics;
+pub mod phase;
+pub mod profiling;
+pub mod progress;
 pub mod stage;
 pub mod stages;
-
-use self::stage::{Stage, StageInput, UnwindInput};
+#[cfg(test)]
+mod test_util;
+
+use self::{
+    barrier::{Barrier, BarrierId, BarrierState},
+    phase::SyncPhase,
+    profiling::{ProfilingStage, StageMetrics},
+    progress::SyncProgress,
+    stage::{Stage, StageInput, UnwindInput},
+    stages::{StageId, HEADERS},
+};
 use crate::{kv::mdbx::*, models::*, stagedsync::stage::*};
-use std::time::{Duration, Instant};
+use anyhow::bail;
+use itertools::Itertools;
+use parking_lot::Mutex;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::watch;
 use tracing::*;
 
 /// Staged synchronization framework
@@ -23,10 +47,18 @@ where
     E: EnvironmentKind,
 {
     stages: Vec<Box<dyn Stage<'db, E>>>,
+    barriers: Vec<Arc<Mutex<BarrierState>>>,
+    skipped_stages: Vec<StageId>,
+    progress: Arc<SyncProgress>,
+    phase: watch::Sender<SyncPhase>,
+    phase_receiver: watch::Receiver<SyncPhase>,
     min_progress_to_commit_after_stage: u64,
     max_block: Option<BlockNumber>,
     exit_after_sync: bool,
     delay_after_sync: Option<Duration>,
+    /// Metrics of the stages pushed with [`StagedSync::push_with_profiling`], which get their commit times recorded.
+    profiled: Vec<(StageId, Arc<Mutex<StageMetrics>>)>,
+    shutdown: Arc<AtomicBool>,
 }
 
 impl<'db, E> Default for StagedSync<'db, E>
@@ -43,12 +75,20 @@ where
     E: EnvironmentKind,
 {
     pub fn new() -> Self {
+        let (phase, phase_receiver) = watch::channel(SyncPhase::Starting);
         Self {
             stages: Vec::new(),
+            barriers: Vec::new(),
+            skipped_stages: Vec::new(),
+            progress: Default::default(),
+            phase,
+            phase_receiver,
             min_progress_to_commit_after_stage: 0,
             max_block: None,
             exit_after_sync: false,
             delay_after_sync: None,
+            profiled: Vec::new(),
+            shutdown: Default::default(),
         }
     }
 
@@ -59,6 +99,66 @@ where
         self.stages.push(Box::new(stage))
     }
 
+    /// Push a stage wrapped in [`ProfilingStage`], returning a handle to its metrics.
+    pub fn push_with_profiling<S>(&mut self, stage: S) -> Arc<Mutex<StageMetrics>>
+    where
+        S: Stage<'db, E> + 'static,
+    {
+        let stage = ProfilingStage::new(stage);
+        let metrics = stage.metrics();
+        self.profiled
+            .push((Stage::<'db, E>::id(&stage), metrics.clone()));
+        self.push(stage);
+        metrics
+    }
+
+    /// Insert a synchronization point after already pushed stages.
+    ///
+    /// Stages pushed after the barrier only see progress once all stages in `after` have reached the same block.
+    pub fn with_barrier(&mut self, after: &[StageId]) -> BarrierId {
+        for stage_id in after {
+            assert!(
+                self.stages.iter().any(|stage| stage.id().0 == stage_id.0),
+                "barrier awaits stage {} which was not pushed before it",
+                stage_id
+            );
+        }
+
+        let barrier_id = BarrierId(self.barriers.len());
+        let state = Arc::new(Mutex::new(BarrierState::new(after)));
+        self.barriers.push(state.clone());
+        self.push(Barrier::new(barrier_id.stage_id(), state));
+
+        barrier_id
+    }
+
+    /// Bypass execution and unwinding of an already pushed stage.
+    ///
+    /// The stage keeps its progress, which is handed to the following stages as is.
+    /// This is meant for debugging only: the database is left inconsistent, so never use it in production.
+    pub fn skip_stage(&mut self, id: StageId) -> anyhow::Result<&mut Self> {
+        if !self.stages.iter().any(|stage| stage.id().0 == id.0) {
+            bail!(
+                "Cannot skip stage {} which was not pushed, valid stages are: {}",
+                id,
+                self.stages.iter().map(|stage| stage.id()).join(", ")
+            );
+        }
+
+        self.skipped_stages.push(id);
+        Ok(self)
+    }
+
+    /// Handle to the progress of the stages, updated on every commit.
+    pub fn progress_handle(&self) -> Arc<SyncProgress> {
+        self.progress.clone()
+    }
+
+    /// Receiver of the sync phase, updated on every commit.
+    pub fn phase_receiver(&self) -> watch::Receiver<SyncPhase> {
+        self.phase_receiver.clone()
+    }
+
     pub fn set_min_progress_to_commit_after_stage(&mut self, v: u64) -> &mut Self {
         self.min_progress_to_commit_after_stage = v;
         self
@@ -79,16 +179,53 @@ where
         self
     }
 
+    /// Flag requesting the sync to exit once the batch in progress is committed.
+    ///
+    /// The embedding application sets it, typically on SIGINT and SIGTERM. Long-running stages should check it between
+    /// sub-batches and return early with [`ExecOutput::Progress::must_commit`] set.
+    pub fn shutdown_handle(&self) -> Arc<AtomicBool> {
+        self.shutdown.clone()
+    }
+
     /// Run staged sync loop.
     /// Invokes each loaded stage, and does unwinds if necessary.
     ///
-    /// NOTE: it should never return, except if the loop or any stage fails with error.
+    /// NOTE: it should never return, except if the loop or any stage fails with error, or shutdown is requested.
     pub async fn run(&mut self, db: &'db MdbxEnvironment<E>) -> anyhow::Result<()> {
+        self.run_passes(db, false).await
+    }
+
+    /// Run each stage until it is done, then return.
+    /// Any requested unwinds are carried out before the pass is retried.
+    pub async fn run_once(&mut self, db: &'db MdbxEnvironment<E>) -> anyhow::Result<()> {
+        self.run_passes(db, true).await
+    }
+
+    async fn run_passes(
+        &mut self,
+        db: &'db MdbxEnvironment<E>,
+        single_pass: bool,
+    ) -> anyhow::Result<()> {
         let num_stages = self.stages.len();
 
+        let stage_ids = self
+            .stages
+            .iter()
+            .map(|stage| stage.id())
+            .collect::<Vec<_>>();
+        self.progress.init(&db.begin()?, &stage_ids)?;
+        self.phase.send(SyncPhase::Starting)?;
+
         let mut unwind_to = None;
         'run_loop: loop {
+            if self.shutdown.load(Ordering::SeqCst) {
+                info!("Staged sync shut down");
+                return Ok(());
+            }
+
             let mut tx = db.begin_mutable()?;
+            // Phase reached by the stages, published once it is committed.
+            let mut phase = None;
 
             // Start with unwinding if it's been requested.
             if let Some(to) = unwind_to.take() {
@@ -96,6 +233,11 @@ where
                 for (stage_index, stage) in self.stages.iter_mut().enumerate().rev() {
                     let stage_id = stage.id();
 
+                    if self.skipped_stages.iter().any(|s| s.0 == stage_id.0) {
+                        debug!("Not unwinding skipped stage {}", stage_id);
+                        continue;
+                    }
+
                     // Unwind magic happens here.
                     // Encapsulated into a future for tracing instrumentation.
                     let res: anyhow::Result<()> = async {
@@ -115,6 +257,10 @@ where
                                     )
                                     .await?;
 
+                                metrics::record_unwind(
+                                    stage_id,
+                                    stage_progress.saturating_sub(*unwind_output.stage_progress),
+                                );
                                 stage_progress = unwind_output.stage_progress;
 
                                 stage_id.save_progress(&tx, stage_progress)?;
@@ -141,10 +287,15 @@ where
                     ))
                     .await;
 
-                    res?;
+                    if let Err(e) = res {
+                        abort(tx);
+                        return Err(e);
+                    }
                 }
 
+                self.progress.update(&tx)?;
                 tx.commit()?;
+                metrics::record_commit();
             } else {
                 // Now that we're done with unwind, let's roll.
 
@@ -159,6 +310,18 @@ where
 
                     let stage_id = stage.id();
 
+                    if self.skipped_stages.iter().any(|s| s.0 == stage_id.0) {
+                        let stage_progress = stage_id.get_progress(&tx)?.unwrap_or_default();
+                        warn!("Skipping stage {} @ {}", stage_id, stage_progress);
+
+                        for barrier in &self.barriers {
+                            barrier.lock().record(stage_id, stage_progress);
+                        }
+
+                        previous_stage = Some((stage_id, stage_progress));
+                        continue;
+                    }
+
                     let start_time = Instant::now();
                     let start_progress = stage_id.get_progress(&tx)?;
 
@@ -168,6 +331,7 @@ where
 
                         let stage_id = stage.id();
 
+                        let invoked_at = Instant::now();
                         let exec_output: anyhow::Result<_> = async {
                             if restarted {
                                 debug!(
@@ -191,6 +355,7 @@ where
                                     &mut tx,
                                     StageInput {
                                         restarted,
+                                        is_first_run: prev_progress.is_none(),
                                         first_started_at: (start_time, start_progress),
                                         previous_stage,
                                         stage_progress: prev_progress,
@@ -248,31 +413,79 @@ where
                         .await;
 
                         // Check how stage run went.
-                        match exec_output? {
+                        let exec_output = match exec_output {
+                            Ok(v) => v,
+                            Err(e) => {
+                                abort(tx);
+                                return Err(e);
+                            }
+                        };
+
+                        match exec_output {
                             stage::ExecOutput::Progress {
                                 stage_progress,
                                 done,
+                                must_commit,
                             } => {
                                 stage_id.save_progress(&tx, stage_progress)?;
 
+                                metrics::record_execute(
+                                    stage_id,
+                                    stage_progress
+                                        .saturating_sub(prev_progress.map(|v| v.0).unwrap_or(0)),
+                                    Instant::now() - invoked_at,
+                                );
+
+                                let target = HEADERS
+                                    .get_progress(&tx)?
+                                    .unwrap_or_default()
+                                    .max(stage_progress);
+                                phase =
+                                    Some(SyncPhase::after_stage(stage_id, stage_progress, target));
+
                                 if let Some(m) = &mut minimum_progress {
                                     *m = std::cmp::min(*m, stage_progress);
                                 } else {
                                     minimum_progress = Some(stage_progress);
                                 }
 
+                                let shutdown = self.shutdown.load(Ordering::SeqCst);
+
                                 // Check if we should commit now.
-                                if stage_progress
-                                    .saturating_sub(start_progress.map(|v| v.0).unwrap_or(0))
-                                    >= self.min_progress_to_commit_after_stage
+                                if must_commit
+                                    || shutdown
+                                    || stage_progress
+                                        .saturating_sub(start_progress.map(|v| v.0).unwrap_or(0))
+                                        >= self.min_progress_to_commit_after_stage
                                 {
                                     // Commit and restart transaction.
                                     debug!("Commit requested");
+                                    self.progress.update(&tx)?;
+                                    let commit_started_at = Instant::now();
                                     tx.commit()?;
-                                    debug!("Commit complete");
+                                    let commit_time = Instant::now() - commit_started_at;
+                                    metrics::record_commit();
+                                    if let Some((_, metrics)) =
+                                        self.profiled.iter().find(|(id, _)| id.0 == stage_id.0)
+                                    {
+                                        metrics.lock().record_commit(commit_time);
+                                    }
+                                    debug!(
+                                        "Commit complete in {}",
+                                        format_duration(commit_time, true)
+                                    );
+                                    if let Some(phase) = phase.take() {
+                                        self.phase.send(phase)?;
+                                    }
                                     tx = db.begin_mutable()?;
                                 }
 
+                                if shutdown {
+                                    info!("Staged sync shut down @ {}", stage_progress);
+                                    abort(tx);
+                                    return Ok(());
+                                }
+
                                 // Stage is "done", that is cannot make any more progress at this time.
                                 if done {
                                     // Break out and move to the next stage.
@@ -292,9 +505,18 @@ where
                     };
                     timings.push((stage_id, Instant::now() - start_time));
 
+                    for barrier in &self.barriers {
+                        barrier.lock().record(stage_id, done_progress);
+                    }
+
                     previous_stage = Some((stage_id, done_progress))
                 }
+                self.progress.update(&tx)?;
                 tx.commit()?;
+                metrics::record_commit();
+                if let Some(phase) = phase.take() {
+                    self.phase.send(phase)?;
+                }
 
                 let t = timings
                     .into_iter()
@@ -303,6 +525,10 @@ where
                     });
                 info!("Staged sync complete.{}", t);
 
+                if single_pass {
+                    return Ok(());
+                }
+
                 if let Some(minimum_progress) = minimum_progress {
                     if let Some(max_block) = self.max_block {
                         if minimum_progress == max_block {
@@ -319,6 +545,15 @@ where
     }
 }
 
+fn abort<E>(tx: MdbxTransaction<'_, RW, E>)
+where
+    E: EnvironmentKind,
+{
+    if let Err(e) = tx.abort() {
+        warn!("Failed to abort transaction: {}", e);
+    }
+}
+
 pub fn format_duration(dur: Duration, subsec_millis: bool) -> String {
     let mut secs = dur.as_secs();
     let mut minutes = secs / 60;
@@ -338,3 +573,285 @@ pub fn format_duration(dur: Duration, subsec_millis: bool) -> String {
         }
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+    use async_trait::async_trait;
+
+    #[derive(Debug)]
+    struct RecordFirstRun {
+        seen: Arc<Mutex<Vec<bool>>>,
+    }
+
+    #[async_trait]
+    impl<'db, E> Stage<'db, E> for RecordFirstRun
+    where
+        E: EnvironmentKind,
+    {
+        fn id(&self) -> StageId {
+            StageId("RecordFirstRun")
+        }
+
+        async fn execute<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: StageInput,
+        ) -> anyhow::Result<ExecOutput>
+        where
+            'db: 'tx,
+        {
+            self.seen.lock().push(input.is_first_run);
+            Ok(ExecOutput::Progress {
+                stage_progress: input.stage_progress.unwrap_or_default() + 1,
+                done: true,
+                must_commit: false,
+            })
+        }
+
+        async fn unwind<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: UnwindInput,
+        ) -> anyhow::Result<UnwindOutput>
+        where
+            'db: 'tx,
+        {
+            Ok(UnwindOutput {
+                stage_progress: input.unwind_to,
+            })
+        }
+    }
+
+    #[derive(Debug)]
+    struct RecordPreviousStage {
+        seen: Arc<Mutex<Vec<Option<BlockNumber>>>>,
+    }
+
+    #[async_trait]
+    impl<'db, E> Stage<'db, E> for RecordPreviousStage
+    where
+        E: EnvironmentKind,
+    {
+        fn id(&self) -> StageId {
+            StageId("RecordPreviousStage")
+        }
+
+        async fn execute<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: StageInput,
+        ) -> anyhow::Result<ExecOutput>
+        where
+            'db: 'tx,
+        {
+            self.seen
+                .lock()
+                .push(input.previous_stage.map(|(_, progress)| progress));
+            Ok(ExecOutput::Progress {
+                stage_progress: input.stage_progress.unwrap_or_default() + 1,
+                done: true,
+                must_commit: false,
+            })
+        }
+
+        async fn unwind<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: UnwindInput,
+        ) -> anyhow::Result<UnwindOutput>
+        where
+            'db: 'tx,
+        {
+            Ok(UnwindOutput {
+                stage_progress: input.unwind_to,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn skip_stage() {
+        let db = new_mem_database().unwrap();
+        let skipped = Arc::new(Mutex::new(vec![]));
+        let seen = Arc::new(Mutex::new(vec![]));
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(RecordFirstRun {
+            seen: skipped.clone(),
+        });
+        staged_sync.push(RecordPreviousStage { seen: seen.clone() });
+        assert!(staged_sync.skip_stage(StageId("Unknown")).is_err());
+        staged_sync.skip_stage(StageId("RecordFirstRun")).unwrap();
+        staged_sync.set_max_block(Some(BlockNumber(2)));
+
+        staged_sync.run(&db).await.unwrap();
+
+        assert!(skipped.lock().is_empty());
+        assert_eq!(
+            *seen.lock(),
+            vec![Some(BlockNumber(0)), Some(BlockNumber(0))]
+        );
+        assert_eq!(
+            StageId("RecordFirstRun")
+                .get_progress(&db.begin().unwrap())
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn run_once() {
+        let db = new_mem_database().unwrap();
+        let seen = Arc::new(Mutex::new(vec![]));
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(RecordFirstRun { seen: seen.clone() });
+
+        staged_sync.run_once(&db).await.unwrap();
+        assert_eq!(*seen.lock(), vec![true]);
+
+        staged_sync.run_once(&db).await.unwrap();
+        assert_eq!(*seen.lock(), vec![true, false]);
+        assert_eq!(
+            StageId("RecordFirstRun")
+                .get_progress(&db.begin().unwrap())
+                .unwrap(),
+            Some(BlockNumber(2))
+        );
+    }
+
+    #[tokio::test]
+    async fn progress_handle() {
+        let db = new_mem_database().unwrap();
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(RecordFirstRun {
+            seen: Default::default(),
+        });
+        staged_sync.set_max_block(Some(BlockNumber(2)));
+        let progress = staged_sync.progress_handle();
+
+        staged_sync.run(&db).await.unwrap();
+        assert_eq!(progress.starting_block(), BlockNumber(0));
+        assert_eq!(progress.current_block(), BlockNumber(2));
+        assert_eq!(progress.highest_block(), BlockNumber(2));
+        assert_eq!(
+            progress.stage_progress(StageId("RecordFirstRun")),
+            Some(BlockNumber(2))
+        );
+        assert_eq!(progress.stage_progress(StageId("Unknown")), None);
+
+        staged_sync.run_once(&db).await.unwrap();
+        assert_eq!(progress.starting_block(), BlockNumber(2));
+        assert_eq!(progress.current_block(), BlockNumber(3));
+    }
+
+    #[tokio::test]
+    async fn phase_receiver() {
+        let db = new_mem_database().unwrap();
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(RecordFirstRun {
+            seen: Default::default(),
+        });
+        let phase = staged_sync.phase_receiver();
+        assert_eq!(*phase.borrow(), SyncPhase::Starting);
+
+        staged_sync.run_once(&db).await.unwrap();
+        assert_eq!(
+            *phase.borrow(),
+            SyncPhase::Executing {
+                current: BlockNumber(1),
+                target: BlockNumber(1),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn first_run_only_without_progress() {
+        let db = new_mem_database().unwrap();
+        let seen = Arc::new(Mutex::new(vec![]));
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.push(RecordFirstRun { seen: seen.clone() });
+        staged_sync.set_max_block(Some(BlockNumber(3)));
+
+        staged_sync.run(&db).await.unwrap();
+
+        assert_eq!(*seen.lock(), vec![true, false, false]);
+    }
+
+    /// Never done, requests shutdown once it reaches `shutdown_at`.
+    #[derive(Debug)]
+    struct ShutdownAt {
+        shutdown: Arc<AtomicBool>,
+        shutdown_at: BlockNumber,
+    }
+
+    #[async_trait]
+    impl<'db, E> Stage<'db, E> for ShutdownAt
+    where
+        E: EnvironmentKind,
+    {
+        fn id(&self) -> StageId {
+            StageId("ShutdownAt")
+        }
+
+        async fn execute<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: StageInput,
+        ) -> anyhow::Result<ExecOutput>
+        where
+            'db: 'tx,
+        {
+            let stage_progress = input.stage_progress.unwrap_or_default() + 1;
+            if stage_progress == self.shutdown_at {
+                self.shutdown.store(true, Ordering::SeqCst);
+            }
+            Ok(ExecOutput::Progress {
+                stage_progress,
+                done: false,
+                must_commit: self.shutdown.load(Ordering::SeqCst),
+            })
+        }
+
+        async fn unwind<'tx>(
+            &mut self,
+            _: &'tx mut MdbxTransaction<'db, RW, E>,
+            input: UnwindInput,
+        ) -> anyhow::Result<UnwindOutput>
+        where
+            'db: 'tx,
+        {
+            Ok(UnwindOutput {
+                stage_progress: input.unwind_to,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn shutdown_commits_batch_in_progress() {
+        let db = new_mem_database().unwrap();
+        let seen = Arc::new(Mutex::new(vec![]));
+
+        let mut staged_sync = StagedSync::new();
+        staged_sync.set_min_progress_to_commit_after_stage(100);
+        staged_sync.push(ShutdownAt {
+            shutdown: staged_sync.shutdown_handle(),
+            shutdown_at: BlockNumber(5),
+        });
+        staged_sync.push(RecordFirstRun { seen: seen.clone() });
+
+        staged_sync.run(&db).await.unwrap();
+
+        assert!(seen.lock().is_empty());
+        let tx = db.begin().unwrap();
+        assert_eq!(
+            StageId("ShutdownAt").get_progress(&tx).unwrap(),
+            Some(BlockNumber(5))
+        );
+        assert_eq!(StageId("RecordFirstRun").get_progress(&tx).unwrap(), None);
+    }
+}
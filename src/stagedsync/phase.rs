@@ -0,0 +1,112 @@
+use super::stages::*;
+use crate::models::*;
+
+/// Phase the staged sync is in, as published after every commit.
+///
+/// Targets are the head of the downloaded headers: the network head is not known to the staged sync,
+/// so while downloading headers the target moves along with the current block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncPhase {
+    Starting,
+    SyncingHeaders {
+        current: BlockNumber,
+        target: BlockNumber,
+    },
+    SyncingBodies {
+        current: BlockNumber,
+        target: BlockNumber,
+    },
+    Executing {
+        current: BlockNumber,
+        target: BlockNumber,
+    },
+    FullySynced {
+        tip: BlockNumber,
+    },
+}
+
+impl SyncPhase {
+    /// Status once `stage_id` has reached `stage_progress`, with headers downloaded up to `target`.
+    pub fn after_stage(
+        stage_id: StageId,
+        stage_progress: BlockNumber,
+        target: BlockNumber,
+    ) -> Self {
+        let current = stage_progress;
+        match stage_id.0 {
+            id if id == GENESIS.0 => Self::Starting,
+            id if id == HEADERS.0 || id == BLOCK_HASHES.0 => {
+                Self::SyncingHeaders { current, target }
+            }
+            id if id == BODIES.0 => Self::SyncingBodies { current, target },
+            id if id == FINISH.0 && current >= target => Self::FullySynced { tip: current },
+            _ => Self::Executing { current, target },
+        }
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        !matches!(self, Self::FullySynced { .. })
+    }
+
+    /// Block processed by the stage currently running, or the tip once synced.
+    pub fn current_block(&self) -> Option<BlockNumber> {
+        match *self {
+            Self::Starting => None,
+            Self::SyncingHeaders { current, .. }
+            | Self::SyncingBodies { current, .. }
+            | Self::Executing { current, .. } => Some(current),
+            Self::FullySynced { tip } => Some(tip),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn after_stage() {
+        let target = BlockNumber(100);
+
+        assert_eq!(
+            SyncPhase::after_stage(GENESIS, BlockNumber(0), target),
+            SyncPhase::Starting
+        );
+        assert_eq!(
+            SyncPhase::after_stage(HEADERS, target, target),
+            SyncPhase::SyncingHeaders {
+                current: target,
+                target
+            }
+        );
+        assert_eq!(
+            SyncPhase::after_stage(BODIES, BlockNumber(50), target),
+            SyncPhase::SyncingBodies {
+                current: BlockNumber(50),
+                target
+            }
+        );
+        assert_eq!(
+            SyncPhase::after_stage(EXECUTION, BlockNumber(40), target),
+            SyncPhase::Executing {
+                current: BlockNumber(40),
+                target
+            }
+        );
+        assert_eq!(
+            SyncPhase::after_stage(FINISH, BlockNumber(40), target),
+            SyncPhase::Executing {
+                current: BlockNumber(40),
+                target
+            }
+        );
+
+        let synced = SyncPhase::after_stage(FINISH, target, target);
+        assert_eq!(synced, SyncPhase::FullySynced { tip: target });
+        assert!(!synced.is_syncing());
+        assert_eq!(synced.current_block(), Some(target));
+
+        assert!(SyncPhase::Starting.is_syncing());
+        assert_eq!(SyncPhase::Starting.current_block(), None);
+    }
+}
@@ -0,0 +1,193 @@
+use super::{stage::*, stages::StageId};
+use crate::{kv::mdbx::*, models::*};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::*;
+
+/// Totals accumulated over all invocations of a profiled stage.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StageMetrics {
+    pub executions: u64,
+    pub execute_time: Duration,
+    pub blocks_executed: u64,
+    pub unwinds: u64,
+    pub unwind_time: Duration,
+    pub blocks_unwound: u64,
+    /// Cursor appends made by the stage.
+    pub appends: u64,
+    pub bytes_written: u64,
+    /// Commits the staged sync made right after an invocation of the stage.
+    pub commits: u64,
+    pub commit_time: Duration,
+}
+
+impl StageMetrics {
+    fn record_writes(&mut self, before: WriteStats, after: WriteStats) -> WriteStats {
+        let writes = WriteStats {
+            appends: after.appends - before.appends,
+            bytes_written: after.bytes_written - before.bytes_written,
+        };
+        self.appends += writes.appends;
+        self.bytes_written += writes.bytes_written;
+        writes
+    }
+
+    pub(crate) fn record_commit(&mut self, commit_time: Duration) {
+        self.commits += 1;
+        self.commit_time += commit_time;
+    }
+}
+
+/// Wrapper which measures every invocation of the inner stage and reports it in a debug span.
+///
+/// Besides the time taken, the MDBX writes of the invocation are counted. Commit times are filled in by the staged sync.
+#[derive(Debug)]
+pub struct ProfilingStage<S> {
+    inner: S,
+    metrics: Arc<Mutex<StageMetrics>>,
+}
+
+impl<S> ProfilingStage<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            metrics: Default::default(),
+        }
+    }
+
+    /// Handle to the metrics, which stays valid once the stage is pushed.
+    pub fn metrics(&self) -> Arc<Mutex<StageMetrics>> {
+        self.metrics.clone()
+    }
+}
+
+#[async_trait]
+impl<'db, E, S> Stage<'db, E> for ProfilingStage<S>
+where
+    E: EnvironmentKind,
+    S: Stage<'db, E>,
+{
+    fn id(&self) -> StageId {
+        self.inner.id()
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> anyhow::Result<ExecOutput>
+    where
+        'db: 'tx,
+    {
+        let prev_progress = input.stage_progress.unwrap_or_default();
+
+        let writes_before = tx.write_stats();
+        let started_at = Instant::now();
+        let output = self.inner.execute(tx, input).await?;
+        let elapsed = Instant::now() - started_at;
+
+        let blocks = match &output {
+            ExecOutput::Progress { stage_progress, .. } => {
+                stage_progress.0.saturating_sub(prev_progress.0)
+            }
+            ExecOutput::Unwind { .. } => 0,
+        };
+
+        let mut metrics = self.metrics.lock();
+        metrics.executions += 1;
+        metrics.execute_time += elapsed;
+        metrics.blocks_executed += blocks;
+        let writes = metrics.record_writes(writes_before, tx.write_stats());
+        super::metrics::record_writes(self.inner.id(), writes);
+        debug_span!(
+            "profile",
+            stage = %self.inner.id(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            blocks,
+            appends = writes.appends,
+            bytes_written = writes.bytes_written,
+            executions = metrics.executions,
+            total_execute_ms = metrics.execute_time.as_millis() as u64,
+            total_blocks = metrics.blocks_executed,
+            total_commit_ms = metrics.commit_time.as_millis() as u64,
+        )
+        .in_scope(|| debug!("Stage executed"));
+
+        Ok(output)
+    }
+
+    async fn unwind<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> anyhow::Result<UnwindOutput>
+    where
+        'db: 'tx,
+    {
+        let writes_before = tx.write_stats();
+        let started_at = Instant::now();
+        let output = self.inner.unwind(tx, input).await?;
+        let elapsed = Instant::now() - started_at;
+
+        let blocks = input
+            .stage_progress
+            .0
+            .saturating_sub(output.stage_progress.0);
+
+        let mut metrics = self.metrics.lock();
+        metrics.unwinds += 1;
+        metrics.unwind_time += elapsed;
+        metrics.blocks_unwound += blocks;
+        let writes = metrics.record_writes(writes_before, tx.write_stats());
+        super::metrics::record_writes(self.inner.id(), writes);
+        debug_span!(
+            "profile",
+            stage = %self.inner.id(),
+            elapsed_ms = elapsed.as_millis() as u64,
+            blocks,
+            appends = writes.appends,
+            bytes_written = writes.bytes_written,
+            unwinds = metrics.unwinds,
+            total_unwind_ms = metrics.unwind_time.as_millis() as u64,
+            total_blocks = metrics.blocks_unwound,
+            total_commit_ms = metrics.commit_time.as_millis() as u64,
+        )
+        .in_scope(|| debug!("Stage unwound"));
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{test_util::Sleepy, StagedSync},
+        *,
+    };
+    use crate::kv::new_mem_database;
+
+    #[tokio::test]
+    async fn profiling_stage() {
+        let db = new_mem_database().unwrap();
+
+        let mut staged_sync = StagedSync::new();
+        let metrics = staged_sync.push_with_profiling(Sleepy(StageId("Sleepy")));
+        staged_sync.set_max_block(Some(BlockNumber(20)));
+
+        staged_sync.run(&db).await.unwrap();
+
+        let metrics = *metrics.lock();
+        assert_eq!(metrics.executions, 2);
+        assert_eq!(metrics.blocks_executed, 20);
+        assert!(metrics.execute_time >= Duration::from_millis(10));
+        assert_eq!(metrics.appends, 2);
+        assert_eq!(metrics.bytes_written, 2 * (8 + 8));
+        assert_eq!(metrics.commits, 2);
+        assert!(metrics.commit_time > Duration::ZERO);
+        assert_eq!(metrics.unwinds, 0);
+    }
+}
@@ -0,0 +1,89 @@
+use super::{metrics, stages::StageId};
+use crate::{kv::mdbx::*, models::*};
+use parking_lot::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Committed progress of a running staged sync, readable from other threads.
+#[derive(Debug, Default)]
+pub struct SyncProgress {
+    starting_block: AtomicU64,
+    stages: RwLock<Vec<(StageId, AtomicU64)>>,
+}
+
+impl SyncProgress {
+    /// Reset to the progress of `stages` found in `tx`, which also becomes the starting block.
+    pub fn init<'db, K, E>(
+        &self,
+        tx: &MdbxTransaction<'db, K, E>,
+        stages: &[StageId],
+    ) -> anyhow::Result<()>
+    where
+        K: TransactionKind,
+        E: EnvironmentKind,
+    {
+        let mut entries = Vec::with_capacity(stages.len());
+        for &stage_id in stages {
+            let progress = stage_id.get_progress(tx)?.unwrap_or_default();
+            entries.push((stage_id, AtomicU64::new(progress.0)));
+        }
+        *self.stages.write() = entries;
+        self.starting_block
+            .store(self.current_block().0, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Refresh progress of the tracked stages from `tx`.
+    ///
+    /// Staged sync calls this right before committing, so that only committed progress is published.
+    pub fn update<'db, K, E>(&self, tx: &MdbxTransaction<'db, K, E>) -> anyhow::Result<()>
+    where
+        K: TransactionKind,
+        E: EnvironmentKind,
+    {
+        for (stage_id, progress) in &*self.stages.read() {
+            let stage_progress = stage_id.get_progress(tx)?.unwrap_or_default().0;
+            progress.store(stage_progress, Ordering::Relaxed);
+            metrics::record_progress(*stage_id, stage_progress);
+        }
+
+        Ok(())
+    }
+
+    pub fn stage_progress(&self, stage_id: StageId) -> Option<BlockNumber> {
+        self.stages
+            .read()
+            .iter()
+            .find(|(id, _)| id.0 == stage_id.0)
+            .map(|(_, progress)| BlockNumber(progress.load(Ordering::Relaxed)))
+    }
+
+    /// Block all stages had processed when the sync started.
+    pub fn starting_block(&self) -> BlockNumber {
+        BlockNumber(self.starting_block.load(Ordering::Relaxed))
+    }
+
+    /// Block all stages have processed.
+    pub fn current_block(&self) -> BlockNumber {
+        BlockNumber(
+            self.stages
+                .read()
+                .iter()
+                .map(|(_, progress)| progress.load(Ordering::Relaxed))
+                .min()
+                .unwrap_or(0),
+        )
+    }
+
+    /// Block the most advanced stage has reached, normally the head of the downloaded headers.
+    pub fn highest_block(&self) -> BlockNumber {
+        BlockNumber(
+            self.stages
+                .read()
+                .iter()
+                .map(|(_, progress)| progress.load(Ordering::Relaxed))
+                .max()
+                .unwrap_or(0),
+        )
+    }
+}
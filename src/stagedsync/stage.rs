@@ -7,11 +7,27 @@ use std::{fmt::Debug, time::Instant};
 #[derive(Clone, Copy, Debug)]
 pub struct StageInput {
     pub restarted: bool,
+    /// Stage has never made any progress, so its tables are empty.
+    pub is_first_run: bool,
     pub first_started_at: (Instant, Option<BlockNumber>),
     pub previous_stage: Option<(StageId, BlockNumber)>,
     pub stage_progress: Option<BlockNumber>,
 }
 
+impl StageInput {
+    /// Blocks to process to catch up with the previous stage, empty if it is not ahead.
+    ///
+    /// `None` for the first stage, which has no previous stage to follow.
+    pub fn range(&self) -> Option<BlockRange> {
+        self.previous_stage.map(|(_, previous_progress)| {
+            BlockRange::new(
+                self.stage_progress.unwrap_or_default() + 1,
+                previous_progress,
+            )
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct UnwindInput {
     pub stage_progress: BlockNumber,
@@ -26,6 +42,8 @@ pub enum ExecOutput {
     Progress {
         stage_progress: BlockNumber,
         done: bool,
+        /// Commit right away, regardless of the progress made since the last commit.
+        must_commit: bool,
     },
 }
 
@@ -2,12 +2,15 @@ use crate::{
     kv::{mdbx::*, tables},
     models::*,
 };
-use std::fmt::Display;
+use anyhow::format_err;
+use itertools::Itertools;
+use std::{fmt::Display, str::FromStr};
 use tracing::*;
 
 #[derive(Clone, Copy, Debug)]
 pub struct StageId(pub &'static str);
 
+pub const GENESIS: StageId = StageId("Genesis");
 pub const HEADERS: StageId = StageId("Headers");
 pub const BLOCK_HASHES: StageId = StageId("BlockHashes");
 pub const BODIES: StageId = StageId("Bodies");
@@ -25,6 +28,27 @@ pub const TX_LOOKUP: StageId = StageId("TxLookup");
 pub const TX_POOL: StageId = StageId("TxPool");
 pub const FINISH: StageId = StageId("Finish");
 
+/// Ids of all stages, which are the only ones [`StageId::from_str`] accepts.
+pub const ALL_STAGES: &[StageId] = &[
+    GENESIS,
+    HEADERS,
+    BLOCK_HASHES,
+    BODIES,
+    SENDERS,
+    TOTAL_GAS_INDEX,
+    TOTAL_TX_INDEX,
+    EXECUTION,
+    INTERMEDIATE_HASHES,
+    HASH_STATE,
+    ACCOUNT_HISTORY_INDEX,
+    STORAGE_HISTORY_INDEX,
+    LOG_INDEX,
+    CALL_TRACES,
+    TX_LOOKUP,
+    TX_POOL,
+    FINISH,
+];
+
 impl AsRef<str> for StageId {
     fn as_ref(&self) -> &str {
         self.0
@@ -43,6 +67,24 @@ impl Display for StageId {
     }
 }
 
+impl FromStr for StageId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL_STAGES
+            .iter()
+            .find(|stage_id| stage_id.0 == s)
+            .copied()
+            .ok_or_else(|| {
+                format_err!(
+                    "unknown stage {}, valid stages are: {}",
+                    s,
+                    ALL_STAGES.iter().join(", ")
+                )
+            })
+    }
+}
+
 impl StageId {
     #[instrument]
     pub fn get_progress<'db, K, E>(
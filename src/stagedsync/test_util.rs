@@ -0,0 +1,54 @@
+//! Stages shared by the staged sync tests.
+
+use super::{stage::*, stages::StageId};
+use crate::{
+    kv::{mdbx::*, tables},
+    models::*,
+};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Sleeps for 5ms and advances by 10 blocks, appending to [`tables::TotalGas`] along the way.
+#[derive(Debug)]
+pub struct Sleepy(pub StageId);
+
+#[async_trait]
+impl<'db, E> Stage<'db, E> for Sleepy
+where
+    E: EnvironmentKind,
+{
+    fn id(&self) -> StageId {
+        self.0
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> anyhow::Result<ExecOutput>
+    where
+        'db: 'tx,
+    {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let stage_progress = input.stage_progress.unwrap_or_default() + 10;
+        tx.cursor(tables::TotalGas)?.append(stage_progress, 0)?;
+        Ok(ExecOutput::Progress {
+            stage_progress,
+            done: true,
+            must_commit: false,
+        })
+    }
+
+    async fn unwind<'tx>(
+        &mut self,
+        _: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> anyhow::Result<UnwindOutput>
+    where
+        'db: 'tx,
+    {
+        Ok(UnwindOutput {
+            stage_progress: input.unwind_to,
+        })
+    }
+}
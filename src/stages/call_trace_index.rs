@@ -118,16 +118,25 @@ where
         if printed {
             info!("Flushing froms index");
         }
-        load_call_traces(&mut tx.cursor(tables::CallFromIndex)?, froms_collector)?;
+        load_call_traces(
+            &mut tx.cursor(tables::CallFromIndex)?,
+            froms_collector,
+            input.is_first_run,
+        )?;
 
         if printed {
             info!("Flushing tos index");
         }
-        load_call_traces(&mut tx.cursor(tables::CallToIndex)?, tos_collector)?;
+        load_call_traces(
+            &mut tx.cursor(tables::CallToIndex)?,
+            tos_collector,
+            input.is_first_run,
+        )?;
 
         Ok(ExecOutput::Progress {
             stage_progress: max_block,
             done: true,
+            must_commit: false,
         })
     }
 
@@ -173,9 +182,13 @@ where
     }
 }
 
+/// Loads collected bitmaps into the index.
+///
+/// On first run the index is empty and collected addresses are sorted, so chunks are appended without merging.
 fn load_call_traces<T>(
     cursor: &mut MdbxCursor<'_, RW, T>,
     mut collector: Collector<'_, Address, croaring::Treemap>,
+    is_first_run: bool,
 ) -> anyhow::Result<()>
 where
     T: Table<Key = BitmapKey<Address>, Value = croaring::Treemap>,
@@ -207,23 +220,27 @@ where
         let (address, mut total_bitmap) = res?;
 
         if !total_bitmap.is_empty() {
-            if let Some((_, last_bitmap)) = cursor.seek_exact(BitmapKey {
-                inner: address,
-                block_number: BlockNumber(u64::MAX),
-            })? {
-                total_bitmap |= last_bitmap;
+            if !is_first_run {
+                if let Some((_, last_bitmap)) = cursor.seek_exact(BitmapKey {
+                    inner: address,
+                    block_number: BlockNumber(u64::MAX),
+                })? {
+                    total_bitmap |= last_bitmap;
+                }
             }
 
             for (block_number, bitmap) in
                 bitmapdb::Chunks::new(total_bitmap, CHUNK_LIMIT).with_keys()
             {
-                cursor.put(
-                    BitmapKey {
-                        inner: address,
-                        block_number,
-                    },
-                    bitmap,
-                )?;
+                let key = BitmapKey {
+                    inner: address,
+                    block_number,
+                };
+                if is_first_run {
+                    cursor.append(key, bitmap)?;
+                } else {
+                    cursor.put(key, bitmap)?;
+                }
             }
         }
     }
@@ -341,6 +358,7 @@ mod tests {
                     &mut tx,
                     StageInput {
                         restarted: false,
+                        is_first_run: true,
                         first_started_at: (Instant::now(), Some(BlockNumber(0))),
                         previous_stage: Some((EXECUTION, BlockNumber(20))),
                         stage_progress: None,
@@ -351,6 +369,7 @@ mod tests {
             ExecOutput::Progress {
                 stage_progress: BlockNumber(20),
                 done: true,
+                must_commit: false,
             }
         );
 
@@ -380,6 +399,7 @@ mod tests {
                     &mut tx,
                     StageInput {
                         restarted: false,
+                        is_first_run: false,
                         first_started_at: (Instant::now(), Some(BlockNumber(10))),
                         previous_stage: Some((EXECUTION, BlockNumber(30))),
                         stage_progress: Some(BlockNumber(10)),
@@ -390,6 +410,7 @@ mod tests {
             ExecOutput::Progress {
                 stage_progress: BlockNumber(30),
                 done: true,
+                must_commit: false,
             }
         );
 
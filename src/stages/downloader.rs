@@ -124,6 +124,7 @@ where
         Ok(ExecOutput::Progress {
             stage_progress,
             done,
+            must_commit: false,
         })
     }
 
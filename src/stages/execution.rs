@@ -1,8 +1,9 @@
 use crate::{
     accessors,
-    consensus::engine_factory,
+    consensus::engine_for_total_difficulty,
     execution::{
         analysis_cache::AnalysisCache,
+        block_hash_cache::BlockHashCache,
         processor::ExecutionProcessor,
         tracer::{CallTracer, CallTracerFlags},
     },
@@ -17,7 +18,13 @@ use crate::{
 };
 use anyhow::{format_err, Context};
 use async_trait::async_trait;
-use std::time::{Duration, Instant};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tracing::*;
 
 /// Execution of blocks through EVM
@@ -29,6 +36,8 @@ pub struct Execution {
     pub batch_until: Option<BlockNumber>,
     pub commit_every: Option<Duration>,
     pub prune_from: BlockNumber,
+    /// Shutdown flag of the staged sync, see [`StagedSync::shutdown_handle`](crate::stagedsync::StagedSync::shutdown_handle).
+    pub shutdown: Arc<AtomicBool>,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -43,15 +52,18 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
     starting_block: BlockNumber,
     first_started_at: (Instant, Option<BlockNumber>),
     prune_from: BlockNumber,
+    shutdown: &AtomicBool,
 ) -> anyhow::Result<BlockNumber> {
     let mut buffer = Buffer::new(tx, prune_from, None);
-    let mut consensus_engine = engine_factory(chain_config.clone())?;
     let mut analysis_cache = AnalysisCache::default();
+    let mut block_hash_cache = BlockHashCache::default();
 
     let mut block_number = starting_block;
     let mut gas_since_start = 0;
     let mut gas_since_last_message = 0;
     let mut gas_since_history_commit = 0;
+    let mut blocks_in_batch = 0_u64;
+    let mut gas_in_batch = 0_u128;
     let batch_started_at = Instant::now();
     let first_started_at_gas = tx
         .get(
@@ -61,20 +73,43 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
         .unwrap();
     let mut last_message = Instant::now();
     let mut printed_at_least_once = false;
+    let mut parent_header = match block_number.0.checked_sub(1) {
+        Some(parent_number) => {
+            let parent_number = BlockNumber(parent_number);
+            let parent_hash = tx
+                .get(tables::CanonicalHeader, parent_number)?
+                .ok_or_else(|| {
+                    format_err!("No canonical hash found for block {}", parent_number)
+                })?;
+            tx.get(tables::Header, (parent_number, parent_hash))?
+        }
+        None => None,
+    };
+    let mut total_difficulty = match &parent_header {
+        Some(parent) if chain_config.consensus.terminal_total_difficulty.is_some() => {
+            accessors::chain::td::read(tx, parent.hash(), parent.number)?
+                .ok_or_else(|| format_err!("No total difficulty for block {}", parent.number))?
+        }
+        _ => U256::ZERO,
+    };
+    let mut past_merge = chain_config
+        .consensus
+        .is_terminal_total_difficulty_reached(total_difficulty);
+    let mut consensus_engine = engine_for_total_difficulty(&chain_config, total_difficulty)?;
     loop {
         let block_hash = tx
             .get(tables::CanonicalHeader, block_number)?
             .ok_or_else(|| format_err!("No canonical hash found for block {}", block_number))?;
-        let header = tx
+        let block_header = tx
             .get(tables::Header, (block_number, block_hash))?
-            .ok_or_else(|| format_err!("Header not found: {}/{:?}", block_number, block_hash))?
-            .into();
+            .ok_or_else(|| format_err!("Header not found: {}/{:?}", block_number, block_hash))?;
+        let header = block_header.clone().into();
         let block = accessors::chain::block_body::read_with_senders(tx, block_hash, block_number)?
             .ok_or_else(|| {
                 format_err!("Block body not found: {}/{:?}", block_number, block_hash)
             })?;
 
-        let block_spec = chain_config.collect_block_spec(block_number);
+        let block_spec = chain_config.collect_block_spec(block_number, parent_header.as_ref());
 
         let mut call_tracer = CallTracer::default();
         let receipts = ExecutionProcessor::new(
@@ -86,6 +121,7 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
             &block,
             &block_spec,
         )
+        .with_block_hash_cache(&mut block_hash_cache)
         .execute_and_write_block()
         .with_context(|| {
             format!(
@@ -103,10 +139,36 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
             }
         }
 
+        total_difficulty += header.difficulty;
+        if !past_merge
+            && chain_config
+                .consensus
+                .is_terminal_total_difficulty_reached(total_difficulty)
+        {
+            info!(
+                "Terminal total difficulty reached at block {}, switching to proof of stake",
+                block_number
+            );
+            consensus_engine = engine_for_total_difficulty(&chain_config, total_difficulty)?;
+            past_merge = true;
+        }
+
         gas_since_start += header.gas_used;
         gas_since_last_message += header.gas_used;
         gas_since_history_commit += header.gas_used;
 
+        blocks_in_batch += 1;
+        gas_in_batch += u128::from(header.gas_used);
+        if blocks_in_batch % 1000 == 0 {
+            info!(
+                blocks_in_batch,
+                total_gas_in_batch = %gas_in_batch,
+                avg_gas_per_block = %(gas_in_batch / u128::from(blocks_in_batch)),
+                estimated_remaining_blocks = max_block.0 - block_number.0,
+                "Batch gas usage"
+            );
+        }
+
         if gas_since_history_commit >= history_batch_size {
             buffer.write_history()?;
             gas_since_history_commit = 0;
@@ -121,7 +183,8 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
             || gas_since_start >= batch_size
             || commit_every
                 .map(|commit_every| now - batch_started_at > commit_every)
-                .unwrap_or(false);
+                .unwrap_or(false)
+            || shutdown.load(Ordering::SeqCst);
 
         let elapsed = now - last_message;
         if elapsed > Duration::from_secs(30) || (end_of_batch && !printed_at_least_once) {
@@ -170,6 +233,7 @@ fn execute_batch_of_blocks<E: EnvironmentKind>(
             break;
         }
 
+        parent_header = Some(block_header);
         block_number.0 += 1;
     }
 
@@ -218,6 +282,7 @@ where
                 starting_block,
                 input.first_started_at,
                 self.prune_from,
+                &self.shutdown,
             )?;
 
             let done = executed_to == max_block || self.exit_after_batch;
@@ -225,11 +290,13 @@ where
             ExecOutput::Progress {
                 stage_progress: executed_to,
                 done,
+                must_commit: self.shutdown.load(Ordering::SeqCst),
             }
         } else {
             ExecOutput::Progress {
                 stage_progress: prev_progress,
                 done: true,
+                must_commit: false,
             }
         })
     }
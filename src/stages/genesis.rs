@@ -0,0 +1,129 @@
+use crate::{
+    genesis::initialize_genesis,
+    kv::mdbx::*,
+    models::*,
+    stagedsync::{stage::*, stages::*},
+    StageId,
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tracing::*;
+
+/// Writes genesis block and state of the chain on the first run.
+#[derive(Debug)]
+pub struct InitializeGenesis {
+    pub chain_spec: ChainSpec,
+    pub temp_dir: Arc<TempDir>,
+}
+
+#[async_trait]
+impl<'db, E> Stage<'db, E> for InitializeGenesis
+where
+    E: EnvironmentKind,
+{
+    fn id(&self) -> StageId {
+        GENESIS
+    }
+
+    async fn execute<'tx>(
+        &mut self,
+        tx: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: StageInput,
+    ) -> anyhow::Result<ExecOutput>
+    where
+        'db: 'tx,
+    {
+        if input.is_first_run && initialize_genesis(tx, &self.temp_dir, self.chain_spec.clone())? {
+            info!("Initialized genesis of {}", self.chain_spec.name);
+        }
+
+        Ok(ExecOutput::Progress {
+            stage_progress: self.chain_spec.genesis.number,
+            done: true,
+            must_commit: false,
+        })
+    }
+
+    async fn unwind<'tx>(
+        &mut self,
+        _: &'tx mut MdbxTransaction<'db, RW, E>,
+        input: UnwindInput,
+    ) -> anyhow::Result<UnwindOutput>
+    where
+        'db: 'tx,
+    {
+        Ok(UnwindOutput {
+            stage_progress: input.unwind_to,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kv::{new_mem_database, tables},
+        res::chainspec::{MAINNET, RINKEBY},
+    };
+    use hex_literal::hex;
+    use std::time::Instant;
+
+    fn stage_input(stage_progress: Option<BlockNumber>) -> StageInput {
+        StageInput {
+            restarted: false,
+            is_first_run: stage_progress.is_none(),
+            first_started_at: (Instant::now(), stage_progress),
+            previous_stage: None,
+            stage_progress,
+        }
+    }
+
+    #[tokio::test]
+    async fn second_run_is_noop() {
+        let db = new_mem_database().unwrap();
+        let mut tx = db.begin_mutable().unwrap();
+        let temp_dir = Arc::new(TempDir::new().unwrap());
+
+        let mut stage = InitializeGenesis {
+            chain_spec: MAINNET.clone(),
+            temp_dir: temp_dir.clone(),
+        };
+        let output = stage.execute(&mut tx, stage_input(None)).await.unwrap();
+        assert_eq!(
+            output,
+            ExecOutput::Progress {
+                stage_progress: BlockNumber(0),
+                done: true,
+                must_commit: false,
+            }
+        );
+
+        let mainnet_genesis_hash = H256(hex!(
+            "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+        ));
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(0)).unwrap(),
+            Some(mainnet_genesis_hash)
+        );
+
+        // Running again, even with another chain spec, must not touch the database.
+        let mut stage = InitializeGenesis {
+            chain_spec: RINKEBY.clone(),
+            temp_dir,
+        };
+        stage
+            .execute(&mut tx, stage_input(Some(BlockNumber(0))))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, BlockNumber(0)).unwrap(),
+            Some(mainnet_genesis_hash)
+        );
+        assert_eq!(
+            tx.get(tables::Config, Default::default()).unwrap(),
+            Some(MAINNET.clone())
+        );
+    }
+}
@@ -9,7 +9,10 @@ use crate::{
 };
 use anyhow::format_err;
 use async_trait::async_trait;
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 use tempfile::TempDir;
 use tokio::pin;
 use tracing::*;
@@ -83,6 +86,7 @@ where
 fn promote_accounts<E>(
     tx: &MdbxTransaction<'_, RW, E>,
     stage_progress: BlockNumber,
+    until: BlockNumber,
 ) -> anyhow::Result<()>
 where
     E: EnvironmentKind,
@@ -96,7 +100,13 @@ where
     let walker = changeset_table.walk(Some(starting_block));
     pin!(walker);
 
-    while let Some((_, tables::AccountChange { address, .. })) = walker.next().transpose()? {
+    while let Some((block_number, tables::AccountChange { address, .. })) =
+        walker.next().transpose()?
+    {
+        if block_number > until {
+            break;
+        }
+
         let hashed_address = || keccak256(address);
         if let Some((_, account)) = account_table.seek_exact(address)? {
             target_table.upsert((hashed_address)(), account)?;
@@ -111,6 +121,7 @@ where
 fn promote_storage<E>(
     tx: &MdbxTransaction<'_, RW, E>,
     stage_progress: BlockNumber,
+    until: BlockNumber,
 ) -> anyhow::Result<()>
 where
     E: EnvironmentKind,
@@ -124,10 +135,17 @@ where
     let mut walker = changeset_table.walk(Some(starting_block));
 
     while let Some((
-        tables::StorageChangeKey { address, .. },
+        tables::StorageChangeKey {
+            block_number,
+            address,
+        },
         tables::StorageChange { location, .. },
     )) = walker.next().transpose()?
     {
+        if block_number > until {
+            break;
+        }
+
         let hashed_address = keccak256(address);
         let hashed_location = keccak256(location);
         let mut v = U256::ZERO;
@@ -142,18 +160,84 @@ where
     Ok(())
 }
 
+/// Reverts hashed state to what it was at `unwind_to`, using plain state change sets.
+pub fn unwind_hashed_state<'db, E>(
+    txn: &MdbxTransaction<'db, RW, E>,
+    unwind_to: BlockNumber,
+) -> anyhow::Result<()>
+where
+    E: EnvironmentKind,
+{
+    info!("Unwinding hashed accounts");
+    let mut hashed_account_cur = txn.cursor(tables::HashedAccount)?;
+    let walker = txn.cursor(tables::AccountChangeSet)?.walk_back(None);
+    pin!(walker);
+    while let Some((block_number, tables::AccountChange { address, account })) =
+        walker.next().transpose()?
+    {
+        if block_number > unwind_to {
+            let hashed_address = keccak256(address);
+
+            if let Some(account) = account {
+                hashed_account_cur.put(hashed_address, account)?
+            } else if hashed_account_cur.seek(hashed_address)?.is_some() {
+                hashed_account_cur.delete_current()?
+            }
+        } else {
+            break;
+        }
+    }
+
+    info!("Unwinding hashed storage");
+    let mut hashed_storage_cur = txn.cursor(tables::HashedStorage)?;
+    let walker = txn.cursor(tables::StorageChangeSet)?.walk_back(None);
+    pin!(walker);
+    while let Some((
+        tables::StorageChangeKey {
+            block_number,
+            address,
+        },
+        tables::StorageChange { location, value },
+    )) = walker.next().transpose()?
+    {
+        if block_number > unwind_to {
+            let hashed_address = keccak256(address);
+            let hashed_location = keccak256(location);
+            upsert_hashed_storage_value(
+                &mut hashed_storage_cur,
+                hashed_address,
+                hashed_location,
+                value,
+            )?;
+        } else {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Blocks whose changes are hashed at once when promoting incrementally, between checks for shutdown.
+const INCREMENTAL_PROMOTION_BATCH: u64 = 100_000;
+
 #[derive(Debug)]
 pub struct HashState {
     temp_dir: Arc<TempDir>,
     clean_promotion_threshold: u64,
+    shutdown: Arc<AtomicBool>,
 }
 
 impl HashState {
-    pub fn new(temp_dir: Arc<TempDir>, clean_promotion_threshold: Option<u64>) -> Self {
+    pub fn new(
+        temp_dir: Arc<TempDir>,
+        clean_promotion_threshold: Option<u64>,
+        shutdown: Arc<AtomicBool>,
+    ) -> Self {
         Self {
             temp_dir,
             clean_promotion_threshold: clean_promotion_threshold
                 .unwrap_or(30_000_000_u64 * 1_000_000_u64),
+            shutdown,
         }
     }
 }
@@ -194,15 +278,31 @@ where
             info!("Generating hashed storage");
             promote_clean_storage(tx, &*self.temp_dir)?;
         } else {
-            info!("Incrementally hashing accounts");
-            promote_accounts(tx, past_progress)?;
-            info!("Incrementally hashing storage");
-            promote_storage(tx, past_progress)?;
+            // Hashed entries are set to the latest plain state, so a batch stopped short of `max_block` is
+            // completed by the next one.
+            info!("Incrementally hashing state");
+            let mut stage_progress = past_progress;
+            while stage_progress < max_block {
+                let until = std::cmp::min(stage_progress + INCREMENTAL_PROMOTION_BATCH, max_block);
+                debug!("Hashing accounts and storage up to block {}", until);
+                promote_accounts(tx, stage_progress, until)?;
+                promote_storage(tx, stage_progress, until)?;
+                stage_progress = until;
+
+                if self.shutdown.load(Ordering::SeqCst) {
+                    return Ok(ExecOutput::Progress {
+                        stage_progress,
+                        done: stage_progress == max_block,
+                        must_commit: true,
+                    });
+                }
+            }
         }
 
         Ok(ExecOutput::Progress {
             stage_progress: max_block,
             done: true,
+            must_commit: false,
         })
     }
 
@@ -214,51 +314,7 @@ where
     where
         'db: 'tx,
     {
-        info!("Unwinding hashed accounts");
-        let mut hashed_account_cur = tx.cursor(tables::HashedAccount)?;
-        let walker = tx.cursor(tables::AccountChangeSet)?.walk_back(None);
-        pin!(walker);
-        while let Some((block_number, tables::AccountChange { address, account })) =
-            walker.next().transpose()?
-        {
-            if block_number > input.unwind_to {
-                let hashed_address = keccak256(address);
-
-                if let Some(account) = account {
-                    hashed_account_cur.put(hashed_address, account)?
-                } else if hashed_account_cur.seek(hashed_address)?.is_some() {
-                    hashed_account_cur.delete_current()?
-                }
-            } else {
-                break;
-            }
-        }
-
-        info!("Unwinding hashed storage");
-        let mut hashed_storage_cur = tx.cursor(tables::HashedStorage)?;
-        let walker = tx.cursor(tables::StorageChangeSet)?.walk_back(None);
-        pin!(walker);
-        while let Some((
-            tables::StorageChangeKey {
-                block_number,
-                address,
-            },
-            tables::StorageChange { location, value },
-        )) = walker.next().transpose()?
-        {
-            if block_number > input.unwind_to {
-                let hashed_address = keccak256(address);
-                let hashed_location = keccak256(location);
-                upsert_hashed_storage_value(
-                    &mut hashed_storage_cur,
-                    hashed_address,
-                    hashed_location,
-                    value,
-                )?;
-            } else {
-                break;
-            }
-        }
+        unwind_hashed_state(tx, input.unwind_to)?;
 
         Ok(UnwindOutput {
             stage_progress: input.unwind_to,
@@ -327,6 +383,7 @@ mod tests {
                 deployment_code.into_iter().chain(contract_code).collect(),
             )],
             ommers: vec![],
+            withdrawals: None,
         };
 
         let mut buffer = Buffer::new(&tx, BlockNumber(0), None);
@@ -403,11 +460,13 @@ mod tests {
             HashState {
                 temp_dir: Arc::new(TempDir::new().unwrap()),
                 clean_promotion_threshold: u64::MAX,
+                shutdown: Default::default(),
             }
             .execute(
                 &mut tx,
                 StageInput {
                     restarted: false,
+                    is_first_run: true,
                     first_started_at: (Instant::now(), None),
                     previous_stage: Some((EXECUTION, BlockNumber(3))),
                     stage_progress: None,
@@ -418,6 +477,7 @@ mod tests {
             ExecOutput::Progress {
                 stage_progress: BlockNumber(3),
                 done: true,
+                must_commit: false,
             }
         );
 
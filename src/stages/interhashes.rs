@@ -6,7 +6,7 @@ use crate::{
         stage::{ExecOutput, Stage, StageInput, UnwindInput, UnwindOutput},
         stages::*,
     },
-    stages::stage_util::should_do_clean_promotion,
+    stages::{stage_util::should_do_clean_promotion, unwind_hashed_state},
     trie::{increment_intermediate_hashes, regenerate_intermediate_hashes},
     StageId,
 };
@@ -110,6 +110,7 @@ where
         Ok(ExecOutput::Progress {
             stage_progress: cmp::max(max_block, past_progress),
             done: true,
+            must_commit: false,
         })
     }
 
@@ -121,13 +122,114 @@ where
     where
         'db: 'tx,
     {
-        let _ = input;
-        // TODO: proper unwind
-        tx.clear_table(tables::TrieAccount)?;
-        tx.clear_table(tables::TrieStorage)?;
+        let block_state_root = tx
+            .get(
+                tables::Header,
+                (
+                    input.unwind_to,
+                    tx.get(tables::CanonicalHeader, input.unwind_to)?
+                        .ok_or_else(|| {
+                            format_err!("No canonical hash for block {}", input.unwind_to)
+                        })?,
+                ),
+            )?
+            .ok_or_else(|| format_err!("No header for block {}", input.unwind_to))?
+            .state_root;
+
+        // Stages are unwound in reverse order, so hashed state is still ahead of the unwind point.
+        // Revert it first and record that, so that HashState is not unwound a second time.
+        if HASH_STATE.get_progress(tx)?.unwrap_or_default() > input.unwind_to {
+            unwind_hashed_state(tx, input.unwind_to)?;
+            HASH_STATE.save_progress(tx, input.unwind_to)?;
+        }
+
+        let res = increment_intermediate_hashes(
+            tx,
+            self.temp_dir.as_ref(),
+            input.unwind_to,
+            Some(block_state_root),
+        );
+        let trie_root = if let Err(DuoError::Validation(_)) = &res {
+            warn!("Failed to unwind intermediate hashes: {res:?}. Attempting regenerate.");
+
+            regenerate_intermediate_hashes(tx, self.temp_dir.as_ref(), Some(block_state_root))
+                .with_context(|| "Failed to generate interhashes")?
+        } else {
+            res.with_context(|| "Failed to unwind interhashes")?
+        };
+
+        info!("Block #{} state root OK: {:?}", input.unwind_to, trie_root);
 
         Ok(UnwindOutput {
-            stage_progress: BlockNumber(0),
+            stage_progress: input.unwind_to,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{crypto::keccak256, kv::new_mem_database, state::genesis::initialize_genesis};
+
+    fn read_trie<E: EnvironmentKind>(tx: &MdbxTransaction<'_, RW, E>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        tx.cursor(tables::TrieAccount)
+            .unwrap()
+            .walk(None)
+            .collect::<anyhow::Result<_>>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unwind_restores_trie() {
+        let db = new_mem_database().unwrap();
+        let mut tx = db.begin_mutable().unwrap();
+
+        let temp_dir = Arc::new(TempDir::new().unwrap());
+        initialize_genesis(&tx, &temp_dir, crate::res::chainspec::MAINNET.clone()).unwrap();
+
+        let genesis_trie = read_trie(&tx);
+
+        // Block 1 creates a new account
+        let address = Address::repeat_byte(0xaa);
+        let account = Account {
+            balance: ETHER.into(),
+            ..Default::default()
+        };
+        tx.set(tables::Account, address, account).unwrap();
+        tx.set(
+            tables::AccountChangeSet,
+            BlockNumber(1),
+            tables::AccountChange {
+                address,
+                account: None,
+            },
+        )
+        .unwrap();
+        tx.set(tables::HashedAccount, keccak256(address), account)
+            .unwrap();
+        HASH_STATE.save_progress(&tx, BlockNumber(1)).unwrap();
+        increment_intermediate_hashes(&tx, temp_dir.as_ref(), BlockNumber(0), None).unwrap();
+        assert_ne!(read_trie(&tx), genesis_trie);
+
+        let mut stage = Interhashes::new(temp_dir.clone(), None);
+        let output = stage
+            .unwind(
+                &mut tx,
+                UnwindInput {
+                    stage_progress: BlockNumber(1),
+                    unwind_to: BlockNumber(0),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(output.stage_progress, BlockNumber(0));
+        assert_eq!(
+            tx.get(tables::HashedAccount, keccak256(address)).unwrap(),
+            None
+        );
+        assert_eq!(read_trie(&tx), genesis_trie);
+        // Hashed state has already been reverted, staged sync won't unwind HashState again.
+        assert_eq!(HASH_STATE.get_progress(&tx).unwrap(), Some(BlockNumber(0)));
+    }
+}
@@ -2,6 +2,7 @@ mod block_hashes;
 mod call_trace_index;
 mod downloader;
 mod execution;
+mod genesis;
 mod hashstate;
 mod interhashes;
 mod sender_recovery;
@@ -14,8 +15,12 @@ pub use block_hashes::BlockHashes;
 pub use call_trace_index::CallTraceIndex;
 pub use downloader::HeaderDownload;
 pub use execution::Execution;
-pub use hashstate::{promote_clean_accounts, promote_clean_storage, HashState};
+pub use genesis::InitializeGenesis;
+pub use hashstate::{
+    promote_clean_accounts, promote_clean_storage, unwind_hashed_state, HashState,
+};
 pub use interhashes::Interhashes;
 pub use sender_recovery::SenderRecovery;
 pub use total_gas_index::TotalGasIndex;
 pub use total_tx_index::TotalTxIndex;
+pub use tx_lookup::TxLookup;
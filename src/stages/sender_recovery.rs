@@ -10,7 +10,13 @@ use crate::{
 };
 use async_trait::async_trait;
 use rayon::prelude::*;
-use std::time::{Duration, Instant};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 use tokio::pin;
 use tracing::*;
 
@@ -18,6 +24,8 @@ use tracing::*;
 #[derive(Debug)]
 pub struct SenderRecovery {
     pub batch_size: usize,
+    /// Shutdown flag of the staged sync, see [`StagedSync::shutdown_handle`](crate::stagedsync::StagedSync::shutdown_handle).
+    pub shutdown: Arc<AtomicBool>,
 }
 
 #[async_trait]
@@ -113,6 +121,10 @@ where
                 break true;
             }
 
+            if self.shutdown.load(Ordering::SeqCst) {
+                break false;
+            }
+
             let now = Instant::now();
             let elapsed = now - started_at;
             if elapsed > Duration::from_secs(30) {
@@ -159,6 +171,7 @@ where
         Ok(ExecOutput::Progress {
             stage_progress: highest_block,
             done,
+            must_commit: self.shutdown.load(Ordering::SeqCst),
         })
     }
 
@@ -210,6 +223,7 @@ mod tests {
             base_tx_id: 1.into(),
             tx_amount: 2,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let tx1_1 = MessageWithSignature {
@@ -260,6 +274,7 @@ mod tests {
             base_tx_id: 3.into(),
             tx_amount: 3,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let tx2_1 = MessageWithSignature {
@@ -332,6 +347,7 @@ mod tests {
             base_tx_id: 6.into(),
             tx_amount: 0,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let hash1 = H256::random();
@@ -349,12 +365,16 @@ mod tests {
         chain::tx::write(&tx, block1.base_tx_id, &[tx1_1, tx1_2]).unwrap();
         chain::tx::write(&tx, block2.base_tx_id, &[tx2_1, tx2_2, tx2_3]).unwrap();
 
+        // Shutdown stops the stage after the first batch.
+        let shutdown = Arc::new(AtomicBool::new(true));
         let mut stage = SenderRecovery {
-            batch_size: 500_000,
+            batch_size: 1,
+            shutdown: shutdown.clone(),
         };
 
         let stage_input = StageInput {
             restarted: false,
+            is_first_run: false,
             first_started_at: (Instant::now(), Some(BlockNumber(0))),
             previous_stage: Some((BODIES, 3.into())),
             stage_progress: Some(0.into()),
@@ -362,11 +382,34 @@ mod tests {
 
         let output: ExecOutput = stage.execute(&mut tx, stage_input).await.unwrap();
 
+        assert_eq!(
+            output,
+            ExecOutput::Progress {
+                stage_progress: 1.into(),
+                done: false,
+                must_commit: true,
+            }
+        );
+
+        shutdown.store(false, Ordering::SeqCst);
+        stage.batch_size = 500_000;
+
+        let stage_input = StageInput {
+            restarted: true,
+            is_first_run: false,
+            first_started_at: (Instant::now(), Some(BlockNumber(0))),
+            previous_stage: Some((BODIES, 3.into())),
+            stage_progress: Some(1.into()),
+        };
+
+        let output: ExecOutput = stage.execute(&mut tx, stage_input).await.unwrap();
+
         assert_eq!(
             output,
             ExecOutput::Progress {
                 stage_progress: 3.into(),
                 done: true,
+                must_commit: false,
             }
         );
 
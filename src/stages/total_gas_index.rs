@@ -31,16 +31,14 @@ where
 
         let mut cumulative_index_cur = tx.cursor(tables::TotalGas)?;
 
-        let starting_block = prev_progress + 1;
-        let max_block = input
-            .previous_stage
-            .map(|(_, v)| v)
+        let range = input
+            .range()
             .ok_or_else(|| format_err!("Cannot be the first stage"))?;
 
-        if max_block >= starting_block {
+        if !range.is_empty() {
             let mut gas = cumulative_index_cur.seek_exact(prev_progress)?.unwrap().1;
 
-            for block_num in starting_block..=max_block {
+            for block_num in range.blocks() {
                 if block_num.0 % 500_000 == 0 {
                     info!("Building total gas index for block {block_num}");
                 }
@@ -61,8 +59,9 @@ where
         }
 
         Ok(ExecOutput::Progress {
-            stage_progress: max_block,
+            stage_progress: range.end_inclusive,
             done: true,
+            must_commit: false,
         })
     }
 
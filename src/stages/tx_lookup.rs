@@ -14,7 +14,7 @@ use tracing::*;
 /// Generation of TransactionHash => BlockNumber mapping
 #[derive(Debug)]
 pub struct TxLookup {
-    temp_dir: Arc<TempDir>,
+    pub temp_dir: Arc<TempDir>,
 }
 
 #[async_trait]
@@ -71,6 +71,7 @@ where
                 .map(|(_, stage)| stage)
                 .unwrap_or_default(),
             done: false,
+            must_commit: false,
         })
     }
 
@@ -148,6 +149,7 @@ mod tests {
             base_tx_id: 1.into(),
             tx_amount: 2,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let tx1_1 = MessageWithSignature {
@@ -200,6 +202,7 @@ mod tests {
             base_tx_id: 3.into(),
             tx_amount: 3,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let tx2_1 = MessageWithSignature {
@@ -278,6 +281,7 @@ mod tests {
             base_tx_id: 6.into(),
             tx_amount: 0,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let hash1 = H256::random();
@@ -297,6 +301,7 @@ mod tests {
 
         let stage_input = StageInput {
             restarted: false,
+            is_first_run: false,
             first_started_at: (Instant::now(), Some(BlockNumber(0))),
             previous_stage: Some((BODIES, 3.into())),
             stage_progress: Some(0.into()),
@@ -309,6 +314,7 @@ mod tests {
             ExecOutput::Progress {
                 stage_progress: 3.into(),
                 done: false,
+                must_commit: false,
             }
         );
 
@@ -336,6 +342,7 @@ mod tests {
 
         let stage_input = StageInput {
             restarted: false,
+            is_first_run: false,
             first_started_at: (Instant::now(), Some(BlockNumber(0))),
             previous_stage: Some((BODIES, 3.into())),
             stage_progress: Some(0.into()),
@@ -348,6 +355,7 @@ mod tests {
             ExecOutput::Progress {
                 stage_progress: 3.into(),
                 done: false,
+                must_commit: false,
             }
         );
     }
@@ -364,6 +372,7 @@ mod tests {
             base_tx_id: 1.into(),
             tx_amount: 2,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let tx1_1 = MessageWithSignature {
@@ -416,6 +425,7 @@ mod tests {
             base_tx_id: 3.into(),
             tx_amount: 3,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let tx2_1 = MessageWithSignature {
@@ -494,6 +504,7 @@ mod tests {
             base_tx_id: 6.into(),
             tx_amount: 0,
             uncles: vec![],
+            withdrawals: None,
         };
 
         let hash1 = H256::random();
@@ -0,0 +1,148 @@
+use super::{seek_storage_key, upsert_storage_value};
+use crate::{
+    kv::{mdbx::*, tables},
+    models::*,
+};
+use bytes::Bytes;
+
+/// Reads the latest state directly from the plain state tables, bypassing history.
+#[derive(Debug)]
+pub struct FlatStateReader<'tx, 'env, K, E>
+where
+    K: TransactionKind,
+    E: EnvironmentKind,
+{
+    txn: &'tx MdbxTransaction<'env, K, E>,
+}
+
+impl<'tx, 'env, K, E> FlatStateReader<'tx, 'env, K, E>
+where
+    K: TransactionKind,
+    E: EnvironmentKind,
+{
+    pub fn new(txn: &'tx MdbxTransaction<'env, K, E>) -> Self {
+        Self { txn }
+    }
+
+    pub fn read_account(&self, address: Address) -> anyhow::Result<Option<Account>> {
+        self.txn.get(tables::Account, address)
+    }
+
+    pub fn read_storage(&self, address: Address, location: U256) -> anyhow::Result<U256> {
+        Ok(
+            seek_storage_key(&mut self.txn.cursor(tables::Storage)?, address, location)?
+                .unwrap_or(U256::ZERO),
+        )
+    }
+
+    pub fn read_code(&self, code_hash: H256) -> anyhow::Result<Bytes> {
+        if code_hash == EMPTY_HASH {
+            return Ok(Bytes::new());
+        }
+
+        Ok(self.txn.get(tables::Code, code_hash)?.unwrap_or_default())
+    }
+}
+
+/// Writes state changes straight into the plain state tables.
+///
+/// Unlike `Buffer`, no change sets are recorded, so these writes cannot be unwound.
+#[derive(Debug)]
+pub struct FlatStateWriter<'tx, 'env, E>
+where
+    E: EnvironmentKind,
+{
+    txn: &'tx MdbxTransaction<'env, RW, E>,
+}
+
+impl<'tx, 'env, E> FlatStateWriter<'tx, 'env, E>
+where
+    E: EnvironmentKind,
+{
+    pub fn new(txn: &'tx MdbxTransaction<'env, RW, E>) -> Self {
+        Self { txn }
+    }
+
+    pub fn reader(&self) -> FlatStateReader<'tx, 'env, RW, E> {
+        FlatStateReader::new(self.txn)
+    }
+
+    /// Writes the account, or deletes it along with its storage if `None`.
+    pub fn write_account(&self, address: Address, account: Option<Account>) -> anyhow::Result<()> {
+        if let Some(account) = account {
+            self.txn.set(tables::Account, address, account)?;
+        } else {
+            self.txn.del(tables::Account, address, None)?;
+            self.txn.del(tables::Storage, address, None)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_storage(
+        &self,
+        address: Address,
+        location: U256,
+        value: U256,
+    ) -> anyhow::Result<()> {
+        upsert_storage_value(
+            &mut self.txn.cursor(tables::Storage)?,
+            address,
+            location,
+            value,
+        )
+    }
+
+    pub fn write_code(&self, code_hash: H256, code: Bytes) -> anyhow::Result<()> {
+        self.txn.set(tables::Code, code_hash, code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::kv::new_mem_database;
+    use hex_literal::hex;
+
+    #[test]
+    fn write_and_read_flat_state() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let address = Address::from(hex!("b000000000000000000000000000000000000008"));
+        let account = Account {
+            nonce: 3,
+            balance: 1000.as_u256(),
+            code_hash: H256::repeat_byte(0xc0),
+        };
+
+        let writer = FlatStateWriter::new(&txn);
+        writer.write_account(address, Some(account)).unwrap();
+        writer
+            .write_storage(address, 1.as_u256(), 5.as_u256())
+            .unwrap();
+        writer
+            .write_storage(address, 2.as_u256(), 7.as_u256())
+            .unwrap();
+        writer
+            .write_storage(address, 2.as_u256(), U256::ZERO)
+            .unwrap();
+        writer
+            .write_code(account.code_hash, Bytes::from_static(&[0x60, 0x00]))
+            .unwrap();
+
+        let reader = writer.reader();
+        assert_eq!(reader.read_account(address).unwrap(), Some(account));
+        assert_eq!(reader.read_storage(address, 1.as_u256()).unwrap(), 5);
+        assert_eq!(reader.read_storage(address, 2.as_u256()).unwrap(), 0);
+        assert_eq!(
+            reader.read_code(account.code_hash).unwrap(),
+            Bytes::from_static(&[0x60, 0x00])
+        );
+        assert_eq!(reader.read_code(EMPTY_HASH).unwrap(), Bytes::new());
+
+        writer.write_account(address, None).unwrap();
+        assert_eq!(reader.read_account(address).unwrap(), None);
+        assert_eq!(reader.read_storage(address, 1.as_u256()).unwrap(), 0);
+    }
+}
@@ -1,8 +1,10 @@
 use crate::{
+    crypto::keccak256,
     kv::{mdbx::*, tables},
     models::*,
     state::*,
 };
+use std::collections::HashMap;
 use tempfile::TempDir;
 
 #[derive(Clone, Debug)]
@@ -17,19 +19,29 @@ impl GenesisState {
 }
 
 impl GenesisState {
-    pub fn initial_state(&self) -> InMemoryState {
+    pub fn initial_state(&self) -> anyhow::Result<InMemoryState> {
+        let genesis = self.chain_spec.genesis.number;
         let mut state_buffer = InMemoryState::new();
-        // Allocate accounts
-        if let Some(balances) = self.chain_spec.balances.get(&BlockNumber(0)) {
+        // Allocate accounts and deploy contracts
+        let mut accounts = HashMap::<Address, Account>::new();
+        if let Some(balances) = self.chain_spec.balances.get(&genesis) {
             for (&address, &balance) in balances {
-                let current_account = Account {
-                    balance,
-                    ..Default::default()
-                };
-                state_buffer.update_account(address, None, Some(current_account));
+                accounts.entry(address).or_default().balance = balance;
             }
         }
-        state_buffer
+        if let Some(contracts) = self.chain_spec.contracts.get(&genesis) {
+            for (&address, contract) in contracts {
+                if let Contract::Contract { code } = contract {
+                    let code_hash = keccak256(code);
+                    state_buffer.update_code(code_hash, code.clone())?;
+                    accounts.entry(address).or_default().code_hash = code_hash;
+                }
+            }
+        }
+        for (address, account) in accounts {
+            state_buffer.update_account(address, None, Some(account));
+        }
+        Ok(state_buffer)
     }
 
     pub fn header(&self, initial_state: &InMemoryState) -> BlockHeader {
@@ -38,7 +50,7 @@ impl GenesisState {
         let state_root = initial_state.state_root_hash();
 
         BlockHeader {
-            parent_hash: H256::zero(),
+            parent_hash: GENESIS_PARENT_HASH,
             beneficiary: genesis.author,
             state_root,
             logs_bloom: Bloom::zero(),
@@ -51,6 +63,10 @@ impl GenesisState {
             mix_hash: seal.mix_hash(),
             nonce: seal.nonce(),
             base_fee_per_gas: None,
+            withdrawals_root: None,
+            blob_gas_used: None,
+            excess_blob_gas: None,
+            parent_beacon_block_root: None,
 
             receipts_root: EMPTY_ROOT,
             ommers_hash: EMPTY_LIST_HASH,
@@ -74,19 +90,25 @@ where
 
     let mut state_buffer = Buffer::new(txn, genesis, None);
     state_buffer.begin_block(genesis);
-    // Allocate accounts
+    // Allocate accounts and deploy contracts
+    let mut accounts = HashMap::<Address, Account>::new();
     if let Some(balances) = chainspec.balances.get(&genesis) {
         for (&address, &balance) in balances {
-            state_buffer.update_account(
-                address,
-                None,
-                Some(Account {
-                    balance,
-                    ..Default::default()
-                }),
-            );
+            accounts.entry(address).or_default().balance = balance;
+        }
+    }
+    if let Some(contracts) = chainspec.contracts.get(&genesis) {
+        for (&address, contract) in contracts {
+            if let Contract::Contract { code } = contract {
+                let code_hash = keccak256(code);
+                state_buffer.update_code(code_hash, code.clone())?;
+                accounts.entry(address).or_default().code_hash = code_hash;
+            }
         }
     }
+    for (address, account) in accounts {
+        state_buffer.update_account(address, None, Some(account));
+    }
 
     state_buffer.write_to_db()?;
 
@@ -95,7 +117,7 @@ where
     let state_root = crate::trie::regenerate_intermediate_hashes(txn, etl_temp_dir, None)?;
 
     let header = BlockHeader {
-        parent_hash: H256::zero(),
+        parent_hash: GENESIS_PARENT_HASH,
         beneficiary: chainspec.genesis.author,
         state_root,
         logs_bloom: Bloom::zero(),
@@ -108,6 +130,10 @@ where
         mix_hash: chainspec.genesis.seal.mix_hash(),
         nonce: chainspec.genesis.seal.nonce(),
         base_fee_per_gas: None,
+        withdrawals_root: None,
+        blob_gas_used: None,
+        excess_blob_gas: None,
+        parent_beacon_block_root: None,
 
         receipts_root: EMPTY_ROOT,
         ommers_hash: EMPTY_LIST_HASH,
@@ -131,6 +157,7 @@ where
             base_tx_id: 0.into(),
             tx_amount: 0,
             uncles: vec![],
+            withdrawals: None,
         },
     )?;
 
@@ -148,11 +175,12 @@ where
 mod tests {
     use super::*;
     use crate::kv::new_mem_database;
+    use bytes::Bytes;
     use hex_literal::hex;
 
     fn genesis_header_hash(chain_spec: &'static ChainSpec) -> H256 {
         let genesis = GenesisState::new(chain_spec.clone());
-        let genesis_header = genesis.header(&genesis.initial_state());
+        let genesis_header = genesis.header(&genesis.initial_state().unwrap());
         genesis_header.hash()
     }
 
@@ -189,4 +217,36 @@ mod tests {
             hex!("d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3").into()
         );
     }
+
+    #[test]
+    fn genesis_contracts() {
+        let mut chain_spec = crate::res::chainspec::RINKEBY.clone();
+        chain_spec.contracts.insert(
+            chain_spec.genesis.number,
+            [(
+                Address::repeat_byte(0xaa),
+                Contract::Contract {
+                    code: Bytes::from_static(&[0x60, 0x00]),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        let genesis = GenesisState::new(chain_spec.clone());
+        let header = genesis.header(&genesis.initial_state().unwrap());
+        assert_ne!(
+            header.hash(),
+            genesis_header_hash(&crate::res::chainspec::RINKEBY)
+        );
+
+        let db = new_mem_database().unwrap();
+        let tx = db.begin_mutable().unwrap();
+        let temp_dir = TempDir::new().unwrap();
+        assert!(initialize_genesis(&tx, &temp_dir, chain_spec).unwrap());
+        assert_eq!(
+            tx.get(tables::CanonicalHeader, 0.into()).unwrap().unwrap(),
+            header.hash()
+        );
+    }
 }
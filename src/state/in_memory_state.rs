@@ -104,6 +104,7 @@ impl InMemoryState {
             header,
             transactions,
             ommers,
+            withdrawals,
         } = block;
 
         let block_number = header.number.0 as usize;
@@ -123,6 +124,7 @@ impl InMemoryState {
             BlockBody {
                 transactions,
                 ommers,
+                withdrawals,
             },
         );
 
@@ -165,6 +167,7 @@ impl InMemoryState {
                             })
                             .collect::<anyhow::Result<_>>()?,
                         ommers: body.ommers.clone(),
+                        withdrawals: body.withdrawals.clone(),
                     })
                 })
                 .transpose();
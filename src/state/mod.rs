@@ -1,6 +1,7 @@
 mod buffer;
 mod database;
 mod delta;
+mod flat;
 pub mod genesis;
 mod in_memory_state;
 mod interface;
@@ -8,5 +9,6 @@ mod intra_block_state;
 mod object;
 
 pub use self::{
-    buffer::*, database::*, in_memory_state::*, interface::*, intra_block_state::*, object::*,
+    buffer::*, database::*, flat::*, in_memory_state::*, interface::*, intra_block_state::*,
+    object::*,
 };
@@ -9,7 +9,7 @@ use crate::{
 use bytes::BytesMut;
 use ethereum_types::H256;
 use rlp::RlpStream;
-use std::{boxed::Box, cmp};
+use std::{boxed::Box, cmp, collections::BTreeMap};
 
 const RLP_EMPTY_STRING_CODE: u8 = 0x80;
 
@@ -78,8 +78,43 @@ fn extension_node_rlp(path: &[u8], child_ref: &[u8]) -> BytesMut {
     stream.out()
 }
 
+/// Keeps RLP of the nodes on the paths to the target keys, so that Merkle proofs can be assembled.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ProofRetainer {
+    targets: Vec<Vec<u8>>,
+    nodes: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ProofRetainer {
+    pub(crate) fn new(targets: Vec<Vec<u8>>) -> Self {
+        Self {
+            targets,
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    fn retain(&mut self, path: &[u8], rlp: &[u8]) {
+        // Nodes shorter than a hash are embedded into their parent, except for the root.
+        if (path.is_empty() || rlp.len() >= KECCAK_LENGTH)
+            && self.targets.iter().any(|target| target.starts_with(path))
+        {
+            self.nodes.insert(path.to_vec(), rlp.to_vec());
+        }
+    }
+
+    /// Proof for the target key, starting from the root node.
+    pub(crate) fn proof(&self, target: &[u8]) -> Vec<Vec<u8>> {
+        self.nodes
+            .iter()
+            .filter(|(path, _)| target.starts_with(path))
+            .map(|(_, rlp)| rlp.clone())
+            .collect()
+    }
+}
+
 pub(crate) struct HashBuilder<'nc> {
     pub(crate) node_collector: Option<NodeCollector<'nc>>,
+    pub(crate) proof_retainer: Option<ProofRetainer>,
     key: Vec<u8>,
     value: HashBuilderValue,
     is_in_db_trie: bool,
@@ -93,6 +128,7 @@ impl<'nc> HashBuilder<'nc> {
     pub(crate) fn new(node_collector: Option<NodeCollector<'nc>>) -> Self {
         Self {
             node_collector,
+            proof_retainer: None,
             key: vec![],
             value: HashBuilderValue::Bytes(vec![]),
             is_in_db_trie: false,
@@ -103,10 +139,21 @@ impl<'nc> HashBuilder<'nc> {
         }
     }
 
+    pub(crate) fn with_proof_retainer(mut self, proof_retainer: ProofRetainer) -> Self {
+        self.proof_retainer = Some(proof_retainer);
+        self
+    }
+
     fn collects_nodes(&self) -> bool {
         self.node_collector.is_some()
     }
 
+    fn retain_proof(&mut self, path: &[u8], rlp: &[u8]) {
+        if let Some(proof_retainer) = &mut self.proof_retainer {
+            proof_retainer.retain(path, rlp);
+        }
+    }
+
     pub(crate) fn add_leaf(&mut self, key: Vec<u8>, value: &[u8]) {
         assert!(key > self.key);
         if !self.key.is_empty() {
@@ -187,9 +234,9 @@ impl<'nc> HashBuilder<'nc> {
                 let value = self.value.clone();
                 match &value {
                     HashBuilderValue::Bytes(leaf_value) => {
-                        let node_ref =
-                            node_ref(leaf_node_rlp(short_node_key.as_slice(), leaf_value).as_ref());
-                        self.stack.push(node_ref);
+                        let rlp = leaf_node_rlp(short_node_key.as_slice(), leaf_value);
+                        self.retain_proof(&current[..len_from], &rlp);
+                        self.stack.push(node_ref(&rlp));
                     }
                     HashBuilderValue::Hash(hash) => {
                         self.stack.push(wrap_hash(hash));
@@ -217,10 +264,9 @@ impl<'nc> HashBuilder<'nc> {
                 }
 
                 let stack_last = self.stack.pop().unwrap();
-                let new_stack_last = node_ref(
-                    extension_node_rlp(short_node_key.as_slice(), stack_last.as_slice()).as_ref(),
-                );
-                self.stack.push(new_stack_last);
+                let rlp = extension_node_rlp(short_node_key.as_slice(), stack_last.as_slice());
+                self.retain_proof(&current[..len_from], &rlp);
+                self.stack.push(node_ref(&rlp));
 
                 self.hash_masks.resize(len_from, 0u16);
                 self.tree_masks.resize(len_from, 0u16);
@@ -231,7 +277,8 @@ impl<'nc> HashBuilder<'nc> {
             }
 
             if !succeeding.is_empty() || preceding_exists {
-                let child_hashes = self.branch_ref(self.groups[len], self.hash_masks[len]);
+                let child_hashes =
+                    self.branch_ref(&current[..len], self.groups[len], self.hash_masks[len]);
 
                 if self.collects_nodes() {
                     if len > 0 {
@@ -283,7 +330,7 @@ impl<'nc> HashBuilder<'nc> {
         }
     }
 
-    fn branch_ref(&mut self, state_mask: u16, hash_mask: u16) -> Vec<Vec<u8>> {
+    fn branch_ref(&mut self, path: &[u8], state_mask: u16, hash_mask: u16) -> Vec<Vec<u8>> {
         assert_subset(hash_mask, state_mask);
         let mut child_hashes = Vec::<Vec<u8>>::with_capacity(hash_mask.count_ones() as usize);
         let first_child_idx = self.stack.len() - state_mask.count_ones() as usize;
@@ -303,8 +350,11 @@ impl<'nc> HashBuilder<'nc> {
         }
         stream.append_empty_data();
 
+        let rlp = stream.out();
+        self.retain_proof(path, &rlp);
+
         self.stack.truncate(first_child_idx);
-        self.stack.push(node_ref(stream.out().as_ref()));
+        self.stack.push(node_ref(&rlp));
 
         child_hashes
     }
@@ -2,6 +2,128 @@ mod hash_builder;
 mod intermediate_hashes;
 mod node;
 mod prefix_set;
+mod proof;
 mod util;
 
 pub use intermediate_hashes::{increment_intermediate_hashes, regenerate_intermediate_hashes};
+pub use proof::{generate_proof, AccountProof, StorageProof};
+
+use crate::{
+    crypto::keccak256,
+    kv::{mdbx::*, tables},
+    models::*,
+};
+use hash_builder::{unpack_nibbles, HashBuilder};
+use std::collections::BTreeMap;
+
+/// Computes storage root of an account straight from `HashedStorage`, without touching `TrieStorage`.
+pub fn calculate_storage_root<K, E>(
+    txn: &MdbxTransaction<'_, K, E>,
+    hashed_address: H256,
+) -> anyhow::Result<H256>
+where
+    K: TransactionKind,
+    E: EnvironmentKind,
+{
+    let mut hb = HashBuilder::new(None);
+    for entry in txn.cursor(tables::HashedStorage)?.walk_dup(hashed_address) {
+        let (location, value) = entry?;
+        hb.add_leaf(
+            unpack_nibbles(location.as_bytes()),
+            rlp::encode(&value).as_ref(),
+        );
+    }
+
+    Ok(hb.compute_root_hash())
+}
+
+/// Computes state root from scratch out of plain state, without relying on hashed state or intermediate hashes.
+///
+/// Whole state is sorted in memory, so this is only suitable for small states and cross-checking.
+pub fn compute_state_root<K, E>(txn: &MdbxTransaction<'_, K, E>) -> anyhow::Result<H256>
+where
+    K: TransactionKind,
+    E: EnvironmentKind,
+{
+    let mut accounts = BTreeMap::new();
+    for entry in crate::accessors::state::account::walk(txn, None)? {
+        let (address, account) = entry?;
+
+        let mut storage = BTreeMap::new();
+        for entry in txn.cursor(tables::Storage)?.walk_dup(address) {
+            let (location, value) = entry?;
+            storage.insert(keccak256(location), value);
+        }
+
+        let mut hb = HashBuilder::new(None);
+        for (location, value) in storage {
+            hb.add_leaf(
+                unpack_nibbles(location.as_bytes()),
+                rlp::encode(&value).as_ref(),
+            );
+        }
+
+        accounts.insert(keccak256(address), account.to_rlp(hb.compute_root_hash()));
+    }
+
+    let mut hb = HashBuilder::new(None);
+    for (address, account) in accounts {
+        hb.add_leaf(
+            unpack_nibbles(address.as_bytes()),
+            rlp::encode(&account).as_ref(),
+        );
+    }
+
+    Ok(hb.compute_root_hash())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{kv::new_mem_database, state::genesis::initialize_genesis};
+    use hex_literal::hex;
+    use tempfile::TempDir;
+
+    #[test]
+    fn mainnet_genesis_state_root() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let temp_dir = TempDir::new().unwrap();
+        initialize_genesis(&txn, &temp_dir, crate::res::chainspec::MAINNET.clone()).unwrap();
+
+        assert_eq!(
+            compute_state_root(&txn).unwrap(),
+            hex!("d7f8974fb5ac78d9ac099b9ad5018bedc2ce0a72dad1827a1709da30580f0544").into()
+        );
+    }
+
+    #[test]
+    fn storage_root_matches_full_computation() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let address = Address::repeat_byte(0x01);
+        let location = H256::repeat_byte(0x02);
+        txn.set(tables::Account, address, Account::default())
+            .unwrap();
+        txn.set(tables::Storage, address, (location, 3.as_u256()))
+            .unwrap();
+        txn.set(
+            tables::HashedStorage,
+            keccak256(address),
+            (keccak256(location), 3.as_u256()),
+        )
+        .unwrap();
+
+        let storage_root = calculate_storage_root(&txn, keccak256(address)).unwrap();
+        assert_ne!(storage_root, EMPTY_ROOT);
+
+        let mut hb = HashBuilder::new(None);
+        hb.add_leaf(
+            unpack_nibbles(keccak256(address).as_bytes()),
+            rlp::encode(&Account::default().to_rlp(storage_root)).as_ref(),
+        );
+        assert_eq!(compute_state_root(&txn).unwrap(), hb.compute_root_hash());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::{
+    crypto::keccak256,
+    kv::{mdbx::*, tables},
+    models::*,
+    trie::{
+        hash_builder::{unpack_nibbles, HashBuilder, ProofRetainer},
+        intermediate_hashes::Cursor,
+        prefix_set::PrefixSet,
+    },
+};
+use anyhow::Result;
+use bytes::Bytes;
+
+/// Merkle proof of a storage slot, as defined in EIP-1186.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    pub proof: Vec<Bytes>,
+}
+
+/// Merkle proof of an account and some of its storage slots, as defined in EIP-1186.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountProof {
+    pub state_root: H256,
+    pub account: Option<Account>,
+    pub storage_root: H256,
+    pub account_proof: Vec<Bytes>,
+    pub storage_proofs: Vec<StorageProof>,
+}
+
+struct TrieProver<'db, 'tx, E>
+where
+    E: EnvironmentKind,
+    'db: 'tx,
+{
+    txn: &'tx MdbxTransaction<'db, RO, E>,
+    hashed_address: H256,
+    storage_keys: Vec<Vec<u8>>,
+    storage_retainer: Option<ProofRetainer>,
+    storage_root: H256,
+}
+
+impl<'db, 'tx, E> TrieProver<'db, 'tx, E>
+where
+    E: EnvironmentKind,
+    'db: 'tx,
+{
+    fn account_root(&mut self, changed: &mut PrefixSet) -> Result<(H256, ProofRetainer)> {
+        let mut state = self.txn.cursor(tables::HashedAccount)?;
+        let mut trie_db_cursor = self.txn.cursor(tables::TrieAccount)?;
+
+        let mut hb =
+            HashBuilder::new(None).with_proof_retainer(ProofRetainer::new(vec![unpack_nibbles(
+                self.hashed_address.as_bytes(),
+            )]));
+
+        let mut trie = Cursor::new(&mut trie_db_cursor, changed, &[])?;
+        while let Some(key) = trie.key() {
+            if trie.can_skip_state {
+                hb.add_branch_node(
+                    key,
+                    trie.hash().as_ref().unwrap(),
+                    trie.children_are_in_trie(),
+                );
+            }
+
+            let seek_key = match trie.first_uncovered_prefix() {
+                Some(mut uncovered) => {
+                    uncovered.resize(32, 0);
+                    uncovered
+                }
+                None => break,
+            };
+
+            trie.next()?;
+
+            let mut acc = state.seek(H256::from_slice(seek_key.as_slice()))?;
+            while let Some((address, account)) = acc {
+                let unpacked_key = unpack_nibbles(address.as_bytes());
+
+                if let Some(key) = trie.key() {
+                    if key < unpacked_key {
+                        break;
+                    }
+                }
+
+                let storage_root = self.storage_root(address, trie.changed_mut())?;
+
+                hb.add_leaf(
+                    unpacked_key,
+                    rlp::encode(&account.to_rlp(storage_root)).as_ref(),
+                );
+
+                acc = state.next()?
+            }
+        }
+
+        let root = hb.compute_root_hash();
+        Ok((root, hb.proof_retainer.take().unwrap()))
+    }
+
+    fn storage_root(&mut self, hashed_address: H256, changed: &mut PrefixSet) -> Result<H256> {
+        let key_with_inc = hashed_address.as_bytes();
+
+        let mut state = self.txn.cursor(tables::HashedStorage)?;
+        let mut trie_db_cursor = self.txn.cursor(tables::TrieStorage)?;
+
+        let is_target = hashed_address == self.hashed_address;
+        let mut hb = HashBuilder::new(None);
+        if is_target {
+            hb = hb.with_proof_retainer(ProofRetainer::new(self.storage_keys.clone()));
+        }
+
+        let mut trie = Cursor::new(&mut trie_db_cursor, changed, key_with_inc)?;
+        while let Some(key) = trie.key() {
+            if trie.can_skip_state {
+                hb.add_branch_node(
+                    key,
+                    trie.hash().as_ref().unwrap(),
+                    trie.children_are_in_trie(),
+                );
+            }
+
+            let seek_key = match trie.first_uncovered_prefix() {
+                Some(mut uncovered) => {
+                    uncovered.resize(32, 0);
+                    uncovered
+                }
+                None => break,
+            };
+
+            trie.next()?;
+
+            let mut storage =
+                state.seek_both_range(hashed_address, H256::from_slice(seek_key.as_slice()))?;
+            while let Some((storage_location, value)) = storage {
+                let unpacked_loc = unpack_nibbles(storage_location.as_bytes());
+                if let Some(key) = trie.key() {
+                    if key < unpacked_loc {
+                        break;
+                    }
+                }
+                hb.add_leaf(unpacked_loc, rlp::encode(&value).as_ref());
+                storage = state.next_dup()?.map(|(_, v)| v);
+            }
+        }
+
+        let root = hb.compute_root_hash();
+        if is_target {
+            self.storage_root = root;
+            self.storage_retainer = hb.proof_retainer.take();
+        }
+
+        Ok(root)
+    }
+}
+
+/// Generates Merkle proofs for the account and its storage slots against the current state root.
+///
+/// Only subtrees on the paths to the requested keys are recomputed, the rest comes from intermediate hashes.
+/// This requires a read-only transaction, since intermediate hashes are not modified.
+pub fn generate_proof<'db, E>(
+    txn: &MdbxTransaction<'db, RO, E>,
+    address: Address,
+    storage_keys: &[H256],
+) -> Result<AccountProof>
+where
+    E: EnvironmentKind,
+{
+    let hashed_address = keccak256(address);
+    let unpacked_storage_keys = storage_keys
+        .iter()
+        .map(|key| unpack_nibbles(keccak256(key).as_bytes()))
+        .collect::<Vec<_>>();
+
+    let mut changed = PrefixSet::new();
+    changed.insert(&unpack_nibbles(hashed_address.as_bytes()));
+    for key in &unpacked_storage_keys {
+        changed.insert(&[hashed_address.as_bytes(), key].concat());
+    }
+
+    let mut prover = TrieProver {
+        txn,
+        hashed_address,
+        storage_keys: unpacked_storage_keys.clone(),
+        storage_retainer: None,
+        storage_root: EMPTY_ROOT,
+    };
+    let (state_root, account_retainer) = prover.account_root(&mut changed)?;
+
+    let to_bytes = |proof: Vec<Vec<u8>>| proof.into_iter().map(Bytes::from).collect::<Vec<_>>();
+
+    let account = txn.get(tables::HashedAccount, hashed_address)?;
+    let mut storage_cursor = txn.cursor(tables::HashedStorage)?;
+    let mut storage_proofs = Vec::with_capacity(storage_keys.len());
+    for (key, unpacked_key) in storage_keys.iter().zip(&unpacked_storage_keys) {
+        let hashed_key = keccak256(key);
+        let value = storage_cursor
+            .seek_both_range(hashed_address, hashed_key)?
+            .filter(|&(l, _)| l == hashed_key)
+            .map(|(_, v)| v)
+            .unwrap_or(U256::ZERO);
+        let proof = prover
+            .storage_retainer
+            .as_ref()
+            .map(|retainer| to_bytes(retainer.proof(unpacked_key)))
+            .unwrap_or_default();
+
+        storage_proofs.push(StorageProof {
+            key: *key,
+            value,
+            proof,
+        });
+    }
+
+    Ok(AccountProof {
+        state_root,
+        account,
+        storage_root: prover.storage_root,
+        account_proof: to_bytes(account_retainer.proof(&unpack_nibbles(hashed_address.as_bytes()))),
+        storage_proofs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        kv::new_mem_database, trie::regenerate_intermediate_hashes, upsert_hashed_storage_value,
+    };
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    /// Minimal Merkle proof verifier, independent from the trie builder.
+    fn verify_proof(root: H256, hashed_key: H256, proof: &[Bytes]) -> Option<Vec<u8>> {
+        let nibbles = unpack_nibbles(hashed_key.as_bytes());
+        let nodes = proof
+            .iter()
+            .map(|node| (keccak256(node), node.to_vec()))
+            .collect::<HashMap<_, _>>();
+
+        let mut node = nodes.get(&root)?.clone();
+        let mut pos = 0;
+        loop {
+            let rlp = rlp::Rlp::new(&node);
+            let next = match rlp.item_count().ok()? {
+                17 => {
+                    if pos == nibbles.len() {
+                        return None;
+                    }
+                    let child = rlp.at(nibbles[pos] as usize).ok()?;
+                    pos += 1;
+                    child
+                }
+                2 => {
+                    let encoded_path = rlp.at(0).ok()?.data().ok()?.to_vec();
+                    let is_leaf = encoded_path[0] & 0x20 != 0;
+                    let mut path = unpack_nibbles(&encoded_path);
+                    path.drain(..if encoded_path[0] & 0x10 != 0 { 1 } else { 2 });
+
+                    if !nibbles[pos..].starts_with(&path) {
+                        return None;
+                    }
+                    pos += path.len();
+
+                    if is_leaf {
+                        if pos != nibbles.len() {
+                            return None;
+                        }
+                        return rlp.at(1).ok()?.data().ok().map(<[u8]>::to_vec);
+                    }
+
+                    rlp.at(1).ok()?
+                }
+                _ => return None,
+            };
+
+            if next.is_empty() {
+                return None;
+            }
+
+            node = if next.is_list() {
+                next.as_raw().to_vec()
+            } else {
+                nodes.get(&H256::from_slice(next.data().ok()?))?.clone()
+            };
+        }
+    }
+
+    #[test]
+    fn account_and_storage_proof() {
+        let db = new_mem_database().unwrap();
+        let txn = db.begin_mutable().unwrap();
+
+        let address = |i: u64| Address::from_low_u64_be(i);
+        let target = address(7);
+        {
+            let mut hashed_accounts = txn.cursor(tables::HashedAccount).unwrap();
+            let mut hashed_storage = txn.cursor(tables::HashedStorage).unwrap();
+            for i in 0..100 {
+                hashed_accounts
+                    .upsert(
+                        keccak256(address(i)),
+                        Account {
+                            nonce: i,
+                            balance: (i * 1000).as_u256(),
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+            }
+            for i in 0..50 {
+                upsert_hashed_storage_value(
+                    &mut hashed_storage,
+                    keccak256(target),
+                    keccak256(H256::from_low_u64_be(i)),
+                    (i + 1).as_u256(),
+                )
+                .unwrap();
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let state_root = regenerate_intermediate_hashes(&txn, &temp_dir, None).unwrap();
+        txn.commit().unwrap();
+
+        let txn = db.begin().unwrap();
+        let present_key = H256::from_low_u64_be(3);
+        let missing_key = H256::from_low_u64_be(1000);
+        let proof = generate_proof(&txn, target, &[present_key, missing_key]).unwrap();
+
+        assert_eq!(proof.state_root, state_root);
+        let account = proof.account.unwrap();
+        assert_eq!(account.nonce, 7);
+
+        let encoded_account =
+            verify_proof(state_root, keccak256(target), &proof.account_proof).unwrap();
+        assert_eq!(
+            encoded_account,
+            rlp::encode(&account.to_rlp(proof.storage_root)).to_vec()
+        );
+
+        assert_eq!(proof.storage_proofs[0].value, 4);
+        assert_eq!(
+            verify_proof(
+                proof.storage_root,
+                keccak256(present_key),
+                &proof.storage_proofs[0].proof
+            )
+            .unwrap(),
+            rlp::encode(&4.as_u256()).to_vec()
+        );
+        assert_eq!(proof.storage_proofs[1].value, 0);
+        assert!(verify_proof(
+            proof.storage_root,
+            keccak256(missing_key),
+            &proof.storage_proofs[1].proof
+        )
+        .is_none());
+    }
+}
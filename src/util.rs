@@ -1,3 +1,4 @@
+use anyhow::{format_err, Context};
 use bytes::{Bytes, BytesMut};
 use ethereum_types::*;
 use ethnum::U256;
@@ -53,8 +54,116 @@ pub fn zeroless_view(v: &impl AsRef<[u8]>) -> &[u8] {
     &v[v.iter().take_while(|b| b.is_zero()).count()..]
 }
 
+/// Decodes hex in any case, with or without `0x` prefix. Odd-length input is left-padded with a zero nibble.
 pub fn hex_to_bytes(s: &str) -> Result<Bytes, hex::FromHexError> {
-    hex::decode(s).map(From::from)
+    let s = strip_hex_prefix(s);
+
+    if s.len() % 2 == 1 {
+        hex::decode(format!("0{}", s))
+    } else {
+        hex::decode(s)
+    }
+    .map(From::from)
+}
+
+fn strip_hex_prefix(s: &str) -> &str {
+    s.strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s)
+}
+
+/// `0x`-prefixed lowercase hex representation.
+pub trait EncodeHex {
+    fn to_hex_string(&self) -> String;
+}
+
+/// Parses the representation produced by [`EncodeHex`], the prefix being optional.
+pub trait DecodeHex: Sized {
+    fn from_hex_string(s: &str) -> anyhow::Result<Self>;
+}
+
+macro_rules! impl_hex_for_hash {
+    ($($t:ty),+) => {
+        $(
+            impl EncodeHex for $t {
+                fn to_hex_string(&self) -> String {
+                    format!("0x{}", hex::encode(self))
+                }
+            }
+
+            impl DecodeHex for $t {
+                fn from_hex_string(s: &str) -> anyhow::Result<Self> {
+                    let mut v = Self::zero();
+                    hex::decode_to_slice(strip_hex_prefix(s), v.as_bytes_mut())?;
+                    Ok(v)
+                }
+            }
+        )+
+    };
+}
+
+impl_hex_for_hash!(H64, H160, H256, Bloom);
+
+/// Numeric value without leading zeros, as in JSON-RPC quantities.
+impl EncodeHex for U256 {
+    fn to_hex_string(&self) -> String {
+        format!("0x{:x}", self)
+    }
+}
+
+impl DecodeHex for U256 {
+    fn from_hex_string(s: &str) -> anyhow::Result<Self> {
+        Ok(U256::from_str_radix(strip_hex_prefix(s), 16)?)
+    }
+}
+
+impl EncodeHex for u64 {
+    fn to_hex_string(&self) -> String {
+        format!("0x{:x}", self)
+    }
+}
+
+impl DecodeHex for u64 {
+    fn from_hex_string(s: &str) -> anyhow::Result<Self> {
+        Ok(u64::from_str_radix(strip_hex_prefix(s), 16)?)
+    }
+}
+
+impl EncodeHex for Bytes {
+    fn to_hex_string(&self) -> String {
+        format!("0x{}", hex::encode(self))
+    }
+}
+
+impl DecodeHex for Bytes {
+    fn from_hex_string(s: &str) -> anyhow::Result<Self> {
+        Ok(hex::decode(strip_hex_prefix(s))?.into())
+    }
+}
+
+/// Hex encoded field `name` of a JSON-RPC object, `None` if absent or `null`.
+pub(crate) fn optional_json_hex_field<T: DecodeHex>(
+    value: &serde_json::Value,
+    name: &str,
+) -> anyhow::Result<Option<T>> {
+    match value.get(name) {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(v) => {
+            let s = v
+                .as_str()
+                .ok_or_else(|| format_err!("{} is not a string", name))?;
+            Ok(Some(
+                T::from_hex_string(s).with_context(|| format!("invalid {}", name))?,
+            ))
+        }
+    }
+}
+
+pub(crate) fn json_hex_field<T: DecodeHex>(
+    value: &serde_json::Value,
+    name: &str,
+) -> anyhow::Result<T> {
+    optional_json_hex_field(value, name)?.ok_or_else(|| format_err!("missing {}", name))
 }
 
 pub fn write_hex_string<B: AsRef<[u8]>>(b: &B, f: &mut Formatter) -> fmt::Result {
@@ -88,16 +197,14 @@ pub mod hexbytes {
     {
         let s = String::deserialize(deserializer)?;
 
-        Ok(hex::decode(s.strip_prefix("0x").unwrap_or(&s))
-            .map_err(D::Error::custom)?
-            .into())
+        Bytes::from_hex_string(&s).map_err(D::Error::custom)
     }
 
     pub fn serialize<S>(b: &Bytes, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        serializer.serialize_str(&format!("0x{}", hex::encode(b)))
+        serializer.serialize_str(&b.to_hex_string())
     }
 }
 
@@ -119,6 +226,7 @@ mod tests {
     use bytes::Buf;
     use bytes_literal::bytes;
     use hex_literal::hex;
+    use proptest::prelude::*;
 
     #[test]
     fn padding() {
@@ -149,6 +257,80 @@ mod tests {
         assert_eq!(repeatedly_padded, bytes!("000000b8"));
     }
 
+    #[test]
+    fn hex_to_bytes_test() {
+        assert_eq!(hex_to_bytes("deadbeef").unwrap(), bytes!("deadbeef"));
+        assert_eq!(hex_to_bytes("DEADBEEF").unwrap(), bytes!("deadbeef"));
+        assert_eq!(hex_to_bytes("DeadBEEF").unwrap(), bytes!("deadbeef"));
+        assert_eq!(hex_to_bytes("0xdeadbeef").unwrap(), bytes!("deadbeef"));
+        assert_eq!(hex_to_bytes("0XDEADBEEF").unwrap(), bytes!("deadbeef"));
+        assert_eq!(hex_to_bytes("eadbeef").unwrap(), bytes!("0eadbeef"));
+        assert_eq!(hex_to_bytes("0x1").unwrap(), bytes!("01"));
+        assert_eq!(hex_to_bytes("").unwrap(), bytes!(""));
+        assert_eq!(hex_to_bytes("0x").unwrap(), bytes!(""));
+
+        assert!(hex_to_bytes("0xdeadbeeg").is_err());
+        assert!(hex_to_bytes("dead beef").is_err());
+        assert!(hex_to_bytes("0x0xdeadbeef").is_err());
+    }
+
+    #[test]
+    fn hex_string() {
+        let address = H160(hex!("d8da6bf26964af9d7eed9e03e53415d37aa96045"));
+        assert_eq!(
+            address.to_hex_string(),
+            "0xd8da6bf26964af9d7eed9e03e53415d37aa96045"
+        );
+        assert_eq!(
+            H160::from_hex_string("0xD8DA6BF26964AF9D7EED9E03E53415D37AA96045").unwrap(),
+            address
+        );
+        assert_eq!(
+            H160::from_hex_string("d8da6bf26964af9d7eed9e03e53415d37aa96045").unwrap(),
+            address
+        );
+        assert!(H160::from_hex_string("0xd8da6bf26964af9d7eed9e03e53415d37aa960").is_err());
+        assert!(H256::from_hex_string("0xd8da6bf26964af9d7eed9e03e53415d37aa96045").is_err());
+
+        assert_eq!(U256::ZERO.to_hex_string(), "0x0");
+        assert_eq!(U256::from(0x4bc00_u64).to_hex_string(), "0x4bc00");
+        assert_eq!(
+            U256::from_hex_string("0x04BC00").unwrap(),
+            U256::from(0x4bc00_u64)
+        );
+        assert!(U256::from_hex_string("0x").is_err());
+
+        assert_eq!(Bytes::new().to_hex_string(), "0x");
+        assert_eq!(Bytes::from_hex_string("0x").unwrap(), Bytes::new());
+        assert!(Bytes::from_hex_string("0xabc").is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn h256_hex_roundtrip(v in any::<[u8; 32]>()) {
+            let v = H256(v);
+            prop_assert_eq!(H256::from_hex_string(&v.to_hex_string()).unwrap(), v);
+        }
+
+        #[test]
+        fn address_hex_roundtrip(v in any::<[u8; 20]>()) {
+            let v = H160(v);
+            prop_assert_eq!(H160::from_hex_string(&v.to_hex_string()).unwrap(), v);
+        }
+
+        #[test]
+        fn u256_hex_roundtrip(v in any::<[u8; 32]>()) {
+            let v = U256::from_be_bytes(v);
+            prop_assert_eq!(U256::from_hex_string(&v.to_hex_string()).unwrap(), v);
+        }
+
+        #[test]
+        fn bytes_hex_roundtrip(v in any::<Vec<u8>>()) {
+            let v = Bytes::from(v);
+            prop_assert_eq!(Bytes::from_hex_string(&v.to_hex_string()).unwrap(), v);
+        }
+    }
+
     #[test]
     fn zeroless_view_test() {
         assert_eq!(